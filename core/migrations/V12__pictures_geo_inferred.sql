@@ -0,0 +1,3 @@
+-- Locations can be inferred from a GPS track (such as a GPX file from a GPS logger)
+-- for pictures without GPS data in their EXIF tags.
+ALTER TABLE pictures_geo ADD COLUMN is_inferred BOOLEAN NOT NULL DEFAULT FALSE CHECK (is_inferred IN (0, 1));
@@ -0,0 +1,8 @@
+-- Content hashes of files imported from cameras, phones, and SD cards so that
+-- files already imported can be skipped.
+CREATE TABLE imports (
+        content_hash       TEXT PRIMARY KEY UNIQUE NOT NULL, -- SHA-256 of file content
+        library_path_b64   TEXT NOT NULL, -- path in library relative to library base path
+        library_path_lossy TEXT, -- for debug only. Never read in Fotema.
+        imported_ts        DATETIME NOT NULL -- when file was imported
+);
@@ -0,0 +1,3 @@
+-- Position in milliseconds of the frame that video thumbnails are made from,
+-- as chosen by the user. NULL means a representative frame is chosen automatically.
+ALTER TABLE videos ADD COLUMN poster_millis INTEGER;
@@ -0,0 +1,143 @@
+-- How a photo was taken, for smart albums of portraits, HDR photos, panoramas,
+-- and screenshots. Selfies already have is_selfie.
+ALTER TABLE pictures ADD COLUMN is_portrait BOOLEAN CHECK (is_portrait IN (0, 1));
+ALTER TABLE pictures ADD COLUMN is_hdr BOOLEAN CHECK (is_hdr IN (0, 1));
+ALTER TABLE pictures ADD COLUMN is_panorama BOOLEAN CHECK (is_panorama IN (0, 1));
+ALTER TABLE pictures ADD COLUMN is_screenshot BOOLEAN CHECK (is_screenshot IN (0, 1));
+
+DROP VIEW visual;
+
+CREATE VIEW visual AS
+SELECT
+  -- Unique ID
+  COALESCE(pictures.picture_id, 'x') || '_' || COALESCE(videos.video_id, 'x') AS visual_id,
+  COALESCE(pictures.link_path_b64, videos.link_path_b64) AS link_path_b64,
+
+  pictures.picture_id,
+  pictures.picture_path_b64,
+  pictures.picture_path_lossy, -- for debug only. Never read in Fotema.
+  pictures.orientation AS picture_orientation,
+  pictures.orientation_override AS picture_orientation_override,
+  picture_edits.orientation AS picture_edit_orientation,
+
+-- If the thumbnail path is absent in the database, then compute the path we know it
+-- will have. Eventually the thumbnail generation background process will create the file
+-- and it will show up in the UI without having to refresh the data.
+-- The computed path is that of the smallest thumbnail.
+  CASE
+        WHEN pictures.picture_id IS NULL THEN NULL
+        ELSE COALESCE(
+          pictures.thumbnail_path,
+          'photo_thumbnails/' || printf('%04d', pictures.picture_id / 1000) || '/' || CAST(pictures.picture_id AS TEXT) || '_200x200.png'
+        )
+  END AS picture_thumbnail,
+  pictures.thumbnail_max_edge AS picture_thumbnail_max_edge,
+
+  pictures.is_selfie,
+  pictures.is_portrait,
+  pictures.is_hdr,
+  pictures.is_panorama,
+  pictures.is_screenshot,
+
+  pictures.camera_model,
+  pictures.burst_id,
+  pictures.is_burst_pick,
+
+  videos.video_id,
+  videos.video_path_b64,
+  videos.video_path_lossy, -- for debug only. Never read in Fotema.
+
+-- If the thumbnail path is absent in the database, then compute the path we know it
+-- will have. Eventually the thumbnail generation background process will create the file
+-- and it will show up in the UI without having to refresh the data.
+-- The computed path is that of the smallest thumbnail.
+  CASE
+        WHEN videos.video_id IS NULL THEN NULL
+        ELSE COALESCE(
+          videos.thumbnail_path,
+          'video_thumbnails/' || printf('%04d', videos.video_id / 1000) || '/' || CAST(videos.video_id AS TEXT) || '_200x200.png'
+        )
+  END AS video_thumbnail,
+  videos.thumbnail_max_edge AS video_thumbnail_max_edge,
+
+  -- Size of the image the thumbnails were made from, before the thumbnail orientation
+  -- is applied. A picture takes precedence over a video, as with the thumbnail.
+  CASE
+        WHEN pictures.picture_id IS NOT NULL THEN pictures.width
+        ELSE videos.width
+  END AS width,
+  CASE
+        WHEN pictures.picture_id IS NOT NULL THEN pictures.height
+        ELSE videos.height
+  END AS height,
+
+  -- Focus that thumbnails are cropped about. A focus chosen by the user takes
+  -- precedence over the detected focus.
+  CASE
+        WHEN pictures.picture_id IS NOT NULL THEN COALESCE(pictures.focus_override_x, pictures.focus_x)
+        ELSE videos.focus_x
+  END AS focus_x,
+  CASE
+        WHEN pictures.picture_id IS NOT NULL THEN COALESCE(pictures.focus_override_y, pictures.focus_y)
+        ELSE videos.focus_y
+  END AS focus_y,
+
+  COALESCE(videos.video_codec, motion_photos.video_codec) AS video_codec,
+
+  COALESCE(videos.video_codec, motion_photos.video_codec) IN ('hevc') AS is_transcode_required,
+
+  COALESCE(videos.transcoded_path, motion_photos.transcoded_path) AS video_transcoded_path,
+
+  COALESCE(videos.rotation, motion_photos.rotation) AS video_rotation,
+
+  -- An iOS live photo is a photo and a video linked with a content ID.
+  -- However, we only really need the video part, and short (<3 seconds)
+  -- videos are possibly live photos that have a missing or misnamed photo.
+  CASE
+        WHEN videos.content_id IS NOT NULL THEN true
+        WHEN videos.duration_millis <= 3000 THEN true
+        WHEN motion_photos.video_path IS NOT NULL THEN true
+        ELSE false
+  END AS is_live_photo,
+
+  COALESCE(videos.duration_millis, motion_photos.duration_millis) as duration_millis,
+
+  motion_photos.video_path AS motion_photo_video_path,
+
+  voice_notes.voice_note_path_b64,
+
+  pictures_geo.longitude AS longitude,
+  pictures_geo.latitude AS latitude,
+  pictures_geo.altitude AS altitude,
+  pictures_geo.direction AS direction,
+
+  -- Timestamp to order visual items by.
+  -- Prefer embedded metadata over file system metadata.
+  COALESCE(
+    pictures.exif_created_ts,
+    videos.stream_created_ts,
+    pictures.exif_modified_ts,
+    pictures.fs_created_ts,
+    videos.fs_created_ts,
+    pictures.fs_modified_ts,
+    videos.fs_modified_ts,
+    CURRENT_TIMESTAMP
+  ) AS ordering_ts
+FROM
+  pictures
+  FULL OUTER JOIN videos USING (link_path_b64, content_id)
+  FULL OUTER JOIN motion_photos USING (picture_id)
+  FULL OUTER JOIN pictures_geo USING (picture_id)
+  LEFT JOIN picture_edits USING (picture_id)
+  -- A picture might have more than one voice note if only the case of the suffix
+  -- differs, so pick one to avoid repeating the picture.
+  LEFT JOIN (
+    SELECT link_path_b64, MIN(voice_note_path_b64) AS voice_note_path_b64
+    FROM voice_notes
+    GROUP BY link_path_b64
+  ) AS voice_notes ON voice_notes.link_path_b64 = pictures.link_path_b64
+WHERE COALESCE(pictures.is_broken, FALSE) IS FALSE
+AND COALESCE(videos.is_broken, FALSE) IS FALSE
+ORDER BY
+  ordering_ts ASC;
+
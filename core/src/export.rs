@@ -0,0 +1,473 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Export photos and videos out of the library into a directory, such as for
+/// sending a selection of photos to somebody else.
+use crate::photo::model::Orientation;
+use crate::video;
+use crate::visual::Visual;
+use anyhow::*;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::io::Reader as ImageReader;
+use image::imageops::FilterType;
+use image::DynamicImage;
+
+use ffmpeg_next as ffmpeg;
+
+use gdk4::prelude::TextureExt;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tracing::{event, Level};
+
+/// How to export items.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportMode {
+    /// Copy original files unmodified, including the video of a live photo.
+    Originals,
+
+    /// Re-encode photos as JPEGs no larger than `max_edge` pixels along the longest edge.
+    /// Re-encoded photos have no metadata. Videos are copied unmodified, but the video of
+    /// a live photo is skipped as it is an export of stills.
+    Jpeg { max_edge: u32, quality: u8 },
+
+    /// Copy files with EXIF, XMP, GPS and other metadata removed for privacy.
+    /// JPEG pixel data is copied without re-encoding, other photo formats are re-encoded,
+    /// and videos are remuxed without re-encoding.
+    StripMetadata,
+}
+
+/// Quality to use when a photo must be re-encoded to strip metadata.
+const STRIP_METADATA_QUALITY: u8 = 95;
+
+#[derive(Debug, Clone)]
+pub struct Exporter {
+    /// Directory to export into
+    target_dir: PathBuf,
+
+    mode: ExportMode,
+}
+
+impl Exporter {
+    pub fn build(target_dir: &Path, mode: ExportMode) -> Result<Exporter> {
+        fs::create_dir_all(target_dir)?;
+        Ok(Exporter {
+            target_dir: PathBuf::from(target_dir),
+            mode,
+        })
+    }
+
+    pub fn mode(&self) -> ExportMode {
+        self.mode
+    }
+
+    /// Export a visual item. Returns paths of the exported files.
+    /// Existing files in the target directory are never overwritten.
+    pub async fn export(&self, visual: &Visual) -> Result<Vec<PathBuf>> {
+        let mut exported = Vec::new();
+
+        if let Some(ref picture_path) = visual.picture_path {
+            let orientation = visual.corrected_picture_orientation();
+            let path = match self.mode {
+                ExportMode::Originals => self.copy(picture_path)?,
+                ExportMode::Jpeg { max_edge, quality } => {
+                    self.to_jpeg(picture_path, orientation, Some(max_edge), quality)
+                        .await?
+                }
+                ExportMode::StripMetadata => self.strip_photo(picture_path, orientation).await?,
+            };
+            exported.push(path);
+        }
+
+        if let Some(ref video_path) = visual.video_path {
+            let is_live_photo_video = visual.picture_path.is_some();
+            let path = match self.mode {
+                ExportMode::Originals => Some(self.copy(video_path)?),
+                ExportMode::Jpeg { .. } if is_live_photo_video => None,
+                ExportMode::Jpeg { .. } => Some(self.copy(video_path)?),
+                ExportMode::StripMetadata => Some(self.strip_video(video_path)?),
+            };
+            exported.extend(path);
+        }
+
+        Ok(exported)
+    }
+
+    /// Copy a file unmodified.
+    fn copy(&self, path: &Path) -> Result<PathBuf> {
+        let target_path = self.target_path(path, None)?;
+        event!(Level::DEBUG, "Exporting {:?} to {:?}", path, target_path);
+        write_target(target_path, |target_path| {
+            fs::copy(path, target_path)?;
+            Ok(())
+        })
+    }
+
+    /// Re-encode photo as a JPEG, which won't have any metadata.
+    async fn to_jpeg(
+        &self,
+        path: &Path,
+        orientation: Orientation,
+        max_edge: Option<u32>,
+        quality: u8,
+    ) -> Result<PathBuf> {
+        let mut image = load_image(path, orientation).await?;
+
+        if let Some(max_edge) = max_edge {
+            if image.width() > max_edge || image.height() > max_edge {
+                image = image.resize(max_edge, max_edge, FilterType::Lanczos3);
+            }
+        }
+
+        let target_path = self.target_path(path, Some("jpg"))?;
+        event!(Level::DEBUG, "Exporting {:?} to {:?}", path, target_path);
+
+        write_target(target_path, |target_path| {
+            let file = fs::File::create(target_path)?;
+            let mut file = BufWriter::new(file);
+
+            // JPEG doesn't support an alpha channel.
+            let image = DynamicImage::ImageRgb8(image.into_rgb8());
+            JpegEncoder::new_with_quality(&mut file, quality).encode_image(&image)?;
+
+            file.flush()?;
+            Ok(())
+        })
+    }
+
+    /// Copy photo without metadata.
+    async fn strip_photo(&self, path: &Path, orientation: Orientation) -> Result<PathBuf> {
+        let ext = path
+            .extension()
+            .and_then(|x| x.to_str())
+            .map(|x| x.to_lowercase());
+
+        match ext.as_deref() {
+            Some("jpg") | Some("jpeg") => {
+                let data = fs::read(path)?;
+                let stripped = strip_jpeg_metadata(&data, orientation)?;
+                let target_path = self.target_path(path, None)?;
+                event!(Level::DEBUG, "Exporting {:?} to {:?}", path, target_path);
+                write_target(target_path, |target_path| {
+                    fs::write(target_path, stripped)?;
+                    Ok(())
+                })
+            }
+            Some("png") => {
+                // image-rs doesn't write any metadata chunks.
+                let image = load_image(path, orientation).await?;
+                let target_path = self.target_path(path, None)?;
+                event!(Level::DEBUG, "Exporting {:?} to {:?}", path, target_path);
+                write_target(target_path, |target_path| {
+                    let file = fs::File::create(target_path)?;
+                    let mut file = BufWriter::new(file);
+                    image.write_with_encoder(PngEncoder::new(&mut file))?;
+                    file.flush()?;
+                    Ok(())
+                })
+            }
+            _ => {
+                // Formats such as HEIC can't be written, so convert to JPEG.
+                self.to_jpeg(path, orientation, None, STRIP_METADATA_QUALITY)
+                    .await
+            }
+        }
+    }
+
+    /// Remux video without container and stream metadata, such as creation time and
+    /// location. Audio and video are copied without re-encoding.
+    fn strip_video(&self, path: &Path) -> Result<PathBuf> {
+        let target_path = self.target_path(path, None)?;
+        event!(Level::DEBUG, "Exporting {:?} to {:?}", path, target_path);
+        write_target(target_path, |target_path| remux_without_metadata(path, target_path))
+    }
+
+    /// Path in target directory for source path. If a file with the same name already exists
+    /// then a number is appended to the file stem. The path is claimed by creating an empty
+    /// file, so items exported in parallel never get the same path.
+    fn target_path(&self, path: &Path, extension: Option<&str>) -> Result<PathBuf> {
+        let stem = path
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_string())
+            .ok_or_else(|| anyhow!("No file name: {:?}", path))?;
+
+        let extension = extension
+            .map(String::from)
+            .or_else(|| path.extension().map(|x| x.to_string_lossy().to_string()));
+
+        let file_name = |suffix: String| match extension {
+            Some(ref ext) => format!("{}{}.{}", stem, suffix, ext),
+            None => format!("{}{}", stem, suffix),
+        };
+
+        let mut target_path = self.target_dir.join(file_name(String::new()));
+        let mut count = 1;
+        loop {
+            let claimed = fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&target_path);
+
+            match claimed {
+                std::result::Result::Ok(_) => return Ok(target_path),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => {
+                    target_path = self.target_dir.join(file_name(format!(" ({})", count)));
+                    count += 1;
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+    }
+}
+
+/// Write a claimed target path. The target is removed if writing fails, so a failed
+/// export doesn't leave an empty or partial file behind.
+fn write_target<F>(target_path: PathBuf, write: F) -> Result<PathBuf>
+where
+    F: FnOnce(&Path) -> Result<()>,
+{
+    if let Err(e) = write(&target_path) {
+        let _ = fs::remove_file(&target_path);
+        return Err(e);
+    }
+    Ok(target_path)
+}
+
+/// Remux video without container and stream metadata, such as creation time and
+/// location. Audio and video are copied without re-encoding.
+fn remux_without_metadata(path: &Path, target_path: &Path) -> Result<()> {
+    let mut ictx = ffmpeg::format::input(&path)?;
+    let mut octx = ffmpeg::format::output(target_path)?;
+
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut input_time_bases = vec![ffmpeg::Rational(0, 1); ictx.nb_streams() as usize];
+
+    let mut output_index = 0;
+    for (input_index, input_stream) in ictx.streams().enumerate() {
+        let medium = input_stream.parameters().medium();
+        if medium != ffmpeg::media::Type::Audio && medium != ffmpeg::media::Type::Video {
+            // Skip data streams, such as the timed metadata tracks in iPhone videos.
+            continue;
+        }
+
+        stream_mapping[input_index] = Some(output_index);
+        input_time_bases[input_index] = input_stream.time_base();
+        output_index += 1;
+
+        let mut output_stream = octx.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+        output_stream.set_parameters(input_stream.parameters());
+        video::metadata::copy_display_matrix(&input_stream, &mut output_stream);
+
+        // Let the muxer pick a codec tag suitable for the container.
+        unsafe {
+            (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+        }
+    }
+
+    // Output context and streams start with empty metadata.
+    octx.write_header()?;
+
+    for (stream, mut packet) in ictx.packets() {
+        let input_index = stream.index();
+        let Some(output_index) = stream_mapping[input_index] else {
+            continue;
+        };
+
+        let output_time_base = octx
+            .stream(output_index)
+            .map(|x| x.time_base())
+            .ok_or_else(|| anyhow!("Missing output stream {}", output_index))?;
+
+        packet.rescale_ts(input_time_bases[input_index], output_time_base);
+        packet.set_position(-1);
+        packet.set_stream(output_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+/// Decode image and transform so it displays the right way up.
+/// Falls back to Glycin for formats image-rs can't decode, such as HEIC.
+async fn load_image(path: &Path, orientation: Orientation) -> Result<DynamicImage> {
+    if let std::result::Result::Ok(image) = ImageReader::open(path)?.decode() {
+        return Ok(orientation.apply_to(image));
+    }
+
+    // As with image-rs, Glycin decodes without applying the orientation.
+    let file = gio::File::for_path(path);
+    let image = glycin::Loader::new(file).load().await?;
+    let frame = image.next_frame().await?;
+
+    let png_file = tempfile::Builder::new().suffix(".png").tempfile()?;
+    frame.texture.save_to_png(png_file.path())?;
+
+    let image = ImageReader::open(png_file.path())?.decode()?;
+    Ok(orientation.apply_to(image))
+}
+
+/// Copy JPEG segments that are needed to display the image and drop all metadata segments,
+/// such as EXIF, XMP, IPTC, and comments. Anything after the end of the image, such as
+/// the video of a motion photo, is also dropped.
+/// An EXIF segment with just the orientation is written if the image needs to be transformed.
+fn strip_jpeg_metadata(data: &[u8], orientation: Orientation) -> Result<Vec<u8>> {
+    const SOI: u8 = 0xD8;
+    const EOI: u8 = 0xD9;
+    const SOS: u8 = 0xDA;
+    const APP0: u8 = 0xE0;
+    const APP1: u8 = 0xE1;
+    const APP2: u8 = 0xE2;
+    const APP14: u8 = 0xEE;
+    const APP15: u8 = 0xEF;
+    const COM: u8 = 0xFE;
+
+    if data.len() < 4 || data[0] != 0xFF || data[1] != SOI {
+        bail!("Not a JPEG");
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    out.extend_from_slice(&data[0..2]);
+
+    if !matches!(orientation, Orientation::North) {
+        out.extend_from_slice(&orientation_exif_segment(orientation));
+    }
+
+    let mut pos = 2;
+    loop {
+        // Skip fill bytes
+        while pos < data.len() && data[pos] == 0xFF && data.get(pos + 1) == Some(&0xFF) {
+            pos += 1;
+        }
+
+        if pos + 4 > data.len() || data[pos] != 0xFF {
+            bail!("Malformed JPEG segment at {}", pos);
+        }
+
+        let marker = data[pos + 1];
+        let len = u16::from_be_bytes([data[pos + 2], data[pos + 3]]) as usize;
+        let end = pos + 2 + len;
+        if end > data.len() {
+            bail!("Truncated JPEG segment at {}", pos);
+        }
+
+        let segment = &data[pos..end];
+        let payload = &data[pos + 4..end];
+
+        let keep = match marker {
+            APP0 | APP14 => true,
+            APP2 => payload.starts_with(b"ICC_PROFILE\0"),
+            APP1..=APP15 | COM => false,
+            _ => true,
+        };
+
+        if keep {
+            out.extend_from_slice(segment);
+        }
+
+        pos = end;
+
+        if marker == SOS {
+            // Entropy coded data, and any further segments for progressive JPEGs, up to
+            // the end of image marker. A 0xFF byte in entropy coded data is always followed
+            // by 0x00 or a restart marker, so the first end of image marker ends the image.
+            let eoi = data[pos..]
+                .windows(2)
+                .position(|w| w[0] == 0xFF && w[1] == EOI)
+                .ok_or_else(|| anyhow!("Missing end of image"))?;
+
+            out.extend_from_slice(&data[pos..pos + eoi + 2]);
+            break;
+        }
+    }
+
+    Ok(out)
+}
+
+/// Minimal big-endian EXIF APP1 segment containing just an orientation tag.
+fn orientation_exif_segment(orientation: Orientation) -> Vec<u8> {
+    let mut tiff = Vec::new();
+    tiff.extend_from_slice(b"MM\x00\x2a"); // big-endian TIFF header
+    tiff.extend_from_slice(&8u32.to_be_bytes()); // offset of first IFD
+    tiff.extend_from_slice(&1u16.to_be_bytes()); // one IFD entry
+    tiff.extend_from_slice(&0x0112u16.to_be_bytes()); // orientation tag
+    tiff.extend_from_slice(&3u16.to_be_bytes()); // SHORT
+    tiff.extend_from_slice(&1u32.to_be_bytes()); // count
+    tiff.extend_from_slice(&(orientation as u16).to_be_bytes());
+    tiff.extend_from_slice(&[0, 0]); // value padding
+    tiff.extend_from_slice(&0u32.to_be_bytes()); // no next IFD
+
+    let mut segment = vec![0xFF, 0xE1];
+    let len = (2 + 6 + tiff.len()) as u16;
+    segment.extend_from_slice(&len.to_be_bytes());
+    segment.extend_from_slice(b"Exif\0\0");
+    segment.extend_from_slice(&tiff);
+    segment
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_jpeg_metadata() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let file = Path::new(dir).join("resources/test/Dandelion.jpg");
+        let data = fs::read(file).unwrap();
+
+        let stripped = strip_jpeg_metadata(&data, Orientation::North).unwrap();
+        assert!(stripped.len() < data.len());
+
+        let exif = exif::Reader::new().read_from_container(&mut std::io::Cursor::new(&stripped));
+        assert!(exif.is_err());
+
+        let image = image::load_from_memory(&stripped).unwrap();
+        let original = image::load_from_memory(&data).unwrap();
+        assert_eq!(original.width(), image.width());
+        assert_eq!(original.height(), image.height());
+    }
+
+    #[test]
+    fn test_strip_jpeg_metadata_keeps_orientation() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let file = Path::new(dir).join("resources/test/Dandelion.jpg");
+        let data = fs::read(file).unwrap();
+
+        let stripped = strip_jpeg_metadata(&data, Orientation::West).unwrap();
+
+        let exif = exif::Reader::new()
+            .read_from_container(&mut std::io::Cursor::new(&stripped))
+            .unwrap();
+        let orientation = exif
+            .get_field(exif::Tag::Orientation, exif::In::PRIMARY)
+            .and_then(|x| x.value.get_uint(0));
+        assert_eq!(Some(6), orientation);
+        assert!(exif
+            .get_field(exif::Tag::GPSLatitude, exif::In::PRIMARY)
+            .is_none());
+    }
+
+    #[test]
+    fn test_to_jpeg_applies_orientation_override() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let file = Path::new(dir).join("resources/test/Dandelion.jpg");
+        let original = image::open(&file).unwrap();
+
+        let target_dir = tempfile::tempdir().unwrap();
+        let exporter = Exporter::build(target_dir.path(), ExportMode::StripMetadata).unwrap();
+
+        // User corrected an upright photo to be a quarter turn clockwise.
+        let orientation = Orientation::North.then(Orientation::West);
+        let exported = gdk4::glib::MainContext::new()
+            .block_on(exporter.to_jpeg(&file, orientation, None, STRIP_METADATA_QUALITY))
+            .unwrap();
+
+        let image = image::open(exported).unwrap();
+        assert_eq!(original.height(), image.width());
+        assert_eq!(original.width(), image.height());
+    }
+}
@@ -0,0 +1,536 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Import photos and videos from a camera, phone, or SD card into the library.
+/// Files are copied into folders named after the capture date and files that have
+/// already been imported are skipped.
+use crate::path_encoding;
+use crate::photo;
+use crate::video;
+use anyhow::*;
+use chrono::prelude::*;
+use rusqlite::params;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io::{BufReader, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tracing::{debug, error};
+use walkdir::WalkDir;
+
+/// Default pattern for the path of imported files relative to the library.
+pub const DEFAULT_PATTERN: &str = "{year}/{month}/{filename}";
+
+/// Pattern for the path of an imported file relative to the library base path.
+///
+/// Supported placeholders:
+/// * `{year}` - four digit capture year.
+/// * `{month}` - two digit capture month.
+/// * `{day}` - two digit capture day of month.
+/// * `{filename}` - original file name, including extension.
+/// * `{stem}` - original file name without extension.
+/// * `{ext}` - original file extension.
+#[derive(Debug, Clone)]
+pub struct ImportPattern(String);
+
+impl ImportPattern {
+    pub fn new(pattern: &str) -> Result<Self> {
+        let mut pattern = pattern.trim().trim_matches('/').to_string();
+
+        if !pattern.contains("{filename}") && !pattern.contains("{stem}") {
+            // Always keep the original file name so live photo pairs stay together.
+            if pattern.is_empty() {
+                pattern = String::from("{filename}");
+            } else {
+                pattern.push_str("/{filename}");
+            }
+        }
+
+        let pattern = ImportPattern(pattern);
+
+        // Check the pattern can't escape the library directory.
+        let example = pattern.expand(Local::now().fixed_offset(), Path::new("example.jpg"));
+        if example
+            .components()
+            .any(|c| !matches!(c, Component::Normal(_)))
+        {
+            bail!("Import pattern must be a relative path: {}", pattern.0);
+        }
+
+        Ok(pattern)
+    }
+
+    /// Path relative to library for a file captured at a given time.
+    pub fn expand(&self, captured_at: DateTime<FixedOffset>, path: &Path) -> PathBuf {
+        let file_name = path
+            .file_name()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let stem = path
+            .file_stem()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let ext = path
+            .extension()
+            .map(|x| x.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let expanded = self
+            .0
+            .replace("{year}", &format!("{:04}", captured_at.year()))
+            .replace("{month}", &format!("{:02}", captured_at.month()))
+            .replace("{day}", &format!("{:02}", captured_at.day()))
+            .replace("{filename}", &file_name)
+            .replace("{stem}", &stem)
+            .replace("{ext}", &ext);
+
+        PathBuf::from(expanded)
+    }
+}
+
+impl Default for ImportPattern {
+    fn default() -> Self {
+        ImportPattern(String::from(DEFAULT_PATTERN))
+    }
+}
+
+/// Files to import together. Usually a single photo or video, but the photo and
+/// video of a live photo, and a photo and its voice note, are imported together
+/// so they stay paired in the library.
+#[derive(Debug, Clone)]
+pub struct ImportItem {
+    /// Photo, if any, is always first.
+    pub paths: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Importer {
+    /// Base path to picture library on file system
+    library_base_path: PathBuf,
+
+    pattern: ImportPattern,
+
+    /// Connection to backing Sqlite database.
+    con: Arc<Mutex<rusqlite::Connection>>,
+}
+
+impl Importer {
+    pub fn build(
+        library_base_path: &Path,
+        pattern: ImportPattern,
+        con: Arc<Mutex<rusqlite::Connection>>,
+    ) -> Result<Importer> {
+        if !library_base_path.is_dir() {
+            bail!("{:?} is not a directory", library_base_path);
+        }
+
+        Ok(Importer {
+            library_base_path: PathBuf::from(library_base_path),
+            pattern,
+            con,
+        })
+    }
+
+    /// Find photos and videos to import under a source directory, such as the DCIM
+    /// folder of a mounted camera.
+    pub fn scan(&self, source: &Path) -> Result<Vec<ImportItem>> {
+        // Group files by folder and file stem so live photo pairs are kept together.
+        let mut groups: HashMap<(PathBuf, String), Vec<PathBuf>> = HashMap::new();
+
+        WalkDir::new(source)
+            .sort_by_file_name()
+            .into_iter()
+            .inspect(|x| {
+                let _ = x
+                    .as_ref()
+                    .inspect_err(|e| error!("Failed walking: {:?}", e));
+            })
+            .flatten()
+            .filter(|x| x.path().is_file())
+            .filter(|x| {
+                photo::Scanner::is_supported(x.path())
+                    || photo::Scanner::is_voice_note(x.path())
+                    || video::Scanner::is_supported(x.path())
+            })
+            .for_each(|x| {
+                let path = x.path();
+                let parent = path.parent().map(PathBuf::from).unwrap_or_default();
+                let stem = path
+                    .file_stem()
+                    .map(|s| s.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                groups.entry((parent, stem)).or_default().push(PathBuf::from(path));
+            });
+
+        let mut items: Vec<ImportItem> = groups
+            .into_values()
+            .flat_map(|paths| {
+                let (notes, paths): (Vec<PathBuf>, Vec<PathBuf>) = paths
+                    .into_iter()
+                    .partition(|p| photo::Scanner::is_voice_note(p));
+
+                let (photos, videos): (Vec<PathBuf>, Vec<PathBuf>) = paths
+                    .into_iter()
+                    .partition(|p| photo::Scanner::is_supported(p));
+
+                if photos.len() == 1 && videos.len() <= 1 {
+                    vec![ImportItem {
+                        paths: photos.into_iter().chain(videos).chain(notes).collect(),
+                    }]
+                } else {
+                    // Voice notes are only imported with the single photo they annotate.
+                    photos
+                        .into_iter()
+                        .chain(videos)
+                        .map(|p| ImportItem { paths: vec![p] })
+                        .collect()
+                }
+            })
+            .collect();
+
+        items.sort_by(|a, b| a.paths[0].cmp(&b.paths[0]));
+
+        Ok(items)
+    }
+
+    /// Copy files into library. Returns paths of files in library that have been
+    /// added. Files that have already been imported are skipped.
+    pub fn import(&self, item: &ImportItem) -> Result<Vec<PathBuf>> {
+        let hashes = item
+            .paths
+            .iter()
+            .map(|p| content_hash(p))
+            .collect::<Result<Vec<String>>>()?;
+
+        let previous = hashes
+            .iter()
+            .map(|h| self.find_imported(h))
+            .collect::<Result<Vec<Option<PathBuf>>>>()?;
+
+        if previous.iter().all(|x| x.is_some()) {
+            debug!("Already imported: {:?}", item.paths);
+            return Ok(Vec::new());
+        }
+
+        // If part of a live photo pair was imported previously, then put the rest
+        // of the pair next to it.
+        let target_stem_path = if let Some(existing) = previous.iter().flatten().next() {
+            strip_extension(existing, existing.extension())
+        } else {
+            let captured_at = capture_time(&item.paths[0]);
+            let target = self.pattern.expand(captured_at, &item.paths[0]);
+            let target = self.library_base_path.join(target);
+            let target = strip_extension(&target, item.paths[0].extension());
+            self.unique_stem_path(&target, &item.paths)
+        };
+
+        let mut imported = Vec::new();
+
+        for ((path, hash), previous) in item.paths.iter().zip(hashes).zip(previous) {
+            if previous.is_some() {
+                continue;
+            }
+
+            let target_path = file_path(&target_stem_path, path);
+
+            if let Some(parent) = target_path.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            debug!("Importing {:?} to {:?}", path, target_path);
+
+            // Copy to temporary file first and then move so that an interrupted copy
+            // doesn't leave a partial file in the library.
+            let temporary_path = target_path.with_extension("import.tmp");
+            fs::copy(path, &temporary_path)?;
+            fs::rename(&temporary_path, &target_path)?;
+
+            self.add_imported(&hash, &target_path)?;
+            imported.push(target_path);
+        }
+
+        Ok(imported)
+    }
+
+    /// Path with file stem changed so that no file of the item would overwrite an existing file.
+    fn unique_stem_path(&self, stem_path: &Path, paths: &[PathBuf]) -> PathBuf {
+        let exists = |candidate: &Path| paths.iter().any(|p| file_path(candidate, p).exists());
+
+        let mut candidate = PathBuf::from(stem_path);
+        let mut count = 1;
+        while exists(&candidate) {
+            let stem = stem_path
+                .file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+            candidate = stem_path.with_file_name(format!("{} ({})", stem, count));
+            count += 1;
+        }
+        candidate
+    }
+
+    fn find_imported(&self, hash: &str) -> Result<Option<PathBuf>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "SELECT library_path_b64
+            FROM imports
+            WHERE content_hash = ?1",
+        )?;
+
+        let mut rows = stmt.query(params![hash])?;
+        let Some(row) = rows.next()? else {
+            return Ok(None);
+        };
+
+        let path: String = row.get(0)?;
+        let path = path_encoding::from_base64(&path)?;
+        Ok(Some(self.library_base_path.join(path)))
+    }
+
+    fn add_imported(&self, hash: &str, library_path: &Path) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "INSERT INTO imports (
+                content_hash,
+                library_path_b64,
+                library_path_lossy,
+                imported_ts
+            ) VALUES (
+                ?1, ?2, ?3, ?4
+            ) ON CONFLICT (content_hash) DO NOTHING",
+        )?;
+
+        // convert to relative path before saving to database
+        let path = library_path.strip_prefix(&self.library_base_path)?;
+        let path_b64 = path_encoding::to_base64(path);
+
+        stmt.execute(params![
+            hash,
+            path_b64,
+            path.to_string_lossy(),
+            Utc::now()
+        ])?;
+
+        Ok(())
+    }
+}
+
+/// Path with an extension removed from the end of the file name. Other dots in the
+/// file name, such as in `PXL_20240101_120000000.MP.jpg`, are kept.
+fn strip_extension(path: &Path, extension: Option<&OsStr>) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .map(|x| x.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let stem = extension
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .and_then(|ext| file_name.strip_suffix(&ext))
+        .unwrap_or(&file_name);
+
+    path.with_file_name(stem)
+}
+
+/// Path in library of a file of an import item, which is the stem path of the
+/// item followed by the extension of the file.
+fn file_path(stem_path: &Path, path: &Path) -> PathBuf {
+    let mut file_name = stem_path
+        .file_name()
+        .map(OsString::from)
+        .unwrap_or_default();
+
+    if let Some(ext) = path.extension() {
+        file_name.push(".");
+        file_name.push(ext);
+    }
+
+    stem_path.with_file_name(file_name)
+}
+
+/// SHA-256 of file content as a hex string.
+fn content_hash(path: &Path) -> Result<String> {
+    let file = fs::File::open(path)?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+
+    let hash = hasher
+        .finalize()
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect();
+
+    Ok(hash)
+}
+
+/// Best guess at when a photo or video was captured, in the local time of the camera
+/// if known. Prefers embedded metadata over file system timestamps.
+fn capture_time(path: &Path) -> DateTime<FixedOffset> {
+    let embedded = if photo::Scanner::is_supported(path) {
+        photo::metadata::from_path(path)
+            .ok()
+            .and_then(|m| m.created_at)
+    } else {
+        video::metadata::from_path(path)
+            .ok()
+            .and_then(|m| m.created_at)
+            .map(|x| x.with_timezone(&Local).fixed_offset())
+    };
+
+    embedded
+        .or_else(|| {
+            fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(|x| DateTime::<Local>::from(x).fixed_offset())
+        })
+        .unwrap_or_else(|| Local::now().fixed_offset())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::database;
+
+    #[test]
+    fn test_pattern_expand() {
+        let pattern = ImportPattern::new("{year}/{month}/{day}").unwrap();
+        let ts = DateTime::parse_from_rfc3339("2024-03-07T10:00:00+10:00").unwrap();
+        let path = pattern.expand(ts, Path::new("/media/DCIM/IMG_0001.JPG"));
+        assert_eq!(PathBuf::from("2024/03/07/IMG_0001.JPG"), path);
+    }
+
+    #[test]
+    fn test_pattern_rejects_parent_dir() {
+        assert!(ImportPattern::new("../{filename}").is_err());
+    }
+
+    #[test]
+    fn test_import_skips_duplicates() {
+        let source = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+
+        fs::write(source.path().join("IMG_0001.JPG"), b"photo").unwrap();
+        fs::write(source.path().join("IMG_0001.MOV"), b"video").unwrap();
+        fs::write(source.path().join("IMG_0002.JPG"), b"another photo").unwrap();
+
+        let con = database::setup_in_memory().unwrap();
+        let con = Arc::new(Mutex::new(con));
+
+        let importer =
+            Importer::build(library.path(), ImportPattern::new("{filename}").unwrap(), con)
+                .unwrap();
+
+        let items = importer.scan(source.path()).unwrap();
+        assert_eq!(2, items.len());
+        assert_eq!(2, items[0].paths.len()); // live photo pair
+
+        let imported: Vec<PathBuf> = items
+            .iter()
+            .flat_map(|item| importer.import(item).unwrap())
+            .collect();
+        assert_eq!(3, imported.len());
+        assert!(library.path().join("IMG_0001.MOV").exists());
+
+        let imported_again: Vec<PathBuf> = items
+            .iter()
+            .flat_map(|item| importer.import(item).unwrap())
+            .collect();
+        assert!(imported_again.is_empty());
+    }
+
+    #[test]
+    fn test_import_keeps_voice_note_with_photo() {
+        let source = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+
+        fs::write(source.path().join("P1010001.JPG"), b"photo").unwrap();
+        fs::write(source.path().join("P1010001.WAV"), b"voice note").unwrap();
+        fs::write(source.path().join("P1010002.WAV"), b"lonely voice note").unwrap();
+
+        let con = database::setup_in_memory().unwrap();
+        let con = Arc::new(Mutex::new(con));
+
+        let importer =
+            Importer::build(library.path(), ImportPattern::new("{filename}").unwrap(), con)
+                .unwrap();
+
+        let items = importer.scan(source.path()).unwrap();
+        assert_eq!(1, items.len());
+        assert_eq!(
+            vec![
+                source.path().join("P1010001.JPG"),
+                source.path().join("P1010001.WAV")
+            ],
+            items[0].paths
+        );
+
+        let imported = importer.import(&items[0]).unwrap();
+        assert_eq!(2, imported.len());
+        assert!(library.path().join("P1010001.WAV").exists());
+    }
+
+    #[test]
+    fn test_import_does_not_overwrite_existing_file() {
+        let source = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+
+        fs::write(source.path().join("IMG_0001.JPG"), b"photo").unwrap();
+        fs::write(library.path().join("IMG_0001.JPG"), b"different photo").unwrap();
+
+        let con = database::setup_in_memory().unwrap();
+        let con = Arc::new(Mutex::new(con));
+
+        let importer =
+            Importer::build(library.path(), ImportPattern::new("{filename}").unwrap(), con)
+                .unwrap();
+
+        let items = importer.scan(source.path()).unwrap();
+        let imported = importer.import(&items[0]).unwrap();
+
+        assert_eq!(vec![library.path().join("IMG_0001 (1).JPG")], imported);
+        assert_eq!(
+            b"different photo".to_vec(),
+            fs::read(library.path().join("IMG_0001.JPG")).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_import_keeps_dots_in_file_name() {
+        let source = tempfile::tempdir().unwrap();
+        let library = tempfile::tempdir().unwrap();
+
+        fs::write(source.path().join("PXL_20240101_120000000.MP.jpg"), b"photo").unwrap();
+        fs::write(library.path().join("PXL_20240101_120000000.MP.jpg"), b"other").unwrap();
+        fs::write(library.path().join("PXL_20240101_120000000.jpg"), b"another").unwrap();
+
+        let con = database::setup_in_memory().unwrap();
+        let con = Arc::new(Mutex::new(con));
+
+        let importer =
+            Importer::build(library.path(), ImportPattern::new("{filename}").unwrap(), con)
+                .unwrap();
+
+        let items = importer.scan(source.path()).unwrap();
+        let imported = importer.import(&items[0]).unwrap();
+
+        assert_eq!(
+            vec![library.path().join("PXL_20240101_120000000.MP (1).jpg")],
+            imported
+        );
+        assert_eq!(
+            b"another".to_vec(),
+            fs::read(library.path().join("PXL_20240101_120000000.jpg")).unwrap()
+        );
+    }
+}
@@ -3,8 +3,11 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 pub mod database;
+pub mod export;
+pub mod import;
 pub mod path_encoding;
 pub mod photo;
+pub mod thumbnail;
 pub mod time;
 pub mod video;
 pub mod visual;
@@ -0,0 +1,636 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Non-destructive photo edits. Edits are kept as a stack of operations in the database
+//! and applied when a photo is rendered. The original file is never modified.
+
+use super::model::Orientation;
+use anyhow::*;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::io::Reader as ImageReader;
+use image::{DynamicImage, RgbaImage};
+
+use gdk4::prelude::{TextureExt, TextureExtManual};
+use std::fmt::Display;
+use std::fs;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use strum::EnumIter;
+
+/// JPEG quality for copies of edited photos.
+const COPY_QUALITY: u8 = 92;
+
+/// Longest edge of previews of edits, which are rendered while an adjustment is changing.
+const PREVIEW_EDGE: u32 = 1280;
+
+/// A rectangle with coordinates and size as a fraction of the width and height of an image.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CropRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl CropRect {
+    /// Largest rectangle with an aspect ratio that fits centred in an image.
+    /// The ratio is swapped for portrait images so the crop follows the image shape.
+    pub fn centred(aspect: CropAspect, image_width: u32, image_height: u32) -> Self {
+        let Some(ratio) = aspect.ratio() else {
+            return Self::full();
+        };
+
+        let image_width = image_width.max(1) as f32;
+        let image_height = image_height.max(1) as f32;
+        let ratio = if image_height > image_width { 1.0 / ratio } else { ratio };
+
+        let (width, height) = if image_width / image_height > ratio {
+            (image_height * ratio, image_height)
+        } else {
+            (image_width, image_width / ratio)
+        };
+
+        let width = width / image_width;
+        let height = height / image_height;
+
+        Self {
+            x: (1.0 - width) / 2.0,
+            y: (1.0 - height) / 2.0,
+            width,
+            height,
+        }
+    }
+
+    pub fn full() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        }
+    }
+
+    /// Map rectangle onto an image that has had an orientation transformation applied.
+    pub fn transform(&self, orientation: Orientation) -> Self {
+        let (flip, turns) = orientation.to_flip_and_turns();
+
+        let mut rect = if flip {
+            Self {
+                x: 1.0 - self.x - self.width,
+                ..*self
+            }
+        } else {
+            *self
+        };
+
+        for _ in 0..turns {
+            rect = Self {
+                x: 1.0 - rect.y - rect.height,
+                y: rect.x,
+                width: rect.height,
+                height: rect.width,
+            };
+        }
+
+        rect
+    }
+
+    /// Crop an image, keeping at least one pixel.
+    fn crop(&self, image: DynamicImage) -> DynamicImage {
+        let (width, height) = (image.width() as f32, image.height() as f32);
+        let x = (self.x.clamp(0.0, 1.0) * width).round() as u32;
+        let y = (self.y.clamp(0.0, 1.0) * height).round() as u32;
+        let w = (self.width.clamp(0.0, 1.0) * width).round() as u32;
+        let h = (self.height.clamp(0.0, 1.0) * height).round() as u32;
+        let x = x.min(image.width() - 1);
+        let y = y.min(image.height() - 1);
+        let w = w.clamp(1, image.width() - x);
+        let h = h.clamp(1, image.height() - y);
+        image.crop_imm(x, y, w, h)
+    }
+}
+
+/// Aspect ratio presets for cropping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter)]
+pub enum CropAspect {
+    /// Remove crop
+    Original,
+    Square,
+    FourThree,
+    ThreeTwo,
+    SixteenNine,
+}
+
+impl CropAspect {
+    /// Width divided by height for landscape images.
+    pub fn ratio(&self) -> Option<f32> {
+        match self {
+            CropAspect::Original => None,
+            CropAspect::Square => Some(1.0),
+            CropAspect::FourThree => Some(4.0 / 3.0),
+            CropAspect::ThreeTwo => Some(3.0 / 2.0),
+            CropAspect::SixteenNine => Some(16.0 / 9.0),
+        }
+    }
+}
+
+/// A single edit operation.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Edit {
+    RotateClockwise,
+    RotateAntiClockwise,
+    FlipHorizontal,
+    FlipVertical,
+
+    /// Crop relative to the image as displayed when the crop was applied.
+    Crop(CropRect),
+
+    /// Exposure adjustment in stops. From -2.0 to 2.0.
+    Exposure(f32),
+
+    /// Contrast adjustment. From -1.0 to 1.0.
+    Contrast(f32),
+
+    /// Saturation adjustment. From -1.0 (greyscale) to 1.0.
+    Saturation(f32),
+}
+
+impl Edit {
+    /// Edits that replace any earlier edit of the same kind instead of building on it.
+    fn is_absolute(&self) -> bool {
+        matches!(
+            self,
+            Edit::Crop(_) | Edit::Exposure(_) | Edit::Contrast(_) | Edit::Saturation(_)
+        )
+    }
+
+    fn is_same_kind(&self, other: &Edit) -> bool {
+        std::mem::discriminant(self) == std::mem::discriminant(other)
+    }
+}
+
+impl Display for Edit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Edit::RotateClockwise => write!(f, "rotate-cw"),
+            Edit::RotateAntiClockwise => write!(f, "rotate-ccw"),
+            Edit::FlipHorizontal => write!(f, "flip-h"),
+            Edit::FlipVertical => write!(f, "flip-v"),
+            Edit::Crop(r) => write!(f, "crop {} {} {} {}", r.x, r.y, r.width, r.height),
+            Edit::Exposure(v) => write!(f, "exposure {}", v),
+            Edit::Contrast(v) => write!(f, "contrast {}", v),
+            Edit::Saturation(v) => write!(f, "saturation {}", v),
+        }
+    }
+}
+
+impl FromStr for Edit {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut parts = s.split_whitespace();
+        let name = parts.next().ok_or_else(|| anyhow!("Empty edit"))?;
+        let values = parts
+            .map(|x| x.parse::<f32>())
+            .collect::<std::result::Result<Vec<f32>, _>>()?;
+
+        let edit = match (name, values.as_slice()) {
+            ("rotate-cw", []) => Edit::RotateClockwise,
+            ("rotate-ccw", []) => Edit::RotateAntiClockwise,
+            ("flip-h", []) => Edit::FlipHorizontal,
+            ("flip-v", []) => Edit::FlipVertical,
+            ("crop", [x, y, width, height]) => Edit::Crop(CropRect {
+                x: *x,
+                y: *y,
+                width: *width,
+                height: *height,
+            }),
+            ("exposure", [v]) => Edit::Exposure(*v),
+            ("contrast", [v]) => Edit::Contrast(*v),
+            ("saturation", [v]) => Edit::Saturation(*v),
+            _ => bail!("Invalid edit: {}", s),
+        };
+
+        Ok(edit)
+    }
+}
+
+/// Net effect of an edit stack.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Adjustments {
+    /// Rotations and flips combined.
+    pub orientation: Orientation,
+
+    /// Crop relative to the image after the orientation has been applied.
+    pub crop: Option<CropRect>,
+
+    pub exposure: f32,
+    pub contrast: f32,
+    pub saturation: f32,
+}
+
+impl Adjustments {
+    /// Do the adjustments change pixels, as opposed to only the orientation?
+    pub fn has_pixel_changes(&self) -> bool {
+        self.crop.is_some() || self.exposure != 0.0 || self.contrast != 0.0 || self.saturation != 0.0
+    }
+}
+
+/// Ordered stack of edits for a photo.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EditStack {
+    edits: Vec<Edit>,
+}
+
+impl EditStack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.edits.is_empty()
+    }
+
+    pub fn edits(&self) -> &[Edit] {
+        &self.edits
+    }
+
+    /// Add an edit. Crops and tonal adjustments replace earlier edits of the same kind.
+    /// Tonal adjustments of zero and crops to the full image remove the earlier edit.
+    pub fn push(&mut self, edit: Edit) {
+        if edit.is_absolute() {
+            self.edits.retain(|e| !e.is_same_kind(&edit));
+        }
+
+        let is_noop = match edit {
+            Edit::Exposure(v) | Edit::Contrast(v) | Edit::Saturation(v) => v == 0.0,
+            Edit::Crop(rect) => rect == CropRect::full(),
+            _ => false,
+        };
+
+        if !is_noop {
+            self.edits.push(edit);
+        }
+    }
+
+    /// Remove most recent edit.
+    pub fn undo(&mut self) -> Option<Edit> {
+        self.edits.pop()
+    }
+
+    pub fn clear(&mut self) {
+        self.edits.clear();
+    }
+
+    /// Reduce the edit stack to its net effect.
+    pub fn adjustments(&self) -> Adjustments {
+        let mut adjustments = Adjustments {
+            orientation: Orientation::North,
+            crop: None,
+            exposure: 0.0,
+            contrast: 0.0,
+            saturation: 0.0,
+        };
+
+        for edit in &self.edits {
+            let transform = match edit {
+                Edit::RotateClockwise => Some(Orientation::West),
+                Edit::RotateAntiClockwise => Some(Orientation::East),
+                Edit::FlipHorizontal => Some(Orientation::NorthMirrored),
+                Edit::FlipVertical => Some(Orientation::SouthMirrored),
+                Edit::Crop(rect) => {
+                    adjustments.crop = Some(*rect);
+                    None
+                }
+                Edit::Exposure(v) => {
+                    adjustments.exposure = *v;
+                    None
+                }
+                Edit::Contrast(v) => {
+                    adjustments.contrast = *v;
+                    None
+                }
+                Edit::Saturation(v) => {
+                    adjustments.saturation = *v;
+                    None
+                }
+            };
+
+            if let Some(transform) = transform {
+                adjustments.orientation = adjustments.orientation.then(transform);
+                adjustments.crop = adjustments.crop.map(|c| c.transform(transform));
+            }
+        }
+
+        adjustments
+    }
+
+    /// Apply edits to an image, including the orientation of the original image,
+    /// so the result can be displayed as-is.
+    pub fn render(&self, image: DynamicImage, orientation: Orientation) -> DynamicImage {
+        let adjustments = self.adjustments();
+        let orientation = orientation.then(adjustments.orientation);
+        let image = orientation.apply_to(image);
+        let image = match adjustments.crop {
+            Some(crop) => crop.crop(image),
+            None => image,
+        };
+        apply_tone(image, &adjustments)
+    }
+
+    /// Apply crop and tonal edits without changing the orientation of the image.
+    /// The result must be displayed with the orientation returned by `display_orientation`,
+    /// such as with the orientation CSS classes.
+    pub fn render_unoriented(&self, image: DynamicImage, orientation: Orientation) -> DynamicImage {
+        let adjustments = self.adjustments();
+        let display_orientation = orientation.then(adjustments.orientation);
+        let image = match adjustments.crop {
+            Some(crop) => crop.transform(display_orientation.inverse()).crop(image),
+            None => image,
+        };
+        apply_tone(image, &adjustments)
+    }
+
+    /// Orientation to display the original image with after edits have been applied.
+    pub fn display_orientation(&self, orientation: Orientation) -> Orientation {
+        orientation.then(self.adjustments().orientation)
+    }
+
+    /// Apply crop and tonal edits to a texture, such as one decoded by Glycin.
+    pub fn render_texture(&self, texture: &gdk4::Texture, orientation: Orientation) -> gdk4::Texture {
+        if !self.adjustments().has_pixel_changes() {
+            return texture.clone();
+        }
+
+        let image = DynamicImage::ImageRgba8(texture_to_image(texture));
+        let image = self.render_unoriented(image, orientation).into_rgba8();
+        image_to_texture(image)
+    }
+
+    /// Write an edited copy of a photo as a JPEG next to the original.
+    /// Returns the path of the new file.
+    pub async fn save_copy(&self, path: &Path, orientation: Orientation) -> Result<PathBuf> {
+        let image = load_source(path).await?;
+        let image = self.render(image, orientation);
+
+        let target_path = copy_path(path)?;
+
+        let file = fs::File::create(&target_path)?;
+        let mut file = BufWriter::new(file);
+
+        // JPEG doesn't support an alpha channel.
+        let image = DynamicImage::ImageRgb8(image.into_rgb8());
+        JpegEncoder::new_with_quality(&mut file, COPY_QUALITY).encode_image(&image)?;
+
+        file.flush()?;
+        Ok(target_path)
+    }
+}
+
+impl Display for EditStack {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let edits: Vec<String> = self.edits.iter().map(|e| e.to_string()).collect();
+        write!(f, "{}", edits.join("\n"))
+    }
+}
+
+impl FromStr for EditStack {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let edits = s
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(Edit::from_str)
+            .collect::<Result<Vec<Edit>>>()?;
+        Ok(Self { edits })
+    }
+}
+
+/// Downscaled copy of a photo for quickly showing the effect of edits, such as while
+/// a slider is being dragged. Rendering is slow enough that it should be done off the
+/// main thread.
+#[derive(Clone)]
+pub struct EditPreview {
+    image: DynamicImage,
+}
+
+impl std::fmt::Debug for EditPreview {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EditPreview")
+            .field("width", &self.image.width())
+            .field("height", &self.image.height())
+            .finish()
+    }
+}
+
+impl EditPreview {
+    pub fn new(texture: &gdk4::Texture) -> Self {
+        let image = DynamicImage::ImageRgba8(texture_to_image(texture));
+        Self {
+            image: image.thumbnail(PREVIEW_EDGE, PREVIEW_EDGE),
+        }
+    }
+
+    /// Apply crop and tonal edits. As with `EditStack::render_texture`, the result must
+    /// be displayed with the orientation returned by `EditStack::display_orientation`.
+    pub fn render_texture(&self, edits: &EditStack, orientation: Orientation) -> gdk4::Texture {
+        let image = edits.render_unoriented(self.image.clone(), orientation);
+        image_to_texture(image.into_rgba8())
+    }
+}
+
+/// Decode image without applying any orientation.
+/// Falls back to Glycin for formats image-rs can't decode, such as HEIC.
+pub async fn load_source(path: &Path) -> Result<DynamicImage> {
+    if let std::result::Result::Ok(image) = ImageReader::open(path)?.decode() {
+        return Ok(image);
+    }
+
+    let file = gio::File::for_path(path);
+    let image = glycin::Loader::new(file).load().await?;
+    let frame = image.next_frame().await?;
+
+    Ok(DynamicImage::ImageRgba8(texture_to_image(&frame.texture)))
+}
+
+/// Copy texture pixels into an RGBA image.
+fn texture_to_image(texture: &gdk4::Texture) -> RgbaImage {
+    let width = texture.width() as u32;
+    let height = texture.height() as u32;
+    let stride = width as usize * 4;
+
+    // Texture downloads are in the default GDK memory format, which is premultiplied
+    // BGRA on little-endian machines. Photos are opaque so premultiplication can be ignored.
+    let mut data = vec![0u8; stride * height as usize];
+    texture.download(&mut data, stride);
+
+    for pixel in data.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+
+    RgbaImage::from_raw(width, height, data).expect("Buffer must match dimensions")
+}
+
+/// Copy RGBA image into a texture.
+fn image_to_texture(image: RgbaImage) -> gdk4::Texture {
+    let (width, height) = image.dimensions();
+    let bytes = gdk4::glib::Bytes::from_owned(image.into_raw());
+    gdk4::MemoryTexture::new(
+        width as i32,
+        height as i32,
+        gdk4::MemoryFormat::R8g8b8a8,
+        &bytes,
+        width as usize * 4,
+    )
+    .into()
+}
+
+/// Apply exposure, contrast, and saturation adjustments.
+fn apply_tone(image: DynamicImage, adjustments: &Adjustments) -> DynamicImage {
+    if adjustments.exposure == 0.0 && adjustments.contrast == 0.0 && adjustments.saturation == 0.0 {
+        return image;
+    }
+
+    // Exposure and contrast are the same for every channel, so precompute a lookup table.
+    let gain = 2f32.powf(adjustments.exposure);
+    let contrast = 1.0 + adjustments.contrast;
+    let lut: Vec<f32> = (0..=255)
+        .map(|v| {
+            let linear = (v as f32 / 255.0).powf(2.2) * gain;
+            let v = linear.powf(1.0 / 2.2);
+            (v - 0.5) * contrast + 0.5
+        })
+        .collect();
+
+    let saturation = 1.0 + adjustments.saturation;
+
+    let mut image = image.into_rgba8();
+    for pixel in image.pixels_mut() {
+        let r = lut[pixel[0] as usize];
+        let g = lut[pixel[1] as usize];
+        let b = lut[pixel[2] as usize];
+        let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+
+        let to_u8 = |v: f32| ((luma + (v - luma) * saturation).clamp(0.0, 1.0) * 255.0).round() as u8;
+        pixel[0] = to_u8(r);
+        pixel[1] = to_u8(g);
+        pixel[2] = to_u8(b);
+    }
+
+    DynamicImage::ImageRgba8(image)
+}
+
+/// Path for a copy of an edited photo that doesn't overwrite an existing file.
+fn copy_path(path: &Path) -> Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| anyhow!("Missing file name: {:?}", path))?;
+
+    let mut target = path.with_file_name(format!("{}_edited.jpg", stem));
+    let mut n = 1;
+    while target.exists() {
+        target = path.with_file_name(format!("{}_edited_{}.jpg", stem, n));
+        n += 1;
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_edit_stack_round_trip() {
+        let mut edits = EditStack::new();
+        edits.push(Edit::RotateClockwise);
+        edits.push(Edit::Crop(CropRect { x: 0.1, y: 0.2, width: 0.5, height: 0.25 }));
+        edits.push(Edit::Exposure(0.5));
+
+        let parsed = EditStack::from_str(&edits.to_string()).unwrap();
+        assert_eq!(edits, parsed);
+    }
+
+    #[test]
+    fn test_push_replaces_tonal_adjustments() {
+        let mut edits = EditStack::new();
+        edits.push(Edit::Contrast(0.5));
+        edits.push(Edit::Contrast(0.25));
+        assert_eq!(edits.edits(), &[Edit::Contrast(0.25)]);
+
+        edits.push(Edit::Contrast(0.0));
+        assert!(edits.is_empty());
+    }
+
+    #[test]
+    fn test_orientation_then() {
+        assert_eq!(Orientation::West.then(Orientation::West), Orientation::South);
+        assert_eq!(Orientation::West.then(Orientation::East), Orientation::North);
+        assert_eq!(Orientation::NorthMirrored.then(Orientation::NorthMirrored), Orientation::North);
+        assert_eq!(Orientation::West.then(Orientation::NorthMirrored), Orientation::WestMirrored);
+
+        for orientation in <Orientation as strum::IntoEnumIterator>::iter() {
+            assert_eq!(orientation.then(orientation.inverse()), Orientation::North);
+        }
+    }
+
+    #[test]
+    fn test_orientation_matches_pixels() {
+        // 2x1 image with a red and a blue pixel.
+        let mut image = RgbaImage::new(2, 1);
+        image.put_pixel(0, 0, image::Rgba([255, 0, 0, 255]));
+        image.put_pixel(1, 0, image::Rgba([0, 0, 255, 255]));
+        let image = DynamicImage::ImageRgba8(image);
+
+        for a in <Orientation as strum::IntoEnumIterator>::iter() {
+            for b in <Orientation as strum::IntoEnumIterator>::iter() {
+                let stepwise = b.apply_to(a.apply_to(image.clone()));
+                let composed = a.then(b).apply_to(image.clone());
+                assert_eq!(stepwise, composed, "{:?} then {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crop_follows_rotation() {
+        let mut edits = EditStack::new();
+        edits.push(Edit::Crop(CropRect { x: 0.0, y: 0.0, width: 0.5, height: 1.0 }));
+        edits.push(Edit::RotateClockwise);
+
+        // Left half becomes the top half after rotating clockwise.
+        let adjustments = edits.adjustments();
+        assert_eq!(adjustments.orientation, Orientation::West);
+        assert_eq!(adjustments.crop, Some(CropRect { x: 0.0, y: 0.0, width: 1.0, height: 0.5 }));
+    }
+
+    #[test]
+    fn test_render_unoriented_matches_render() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_fn(4, 2, |x, y| {
+            image::Rgba([(x * 60) as u8, (y * 120) as u8, 0, 255])
+        }));
+
+        let mut edits = EditStack::new();
+        edits.push(Edit::RotateAntiClockwise);
+        edits.push(Edit::Crop(CropRect { x: 0.0, y: 0.0, width: 1.0, height: 0.5 }));
+
+        let rendered = edits.render(image.clone(), Orientation::NorthMirrored);
+
+        let unoriented = edits.render_unoriented(image, Orientation::NorthMirrored);
+        let unoriented = edits
+            .display_orientation(Orientation::NorthMirrored)
+            .apply_to(unoriented);
+
+        assert_eq!(rendered, unoriented);
+    }
+
+    #[test]
+    fn test_centred_crop() {
+        let rect = CropRect::centred(CropAspect::Square, 200, 100);
+        assert_eq!(rect, CropRect { x: 0.25, y: 0.0, width: 0.5, height: 1.0 });
+    }
+}
@@ -0,0 +1,222 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Write the EXIF orientation tag of a photo file in place.
+
+use super::model::Orientation;
+use anyhow::*;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+
+/// EXIF tag number for orientation.
+const ORIENTATION_TAG: u16 = 0x0112;
+
+/// TIFF field type for an unsigned 16-bit integer.
+const SHORT_TYPE: u16 = 3;
+
+/// Overwrite the EXIF orientation tag of a JPEG or TIFF file.
+/// The file must already have an orientation tag, because adding a tag means rewriting
+/// the EXIF structure and every offset in it. Only the two bytes holding the orientation
+/// are changed, and the file is replaced atomically.
+pub fn write(path: &Path, orientation: Orientation) -> Result<()> {
+    let mut data = fs::read(path)?;
+
+    let (offset, is_big_endian) = find_orientation_value(&data)?;
+
+    let value = orientation as u16;
+    let bytes = if is_big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    data[offset..offset + 2].copy_from_slice(&bytes);
+
+    let dir = path
+        .parent()
+        .ok_or_else(|| anyhow!("No parent directory: {:?}", path))?;
+
+    let mut file = tempfile::NamedTempFile::new_in(dir)?;
+    file.write_all(&data)?;
+    file.flush()?;
+
+    // Keep original file permissions.
+    let permissions = fs::metadata(path)?.permissions();
+    fs::set_permissions(file.path(), permissions)?;
+
+    file.persist(path)?;
+    Ok(())
+}
+
+/// Find offset of the orientation value and whether the EXIF data is big-endian.
+fn find_orientation_value(data: &[u8]) -> Result<(usize, bool)> {
+    if data.starts_with(b"II*\0") || data.starts_with(b"MM\0*") {
+        return find_in_tiff(data, 0);
+    }
+
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        bail!("Only JPEG and TIFF files are supported");
+    }
+
+    let mut pos = 2;
+    while pos + 4 <= data.len() {
+        if data[pos] != 0xFF {
+            bail!("Invalid JPEG marker at {}", pos);
+        }
+
+        let marker = data[pos + 1];
+        match marker {
+            // Fill byte
+            0xFF => {
+                pos += 1;
+                continue;
+            }
+            // Markers without a length
+            0x01 | 0xD0..=0xD7 => {
+                pos += 2;
+                continue;
+            }
+            // Start of scan or end of image. EXIF must come before image data.
+            0xDA | 0xD9 => break,
+            _ => {}
+        }
+
+        let len = read_u16(data, pos + 2, true)? as usize;
+        let segment_start = pos + 4;
+
+        if marker == 0xE1 && data[segment_start..].starts_with(b"Exif\0\0") {
+            return find_in_tiff(data, segment_start + 6);
+        }
+
+        pos += 2 + len;
+    }
+
+    bail!("No EXIF data")
+}
+
+/// Find orientation in first image file directory of TIFF structure starting at an offset.
+fn find_in_tiff(data: &[u8], start: usize) -> Result<(usize, bool)> {
+    let is_big_endian = match data.get(start..start + 2) {
+        Some(b"MM") => true,
+        Some(b"II") => false,
+        _ => bail!("Invalid TIFF byte order"),
+    };
+
+    let ifd0 = start + read_u32(data, start + 4, is_big_endian)? as usize;
+    let count = read_u16(data, ifd0, is_big_endian)? as usize;
+
+    for i in 0..count {
+        let entry = ifd0 + 2 + i * 12;
+        if read_u16(data, entry, is_big_endian)? != ORIENTATION_TAG {
+            continue;
+        }
+
+        if read_u16(data, entry + 2, is_big_endian)? != SHORT_TYPE {
+            bail!("Unexpected type for orientation tag");
+        }
+
+        // Values of two bytes or less are stored in the entry itself.
+        let offset = entry + 8;
+        read_u16(data, offset, is_big_endian)?;
+        return Ok((offset, is_big_endian));
+    }
+
+    bail!("No orientation tag")
+}
+
+fn read_u16(data: &[u8], pos: usize, is_big_endian: bool) -> Result<u16> {
+    let bytes: [u8; 2] = data
+        .get(pos..pos + 2)
+        .and_then(|x| x.try_into().ok())
+        .ok_or_else(|| anyhow!("Unexpected end of data"))?;
+
+    Ok(if is_big_endian {
+        u16::from_be_bytes(bytes)
+    } else {
+        u16::from_le_bytes(bytes)
+    })
+}
+
+fn read_u32(data: &[u8], pos: usize, is_big_endian: bool) -> Result<u32> {
+    let bytes: [u8; 4] = data
+        .get(pos..pos + 4)
+        .and_then(|x| x.try_into().ok())
+        .ok_or_else(|| anyhow!("Unexpected end of data"))?;
+
+    Ok(if is_big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// TIFF structure with one orientation entry.
+    fn tiff(is_big_endian: bool, orientation: u16) -> Vec<u8> {
+        let u16_bytes = |v: u16| if is_big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+        let u32_bytes = |v: u32| if is_big_endian { v.to_be_bytes() } else { v.to_le_bytes() };
+
+        let mut data = Vec::new();
+        data.extend_from_slice(if is_big_endian { b"MM" } else { b"II" });
+        data.extend_from_slice(&u16_bytes(42));
+        data.extend_from_slice(&u32_bytes(8));
+        data.extend_from_slice(&u16_bytes(1));
+        data.extend_from_slice(&u16_bytes(ORIENTATION_TAG));
+        data.extend_from_slice(&u16_bytes(SHORT_TYPE));
+        data.extend_from_slice(&u32_bytes(1));
+        data.extend_from_slice(&u16_bytes(orientation));
+        data.extend_from_slice(&[0, 0]);
+        data.extend_from_slice(&u32_bytes(0));
+        data
+    }
+
+    fn jpeg(orientation: u16) -> Vec<u8> {
+        let exif = [b"Exif\0\0".to_vec(), tiff(false, orientation)].concat();
+        let mut data = vec![0xFF, 0xD8, 0xFF, 0xE1];
+        data.extend_from_slice(&((exif.len() + 2) as u16).to_be_bytes());
+        data.extend_from_slice(&exif);
+        data.extend_from_slice(&[0xFF, 0xD9]);
+        data
+    }
+
+    fn read_orientation(path: &Path) -> u32 {
+        let file = fs::File::open(path).unwrap();
+        let mut reader = std::io::BufReader::new(file);
+        let exif = exif::Reader::new().read_from_container(&mut reader).unwrap();
+        let field = exif.get_field(exif::Tag::Orientation, exif::In::PRIMARY).unwrap();
+        field.value.get_uint(0).unwrap()
+    }
+
+    #[test]
+    fn test_write_jpeg() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("photo.jpg");
+        fs::write(&path, jpeg(1)).unwrap();
+
+        write(&path, Orientation::West).unwrap();
+
+        assert_eq!(read_orientation(&path), 6);
+    }
+
+    #[test]
+    fn test_write_big_endian_tiff() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("scan.tif");
+        fs::write(&path, tiff(true, 8)).unwrap();
+
+        write(&path, Orientation::North).unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert_eq!(find_orientation_value(&data).unwrap(), (18, true));
+        assert_eq!(read_u16(&data, 18, true).unwrap(), 1);
+    }
+
+    #[test]
+    fn test_missing_exif() {
+        assert!(find_orientation_value(&[0xFF, 0xD8, 0xFF, 0xD9]).is_err());
+    }
+}
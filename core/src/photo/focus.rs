@@ -0,0 +1,165 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Focus point that thumbnails are cropped about, so that cropping a photo to a square
+//! doesn't cut off the interesting part, such as the head of a person in a portrait shot.
+//!
+//! Without a focus chosen by the user, the focus is found with an edge-energy heuristic.
+//! Detailed regions with strong edges, such as faces and subjects in focus, are usually
+//! more interesting than smooth regions, such as sky, walls, or a blurred background.
+
+use image::{imageops, GrayImage, RgbImage};
+
+/// Longest edge of the image that edge energy is computed on. Small enough to be quick,
+/// but large enough to find a subject.
+const ANALYSIS_EDGE: u32 = 64;
+
+/// Point of interest in an image, as fractions of the width and height of the image
+/// before any orientation is applied. For an edited photo, the image is the cropped photo.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Focus {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Default for Focus {
+    fn default() -> Self {
+        Focus { x: 0.5, y: 0.5 }
+    }
+}
+
+impl Focus {
+    pub fn new(x: f64, y: f64) -> Focus {
+        Focus {
+            x: x.clamp(0.0, 1.0),
+            y: y.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Find the focus of an image with an edge-energy heuristic.
+    /// An image without any detail has its focus in the centre.
+    pub fn detect(image: &RgbImage) -> Focus {
+        let (width, height) = image.dimensions();
+        if width == 0 || height == 0 {
+            return Focus::default();
+        }
+
+        let gray = if width.max(height) > ANALYSIS_EDGE {
+            let scale = ANALYSIS_EDGE as f64 / width.max(height) as f64;
+            let small_width = ((width as f64 * scale).round() as u32).max(1);
+            let small_height = ((height as f64 * scale).round() as u32).max(1);
+            imageops::grayscale(&imageops::thumbnail(image, small_width, small_height))
+        } else {
+            imageops::grayscale(image)
+        };
+
+        Self::detect_gray(&gray)
+    }
+
+    fn detect_gray(gray: &GrayImage) -> Focus {
+        let (width, height) = gray.dimensions();
+        if width < 2 || height < 2 {
+            return Focus::default();
+        }
+
+        // Energy of a pixel is the size of the change in brightness to the neighbouring pixels.
+        let mut energies = Vec::with_capacity(((width - 1) * (height - 1)) as usize);
+        for y in 0..height - 1 {
+            for x in 0..width - 1 {
+                let value = gray.get_pixel(x, y).0[0] as f64;
+                let dx = gray.get_pixel(x + 1, y).0[0] as f64 - value;
+                let dy = gray.get_pixel(x, y + 1).0[0] as f64 - value;
+                energies.push((x, y, dx.abs() + dy.abs()));
+            }
+        }
+
+        // Ignore weak edges, such as noise and texture, by only counting energy above the mean.
+        // Squaring favours regions of strong detail over a scattering of weaker edges.
+        let mean = energies.iter().map(|(_, _, e)| e).sum::<f64>() / energies.len() as f64;
+
+        let mut total = 0.0;
+        let (mut sum_x, mut sum_y) = (0.0, 0.0);
+        for (x, y, energy) in energies {
+            let weight = (energy - mean).max(0.0).powi(2);
+            total += weight;
+            sum_x += weight * (x as f64 + 0.5);
+            sum_y += weight * (y as f64 + 0.5);
+        }
+
+        if total <= 0.0 {
+            return Focus::default();
+        }
+
+        Focus::new(sum_x / total / width as f64, sum_y / total / height as f64)
+    }
+
+    /// Position of the largest crop with the aspect ratio of `dst` from an image of size `src`,
+    /// such that the crop is centred on the focus where possible.
+    /// As with fast_image_resize, (0, 0) is a crop at the top-left and (1, 1) at the bottom-right.
+    pub fn centering(&self, src: (u32, u32), dst: (u32, u32)) -> (f64, f64) {
+        let (src_width, src_height) = (src.0 as f64, src.1 as f64);
+        let (dst_width, dst_height) = (dst.0.max(1) as f64, dst.1.max(1) as f64);
+
+        let crop_width = src_width.min(src_height * dst_width / dst_height);
+        let crop_height = src_height.min(src_width * dst_height / dst_width);
+
+        let centre = |focus: f64, length: f64, crop_length: f64| {
+            let slack = length - crop_length;
+            if slack < 1.0 {
+                0.5
+            } else {
+                ((focus * length - crop_length / 2.0) / slack).clamp(0.0, 1.0)
+            }
+        };
+
+        (
+            centre(self.x, src_width, crop_width),
+            centre(self.y, src_height, crop_height),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Luma, Rgb};
+
+    #[test]
+    fn test_detect_plain_image() {
+        let image = RgbImage::from_pixel(100, 50, Rgb([40, 80, 120]));
+        assert_eq!(Focus::detect(&image), Focus::default());
+    }
+
+    #[test]
+    fn test_detect_detail_near_top() {
+        // Tall smooth image with a checkerboard near the top, like a face above a plain shirt.
+        let mut gray = GrayImage::from_pixel(32, 64, Luma([128]));
+        for y in 8..16 {
+            for x in 12..20 {
+                let value = if (x + y) % 2 == 0 { 0 } else { 255 };
+                gray.put_pixel(x, y, Luma([value]));
+            }
+        }
+
+        let focus = Focus::detect_gray(&gray);
+        assert!((focus.x - 0.5).abs() < 0.05, "x = {}", focus.x);
+        assert!(focus.y < 0.3, "y = {}", focus.y);
+    }
+
+    #[test]
+    fn test_centering() {
+        let centre = Focus::default();
+        assert_eq!(centre.centering((400, 800), (100, 100)), (0.5, 0.5));
+
+        // Crop moves up towards the focus, but not off the image.
+        let top = Focus::new(0.5, 0.1);
+        assert_eq!(top.centering((400, 800), (100, 100)), (0.5, 0.0));
+
+        let upper = Focus::new(0.5, 0.375);
+        assert_eq!(upper.centering((400, 800), (100, 100)), (0.5, 0.25));
+
+        // Crop that fills the image can't move.
+        assert_eq!(top.centering((400, 800), (1, 2)), (0.5, 0.5));
+    }
+}
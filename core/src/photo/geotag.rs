@@ -0,0 +1,319 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Infer the location of photos without GPS data by matching their capture time
+/// against the points of a GPS track recorded by a separate GPS logger.
+use crate::photo::model::{Picture, PictureId};
+use anyhow::*;
+use chrono::{DateTime, TimeDelta, Utc};
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::path::{Path, PathBuf};
+
+/// A point on a GPS track.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TrackPoint {
+    pub time: DateTime<Utc>,
+
+    /// Decimal latitude
+    pub latitude: f64,
+
+    /// Decimal longitude
+    pub longitude: f64,
+
+    /// Elevation in metres
+    pub elevation: Option<f64>,
+}
+
+/// GPS track points in ascending time order.
+#[derive(Debug, Clone, Default)]
+pub struct Track {
+    points: Vec<TrackPoint>,
+}
+
+/// Latitude, longitude, time, and elevation of a track point being parsed.
+type PartialPoint = (f64, f64, Option<DateTime<Utc>>, Option<f64>);
+
+impl Track {
+    /// Load track from a GPX file.
+    pub fn from_gpx_path(path: &Path) -> Result<Track> {
+        let gpx = std::fs::read_to_string(path)?;
+        Self::from_gpx_str(&gpx).with_context(|| format!("GPX path: {:?}", path))
+    }
+
+    /// Parse track from GPX document.
+    /// All track points (trkpt) of all tracks and segments are merged into one track.
+    /// Points without a timestamp are ignored as they cannot be matched to a photo.
+    pub fn from_gpx_str(gpx: &str) -> Result<Track> {
+        let mut reader = Reader::from_str(gpx);
+        reader.trim_text(true);
+
+        let mut points = Vec::new();
+
+        // Track point currently being parsed.
+        let mut current: Option<PartialPoint> = None;
+
+        // Name of element containing the text currently being read.
+        let mut text_element: Option<Vec<u8>> = None;
+
+        loop {
+            match reader.read_event()? {
+                Event::Start(e) if e.local_name().as_ref() == b"trkpt" => {
+                    let lat = e
+                        .try_get_attribute("lat")?
+                        .and_then(|a| a.unescape_value().ok())
+                        .and_then(|v| v.parse::<f64>().ok());
+                    let lon = e
+                        .try_get_attribute("lon")?
+                        .and_then(|a| a.unescape_value().ok())
+                        .and_then(|v| v.parse::<f64>().ok());
+
+                    current = lat.zip(lon).map(|(lat, lon)| (lat, lon, None, None));
+                }
+                Event::Start(e) => {
+                    text_element = Some(e.local_name().as_ref().to_vec());
+                }
+                Event::Text(t) => {
+                    let Some((_, _, ref mut time, ref mut elevation)) = current else {
+                        continue;
+                    };
+                    let text = t.unescape()?;
+                    match text_element.as_deref() {
+                        Some(b"time") => {
+                            *time = DateTime::parse_from_rfc3339(&text)
+                                .ok()
+                                .map(|x| x.to_utc());
+                        }
+                        Some(b"ele") => {
+                            *elevation = text.parse::<f64>().ok();
+                        }
+                        _ => {}
+                    }
+                }
+                Event::End(e) if e.local_name().as_ref() == b"trkpt" => {
+                    if let Some((latitude, longitude, Some(time), elevation)) = current.take() {
+                        points.push(TrackPoint {
+                            time,
+                            latitude,
+                            longitude,
+                            elevation,
+                        });
+                    }
+                }
+                Event::End(_) => {
+                    text_element = None;
+                }
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+
+        points.sort_by_key(|p| p.time);
+
+        Ok(Track { points })
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Estimate where the GPS logger was at a given time by linearly interpolating
+    /// between the track points either side of the time.
+    /// No location is returned if the nearest track point is more than `max_gap` away.
+    pub fn locate(&self, ts: DateTime<Utc>, max_gap: TimeDelta) -> Option<TrackPoint> {
+        let index = self.points.partition_point(|p| p.time < ts);
+
+        let after = self.points.get(index);
+        let before = index.checked_sub(1).and_then(|i| self.points.get(i));
+
+        match (before, after) {
+            (_, Some(after)) if after.time == ts => Some(*after),
+            (Some(before), Some(after)) => {
+                if ts - before.time > max_gap && after.time - ts > max_gap {
+                    // Logger was probably switched off between these points.
+                    return None;
+                }
+
+                let span = (after.time - before.time).num_milliseconds() as f64;
+                let fraction = (ts - before.time).num_milliseconds() as f64 / span;
+
+                // Take the short way around if the track crosses the antimeridian.
+                let mut delta_lng = after.longitude - before.longitude;
+                if delta_lng > 180.0 {
+                    delta_lng -= 360.0;
+                } else if delta_lng < -180.0 {
+                    delta_lng += 360.0;
+                }
+
+                let mut longitude = before.longitude + delta_lng * fraction;
+                if longitude > 180.0 {
+                    longitude -= 360.0;
+                } else if longitude < -180.0 {
+                    longitude += 360.0;
+                }
+
+                let elevation = match (before.elevation, after.elevation) {
+                    (Some(x), Some(y)) => Some(x + (y - x) * fraction),
+                    (x, y) => x.or(y),
+                };
+
+                Some(TrackPoint {
+                    time: ts,
+                    latitude: before.latitude + (after.latitude - before.latitude) * fraction,
+                    longitude,
+                    elevation,
+                })
+            }
+            (Some(point), None) | (None, Some(point)) => {
+                // Before the start or after the end of the track
+                if (ts - point.time).abs() <= max_gap {
+                    Some(TrackPoint { time: ts, ..*point })
+                } else {
+                    None
+                }
+            }
+            (None, None) => None,
+        }
+    }
+}
+
+/// A picture that can be tagged with a location from the track.
+#[derive(Debug, Clone)]
+pub struct GeotagMatch {
+    pub picture_id: PictureId,
+
+    pub path: PathBuf,
+
+    /// Capture time after correcting for the camera clock offset.
+    pub capture_ts: DateTime<Utc>,
+
+    pub location: TrackPoint,
+}
+
+/// Matches pictures to locations on a GPS track.
+#[derive(Debug, Clone)]
+pub struct Geotagger {
+    track: Track,
+
+    /// How far ahead the camera clock is of the GPS logger clock.
+    /// Negative if the camera clock is behind.
+    ///
+    /// Note that EXIF timestamps without an offset are treated as UTC, so the
+    /// camera clock offset must also include the time zone the camera was set to.
+    camera_offset: TimeDelta,
+
+    /// Maximum distance in time from a track point for a match.
+    max_gap: TimeDelta,
+}
+
+impl Geotagger {
+    pub fn new(track: Track) -> Self {
+        Self {
+            track,
+            camera_offset: TimeDelta::zero(),
+            max_gap: TimeDelta::try_minutes(5).expect("Valid minutes"),
+        }
+    }
+
+    pub fn camera_offset(mut self, camera_offset: TimeDelta) -> Self {
+        self.camera_offset = camera_offset;
+        self
+    }
+
+    pub fn max_gap(mut self, max_gap: TimeDelta) -> Self {
+        self.max_gap = max_gap;
+        self
+    }
+
+    pub fn track(&self) -> &Track {
+        &self.track
+    }
+
+    /// Pictures that would be tagged, and where. Pictures should be those without a location,
+    /// with an ordering timestamp of their EXIF creation time.
+    pub fn preview(&self, pictures: &[Picture]) -> Vec<GeotagMatch> {
+        pictures
+            .iter()
+            .filter_map(|pic| {
+                let capture_ts = pic.ordering_ts - self.camera_offset;
+                self.track
+                    .locate(capture_ts, self.max_gap)
+                    .map(|location| GeotagMatch {
+                        picture_id: pic.picture_id,
+                        path: pic.path.clone(),
+                        capture_ts,
+                        location,
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GPX: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<gpx version="1.1" creator="test" xmlns="http://www.topografix.com/GPX/1/1">
+  <trk><trkseg>
+    <trkpt lat="10.0" lon="20.0"><ele>100</ele><time>2024-06-01T10:00:00Z</time></trkpt>
+    <trkpt lat="11.0" lon="22.0"><ele>200</ele><time>2024-06-01T10:10:00Z</time></trkpt>
+    <trkpt lat="12.0" lon="24.0"><time>2024-06-01T11:00:00Z</time></trkpt>
+    <trkpt lat="13.0" lon="26.0"></trkpt>
+  </trkseg></trk>
+</gpx>"#;
+
+    fn ts(s: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(s).unwrap().to_utc()
+    }
+
+    #[test]
+    fn test_parse_gpx() {
+        let track = Track::from_gpx_str(GPX).unwrap();
+        assert_eq!(3, track.len());
+    }
+
+    #[test]
+    fn test_locate_interpolates() {
+        let track = Track::from_gpx_str(GPX).unwrap();
+        let max_gap = TimeDelta::try_minutes(5).unwrap();
+
+        let point = track.locate(ts("2024-06-01T10:05:00Z"), max_gap).unwrap();
+        assert!((point.latitude - 10.5).abs() < 1e-9);
+        assert!((point.longitude - 21.0).abs() < 1e-9);
+        assert_eq!(Some(150.0), point.elevation);
+    }
+
+    #[test]
+    fn test_locate_rejects_gaps() {
+        let track = Track::from_gpx_str(GPX).unwrap();
+        let max_gap = TimeDelta::try_minutes(5).unwrap();
+
+        // 20 minutes from either neighbouring point
+        assert!(track.locate(ts("2024-06-01T10:30:00Z"), max_gap).is_none());
+
+        // Shortly after end of track
+        assert!(track.locate(ts("2024-06-01T11:02:00Z"), max_gap).is_some());
+
+        // Well before start of track
+        assert!(track.locate(ts("2024-06-01T09:00:00Z"), max_gap).is_none());
+    }
+
+    #[test]
+    fn test_locate_antimeridian() {
+        let gpx = r#"<gpx><trk><trkseg>
+            <trkpt lat="0" lon="179"><time>2024-06-01T10:00:00Z</time></trkpt>
+            <trkpt lat="0" lon="-179"><time>2024-06-01T10:02:00Z</time></trkpt>
+        </trkseg></trk></gpx>"#;
+        let track = Track::from_gpx_str(gpx).unwrap();
+        let max_gap = TimeDelta::try_minutes(5).unwrap();
+
+        let point = track.locate(ts("2024-06-01T10:01:30Z"), max_gap).unwrap();
+        assert!((point.longitude - -179.5).abs() < 1e-9);
+    }
+}
@@ -6,6 +6,7 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use anyhow::*;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
 /// GPS code derived from Loupe.
 /// See https://gitlab.gnome.org/GNOME/loupe/-/blob/main/src/metadata/gps.rs
 use h3o::{CellIndex, LatLng, Resolution};
@@ -14,6 +15,19 @@ use h3o::{CellIndex, LatLng, Resolution};
 pub struct GPSLocation {
     pub latitude: GPSCoord,
     pub longitude: GPSCoord,
+
+    /// Altitude in metres. Negative if below sea level.
+    pub altitude: Option<f64>,
+
+    /// Direction camera was pointing when the photo was taken, in degrees
+    /// clockwise from north (0 to 359.99).
+    pub direction: Option<f64>,
+
+    /// Horizontal positioning error in metres.
+    pub horizontal_error: Option<f64>,
+
+    /// Time of the GPS fix. Not necessarily the same as the capture time.
+    pub timestamp: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -99,11 +113,228 @@ impl GPSLocation {
                 min: lon_min,
                 sec: lon_sec,
             },
+            altitude: None,
+            direction: None,
+            horizontal_error: None,
+            timestamp: None,
         })
     }
 
+    /// Add the optional GPS fields that qualify a location.
+    pub fn with_exif_details(mut self, exif: &exif::Exif) -> Self {
+        self.altitude = Self::altitude_exif(exif);
+        self.direction = Self::direction_exif(exif);
+        self.horizontal_error = Self::horizontal_error_exif(exif);
+        self.timestamp = Self::timestamp_exif(exif);
+        self
+    }
+
+    fn first_rational(exif: &exif::Exif, tag: exif::Tag) -> Option<f64> {
+        let field = exif.get_field(tag, exif::In::PRIMARY)?;
+        match field.value {
+            exif::Value::Rational(ref v) => v.first().map(exif::Rational::to_f64),
+            _ => None,
+        }
+        .filter(|x| x.is_finite())
+    }
+
+    fn altitude_exif(exif: &exif::Exif) -> Option<f64> {
+        let altitude = Self::first_rational(exif, exif::Tag::GPSAltitude)?;
+
+        // 0 is above sea level, 1 is below sea level.
+        let is_below_sea_level = exif
+            .get_field(exif::Tag::GPSAltitudeRef, exif::In::PRIMARY)
+            .and_then(|x| x.value.get_uint(0))
+            .is_some_and(|x| x == 1);
+
+        if is_below_sea_level {
+            Some(-altitude)
+        } else {
+            Some(altitude)
+        }
+    }
+
+    fn direction_exif(exif: &exif::Exif) -> Option<f64> {
+        // Note that GPSImgDirectionRef says if the direction is relative to true
+        // or magnetic north, but the difference isn't significant enough to show.
+        Self::first_rational(exif, exif::Tag::GPSImgDirection).map(|x| x.rem_euclid(360.0))
+    }
+
+    fn horizontal_error_exif(exif: &exif::Exif) -> Option<f64> {
+        // GPSHPositioningError was added in EXIF 2.31 and isn't known to kamadak-exif
+        // by name, so look it up by its tag number.
+        Self::first_rational(exif, exif::Tag(exif::Context::Gps, 0x1f))
+    }
+
+    fn timestamp_exif(exif: &exif::Exif) -> Option<DateTime<Utc>> {
+        let date = exif.get_field(exif::Tag::GPSDateStamp, exif::In::PRIMARY)?;
+        let date = match date.value {
+            exif::Value::Ascii(ref v) => v.first().and_then(|x| std::str::from_utf8(x).ok()),
+            _ => None,
+        }?;
+        let date = NaiveDate::parse_from_str(date.trim_end_matches('\0'), "%Y:%m:%d").ok()?;
+
+        let time = exif.get_field(exif::Tag::GPSTimeStamp, exif::In::PRIMARY)?;
+        let exif::Value::Rational(ref hms) = time.value else {
+            return None;
+        };
+        let (h, m, s) = (hms.first()?, hms.get(1)?, hms.get(2)?);
+        let secs = s.to_f64();
+        let time = NaiveTime::from_hms_milli_opt(
+            h.to_f64() as u32,
+            m.to_f64() as u32,
+            secs.trunc() as u32,
+            (secs.fract() * 1000.0) as u32,
+        )?;
+
+        Some(date.and_time(time).and_utc())
+    }
+
     pub fn to_cell_index(&self, resolution: Resolution) -> Result<CellIndex> {
         let ll = LatLng::new(self.latitude.to_f64(), self.longitude.to_f64())?;
         Ok(ll.to_cell(resolution))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use exif::experimental::Writer;
+    use exif::{Field, In, Rational, Tag, Value};
+    use std::io::Cursor;
+
+    fn rational(value: u32) -> Value {
+        Value::Rational(vec![Rational {
+            num: value,
+            denom: 1,
+        }])
+    }
+
+    /// Parse EXIF data holding just some fields.
+    fn exif(fields: &[Field]) -> exif::Exif {
+        let mut writer = Writer::new();
+        for field in fields {
+            writer.push_field(field);
+        }
+        let mut buf = Cursor::new(Vec::new());
+        writer.write(&mut buf, false).unwrap();
+        exif::Reader::new().read_raw(buf.into_inner()).unwrap()
+    }
+
+    fn location() -> GPSLocation {
+        let position = [Rational { num: 10, denom: 1 }];
+        GPSLocation::for_exif(&position, &[b"N".to_vec()], &position, &[b"E".to_vec()]).unwrap()
+    }
+
+    fn field(tag: Tag, value: Value) -> Field {
+        Field {
+            tag,
+            ifd_num: In::PRIMARY,
+            value,
+        }
+    }
+
+    #[test]
+    fn test_no_details() {
+        let exif = exif(&[field(
+            Tag::GPSLatitudeRef,
+            Value::Ascii(vec![b"N".to_vec()]),
+        )]);
+        let location = location().with_exif_details(&exif);
+        assert_eq!(None, location.altitude);
+        assert_eq!(None, location.direction);
+        assert_eq!(None, location.horizontal_error);
+        assert_eq!(None, location.timestamp);
+    }
+
+    #[test]
+    fn test_altitude_above_sea_level() {
+        let exif = exif(&[
+            field(Tag::GPSAltitude, rational(120)),
+            field(Tag::GPSAltitudeRef, Value::Byte(vec![0])),
+        ]);
+        assert_eq!(Some(120.0), location().with_exif_details(&exif).altitude);
+    }
+
+    #[test]
+    fn test_altitude_below_sea_level() {
+        let exif = exif(&[
+            field(Tag::GPSAltitude, rational(28)),
+            field(Tag::GPSAltitudeRef, Value::Byte(vec![1])),
+        ]);
+        assert_eq!(Some(-28.0), location().with_exif_details(&exif).altitude);
+    }
+
+    #[test]
+    fn test_direction_true_and_magnetic() {
+        for reference in [b"T", b"M"] {
+            let exif = exif(&[
+                field(Tag::GPSImgDirection, rational(270)),
+                field(
+                    Tag::GPSImgDirectionRef,
+                    Value::Ascii(vec![reference.to_vec()]),
+                ),
+            ]);
+            assert_eq!(Some(270.0), location().with_exif_details(&exif).direction);
+        }
+    }
+
+    #[test]
+    fn test_direction_wraps() {
+        let exif = exif(&[field(Tag::GPSImgDirection, rational(360))]);
+        assert_eq!(Some(0.0), location().with_exif_details(&exif).direction);
+    }
+
+    #[test]
+    fn test_horizontal_error() {
+        let exif = exif(&[field(Tag(exif::Context::Gps, 0x1f), rational(5))]);
+        assert_eq!(
+            Some(5.0),
+            location().with_exif_details(&exif).horizontal_error
+        );
+    }
+
+    #[test]
+    fn test_timestamp() {
+        let exif = exif(&[
+            field(
+                Tag::GPSDateStamp,
+                Value::Ascii(vec![b"2024:06:01".to_vec()]),
+            ),
+            field(
+                Tag::GPSTimeStamp,
+                Value::Rational(vec![
+                    Rational { num: 13, denom: 1 },
+                    Rational { num: 45, denom: 1 },
+                    Rational {
+                        num: 305,
+                        denom: 10,
+                    },
+                ]),
+            ),
+        ]);
+
+        let expected = NaiveDate::from_ymd_opt(2024, 6, 1)
+            .unwrap()
+            .and_hms_milli_opt(13, 45, 30, 500)
+            .unwrap()
+            .and_utc();
+        assert_eq!(
+            Some(expected),
+            location().with_exif_details(&exif).timestamp
+        );
+    }
+
+    #[test]
+    fn test_timestamp_needs_date() {
+        let exif = exif(&[field(
+            Tag::GPSTimeStamp,
+            Value::Rational(vec![
+                Rational { num: 13, denom: 1 },
+                Rational { num: 45, denom: 1 },
+                Rational { num: 30, denom: 1 },
+            ]),
+        )]);
+        assert_eq!(None, location().with_exif_details(&exif).timestamp);
+    }
+}
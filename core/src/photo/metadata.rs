@@ -4,6 +4,7 @@
 
 use super::gps::GPSLocation;
 use super::model::Orientation;
+use super::xmp::{self, Xmp};
 use super::Metadata;
 use anyhow::*;
 use chrono::prelude::*;
@@ -11,7 +12,7 @@ use chrono::{DateTime, FixedOffset};
 use exif;
 use exif::Exif;
 use std::fs;
-use std::io::BufReader;
+use std::io::{BufReader, Cursor, Read, Seek, SeekFrom};
 use std::path::Path;
 use std::result::Result::Ok;
 
@@ -25,23 +26,91 @@ use std::result::Result::Ok;
 /// 1. Orientation.
 /// 2. Motion photos.
 /// 3. GPS coordinates.
-pub const VERSION: u32 = 3;
-
-/// Extract EXIF metadata from file
+/// 4. GPS altitude, direction, accuracy, and timestamp.
+/// 5. Burst ID and camera model.
+/// 6. Portraits, HDR, panoramas, screenshots, and Android selfies.
+pub const VERSION: u32 = 6;
+
+/// Photos at least this many times wider than they are tall, or taller than they are wide,
+/// are panoramas.
+const PANORAMA_ASPECT_RATIO: f64 = 2.5;
+
+/// Auxiliary image of Apple portrait photos for separating the subject from the background.
+const APPLE_PORTRAIT_MATTE: &[u8] = b"urn:com:apple:photo:2018:aux:portraiteffectsmatte";
+
+/// Common display sizes of phones, tablets, and computers as the short and long edge.
+/// PNG images at one of these sizes are very likely to be screenshots.
+const DISPLAY_SIZES: [(u32, u32); 26] = [
+    (720, 1280),
+    (750, 1334),
+    (768, 1024),
+    (768, 1366),
+    (800, 1280),
+    (828, 1792),
+    (900, 1440),
+    (900, 1600),
+    (1080, 1920),
+    (1080, 2340),
+    (1080, 2400),
+    (1125, 2436),
+    (1170, 2532),
+    (1179, 2556),
+    (1200, 1920),
+    (1242, 2208),
+    (1242, 2688),
+    (1284, 2778),
+    (1290, 2796),
+    (1440, 2560),
+    (1440, 3120),
+    (1440, 3200),
+    (1536, 2048),
+    (1600, 2560),
+    (1800, 2880),
+    (2160, 3840),
+];
+
+/// Bytes at the start of a file that are searched for XMP packets. The XMP packet
+/// of the main image is near the start.
+const XMP_HEAD_BYTES: u64 = 512 * 1024;
+
+/// Bytes at the end of a file that are searched for XMP packets. Depth maps and gain
+/// maps have their own XMP packets and are usually appended after the main image.
+const XMP_TAIL_BYTES: u64 = 2 * 1024 * 1024;
+
+/// Extract EXIF and XMP metadata from file
 pub fn from_path(path: &Path) -> Result<Metadata> {
-    let file = fs::File::open(path)?;
-    let file = &mut BufReader::new(file);
-    let exif_data = {
-        match exif::Reader::new().read_from_container(file) {
-            Ok(exif) => exif,
-            Err(_) => {
-                // Assume this error is when there is no EXIF data.
-                return Ok(Metadata::default());
-            }
-        }
+    let file = &mut BufReader::new(fs::File::open(path)?);
+
+    // Assume an error is when there is no EXIF data.
+    let exif_data = exif::Reader::new().read_from_container(file).ok();
+
+    let mut metadata = match exif_data {
+        Some(ref exif_data) => from_exif(exif_data)?,
+        None => Metadata::default(),
     };
 
-    let mut metadata = from_exif(exif_data)?;
+    let data = read_head_and_tail(file.get_mut())?;
+    let xmp = Xmp::from_bytes(&data);
+
+    metadata.is_portrait = metadata.is_portrait
+        || xmp.has_namespace(xmp::ns::APPLE_DEPTH_DATA)
+        || xmp.has_namespace(xmp::ns::GOOGLE_DEPTH_MAP)
+        || xmp
+            .values(xmp::ns::GOOGLE_CONTAINER_ITEM, "Semantic")
+            .any(|x| x == "Depth")
+        || xmp::find(&data, APPLE_PORTRAIT_MATTE).is_some();
+
+    metadata.is_hdr = metadata.is_hdr || xmp.has_namespace(xmp::ns::HDR_GAIN_MAP);
+
+    metadata.is_panorama = metadata.is_panorama
+        || xmp
+            .value(xmp::ns::GOOGLE_PANORAMA, "ProjectionType")
+            .is_some_and(|x| x != "flat")
+        || xmp
+            .value(xmp::ns::GOOGLE_PANORAMA, "UsePanoramaViewer")
+            .is_some_and(|x| x.eq_ignore_ascii_case("true"));
+
+    metadata.is_screenshot = is_screenshot(path, &data, &xmp, &metadata);
 
     // FIXME what is a better way of doing this?
     //
@@ -79,10 +148,10 @@ pub fn from_raw(data: Vec<u8>) -> Result<Metadata> {
         }
     };
 
-    from_exif(exif_data)
+    from_exif(&exif_data)
 }
 
-fn from_exif(exif_data: Exif) -> Result<Metadata> {
+fn from_exif(exif_data: &Exif) -> Result<Metadata> {
     fn parse_date_time(
         date_time_field: Option<&exif::Field>,
         time_offset_field: Option<&exif::Field>,
@@ -141,23 +210,119 @@ fn from_exif(exif_data: Exif) -> Result<Metadata> {
         .and_then(|e| e.value.get_uint(0))
         .map(Orientation::from);
 
-    let content_id = ios_content_id(&exif_data);
+    let camera_model = camera_model(exif_data);
+
+    let content_id = ios_content_id(exif_data);
+
+    let burst_id = ios_burst_id(exif_data);
+
+    let location = gps_location(exif_data);
+
+    // iOS records how a photo was taken as non-standard values of the custom rendered tag.
+    // 2 and 3 are HDR, 6 is a panorama, 7 is a portrait HDR, and 8 is a portrait.
+    let custom_rendered = exif_data
+        .get_field(exif::Tag::CustomRendered, exif::In::PRIMARY)
+        .and_then(|e| e.value.get_uint(0));
+
+    let is_portrait = custom_rendered.is_some_and(|x| x == 7 || x == 8);
 
-    let location = gps_location(&exif_data);
+    let is_hdr = custom_rendered.is_some_and(|x| x == 2 || x == 3 || x == 7)
+        || ios_hdr_image_type(exif_data).is_some_and(|x| x == 3);
+
+    let is_panorama = custom_rendered.is_some_and(|x| x == 6)
+        || dimensions(exif_data).is_some_and(|(width, height)| is_panorama_size(width, height));
 
     let metadata = Metadata {
         created_at,
         modified_at,
         lens_model,
+        camera_model,
         orientation,
         content_id,
+        burst_id,
         location,
+        is_portrait,
+        is_hdr,
+        is_panorama,
+        is_screenshot: false,
     };
 
     Ok(metadata)
 }
 
-/// Parse GPS latitude and longitude from EXIF data
+/// Read the start and end of a file, where XMP packets are, or all of a small file.
+/// The bytes of the end follow those of the start.
+fn read_head_and_tail(file: &mut fs::File) -> Result<Vec<u8>> {
+    let len = file.metadata()?.len();
+    file.rewind()?;
+
+    if len <= XMP_HEAD_BYTES + XMP_TAIL_BYTES {
+        let mut data = Vec::with_capacity(len as usize);
+        file.read_to_end(&mut data)?;
+        return Ok(data);
+    }
+
+    let mut data = vec![0; (XMP_HEAD_BYTES + XMP_TAIL_BYTES) as usize];
+    let (head, tail) = data.split_at_mut(XMP_HEAD_BYTES as usize);
+    file.read_exact(head)?;
+    file.seek(SeekFrom::End(-(XMP_TAIL_BYTES as i64)))?;
+    file.read_exact(tail)?;
+    Ok(data)
+}
+
+/// Is an image a screenshot? Screenshots are saved to a screenshots folder by most
+/// desktops and phones, are PNG images the size of a display, or are marked as a
+/// screenshot by iOS.
+fn is_screenshot(path: &Path, data: &[u8], xmp: &Xmp, metadata: &Metadata) -> bool {
+    let is_in_screenshots_folder = path
+        .parent()
+        .and_then(|x| x.file_name())
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.to_lowercase().contains("screenshot"));
+
+    let is_marked_screenshot = xmp
+        .value(xmp::ns::EXIF, "UserComment")
+        .is_some_and(|x| x == "Screenshot");
+
+    let is_png = path
+        .extension()
+        .and_then(|x| x.to_str())
+        .is_some_and(|x| x.eq_ignore_ascii_case("png"));
+
+    let is_display_sized_png = is_png
+        && metadata.camera_model.is_none()
+        && png::Decoder::new(Cursor::new(data))
+            .read_info()
+            .is_ok_and(|reader| {
+                let info = reader.info();
+                let size = (info.width.min(info.height), info.width.max(info.height));
+                DISPLAY_SIZES.contains(&size)
+            });
+
+    is_in_screenshots_folder || is_marked_screenshot || is_display_sized_png
+}
+
+/// Width and height of the image recorded in the EXIF data.
+fn dimensions(exif_data: &Exif) -> Option<(u32, u32)> {
+    let width = exif_data
+        .get_field(exif::Tag::PixelXDimension, exif::In::PRIMARY)
+        .and_then(|e| e.value.get_uint(0))?;
+
+    let height = exif_data
+        .get_field(exif::Tag::PixelYDimension, exif::In::PRIMARY)
+        .and_then(|e| e.value.get_uint(0))?;
+
+    Some((width, height))
+}
+
+/// Is an image of this size long and narrow enough to be a panorama?
+fn is_panorama_size(width: u32, height: u32) -> bool {
+    let long_edge = f64::from(width.max(height));
+    let short_edge = f64::from(width.min(height));
+    short_edge > 0.0 && long_edge / short_edge >= PANORAMA_ASPECT_RATIO
+}
+
+/// Parse GPS latitude, longitude, and any altitude, direction, and accuracy from EXIF data
 /// Mostly borrowed from Loupe.
 /// See https://gitlab.gnome.org/GNOME/loupe/-/blob/main/src/metadata.rs
 fn gps_location(exif: &Exif) -> Option<GPSLocation> {
@@ -178,15 +343,60 @@ fn gps_location(exif: &Exif) -> Option<GPSLocation> {
             &longitude.value,
             &longitude_ref.value,
         ) {
-            return GPSLocation::for_exif(latitude, latitude_ref, longitude, longitude_ref);
+            return GPSLocation::for_exif(latitude, latitude_ref, longitude, longitude_ref)
+                .map(|location| location.with_exif_details(exif));
         }
     }
 
     None
 }
 
+/// Make and model of camera, such as "Apple iPhone 12 mini".
+fn camera_model(exif_data: &Exif) -> Option<String> {
+    let make = exif_data
+        .get_field(exif::Tag::Make, exif::In::PRIMARY)
+        .and_then(ascii);
+
+    let model = exif_data
+        .get_field(exif::Tag::Model, exif::In::PRIMARY)
+        .and_then(ascii)?;
+
+    // Some cameras already start the model with the make.
+    match make {
+        Some(make) if !model.starts_with(&make) => Some(format!("{} {}", make, model)),
+        _ => Some(model),
+    }
+}
+
 /// Parse content ID from the Apple maker note
 fn ios_content_id(exif_data: &Exif) -> Option<String> {
+    // 0x11 is the tag ID Apple uses for the content ID.
+    let maker_note = apple_maker_note(exif_data)?;
+    let content_id =
+        maker_note.get_field(exif::Tag(exif::Context::Tiff, 0x11), exif::In::PRIMARY)?;
+    ascii(content_id)
+}
+
+/// Parse HDR image type from the Apple maker note. 3 is an HDR photo and 4 is the
+/// original photo kept alongside an HDR photo.
+fn ios_hdr_image_type(exif_data: &Exif) -> Option<u32> {
+    // 0x0a is the tag ID Apple uses for the HDR image type.
+    let maker_note = apple_maker_note(exif_data)?;
+    let hdr_image_type =
+        maker_note.get_field(exif::Tag(exif::Context::Tiff, 0x0a), exif::In::PRIMARY)?;
+    hdr_image_type.value.get_uint(0)
+}
+
+/// Parse burst ID from the Apple maker note. Every photo of a burst has the same ID.
+fn ios_burst_id(exif_data: &Exif) -> Option<String> {
+    // 0x0b is the tag ID Apple uses for the burst UUID.
+    let maker_note = apple_maker_note(exif_data)?;
+    let burst_id = maker_note.get_field(exif::Tag(exif::Context::Tiff, 0x0b), exif::In::PRIMARY)?;
+    ascii(burst_id)
+}
+
+/// Parse the tags of an Apple maker note
+fn apple_maker_note(exif_data: &Exif) -> Option<Exif> {
     let maker_note = exif_data.get_field(exif::Tag::MakerNote, exif::In::PRIMARY)?;
     let exif::Value::Undefined(ref raw, _offset) = maker_note.value else {
         return None;
@@ -218,17 +428,16 @@ fn ios_content_id(exif_data: &Exif) -> Option<String> {
     buf[6] = 0;
     buf[7] = 14; // first piece of data starts at byte 14
 
-    let exif_data = exif::Reader::new().read_raw(buf).ok()?;
-
-    // 0x11 is the tag ID Apple uses for the content ID.
-    let content_id =
-        exif_data.get_field(exif::Tag(exif::Context::Tiff, 0x11), exif::In::PRIMARY)?;
+    exif::Reader::new().read_raw(buf).ok()
+}
 
-    match content_id.value {
+/// First string of an ASCII field, without trailing spaces.
+fn ascii(field: &exif::Field) -> Option<String> {
+    match field.value {
         exif::Value::Ascii(ref vecs) => {
-            let mut bytes = Vec::with_capacity(vecs[0].len());
-            bytes.extend_from_slice(&vecs[0]);
-            String::from_utf8(bytes).ok()
+            let bytes = vecs.first()?.clone();
+            let value = String::from_utf8(bytes).ok()?;
+            Some(value.trim_end().to_string()).filter(|x| !x.is_empty())
         }
         _ => None,
     }
@@ -237,6 +446,7 @@ fn ios_content_id(exif_data: &Exif) -> Option<String> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::BufReader;
 
     #[test]
     fn test_ios_content_id() {
@@ -253,4 +463,47 @@ mod tests {
             content_id
         );
     }
+
+    #[test]
+    fn test_camera_model() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let file = Path::new(dir).join("resources/test/Dandelion.jpg");
+        let file = fs::File::open(file).unwrap();
+        let file = &mut BufReader::new(file);
+
+        let exif_data = exif::Reader::new().read_from_container(file).ok().unwrap();
+
+        assert_eq!(
+            Some("Apple iPhone XS".to_string()),
+            camera_model(&exif_data)
+        );
+        assert_eq!(None, ios_burst_id(&exif_data));
+    }
+
+    #[test]
+    fn test_screenshots() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let photo = Path::new(dir).join("resources/test/Dandelion.jpg");
+
+        let metadata = from_path(&photo).unwrap();
+        assert!(!metadata.is_screenshot);
+
+        let screenshots_dir = tempfile::tempdir().unwrap();
+        let screenshots_dir = screenshots_dir.path().join("Screenshots");
+        fs::create_dir(&screenshots_dir).unwrap();
+        let screenshot = screenshots_dir.join("Dandelion.jpg");
+        fs::copy(&photo, &screenshot).unwrap();
+
+        let metadata = from_path(&screenshot).unwrap();
+        assert!(metadata.is_screenshot);
+    }
+
+    #[test]
+    fn test_is_panorama_size() {
+        assert!(!is_panorama_size(4032, 3024));
+        assert!(!is_panorama_size(1080, 2400));
+        assert!(is_panorama_size(16000, 4000));
+        assert!(is_panorama_size(3000, 9000));
+        assert!(!is_panorama_size(100, 0));
+    }
 }
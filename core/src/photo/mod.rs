@@ -2,6 +2,10 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod edit;
+pub mod exif_orientation;
+pub mod focus;
+pub mod geotag;
 pub mod gps;
 pub mod metadata;
 pub mod model;
@@ -9,9 +13,13 @@ pub mod motion_photo;
 pub mod repo;
 pub mod scanner;
 pub mod thumbnail;
+pub mod xmp;
 
 pub use model::PictureId;
 
+pub use edit::EditStack;
+pub use focus::Focus;
+pub use geotag::Geotagger;
 pub use model::Metadata;
 pub use motion_photo::MotionPhotoExtractor;
 pub use repo::Repository;
@@ -4,6 +4,7 @@
 
 use super::gps::GPSLocation;
 use chrono::{DateTime, FixedOffset, TimeDelta, Utc};
+use image::DynamicImage;
 use std::fmt::Display;
 use std::path::PathBuf;
 use strum::{AsRefStr, EnumIter};
@@ -41,6 +42,9 @@ pub struct Picture {
     /// Full path to square preview image
     pub thumbnail_path: Option<PathBuf>,
 
+    /// Largest edge length of the thumbnails that exist.
+    pub thumbnail_max_edge: Option<u32>,
+
     /// Ordering timestamp
     pub ordering_ts: DateTime<Utc>,
 
@@ -84,7 +88,7 @@ pub struct ScannedFile {
 //
 // TODO this is also used by videos so move to a common place.
 
-#[derive(Debug, Clone, Copy, AsRefStr, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, AsRefStr, EnumIter)]
 pub enum Orientation {
     // no rotation, no flip
     North = 1,
@@ -121,6 +125,79 @@ impl Orientation {
             _ => Self::default(),
         }
     }
+
+    /// Transform an image so that it displays the right way up.
+    /// Note that image-rs doesn't apply the EXIF orientation when decoding.
+    pub fn apply_to(&self, image: DynamicImage) -> DynamicImage {
+        match self {
+            Orientation::North => image,
+            Orientation::NorthMirrored => image.fliph(),
+            Orientation::South => image.rotate180(),
+            Orientation::SouthMirrored => image.flipv(),
+            Orientation::WestMirrored => image.rotate90().fliph(),
+            Orientation::West => image.rotate90(),
+            Orientation::EastMirrored => image.rotate270().fliph(),
+            Orientation::East => image.rotate270(),
+        }
+    }
+
+    /// Describe orientation as an optional flip on the X axis followed by
+    /// a number of clockwise quarter turns.
+    pub fn to_flip_and_turns(&self) -> (bool, u8) {
+        match self {
+            Orientation::North => (false, 0),
+            Orientation::NorthMirrored => (true, 0),
+            Orientation::West => (false, 1),
+            Orientation::EastMirrored => (true, 1),
+            Orientation::South => (false, 2),
+            Orientation::SouthMirrored => (true, 2),
+            Orientation::East => (false, 3),
+            Orientation::WestMirrored => (true, 3),
+        }
+    }
+
+    /// Inverse of `to_flip_and_turns`.
+    pub fn from_flip_and_turns(flip: bool, turns: u8) -> Self {
+        match (flip, turns % 4) {
+            (false, 0) => Orientation::North,
+            (true, 0) => Orientation::NorthMirrored,
+            (false, 1) => Orientation::West,
+            (true, 1) => Orientation::EastMirrored,
+            (false, 2) => Orientation::South,
+            (true, 2) => Orientation::SouthMirrored,
+            (false, _) => Orientation::East,
+            (true, _) => Orientation::WestMirrored,
+        }
+    }
+
+    /// Orientation equivalent to applying this transformation and then the next one.
+    pub fn then(&self, next: Orientation) -> Self {
+        let (flip, turns) = self.to_flip_and_turns();
+        let (next_flip, next_turns) = next.to_flip_and_turns();
+
+        // Flipping after rotating is the same as flipping and then rotating
+        // in the opposite direction.
+        let (flip, turns) = if next_flip {
+            (!flip, (4 - turns) % 4)
+        } else {
+            (flip, turns)
+        };
+
+        Self::from_flip_and_turns(flip, turns + next_turns)
+    }
+
+    /// Orientation that undoes this transformation.
+    pub fn inverse(&self) -> Self {
+        match self.to_flip_and_turns() {
+            (true, _) => *self,
+            (false, turns) => Self::from_flip_and_turns(false, 4 - turns),
+        }
+    }
+
+    /// Does the transformation swap the width and height of an image?
+    pub fn is_sideways(&self) -> bool {
+        self.to_flip_and_turns().1 % 2 == 1
+    }
 }
 
 impl Default for Orientation {
@@ -151,12 +228,18 @@ pub struct Metadata {
 
     pub modified_at: Option<DateTime<FixedOffset>>,
 
-    /// On iPhone the lens model tells you if it was the front or back camera.
+    /// On iPhone and Pixel phones the lens model tells you if it was the front or back camera.
     pub lens_model: Option<String>,
 
+    /// Make and model of camera, for telling apart photos taken together by different cameras.
+    pub camera_model: Option<String>,
+
     // iOS id for linking a video with a photo
     pub content_id: Option<String>,
 
+    // iOS id shared by the photos of a burst
+    pub burst_id: Option<String>,
+
     // EXIF orientation.
     // Some images... annoyingly... needs a rotation and mirror transformation applied
     // to display correctly.
@@ -164,13 +247,25 @@ pub struct Metadata {
 
     // GPS location
     pub location: Option<GPSLocation>,
+
+    /// Portrait photo with a depth map for blurring the background.
+    pub is_portrait: bool,
+
+    /// HDR photo, either merged from several exposures or with a gain map for HDR displays.
+    pub is_hdr: bool,
+
+    /// Panorama or photo sphere.
+    pub is_panorama: bool,
+
+    /// Screenshot rather than a photo taken with a camera.
+    pub is_screenshot: bool,
 }
 
 impl Metadata {
     pub fn is_selfie(&self) -> bool {
         self.lens_model
             .as_ref()
-            .is_some_and(|x| x.contains("front"))
+            .is_some_and(|x| x.to_lowercase().contains("front"))
     }
 }
 
@@ -185,4 +280,7 @@ pub struct MotionPhotoVideo {
     // Rotation of video in degrees.
     // Should be 90, 180, 270, or the negative of those.
     pub rotation: Option<i32>,
+
+    // Position in the video of the frame the still photo was taken from.
+    pub still_ts: Option<TimeDelta>,
 }
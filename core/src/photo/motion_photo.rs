@@ -4,9 +4,12 @@
 
 use crate::photo::model::PictureId;
 use anyhow::*;
+use chrono::TimeDelta;
 
 use super::model::MotionPhotoVideo;
+use super::xmp::{ns, Element, Xmp};
 use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
 use std::path::{Path, PathBuf};
 use std::result::Result::Ok;
 use tracing::debug;
@@ -20,10 +23,23 @@ use crate::video::transcode;
 /// a bug fix or feature addition that changes the motion photo data produced.
 /// Each photo will be saved with a motion photo extraction version which will allow for
 /// easy selection of photos when their motion photo can be updated.
+///
+/// History:
+/// 1. Initial version.
+/// 2. Motion photos located by XMP, and the position of the still photo in the video.
+/// 3. Padding after container items, and only the video is extracted.
+pub const VERSION: u32 = 3;
 
-pub const VERSION: u32 = 1;
+/// Bytes at the start of a photo that are searched for the XMP locating its video.
+/// The XMP of the main image is near the start of JPEG and HEIC files.
+const XMP_HEAD_BYTES: u64 = 256 * 1024;
 
 /// Motion photos are an image followed by an embedded MP4 video.
+///
+/// Google, newer Samsung, and Xiaomi phones describe where the video is in the XMP
+/// of the photo, either as a Motion Photo container directory or as an older Micro
+/// Video offset. Other motion photos, such as those of older Samsung phones, are
+/// left to the sm_motion_photo library.
 #[derive(Debug, Clone)]
 pub struct MotionPhotoExtractor {
     base_path: PathBuf,
@@ -43,17 +59,6 @@ impl MotionPhotoExtractor {
         picture_id: &PictureId,
         picture_path: &Path,
     ) -> Result<Option<MotionPhotoVideo>> {
-        let photo_file = File::open(picture_path)?;
-        let Some(sm) = SmMotion::with(&photo_file) else {
-            return Ok(None); // would be nice if API returned a result instead of an option.
-        };
-
-        if !sm.has_video() {
-            return Ok(None);
-        }
-
-        debug!("Photo {:?} has an embedded motion video.", picture_path);
-
         let video_path = {
             // Create a directory per 1000 motion photos
             let partition = (picture_id.id() / 1000) as i32;
@@ -62,13 +67,40 @@ impl MotionPhotoExtractor {
             self.base_path.join(partition).join(file_name)
         };
 
-        if !video_path.exists() {
+        let mut photo_file = File::open(picture_path)?;
+        let mut head = Vec::new();
+        (&mut photo_file).take(XMP_HEAD_BYTES).read_to_end(&mut head)?;
+        let xmp = Xmp::from_bytes(&head);
+
+        // Videos are always written, rather than kept if they exist, because an earlier
+        // version might have extracted a broken video.
+        if let Some(video) = embedded_video(&mut photo_file, &xmp)? {
+            debug!("Photo {:?} has an XMP motion video.", picture_path);
+
+            if let Some(p) = video_path.parent() {
+                let _ = std::fs::create_dir_all(p);
+            }
+
+            std::fs::write(&video_path, video)?;
+        } else {
+            let photo_file = File::open(picture_path)?;
+            let Some(sm) = SmMotion::with(&photo_file) else {
+                return Ok(None); // would be nice if API returned a result instead of an option.
+            };
+
+            if !sm.has_video() {
+                return Ok(None);
+            }
+
+            debug!("Photo {:?} has an embedded motion video.", picture_path);
+
             if let Some(p) = video_path.parent() {
                 let _ = std::fs::create_dir_all(p);
             }
 
-            let mut video_file = File::create(&video_path).unwrap();
-            sm.dump_video_file(&mut video_file).unwrap();
+            let mut video_file = File::create(&video_path)?;
+            sm.dump_video_file(&mut video_file)
+                .map_err(|e| anyhow!("Failed writing motion video: {:?}", e))?;
         }
 
         let mut mpv = MotionPhotoVideo {
@@ -77,6 +109,7 @@ impl MotionPhotoExtractor {
             video_codec: None,
             rotation: None,
             transcoded_path: None,
+            still_ts: still_timestamp(&xmp),
         };
 
         if let Ok(meta) = video_metadata::from_path(&video_path) {
@@ -112,9 +145,188 @@ impl MotionPhotoExtractor {
     }
 }
 
+/// Video embedded at the end of a photo, located by the XMP of the photo.
+/// Only the video is read, rather than the whole photo.
+fn embedded_video<R: Read + Seek>(photo: &mut R, xmp: &Xmp) -> Result<Option<Vec<u8>>> {
+    let photo_length = photo.seek(SeekFrom::End(0))?;
+
+    let Some((start, length)) =
+        motion_photo_range(xmp, photo_length).or_else(|| micro_video_range(xmp, photo_length))
+    else {
+        return Ok(None);
+    };
+
+    let mut video = Vec::new();
+    photo.seek(SeekFrom::Start(start))?;
+    photo.take(length).read_to_end(&mut video)?;
+
+    // MP4 files start with a file type box, so anything else means the XMP is wrong.
+    if video.len() as u64 == length && video.get(4..8) == Some(b"ftyp") {
+        Ok(Some(video))
+    } else {
+        Ok(None)
+    }
+}
+
+/// Start and length in bytes of the video of a Motion Photo. Items of the container
+/// directory are appended to the photo in order, each followed by any padding, so
+/// the video is followed only by the items after it and their padding.
+fn motion_photo_range(xmp: &Xmp, photo_length: u64) -> Option<(u64, u64)> {
+    let items: Vec<_> = xmp.elements(ns::GOOGLE_CONTAINER, "Item").collect();
+
+    let video_index = items.iter().position(|item| {
+        item.attribute(ns::GOOGLE_CONTAINER_ITEM, "Semantic") == Some("MotionPhoto")
+    })?;
+
+    let number = |item: &Element, name: &str| -> Option<u64> {
+        item.attribute(ns::GOOGLE_CONTAINER_ITEM, name)?.parse().ok()
+    };
+
+    let length = number(items[video_index], "Length")?;
+
+    let trailing: u64 = items[video_index..]
+        .iter()
+        .map(|item| Some(number(item, "Length")? + number(item, "Padding").unwrap_or(0)))
+        .sum::<Option<u64>>()?;
+
+    let start = photo_length.checked_sub(trailing)?;
+    Some((start, length))
+}
+
+/// Start and length in bytes of the video of an older Micro Video, which is at the
+/// end of the file.
+fn micro_video_range(xmp: &Xmp, photo_length: u64) -> Option<(u64, u64)> {
+    let offset: u64 = xmp
+        .value(ns::GOOGLE_CAMERA, "MicroVideoOffset")?
+        .parse()
+        .ok()
+        .filter(|x| *x > 0)?;
+
+    let start = photo_length.checked_sub(offset)?;
+    Some((start, offset))
+}
+
+/// Position in the video of the frame the still photo was taken from.
+fn still_timestamp(xmp: &Xmp) -> Option<TimeDelta> {
+    let micros: i64 = xmp
+        .value(ns::GOOGLE_CAMERA, "MotionPhotoPresentationTimestampUs")
+        .or_else(|| xmp.value(ns::GOOGLE_CAMERA, "MicroVideoPresentationTimestampUs"))?
+        .parse()
+        .ok()?;
+
+    // -1 means the position isn't known.
+    if micros >= 0 {
+        Some(TimeDelta::microseconds(micros))
+    } else {
+        None
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::io::Cursor;
+
+    /// Start of an MP4 file.
+    const VIDEO: &[u8] = b"\x00\x00\x00\x18ftypmp42 rest of video";
+
+    fn photo(xmp: &str, trailer: &[u8]) -> Vec<u8> {
+        let mut data = b"\xff\xd8\xff\xe1".to_vec();
+        data.extend_from_slice(xmp.as_bytes());
+        data.extend_from_slice(b"\xff\xd9");
+        data.extend_from_slice(trailer);
+        data
+    }
+
+    #[test]
+    fn test_motion_photo() {
+        let padding = b"\x00\x00\x00\x00";
+        let gain_map = b"gain map";
+        let xmp = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:GCamera="http://ns.google.com/photos/1.0/camera/"
+        xmlns:Container="http://ns.google.com/photos/1.0/container/"
+        xmlns:Item="http://ns.google.com/photos/1.0/container/item/"
+        GCamera:MotionPhoto="1"
+        GCamera:MotionPhotoVersion="1"
+        GCamera:MotionPhotoPresentationTimestampUs="1234567">
+      <Container:Directory>
+        <rdf:Seq>
+          <rdf:li rdf:parseType="Resource">
+            <Container:Item Item:Mime="image/jpeg" Item:Semantic="Primary" Item:Length="0" Item:Padding="0"/>
+          </rdf:li>
+          <rdf:li rdf:parseType="Resource">
+            <Container:Item Item:Mime="video/mp4" Item:Semantic="MotionPhoto" Item:Length="{}" Item:Padding="{}"/>
+          </rdf:li>
+          <rdf:li rdf:parseType="Resource">
+            <Container:Item Item:Mime="image/jpeg" Item:Semantic="GainMap" Item:Length="{}"/>
+          </rdf:li>
+        </rdf:Seq>
+      </Container:Directory>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>"#,
+            VIDEO.len(),
+            padding.len(),
+            gain_map.len()
+        );
+
+        let data = photo(&xmp, &[VIDEO, padding, gain_map].concat());
+        let xmp = Xmp::from_bytes(&data);
+
+        assert_eq!(
+            Some(VIDEO.to_vec()),
+            embedded_video(&mut Cursor::new(&data), &xmp).unwrap()
+        );
+        assert_eq!(
+            Some(TimeDelta::microseconds(1234567)),
+            still_timestamp(&xmp)
+        );
+    }
+
+    #[test]
+    fn test_micro_video() {
+        let xmp = format!(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:GCamera="http://ns.google.com/photos/1.0/camera/"
+        GCamera:MicroVideo="1"
+        GCamera:MicroVideoVersion="1"
+        GCamera:MicroVideoOffset="{}"
+        GCamera:MicroVideoPresentationTimestampUs="-1"/>
+  </rdf:RDF>
+</x:xmpmeta>"#,
+            VIDEO.len()
+        );
+
+        let data = photo(&xmp, VIDEO);
+        let xmp = Xmp::from_bytes(&data);
+
+        assert_eq!(
+            Some(VIDEO.to_vec()),
+            embedded_video(&mut Cursor::new(&data), &xmp).unwrap()
+        );
+        assert_eq!(None, still_timestamp(&xmp));
+    }
+
+    #[test]
+    fn test_wrong_offset_is_not_video() {
+        let xmp = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:GCamera="http://ns.google.com/photos/1.0/camera/"
+        GCamera:MicroVideoOffset="5"/>
+  </rdf:RDF>
+</x:xmpmeta>"#;
+
+        let data = photo(xmp, VIDEO);
+        let xmp = Xmp::from_bytes(&data);
+
+        assert_eq!(None, embedded_video(&mut Cursor::new(&data), &xmp).unwrap());
+    }
 
     #[test]
     fn test_extract_motion_photo() {
@@ -4,14 +4,19 @@
 
 use crate::photo::model::{Picture, PictureId, ScannedFile};
 
+use super::edit::EditStack;
+use super::focus::Focus;
+use super::geotag::GeotagMatch;
 use super::metadata;
-use super::model::MotionPhotoVideo;
+use super::model::{MotionPhotoVideo, Orientation};
 use super::motion_photo;
 use super::Metadata;
 use crate::path_encoding;
+use crate::thumbnail::{self, Thumbnail};
 use anyhow::*;
 use rusqlite;
 use rusqlite::params;
+use rusqlite::OptionalExtension;
 use rusqlite::Row;
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
@@ -66,7 +71,13 @@ impl Repository {
                     exif_modified_ts = ?4,
                     is_selfie = ?5,
                     content_id = ?6,
-                    orientation = ?7
+                    orientation = ?7,
+                    burst_id = ?8,
+                    camera_model = ?9,
+                    is_portrait = ?10,
+                    is_hdr = ?11,
+                    is_panorama = ?12,
+                    is_screenshot = ?13
                 WHERE picture_id = ?1",
             )?;
 
@@ -74,12 +85,22 @@ impl Repository {
                 "INSERT INTO pictures_geo (
                     picture_id,
                     latitude,
-                    longitude
+                    longitude,
+                    altitude,
+                    direction,
+                    horizontal_error,
+                    gps_ts,
+                    is_inferred
                 ) VALUES (
-                    ?1, ?2, ?3
+                    ?1, ?2, ?3, ?4, ?5, ?6, ?7, FALSE
                 ) ON CONFLICT (picture_id) DO UPDATE SET
                     latitude = ?2,
-                    longitude = ?3
+                    longitude = ?3,
+                    altitude = ?4,
+                    direction = ?5,
+                    horizontal_error = ?6,
+                    gps_ts = ?7,
+                    is_inferred = FALSE
                 ",
             )?;
 
@@ -92,13 +113,23 @@ impl Repository {
                     metadata.is_selfie(),
                     metadata.content_id,
                     metadata.orientation.map(|x| x as u8),
+                    metadata.burst_id,
+                    metadata.camera_model,
+                    metadata.is_portrait,
+                    metadata.is_hdr,
+                    metadata.is_panorama,
+                    metadata.is_screenshot,
                 ])?;
 
                 if let Some(location) = metadata.location {
                     update_geo.execute(params![
                         picture_id.id(),
                         location.latitude.to_f64(),
-                        location.longitude.to_f64()
+                        location.longitude.to_f64(),
+                        location.altitude,
+                        location.direction,
+                        location.horizontal_error,
+                        location.timestamp,
                     ])?;
                 }
             }
@@ -108,7 +139,48 @@ impl Repository {
         Ok(())
     }
 
-    pub fn add_thumbnail(&mut self, picture_id: &PictureId, thumbnail_path: &Path) -> Result<()> {
+    /// Add locations inferred from a GPS track.
+    /// Will not overwrite locations read from EXIF data.
+    pub fn add_inferred_locations(&mut self, matches: &Vec<GeotagMatch>) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO pictures_geo (
+                    picture_id,
+                    latitude,
+                    longitude,
+                    altitude,
+                    gps_ts,
+                    is_inferred
+                ) VALUES (
+                    ?1, ?2, ?3, ?4, ?5, TRUE
+                ) ON CONFLICT (picture_id) DO UPDATE SET
+                    latitude = ?2,
+                    longitude = ?3,
+                    altitude = ?4,
+                    gps_ts = ?5
+                WHERE is_inferred IS TRUE
+                ",
+            )?;
+
+            for m in matches {
+                stmt.execute(params![
+                    m.picture_id.id(),
+                    m.location.latitude,
+                    m.location.longitude,
+                    m.location.elevation,
+                    m.location.time,
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    pub fn add_thumbnail(&mut self, picture_id: &PictureId, thumbnail: &Thumbnail) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
 
@@ -117,16 +189,26 @@ impl Repository {
                 "UPDATE pictures
                 SET
                     thumbnail_path = ?2,
+                    thumbnail_max_edge = ?3,
+                    width = COALESCE(?4, width),
+                    height = COALESCE(?5, height),
+                    focus_x = COALESCE(?6, focus_x),
+                    focus_y = COALESCE(?7, focus_y),
                     is_broken = FALSE
                 WHERE picture_id = ?1",
             )?;
 
             // convert to relative path before saving to database
-            let thumbnail_path = thumbnail_path.strip_prefix(&self.cache_dir_base_path).ok();
+            let thumbnail_path = thumbnail.path.strip_prefix(&self.cache_dir_base_path).ok();
 
             stmt.execute(params![
                 picture_id.id(),
                 thumbnail_path.as_ref().map(|p| p.to_str()),
+                thumbnail::LARGEST_EDGE,
+                thumbnail.size.map(|(width, _)| width),
+                thumbnail.size.map(|(_, height)| height),
+                thumbnail.focus.map(|focus| focus.x),
+                thumbnail.focus.map(|focus| focus.y),
             ])?;
         }
 
@@ -134,6 +216,180 @@ impl Repository {
         Ok(())
     }
 
+    /// Records the largest thumbnail that remains after larger thumbnails were removed
+    /// to keep the cache within its budget.
+    pub fn set_thumbnail_max_edge(&mut self, thumbnail_path: &Path, max_edge: u32) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE pictures
+            SET thumbnail_max_edge = ?2
+            WHERE thumbnail_path = ?1",
+        )?;
+
+        // thumbnail paths are relative in the database
+        let thumbnail_path = thumbnail_path.strip_prefix(&self.cache_dir_base_path)?;
+
+        stmt.execute(params![thumbnail_path.to_str(), max_edge])?;
+
+        Ok(())
+    }
+
+    /// Gets edits for a picture. Pictures without edits have an empty edit stack.
+    pub fn edits(&self, picture_id: &PictureId) -> Result<EditStack> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare("SELECT edits FROM picture_edits WHERE picture_id = ?1")?;
+
+        let edits: Option<String> = stmt
+            .query_row(params![picture_id.id()], |row| row.get(0))
+            .optional()?;
+
+        edits
+            .map(|x| x.parse::<EditStack>())
+            .unwrap_or_else(|| Ok(EditStack::new()))
+    }
+
+    /// Gets orientation of a picture after any user correction of the EXIF orientation.
+    pub fn corrected_orientation(&self, picture_id: &PictureId) -> Result<Orientation> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT orientation, orientation_override FROM pictures WHERE picture_id = ?1",
+        )?;
+
+        let (orientation, correction): (Option<u32>, Option<u32>) = stmt
+            .query_row(params![picture_id.id()], |row| {
+                std::result::Result::Ok((row.get(0)?, row.get(1)?))
+            })?;
+
+        let orientation = orientation.map(Orientation::from).unwrap_or_default();
+
+        Ok(match correction {
+            Some(correction) => orientation.then(Orientation::from(correction)),
+            None => orientation,
+        })
+    }
+
+    /// Gets the focus chosen by the user for cropping thumbnails, if any.
+    pub fn focus_override(&self, picture_id: &PictureId) -> Result<Option<Focus>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT focus_override_x, focus_override_y FROM pictures WHERE picture_id = ?1",
+        )?;
+
+        let (x, y): (Option<f64>, Option<f64>) = stmt
+            .query_row(params![picture_id.id()], |row| {
+                std::result::Result::Ok((row.get(0)?, row.get(1)?))
+            })?;
+
+        Ok(x.zip(y).map(|(x, y)| Focus::new(x, y)))
+    }
+
+    /// Set the focus chosen by the user for cropping thumbnails.
+    /// None goes back to the detected focus.
+    pub fn set_focus_override(&mut self, picture_id: &PictureId, focus: Option<Focus>) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE pictures
+            SET
+                focus_override_x = ?2,
+                focus_override_y = ?3
+            WHERE picture_id = ?1",
+        )?;
+
+        stmt.execute(params![picture_id.id(), focus.map(|f| f.x), focus.map(|f| f.y)])?;
+        Ok(())
+    }
+
+    /// Save edits for a picture. An empty edit stack removes all edits.
+    pub fn set_edits(&mut self, picture_id: &PictureId, edits: &EditStack) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        if edits.is_empty() {
+            let mut stmt = tx.prepare_cached("DELETE FROM picture_edits WHERE picture_id = ?1")?;
+            stmt.execute(params![picture_id.id()])?;
+        } else {
+            let mut stmt = tx.prepare_cached(
+                "INSERT INTO picture_edits (
+                    picture_id,
+                    edits,
+                    orientation,
+                    edited_ts
+                ) VALUES (
+                    ?1, ?2, ?3, CURRENT_TIMESTAMP
+                ) ON CONFLICT (picture_id) DO UPDATE SET
+                    edits = ?2,
+                    orientation = ?3,
+                    edited_ts = CURRENT_TIMESTAMP
+                ",
+            )?;
+
+            stmt.execute(params![
+                picture_id.id(),
+                edits.to_string(),
+                edits.adjustments().orientation as u8,
+            ])?;
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set a user correction to apply after the EXIF orientation.
+    /// None removes the correction.
+    pub fn set_orientation_override(
+        &mut self,
+        picture_id: &PictureId,
+        orientation: Option<Orientation>,
+    ) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE pictures
+            SET orientation_override = ?2
+            WHERE picture_id = ?1",
+        )?;
+
+        stmt.execute(params![picture_id.id(), orientation.map(|x| x as u8)])?;
+        Ok(())
+    }
+
+    /// Choose the picture of a burst to keep. The other pictures of the burst are
+    /// marked as not chosen.
+    pub fn set_burst_pick(&mut self, pick: &PictureId, burst: &[PictureId]) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        {
+            let mut stmt = tx.prepare_cached(
+                "UPDATE pictures
+                SET is_burst_pick = ?2
+                WHERE picture_id = ?1",
+            )?;
+
+            for picture_id in burst {
+                stmt.execute(params![picture_id.id(), picture_id == pick])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Set the orientation after it has been written to the EXIF data of the picture file.
+    /// Removes any user correction because it is now part of the orientation.
+    pub fn set_orientation(&mut self, picture_id: &PictureId, orientation: Orientation) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE pictures
+            SET
+                orientation = ?2,
+                orientation_override = NULL
+            WHERE picture_id = ?1",
+        )?;
+
+        stmt.execute(params![picture_id.id(), orientation as u8])?;
+        Ok(())
+    }
+
     pub fn mark_broken(&mut self, picture_id: &PictureId) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
@@ -181,13 +437,7 @@ impl Repository {
                 let picture_path = pic.path.strip_prefix(&self.library_base_path)?;
                 let picture_path_b64 = path_encoding::to_base64(picture_path);
 
-                // Path without suffix so sibling pictures and videos can be related
-                let link_path = picture_path
-                    .file_stem()
-                    .and_then(|x| x.to_str())
-                    .expect("Must exist");
-
-                let link_path = picture_path.with_file_name(link_path);
+                let link_path = link_path(picture_path);
                 let link_path_b64 = path_encoding::to_base64(&link_path);
 
                 pic_insert_stmt.execute(params![
@@ -205,6 +455,74 @@ impl Repository {
         Ok(())
     }
 
+    /// Add voice notes, which are linked to the picture with the same path minus suffix.
+    pub fn add_voice_notes(&mut self, notes: &Vec<ScannedFile>) -> Result<()> {
+        let mut con = self.con.lock().unwrap();
+        let tx = con.transaction()?;
+
+        // Create a scope to make borrowing of tx not be an error.
+        {
+            let mut note_insert_stmt = tx.prepare_cached(
+                "INSERT INTO voice_notes (
+                    voice_note_path_b64,
+                    voice_note_path_lossy,
+                    link_path_b64,
+                    link_path_lossy
+                ) VALUES (
+                    ?1, ?2, ?3, ?4
+                ) ON CONFLICT (voice_note_path_b64) DO NOTHING
+                ",
+            )?;
+
+            for note in notes {
+                // convert to relative path before saving to database
+                let note_path = note.path.strip_prefix(&self.library_base_path)?;
+                let note_path_b64 = path_encoding::to_base64(note_path);
+
+                let link_path = link_path(note_path);
+                let link_path_b64 = path_encoding::to_base64(&link_path);
+
+                note_insert_stmt.execute(params![
+                    note_path_b64,
+                    note_path.to_string_lossy(),
+                    link_path_b64,
+                    link_path.to_string_lossy(),
+                ])?;
+            }
+        }
+
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Gets full paths of all voice notes in the repository.
+    pub fn all_voice_notes(&self) -> Result<Vec<PathBuf>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare("SELECT voice_note_path_b64 FROM voice_notes")?;
+
+        let result = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .flatten()
+            .flat_map(|x| path_encoding::from_base64(&x))
+            .map(|x| self.library_base_path.join(x))
+            .collect();
+
+        Ok(result)
+    }
+
+    /// Forget a voice note, such as one that no longer exists on the file system.
+    pub fn remove_voice_note(&mut self, note_path: &Path) -> Result<()> {
+        let note_path = note_path.strip_prefix(&self.library_base_path)?;
+        let note_path_b64 = path_encoding::to_base64(note_path);
+
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare("DELETE FROM voice_notes WHERE voice_note_path_b64 = ?1")?;
+
+        stmt.execute([note_path_b64])?;
+
+        Ok(())
+    }
+
     /// Gets all pictures in the repository, in ascending order of modification timestamp.
     pub fn all(&self) -> Result<Vec<Picture>> {
         let con = self.con.lock().unwrap();
@@ -213,6 +531,7 @@ impl Repository {
                     pictures.picture_id,
                     pictures.picture_path_b64,
                     pictures.thumbnail_path,
+                    pictures.thumbnail_max_edge,
                     COALESCE(
                         pictures.exif_created_ts,
                         pictures.exif_modified_ts,
@@ -244,6 +563,7 @@ impl Repository {
                     pictures.picture_id,
                     pictures.picture_path_b64,
                     pictures.thumbnail_path,
+                    pictures.thumbnail_max_edge,
                     COALESCE(
                         pictures.exif_created_ts,
                         pictures.exif_modified_ts,
@@ -266,6 +586,35 @@ impl Repository {
         Ok(result)
     }
 
+    /// Gets all pictures without an EXIF location that have an EXIF creation timestamp,
+    /// which makes them candidates for geotagging from a GPS track.
+    /// The ordering timestamp of each picture will be the EXIF creation timestamp.
+    pub fn find_need_geotag(&self) -> Result<Vec<Picture>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare(
+            "SELECT
+                    pictures.picture_id,
+                    pictures.picture_path_b64,
+                    pictures.thumbnail_path,
+                    pictures.thumbnail_max_edge,
+                    pictures.exif_created_ts AS ordering_ts,
+                    pictures.is_selfie
+                FROM pictures
+                LEFT JOIN pictures_geo USING (picture_id)
+                WHERE COALESCE(pictures_geo.is_inferred, TRUE) IS TRUE
+                AND pictures.exif_created_ts IS NOT NULL
+                AND COALESCE(is_broken, FALSE) IS FALSE
+                ORDER BY ordering_ts ASC",
+        )?;
+
+        let result = stmt
+            .query_map([], |row| self.to_picture(row))?
+            .flatten()
+            .collect();
+
+        Ok(result)
+    }
+
     /// Gets all pictures that haven't been inspected for containing a motion photo.
     pub fn find_need_motion_photo_extract(&self) -> Result<Vec<Picture>> {
         let con = self.con.lock().unwrap();
@@ -274,6 +623,7 @@ impl Repository {
                     pictures.picture_id,
                     pictures.picture_path_b64,
                     pictures.thumbnail_path,
+                    pictures.thumbnail_max_edge,
                     COALESCE(
                         pictures.exif_created_ts,
                         pictures.exif_modified_ts,
@@ -314,16 +664,18 @@ impl Repository {
                         duration_millis,
                         video_codec,
                         rotation,
-                        transcoded_path
+                        transcoded_path,
+                        still_timestamp_micros
                     ) VALUES (
-                        ?1, ?2, ?3, ?4, ?5, ?6, ?7
+                        ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8
                     ) ON CONFLICT (picture_id) DO UPDATE SET
                         extract_version = ?2,
                         video_path = ?3,
                         duration_millis = ?4,
                         video_codec = ?5,
                         rotation = ?6,
-                        transcoded_path = ?7
+                        transcoded_path = ?7,
+                        still_timestamp_micros = ?8
                     ",
                 )?;
 
@@ -343,6 +695,7 @@ impl Repository {
                     video.video_codec,
                     video.rotation,
                     transcoded_path.as_ref().map(|p| p.to_string_lossy()),
+                    video.still_ts.and_then(|x| x.num_microseconds()),
                 ])?;
             } else {
                 let mut stmt = tx.prepare(
@@ -352,15 +705,17 @@ impl Repository {
                     video_path,
                     duration_millis,
                     video_codec,
-                    transcoded_path
+                    transcoded_path,
+                    still_timestamp_micros
                 ) VALUES (
-                    ?1, ?2, NULL, NULL, NULL, NULL
+                    ?1, ?2, NULL, NULL, NULL, NULL, NULL
                 ) ON CONFLICT (picture_id) DO UPDATE SET
                     extract_version = ?2,
                     video_path = NULL,
                     duration_millis = NULL,
                     video_codec = NULL,
-                    transcoded_path = NULL
+                    transcoded_path = NULL,
+                    still_timestamp_micros = NULL
                 ",
                 )?;
 
@@ -385,6 +740,8 @@ impl Repository {
             .map(|p: String| self.cache_dir_base_path.join(p))
             .ok();
 
+        let thumbnail_max_edge = row.get("thumbnail_max_edge").ok();
+
         let ordering_ts = row.get("ordering_ts").expect("must have ordering_ts");
         let is_selfie = row.get("is_selfie").ok();
 
@@ -392,6 +749,7 @@ impl Repository {
             picture_id,
             path: picture_path,
             thumbnail_path,
+            thumbnail_max_edge,
             ordering_ts,
             is_selfie,
         })
@@ -406,3 +764,12 @@ impl Repository {
         Ok(())
     }
 }
+
+/// Path without suffix so sibling pictures, videos, and voice notes can be related.
+fn link_path(path: &Path) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .expect("Must exist");
+    path.with_file_name(stem)
+}
@@ -28,22 +28,47 @@ impl Scanner {
         Ok(Self { scan_base })
     }
 
+    /// Does the path have the file extension of a supported image type?
+    pub fn is_supported(path: &Path) -> bool {
+        let picture_suffixes = [
+            "avif",
+            "heic", // not supported by image-rs
+            "jpeg",
+            "jpg",
+            "jxl",
+            "png",
+            "tiff",
+            "webp",
+        ];
+
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .is_some_and(|ext| picture_suffixes.contains(&ext.as_str()))
+    }
+
+    /// Does the path have the file extension of a voice note? Some cameras record
+    /// voice notes with the same path as the picture they annotate, except for the suffix.
+    pub fn is_voice_note(path: &Path) -> bool {
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .is_some_and(|ext| ext == "wav")
+    }
+
     /// Scans all pictures in the base directory for function `func` to visit.
     pub fn scan_all_visit<F>(&self, func: F)
     where
         F: FnMut(ScannedFile),
     {
-        let picture_suffixes = [
-            String::from("avif"),
-            String::from("heic"), // not supported by image-rs
-            String::from("jpeg"),
-            String::from("jpg"),
-            String::from("jxl"),
-            String::from("png"),
-            String::from("tiff"),
-            String::from("webp"),
-        ];
+        self.scan_matching_visit(Self::is_supported, func);
+    }
 
+    /// Scans all files in the base directory matching `is_match` for function `func` to visit.
+    fn scan_matching_visit<F>(&self, is_match: fn(&Path) -> bool, func: F)
+    where
+        F: FnMut(ScannedFile),
+    {
         WalkDir::new(&self.scan_base)
             .into_iter()
             .inspect(|x| {
@@ -53,22 +78,14 @@ impl Scanner {
             })
             .flatten() // skip files we failed to read
             .filter(|x| x.path().is_file()) // only process files
-            .filter(|x| {
-                // only process supported image types
-                let ext = x
-                    .path()
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_lowercase());
-                picture_suffixes.contains(&ext.unwrap_or(String::from("not_an_image")))
-            })
-            .map(|x| self.scan_one(x.path())) // Get picture info for image path
+            .filter(|x| is_match(x.path())) // only process supported types
+            .map(|x| self.scan_one(x.path())) // Get file info for path
             .inspect(|x| {
                 let _ = x
                     .as_ref()
                     .inspect_err(|e| error!("Failed scanning: {:?}", e));
             })
-            .flatten() // ignore any errors when reading images
+            .flatten() // ignore any errors when reading files
             .for_each(func); // visit
     }
 
@@ -82,6 +99,54 @@ impl Scanner {
         Ok(pics)
     }
 
+    /// Scans all pictures and voice notes in the base directory, walking it once.
+    /// Returns the pictures and then the voice notes.
+    pub fn scan_all_with_voice_notes(&self) -> Result<(Vec<ScannedFile>, Vec<ScannedFile>)> {
+        let mut pics = Vec::new();
+        let mut notes = Vec::new();
+        self.scan_matching_visit(
+            |path| Self::is_supported(path) || Self::is_voice_note(path),
+            |file| {
+                if Self::is_voice_note(&file.path) {
+                    notes.push(file);
+                } else {
+                    pics.push(file);
+                }
+            },
+        );
+        Ok((pics, notes))
+    }
+
+    /// Scans just the given paths, such as newly imported files.
+    /// Paths that aren't pictures are ignored.
+    pub fn scan_paths(&self, paths: &[PathBuf]) -> Result<Vec<ScannedFile>> {
+        Ok(self.scan_matching_paths(paths, Self::is_supported))
+    }
+
+    /// Scans just the given paths for voice notes.
+    /// Paths that aren't voice notes are ignored.
+    pub fn scan_voice_note_paths(&self, paths: &[PathBuf]) -> Result<Vec<ScannedFile>> {
+        Ok(self.scan_matching_paths(paths, Self::is_voice_note))
+    }
+
+    fn scan_matching_paths(
+        &self,
+        paths: &[PathBuf],
+        is_match: fn(&Path) -> bool,
+    ) -> Vec<ScannedFile> {
+        paths
+            .iter()
+            .filter(|x| x.is_file() && is_match(x))
+            .map(|x| self.scan_one(x))
+            .inspect(|x| {
+                let _ = x
+                    .as_ref()
+                    .inspect_err(|e| error!("Failed scanning: {:?}", e));
+            })
+            .flatten()
+            .collect()
+    }
+
     pub fn scan_one(&self, path: &Path) -> Result<ScannedFile> {
         let file = fs::File::open(path)?;
 
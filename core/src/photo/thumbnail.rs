@@ -2,14 +2,20 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::photo::model::PictureId;
+use crate::photo::edit::{self, EditStack};
+use crate::photo::focus::Focus;
+use crate::photo::metadata;
+use crate::photo::model::{Orientation, PictureId};
+use crate::thumbnail::freedesktop::{SharedCache, SharedThumbnail};
+use crate::thumbnail::{self, Format, Shape, Thumbnail};
 use anyhow::*;
 
+use image::codecs::jpeg::JpegEncoder;
 use image::codecs::png::PngEncoder;
 use image::io::Reader as ImageReader;
 use image::DynamicImage;
-use image::ExtendedColorType;
 use image::ImageEncoder;
+use image::{RgbImage, RgbaImage};
 
 use fast_image_resize as fr;
 use fr::images::Image;
@@ -20,16 +26,18 @@ use glycin;
 use std::io::BufWriter;
 use std::io::Write;
 use std::path::{Path, PathBuf};
+use std::rc::Rc;
 use tracing::{event, Level};
 
 use tempfile;
 
-const EDGE: u32 = 200;
-
 /// Thumbnail operations for photos.
 #[derive(Debug, Clone)]
 pub struct Thumbnailer {
     base_path: PathBuf,
+    shape: Shape,
+    format: Format,
+    shared_cache: Option<SharedCache>,
 }
 
 impl Thumbnailer {
@@ -37,87 +45,502 @@ impl Thumbnailer {
         let base_path = PathBuf::from(base_path).join("photo_thumbnails");
         std::fs::create_dir_all(&base_path)?;
 
-        Ok(Thumbnailer { base_path })
+        Ok(Thumbnailer {
+            base_path,
+            shape: Shape::default(),
+            format: Format::default(),
+            shared_cache: None,
+        })
+    }
+
+    /// Shape of thumbnails to generate.
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
     }
 
-    /// Computes a preview square for an image that has been inserted
-    /// into the Repository. Preview image will be written to file system and path returned.
-    pub async fn thumbnail(&self, picture_id: &PictureId, picture_path: &Path) -> Result<PathBuf> {
+    /// File format of thumbnails to generate.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Shared thumbnail cache to make thumbnails from, and to write thumbnails to.
+    pub fn with_shared_cache(mut self, shared_cache: Option<SharedCache>) -> Self {
+        self.shared_cache = shared_cache;
+        self
+    }
+
+    /// Computes previews of every thumbnail size for an image that has been inserted
+    /// into the Repository. Preview images will be written to file system and the path
+    /// of the smallest returned, along with the size of the image.
+    /// Square previews are cropped about the given focus, or else about a detected focus.
+    pub async fn thumbnail(
+        &self,
+        picture_id: &PictureId,
+        picture_path: &Path,
+        focus: Option<Focus>,
+    ) -> Result<Thumbnail> {
         let thumbnail_path = {
             // Create a directory per 1000 thumbnails
             let partition = (picture_id.id() / 1000) as i32;
             let partition = format!("{:0>4}", partition);
-            let file_name = format!("{}_{}x{}.png", picture_id, EDGE, EDGE);
+            let file_name = thumbnail::file_name(picture_id, "", self.shape, self.format);
             self.base_path.join(partition).join(file_name)
         };
 
-        if thumbnail_path.exists() {
-            return Ok(thumbnail_path);
+        if thumbnail::all_exist(&thumbnail_path) {
+            let size = self.existing_size(picture_path, &thumbnail_path);
+            return Ok(Thumbnail {
+                path: thumbnail_path,
+                size,
+                focus: None,
+            });
         } else if let Some(p) = thumbnail_path.parent() {
             let _ = std::fs::create_dir_all(p);
         }
 
+        if let Some(thumbnail) = self.shared_thumbnail(picture_path, &thumbnail_path, focus) {
+            return Ok(thumbnail);
+        }
+
+        let shared = self
+            .shared_cache
+            .as_ref()
+            .map(|cache| (cache, picture_path));
+
         event!(Level::DEBUG, "Standard thumbnail: {:?}", picture_path);
-        let thumbnail = Self::fast_thumbnail(picture_path, &thumbnail_path);
+        let thumbnail = Self::fast_thumbnail(
+            picture_path,
+            &thumbnail_path,
+            self.shape,
+            self.format,
+            focus,
+            shared,
+        );
+
+        let thumbnail = match thumbnail {
+            std::result::Result::Ok(thumbnail) => thumbnail,
+            Err(_) => {
+                event!(Level::DEBUG, "Fallback thumbnail: {:?}", picture_path);
+                Self::fallback_thumbnail(
+                    picture_path,
+                    &thumbnail_path,
+                    self.shape,
+                    self.format,
+                    focus,
+                    shared,
+                )
+                .await?
+            }
+        };
 
-        if thumbnail.is_err() {
-            event!(Level::DEBUG, "Fallback thumbnail: {:?}", picture_path);
-            Self::fallback_thumbnail(picture_path, &thumbnail_path).await?;
+        Ok(thumbnail)
+    }
+
+    /// Remove the standard square thumbnails of a photo so that the next call to
+    /// `thumbnail` regenerates them, such as after the focus has changed.
+    /// Aspect-preserving thumbnails aren't cropped, so don't depend on the focus.
+    pub fn remove_square(&self, picture_id: &PictureId) -> Result<()> {
+        let partition = format!("{:0>4}", (picture_id.id() / 1000) as i32);
+        let file_name = thumbnail::file_name(picture_id, "", Shape::Square, self.format);
+        let thumbnail_path = self.base_path.join(partition).join(file_name);
+
+        for edge in thumbnail::EDGES {
+            let path = thumbnail::sized_path(&thumbnail_path, edge);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
         }
 
-        Ok(thumbnail_path)
+        Ok(())
     }
 
-    pub fn fast_thumbnail(path: &Path, thumbnail_path: &Path) -> Result<()> {
-        let src_image = ImageReader::open(path)?.decode()?.into_rgb8();
+    /// Make thumbnails from thumbnails in the shared cache, which is much quicker than
+    /// decoding the original. Each size is made from the smallest shared thumbnail that is
+    /// large enough for it, and nothing is written unless every size can be made.
+    /// Shared thumbnails have the EXIF orientation applied, so it is undone to match
+    /// thumbnails made from the original.
+    fn shared_thumbnail(
+        &self,
+        picture_path: &Path,
+        thumbnail_path: &Path,
+        focus: Option<Focus>,
+    ) -> Option<Thumbnail> {
+        let cache = self.shared_cache.as_ref()?;
+
+        // Image-rs can read the size of most images from the header without decoding them.
+        let size = image::image_dimensions(picture_path).ok();
+
+        // Shared thumbnail for each size, smallest first. Sizes often share a thumbnail.
+        let mut shared: Vec<(u32, Rc<SharedThumbnail>)> = Vec::new();
+        for edge in thumbnail::EDGES {
+            let min_edge = self.shared_edge(edge, size);
+            let previous = shared.last().map(|(_, thumbnail)| thumbnail.clone());
+            let thumbnail = match previous {
+                Some(thumbnail) if longest_edge(&thumbnail.image) >= min_edge => thumbnail,
+                _ => Rc::new(cache.find(picture_path, min_edge)?),
+            };
+            shared.push((edge, thumbnail));
+        }
+        event!(Level::DEBUG, "Shared thumbnail: {:?}", picture_path);
+
+        let orientation = Self::exif_orientation(picture_path);
+
+        self.write_shared(shared, orientation, thumbnail_path, focus)
+            .inspect_err(|e| {
+                event!(
+                    Level::DEBUG,
+                    "Failed thumbnail from shared thumbnail: {:?}",
+                    e
+                )
+            })
+            .ok()
+            .map(|thumbnail| Thumbnail {
+                // Size recorded with a shared thumbnail is of the original before orientation.
+                size: size.or(thumbnail.size),
+                ..thumbnail
+            })
+    }
 
-        // WARNING src_image, dst_image, and the PngEncoder must all
-        // use the _same_ pixel type or the PngEncoder will throw errors
+    /// Longest edge a shared thumbnail needs for a thumbnail with an edge length.
+    /// Square thumbnails are cropped, so the shorter edge must cover the square.
+    /// Shared thumbnails are never larger than the original.
+    fn shared_edge(&self, edge: u32, size: Option<(u32, u32)>) -> u32 {
+        let Some((width, height)) = size else {
+            return edge;
+        };
+
+        let (long, short) = (width.max(height), width.min(height).max(1));
+        let min_edge = match self.shape {
+            Shape::Square => (edge as f64 * long as f64 / short as f64).ceil() as u32,
+            Shape::Aspect => edge,
+        };
+        min_edge.min(long)
+    }
+
+    /// Write each size of thumbnail from its shared thumbnail.
+    fn write_shared(
+        &self,
+        shared: Vec<(u32, Rc<SharedThumbnail>)>,
+        orientation: Orientation,
+        thumbnail_path: &Path,
+        focus: Option<Focus>,
+    ) -> Result<Thumbnail> {
+        let mut detected = None;
+        let mut size = None;
+
+        // Largest first, so that the focus is detected on the largest shared thumbnail.
+        for (edge, thumbnail) in shared.into_iter().rev() {
+            let image = orientation.inverse().apply_to(thumbnail.image.clone());
+            let image = if image.color().has_alpha() && self.format.has_alpha() {
+                DynamicImage::ImageRgba8(image.into_rgba8())
+            } else {
+                DynamicImage::ImageRgb8(image.into_rgb8())
+            };
+
+            if focus.is_none() && detected.is_none() {
+                detected = Some(Focus::detect(&image.to_rgb8()));
+            }
+            let crop_focus = focus.or(detected).unwrap_or_default();
+
+            size = size.or(thumbnail.size);
+
+            let (width, height) = self.shape.thumbnail_size(edge, (image.width(), image.height()));
+            let image = Self::resize(image, width, height, crop_focus)?;
+            Self::write_image(
+                &image,
+                &thumbnail::sized_path(thumbnail_path, edge),
+                self.format,
+            )?;
+        }
+
+        Self::remove_other_variants(thumbnail_path);
+
+        Ok(Thumbnail {
+            path: PathBuf::from(thumbnail_path),
+            size,
+            focus: detected,
+        })
+    }
+
+    /// Orientation that other thumbnailers apply when writing to the shared cache.
+    fn exif_orientation(path: &Path) -> Orientation {
+        metadata::from_path(path)
+            .ok()
+            .and_then(|m| m.orientation)
+            .unwrap_or(Orientation::North)
+    }
+
+    /// Write thumbnails of an image fitted within the largest thumbnail to the shared cache.
+    /// Failing to write to the shared cache doesn't fail thumbnail generation.
+    fn save_shared(
+        cache: &SharedCache,
+        original_path: &Path,
+        image: &DynamicImage,
+        size: (u32, u32),
+    ) {
+        if !cache.is_writable() {
+            return;
+        }
+
+        let orientation = Self::exif_orientation(original_path);
+        let image = orientation.apply_to(image.clone()).into_rgb8();
+
+        if let Err(e) = cache.save(original_path, &image, size) {
+            event!(
+                Level::WARN,
+                "Failed writing shared thumbnail for {:?}: {:?}",
+                original_path,
+                e
+            );
+        }
+    }
+
+    /// Size of an image whose thumbnails already exist, without decoding the image.
+    /// If image-rs can't read the image header, then aspect-preserving thumbnails
+    /// still give the aspect ratio.
+    fn existing_size(&self, picture_path: &Path, thumbnail_path: &Path) -> Option<(u32, u32)> {
+        image::image_dimensions(picture_path).ok().or_else(|| {
+            if self.shape == Shape::Aspect {
+                let largest = thumbnail::sized_path(thumbnail_path, thumbnail::LARGEST_EDGE);
+                image::image_dimensions(largest).ok()
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Computes previews for a photo with edits applied. Orientation edits are
+    /// left for the viewer to apply, as with the EXIF orientation.
+    /// If the edits don't change pixels, then the standard thumbnail is used.
+    pub async fn edited_thumbnail(
+        &self,
+        picture_id: &PictureId,
+        picture_path: &Path,
+        orientation: Orientation,
+        edits: &EditStack,
+        focus: Option<Focus>,
+    ) -> Result<Thumbnail> {
+        if !edits.adjustments().has_pixel_changes() {
+            return self.thumbnail(picture_id, picture_path, focus).await;
+        }
+
+        let thumbnail_path = {
+            let partition = (picture_id.id() / 1000) as i32;
+            let partition = format!("{:0>4}", partition);
+            let file_name = thumbnail::file_name(picture_id, "_edited", self.shape, self.format);
+            self.base_path.join(partition).join(file_name)
+        };
+
+        if let Some(p) = thumbnail_path.parent() {
+            let _ = std::fs::create_dir_all(p);
+        }
+
+        event!(Level::DEBUG, "Edited thumbnail: {:?}", picture_path);
+        let image = edit::load_source(picture_path).await?;
+        let image = edits.render_unoriented(image, orientation);
+        Self::write_thumbnail(image, &thumbnail_path, self.shape, self.format, focus, None)
+    }
+
+    /// Write thumbnails for an image that image-rs can decode.
+    /// If a shared cache and the original file are given, then thumbnails are also
+    /// written to the shared cache for the original.
+    pub fn fast_thumbnail(
+        path: &Path,
+        thumbnail_path: &Path,
+        shape: Shape,
+        format: Format,
+        focus: Option<Focus>,
+        shared: Option<(&SharedCache, &Path)>,
+    ) -> Result<Thumbnail> {
+        let src_image = ImageReader::open(path)?.decode()?;
+        Self::write_thumbnail(src_image, thumbnail_path, shape, format, focus, shared)
+    }
+
+    /// Write every size of thumbnail, given the path of the smallest.
+    /// Square thumbnails are cropped about the focus, which is detected if not given.
+    /// Alpha is kept if both the image and the format have it.
+    pub fn write_thumbnail(
+        src_image: DynamicImage,
+        thumbnail_path: &Path,
+        shape: Shape,
+        format: Format,
+        focus: Option<Focus>,
+        shared: Option<(&SharedCache, &Path)>,
+    ) -> Result<Thumbnail> {
+        let size = (src_image.width(), src_image.height());
+
+        // Scale the image down to the largest thumbnail first, which is much quicker
+        // to make each size from than the original image. Square thumbnails are cropped
+        // from it, so it must cover the largest square rather than fit within it.
+        let (width, height) = match shape {
+            Shape::Square => cover_size(thumbnail::LARGEST_EDGE, size),
+            Shape::Aspect => Shape::Aspect.thumbnail_size(thumbnail::LARGEST_EDGE, size),
+        };
+        let src_image = if src_image.color().has_alpha() && format.has_alpha() {
+            DynamicImage::ImageRgba8(src_image.into_rgba8())
+        } else {
+            DynamicImage::ImageRgb8(src_image.into_rgb8())
+        };
+        let mut src_image = if (src_image.width(), src_image.height()) == (width, height) {
+            src_image
+        } else {
+            Self::resize(src_image, width, height, Focus::default())?
+        };
+
+        // The shared cache takes images fitted within the largest thumbnail. Detecting
+        // the focus on that is also much quicker than on the original image.
+        let (width, height) = Shape::Aspect.thumbnail_size(thumbnail::LARGEST_EDGE, size);
+        let fitted_image = if (src_image.width(), src_image.height()) == (width, height) {
+            None
+        } else {
+            Some(Self::resize(src_image.clone(), width, height, Focus::default())?)
+        };
+        let fitted_image = fitted_image.as_ref().unwrap_or(&src_image);
+
+        if let Some((cache, original_path)) = shared {
+            Self::save_shared(cache, original_path, fitted_image, size);
+        }
+
+        let detected = if focus.is_none() {
+            Some(Focus::detect(&fitted_image.to_rgb8()))
+        } else {
+            None
+        };
+        let crop_focus = focus.or(detected).unwrap_or_default();
+
+        // Each size is resized from the next size up, which is much quicker than
+        // resizing from the original image every time.
+        for edge in thumbnail::EDGES.into_iter().rev() {
+            let (width, height) = shape.thumbnail_size(edge, size);
+            let dst_image = if (src_image.width(), src_image.height()) == (width, height) {
+                src_image
+            } else {
+                Self::resize(src_image, width, height, crop_focus)?
+            };
+            Self::write_image(
+                &dst_image,
+                &thumbnail::sized_path(thumbnail_path, edge),
+                format,
+            )?;
+            src_image = dst_image;
+        }
+
+        Self::remove_other_variants(thumbnail_path);
+
+        Ok(Thumbnail {
+            path: PathBuf::from(thumbnail_path),
+            size: Some(size),
+            focus: detected,
+        })
+    }
+
+    /// Remove thumbnails of the same item in other shapes and formats, which were made before
+    /// the preferences changed. Failing to remove them doesn't fail thumbnail generation.
+    fn remove_other_variants(thumbnail_path: &Path) {
+        if let Err(e) = thumbnail::remove_other_variants(thumbnail_path) {
+            event!(
+                Level::WARN,
+                "Failed removing old thumbnails of {:?}: {:?}",
+                thumbnail_path,
+                e
+            );
+        }
+    }
+
+    /// Resize image to fill a width and height, cropping about the focus
+    /// if the aspect ratios differ.
+    fn resize(
+        src_image: DynamicImage,
+        width: u32,
+        height: u32,
+        focus: Focus,
+    ) -> Result<DynamicImage> {
+        // WARNING src_image, dst_image, and the encoder must all
+        // use the _same_ pixel type or the encoder will throw errors
         // about having an unexpected number of bytes.
         // PixelType::U8x3 == RGB8
         // PixelType::U8x4 == RGBA8
         //
-        // For now I'm using RGB, not RGBA, because I don't think an alpha channel
-        // makes sense for thumbnails.
+        // Alpha is only kept for images that have it, because most photos don't
+        // and RGBA thumbnails would be a third larger for nothing.
 
-        let src_image = DynamicImage::ImageRgb8(src_image);
+        let centering = focus.centering((src_image.width(), src_image.height()), (width, height));
+        let has_alpha = src_image.color().has_alpha();
 
-        let mut dst_image = Image::new(EDGE, EDGE, fr::PixelType::U8x3);
+        let pixel_type = if has_alpha {
+            fr::PixelType::U8x4
+        } else {
+            fr::PixelType::U8x3
+        };
+        let mut dst_image = Image::new(width, height, pixel_type);
 
         let mut resizer = Resizer::new();
 
         resizer.resize(
             &src_image,
             &mut dst_image,
-            &ResizeOptions::new().fit_into_destination(Some((0.5, 0.5))),
+            &ResizeOptions::new().fit_into_destination(Some(centering)),
         )?;
 
-        // Write destination image as PNG-file
+        let dst_image = if has_alpha {
+            RgbaImage::from_raw(width, height, dst_image.into_vec()).map(DynamicImage::ImageRgba8)
+        } else {
+            RgbImage::from_raw(width, height, dst_image.into_vec()).map(DynamicImage::ImageRgb8)
+        };
+
+        dst_image.ok_or_else(|| anyhow!("Resized image has wrong size"))
+    }
+
+    fn write_image(image: &DynamicImage, thumbnail_path: &Path, format: Format) -> Result<()> {
         // Write to temporary file first and then move so that an interrupted write
         // doesn't result in a corrupt thumbnail
 
-        let temporary_png_file = thumbnail_path.with_extension("tmp");
+        let temporary_file = thumbnail_path.with_extension("tmp");
 
-        let file = std::fs::File::create(&temporary_png_file)?;
+        let file = std::fs::File::create(&temporary_file)?;
         let mut file = BufWriter::new(file);
 
-        PngEncoder::new(&mut file).write_image(
-            dst_image.buffer(),
-            EDGE,
-            EDGE,
-            ExtendedColorType::Rgb8,
-        )?;
+        let (width, height) = (image.width(), image.height());
+        let color_type = image.color().into();
+
+        match format {
+            Format::Png => PngEncoder::new(&mut file).write_image(
+                image.as_bytes(),
+                width,
+                height,
+                color_type,
+            )?,
+            Format::WebP { quality } => {
+                let encoder = if image.color().has_alpha() {
+                    webp::Encoder::from_rgba(image.as_bytes(), width, height)
+                } else {
+                    webp::Encoder::from_rgb(image.as_bytes(), width, height)
+                };
+                file.write_all(&encoder.encode(quality as f32))?;
+            }
+            Format::Jpeg { quality } => JpegEncoder::new_with_quality(&mut file, quality)
+                .write_image(image.as_bytes(), width, height, color_type)?,
+        }
 
         file.flush()?;
 
-        std::fs::rename(temporary_png_file, thumbnail_path)?;
+        std::fs::rename(temporary_file, thumbnail_path)?;
 
         Ok(())
     }
 
     /// Copy an image to a PNG file using Glycin, and then use image-rs to compute the thumbnail.
     /// This is the fallback if image-rs can't decode the original image (such as HEIC images).
-    pub async fn fallback_thumbnail(source_path: &Path, thumbnail_path: &Path) -> Result<()> {
+    pub async fn fallback_thumbnail(
+        source_path: &Path,
+        thumbnail_path: &Path,
+        shape: Shape,
+        format: Format,
+        focus: Option<Focus>,
+        shared: Option<(&SharedCache, &Path)>,
+    ) -> Result<Thumbnail> {
         let file = gio::File::for_path(source_path);
 
         let image = glycin::Loader::new(file).load().await?;
@@ -128,6 +551,98 @@ impl Thumbnailer {
 
         frame.texture.save_to_png(png_file.path())?;
 
-        Self::fast_thumbnail(png_file.path(), thumbnail_path)
+        Self::fast_thumbnail(
+            png_file.path(),
+            thumbnail_path,
+            shape,
+            format,
+            focus,
+            shared,
+        )
+    }
+}
+
+/// Size of an image scaled so that its shorter edge is an edge length, which covers
+/// a square of that edge length. Images that are already small enough keep their size.
+fn cover_size(edge: u32, (width, height): (u32, u32)) -> (u32, u32) {
+    if width.min(height) <= edge {
+        (width, height)
+    } else if width >= height {
+        let width = (width as f64 * edge as f64 / height as f64).round();
+        (width as u32, edge)
+    } else {
+        let height = (height as f64 * edge as f64 / width as f64).round();
+        (edge, height as u32)
+    }
+}
+
+/// Longest edge of an image.
+fn longest_edge(image: &DynamicImage) -> u32 {
+    image.width().max(image.height())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{ImageFormat, Rgb};
+
+    #[test]
+    fn test_shared_thumbnail_from_smaller_shared_sizes() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = SharedCache::build(cache_dir.path()).with_writes(true);
+
+        let photo = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        RgbImage::from_pixel(500, 375, Rgb([0, 0, 255]))
+            .save_with_format(photo.path(), ImageFormat::Png)
+            .unwrap();
+
+        // Only large and x-large shared thumbnails exist. A different colour to
+        // the original shows which thumbnails were made from.
+        let shared = RgbImage::from_pixel(500, 375, Rgb([255, 0, 0]));
+        cache.save(photo.path(), &shared, (500, 375)).unwrap();
+
+        let thumbnail_dir = tempfile::tempdir().unwrap();
+        let thumbnailer = Thumbnailer::build(thumbnail_dir.path())
+            .unwrap()
+            .with_shape(Shape::Aspect)
+            .with_shared_cache(Some(cache));
+
+        let thumbnail_path = thumbnail_dir.path().join("1_aspect_200x200.png");
+        let thumbnail = thumbnailer
+            .shared_thumbnail(photo.path(), &thumbnail_path, None)
+            .expect("Thumbnail from shared thumbnails");
+
+        assert_eq!(thumbnail.size, Some((500, 375)));
+        assert!(thumbnail::all_exist(&thumbnail_path));
+
+        let largest = image::open(thumbnail::sized_path(&thumbnail_path, 800)).unwrap();
+        assert_eq!((largest.width(), largest.height()), (800, 600));
+        assert_eq!(largest.to_rgb8().get_pixel(400, 300), &Rgb([255, 0, 0]));
+    }
+
+    #[test]
+    fn test_shared_thumbnail_too_small() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = SharedCache::build(cache_dir.path()).with_writes(true);
+
+        let photo = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        let image = RgbImage::from_pixel(2000, 1500, Rgb([0, 0, 255]));
+        image
+            .save_with_format(photo.path(), ImageFormat::Png)
+            .unwrap();
+        cache.save(photo.path(), &image, (2000, 1500)).unwrap();
+
+        let thumbnail_dir = tempfile::tempdir().unwrap();
+        let thumbnailer = Thumbnailer::build(thumbnail_dir.path())
+            .unwrap()
+            .with_shape(Shape::Aspect)
+            .with_shared_cache(Some(cache));
+
+        // x-large shared thumbnails can't make an 800 pixel thumbnail.
+        let thumbnail_path = thumbnail_dir.path().join("1_aspect_200x200.png");
+        assert!(thumbnailer
+            .shared_thumbnail(photo.path(), &thumbnail_path, None)
+            .is_none());
+        assert!(!thumbnail::sized_path(&thumbnail_path, 200).exists());
     }
 }
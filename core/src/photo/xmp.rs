@@ -0,0 +1,305 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Properties from the XMP packets embedded in photos.
+//!
+//! XMP is RDF written as XML, where a property can be either an attribute or an element,
+//! so properties are looked up by namespace and name regardless of how they were written.
+//! A file can have more than one packet, such as when a depth map or gain map embedded
+//! in a photo has its own packet, so the packets of the whole file are read together.
+
+use quick_xml::events::Event;
+use quick_xml::name::ResolveResult;
+use quick_xml::NsReader;
+use std::path::Path;
+use tracing::{event, Level};
+
+/// Namespaces of properties used to recognise how a photo was taken and what is embedded in it.
+pub mod ns {
+    /// Apple depth data of portrait photos.
+    pub const APPLE_DEPTH_DATA: &str = "http://ns.apple.com/depthData/1.0/";
+
+    /// EXIF properties copied into XMP, such as by iOS for screenshots.
+    pub const EXIF: &str = "http://ns.adobe.com/exif/1.0/";
+
+    /// Google camera properties, such as where the video of a motion photo is.
+    pub const GOOGLE_CAMERA: &str = "http://ns.google.com/photos/1.0/camera/";
+
+    /// Google container of media appended to a photo, such as the video of a motion photo.
+    pub const GOOGLE_CONTAINER: &str = "http://ns.google.com/photos/1.0/container/";
+
+    /// Properties of items of a Google container.
+    pub const GOOGLE_CONTAINER_ITEM: &str = "http://ns.google.com/photos/1.0/container/item/";
+
+    /// Google depth map of portrait photos from older Android phones.
+    pub const GOOGLE_DEPTH_MAP: &str = "http://ns.google.com/photos/1.0/depthmap/";
+
+    /// Google photo sphere and panorama properties.
+    pub const GOOGLE_PANORAMA: &str = "http://ns.google.com/photos/1.0/panorama/";
+
+    /// Gain map for showing a photo on an HDR display, such as Android Ultra HDR photos.
+    pub const HDR_GAIN_MAP: &str = "http://ns.adobe.com/hdr-gain-map/1.0/";
+}
+
+/// Start and end of an XMP packet.
+const XMPMETA_START: &[u8] = b"<x:xmpmeta";
+const XMPMETA_END: &[u8] = b"</x:xmpmeta>";
+
+/// Properties of all XMP packets in a file.
+#[derive(Debug, Clone, Default)]
+pub struct Xmp {
+    /// Elements in the order they appear in the packets.
+    elements: Vec<Element>,
+}
+
+/// An element of an XMP packet.
+#[derive(Debug, Clone)]
+pub struct Element {
+    pub namespace: String,
+
+    pub name: String,
+
+    /// Namespace, name, and value of attributes.
+    pub attributes: Vec<(String, String, String)>,
+
+    /// Text directly within the element.
+    pub text: String,
+
+    /// Nesting depth of the element, for finding the text of nested values.
+    depth: usize,
+}
+
+impl Element {
+    /// Value of an attribute.
+    pub fn attribute(&self, namespace: &str, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(ns, n, _)| ns == namespace && n == name)
+            .map(|(_, _, value)| value.as_str())
+    }
+}
+
+impl Xmp {
+    /// Read the XMP packets of a file.
+    pub fn from_path(path: &Path) -> std::io::Result<Xmp> {
+        let data = std::fs::read(path)?;
+        Ok(Xmp::from_bytes(&data))
+    }
+
+    /// Find and parse the XMP packets in the data of a file. Packets that can't
+    /// be parsed are skipped.
+    pub fn from_bytes(data: &[u8]) -> Xmp {
+        let mut xmp = Xmp::default();
+
+        let mut offset = 0;
+        while let Some(start) = find(&data[offset..], XMPMETA_START) {
+            let start = offset + start;
+            let Some(end) = find(&data[start..], XMPMETA_END) else {
+                break;
+            };
+
+            // A packet cut short is followed by the start of the next packet.
+            if let Some(next) = find(&data[start + 1..start + end], XMPMETA_START) {
+                offset = start + 1 + next;
+                continue;
+            }
+
+            let end = start + end + XMPMETA_END.len();
+            offset = end;
+
+            let Ok(packet) = std::str::from_utf8(&data[start..end]) else {
+                continue;
+            };
+
+            if let Err(e) = xmp.parse(packet) {
+                event!(Level::DEBUG, "Skipping unparsable XMP packet: {:?}", e);
+            }
+        }
+
+        xmp
+    }
+
+    fn parse(&mut self, packet: &str) -> quick_xml::Result<()> {
+        let mut reader = NsReader::from_str(packet);
+        reader.trim_text(true);
+
+        // Indexes of elements currently open.
+        let mut open: Vec<usize> = Vec::new();
+
+        loop {
+            let (ns, event) = reader.read_resolved_event()?;
+            let ns = namespace(&ns);
+            match event {
+                Event::Start(ref e) | Event::Empty(ref e) => {
+                    let is_empty = matches!(event, Event::Empty(_));
+
+                    let attributes = e
+                        .attributes()
+                        .flatten()
+                        .filter_map(|attr| {
+                            let (ns, name) = reader.resolve_attribute(attr.key);
+                            let value = attr.unescape_value().ok()?;
+                            Some((
+                                namespace(&ns),
+                                String::from_utf8_lossy(name.as_ref()).to_string(),
+                                value.to_string(),
+                            ))
+                        })
+                        .collect();
+
+                    self.elements.push(Element {
+                        namespace: ns,
+                        name: String::from_utf8_lossy(e.local_name().as_ref()).to_string(),
+                        attributes,
+                        text: String::new(),
+                        depth: open.len(),
+                    });
+
+                    if !is_empty {
+                        open.push(self.elements.len() - 1);
+                    }
+                }
+                Event::Text(e) => {
+                    if let Some(&index) = open.last() {
+                        self.elements[index].text.push_str(&e.unescape()?);
+                    }
+                }
+                Event::End(_) => {
+                    open.pop();
+                }
+                Event::Eof => break,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Is any property in a namespace?
+    pub fn has_namespace(&self, namespace: &str) -> bool {
+        self.elements.iter().any(|e| {
+            e.namespace == namespace || e.attributes.iter().any(|(ns, _, _)| ns == namespace)
+        })
+    }
+
+    /// Elements with a name.
+    pub fn elements<'a>(
+        &'a self,
+        namespace: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a Element> + 'a {
+        self.elements
+            .iter()
+            .filter(move |e| e.namespace == namespace && e.name == name)
+    }
+
+    /// All values of a property. Values of elements that hold a list or alternatives,
+    /// such as text in several languages, are the text of the first item.
+    pub fn values<'a>(
+        &'a self,
+        namespace: &'a str,
+        name: &'a str,
+    ) -> impl Iterator<Item = &'a str> + 'a {
+        let attributes = self
+            .elements
+            .iter()
+            .filter_map(move |e| e.attribute(namespace, name));
+
+        let elements = self
+            .elements
+            .iter()
+            .enumerate()
+            .filter(move |(_, e)| e.namespace == namespace && e.name == name)
+            .filter_map(|(index, e)| {
+                let nested = self.elements[index + 1..]
+                    .iter()
+                    .take_while(|x| x.depth > e.depth);
+
+                std::iter::once(e)
+                    .chain(nested)
+                    .map(|x| x.text.as_str())
+                    .find(|text| !text.is_empty())
+            });
+
+        attributes.chain(elements)
+    }
+
+    /// First value of a property.
+    pub fn value<'a>(&'a self, namespace: &'a str, name: &'a str) -> Option<&'a str> {
+        self.values(namespace, name).next()
+    }
+}
+
+fn namespace(ns: &ResolveResult) -> String {
+    match ns {
+        ResolveResult::Bound(ns) => String::from_utf8_lossy(ns.as_ref()).to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Position of the first occurrence of a needle in a haystack.
+pub(crate) fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKET: &str = r#"<?xpacket begin="" id="W5M0MpCehiHzreSzNTczkc9d"?>
+<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description rdf:about=""
+        xmlns:GPano="http://ns.google.com/photos/1.0/panorama/"
+        xmlns:exif="http://ns.adobe.com/exif/1.0/"
+        GPano:ProjectionType="equirectangular">
+      <GPano:UsePanoramaViewer>True</GPano:UsePanoramaViewer>
+      <exif:UserComment>
+        <rdf:Alt>
+          <rdf:li xml:lang="x-default">Screenshot</rdf:li>
+        </rdf:Alt>
+      </exif:UserComment>
+    </rdf:Description>
+  </rdf:RDF>
+</x:xmpmeta>
+<?xpacket end="w"?>"#;
+
+    #[test]
+    fn test_values_of_attributes_and_elements() {
+        let mut data = b"\xff\xd8\xff\xe1junk".to_vec();
+        data.extend_from_slice(PACKET.as_bytes());
+
+        let xmp = Xmp::from_bytes(&data);
+
+        assert_eq!(
+            Some("equirectangular"),
+            xmp.value(ns::GOOGLE_PANORAMA, "ProjectionType")
+        );
+        assert_eq!(
+            Some("True"),
+            xmp.value(ns::GOOGLE_PANORAMA, "UsePanoramaViewer")
+        );
+        assert_eq!(Some("Screenshot"), xmp.value(ns::EXIF, "UserComment"));
+        assert_eq!(None, xmp.value(ns::EXIF, "DateTimeOriginal"));
+        assert!(xmp.has_namespace(ns::GOOGLE_PANORAMA));
+        assert!(!xmp.has_namespace(ns::HDR_GAIN_MAP));
+    }
+
+    #[test]
+    fn test_all_packets_are_read() {
+        let gain_map = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/">
+  <rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+    <rdf:Description xmlns:hdrgm="http://ns.adobe.com/hdr-gain-map/1.0/" hdrgm:Version="1.0"/>
+  </rdf:RDF>
+</x:xmpmeta>"#;
+
+        let data = format!("{}\u{0}<x:xmpmeta broken\u{0}{}", PACKET, gain_map);
+        let xmp = Xmp::from_bytes(data.as_bytes());
+
+        assert!(xmp.has_namespace(ns::GOOGLE_PANORAMA));
+        assert_eq!(Some("1.0"), xmp.value(ns::HDR_GAIN_MAP, "Version"));
+    }
+}
@@ -0,0 +1,238 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Size of the thumbnail cache, and trimming the cache to fit within a budget.
+//! Cached files of any kind, such as transcoded videos, can be sized and removed
+//! least recently viewed first in the same way.
+//!
+//! Larger sizes of thumbnail are removed before smaller sizes, and of each size the
+//! thumbnails that were viewed longest ago are removed first. When a thumbnail was last
+//! viewed is taken from its access time. File systems mounted with `relatime`, the default,
+//! update access times at most once a day, which is precise enough. File systems mounted
+//! with `noatime` never do, so thumbnails generated longest ago are removed first instead.
+
+use super::{smallest_path, EDGES, SMALLEST_EDGE};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use tracing::{event, Level};
+use walkdir::WalkDir;
+
+/// A file in a cache.
+#[derive(Debug)]
+pub struct CachedFile {
+    pub path: PathBuf,
+
+    pub bytes: u64,
+
+    /// When the file was last read, or written if access times aren't available.
+    pub accessed: SystemTime,
+}
+
+/// Thumbnails of an item that had larger sizes removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trimmed {
+    /// Path of the smallest thumbnail of the item.
+    pub path: PathBuf,
+
+    /// Largest edge length of the thumbnails that remain.
+    pub max_edge: u32,
+}
+
+/// Files under some directories. Partly written files, which have a `tmp` extension
+/// before any other extension, are ignored.
+pub fn files(dirs: &[PathBuf]) -> Vec<CachedFile> {
+    dirs.iter()
+        .flat_map(|dir| WalkDir::new(dir).into_iter().flatten())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| !is_partial(entry.path()))
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let accessed = metadata
+                .accessed()
+                .or_else(|_| metadata.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            Some(CachedFile {
+                path: entry.into_path(),
+                bytes: metadata.len(),
+                accessed,
+            })
+        })
+        .collect()
+}
+
+/// Total bytes of the files under some directories, except partly written files.
+pub fn size(dirs: &[PathBuf]) -> u64 {
+    files(dirs).iter().map(|file| file.bytes).sum()
+}
+
+/// Remove files in the order given until the total bytes fit within a budget.
+/// Each file comes with a value that is returned along with it if it is removed.
+pub fn remove_in_order<T>(
+    files: Vec<(CachedFile, T)>,
+    mut total: u64,
+    budget: u64,
+) -> Vec<(CachedFile, T)> {
+    let mut removed = Vec::new();
+
+    for (file, value) in files {
+        if total <= budget {
+            break;
+        }
+
+        if let Err(e) = std::fs::remove_file(&file.path) {
+            event!(Level::WARN, "Failed removing cached file {:?}: {}", file.path, e);
+            continue;
+        }
+
+        total = total.saturating_sub(file.bytes);
+        removed.push((file, value));
+    }
+
+    removed
+}
+
+/// Remove thumbnails larger than the smallest size until the thumbnails under some
+/// directories fit within a budget of bytes. The smallest size is always kept, because
+/// albums show it. Returns the items that had thumbnails removed.
+pub fn trim(dirs: &[PathBuf], budget: u64) -> Vec<Trimmed> {
+    // Path of the smallest thumbnail of the same item, and edge length, of each thumbnail.
+    // Other files are ignored.
+    let mut thumbnails: Vec<(CachedFile, (PathBuf, u32))> = files(dirs)
+        .into_iter()
+        .filter_map(|file| {
+            let smallest = smallest_path(&file.path)?;
+            Some((file, smallest))
+        })
+        .collect();
+
+    let total: u64 = thumbnails.iter().map(|(file, _)| file.bytes).sum();
+    if total <= budget {
+        return Vec::new();
+    }
+
+    thumbnails.retain(|(_, (_, edge))| *edge > SMALLEST_EDGE);
+    thumbnails.sort_by(|(a, (_, a_edge)), (b, (_, b_edge))| {
+        b_edge.cmp(a_edge).then(a.accessed.cmp(&b.accessed))
+    });
+
+    let mut max_edges: HashMap<PathBuf, u32> = HashMap::new();
+
+    for (_, (smallest, edge)) in remove_in_order(thumbnails, total, budget) {
+        // Larger sizes are removed first, so the next size down is the largest that remains.
+        let remaining = EDGES
+            .iter()
+            .copied()
+            .filter(|e| *e < edge)
+            .max()
+            .unwrap_or(SMALLEST_EDGE);
+
+        max_edges
+            .entry(smallest)
+            .and_modify(|max_edge| *max_edge = (*max_edge).min(remaining))
+            .or_insert(remaining);
+    }
+
+    let mut trimmed: Vec<Trimmed> = max_edges
+        .into_iter()
+        .map(|(path, max_edge)| Trimmed { path, max_edge })
+        .collect();
+    trimmed.sort_by(|a, b| a.path.cmp(&b.path));
+    trimmed
+}
+
+/// Is a file only partly written? Such files are renamed once complete.
+fn is_partial(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|name| name.to_str())
+        .is_some_and(|name| name.split('.').skip(1).any(|extension| extension == "tmp"))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::fs::{File, FileTimes};
+    use std::time::Duration;
+
+    /// Write a file of some bytes that was last read some days ago.
+    pub(crate) fn write(dir: &Path, name: &str, bytes: usize, days_ago: u64) -> PathBuf {
+        let path = dir.join(name);
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(&path, vec![0; bytes]).unwrap();
+
+        let accessed = SystemTime::now() - Duration::from_secs(days_ago * 24 * 60 * 60);
+        let file = File::options().write(true).open(&path).unwrap();
+        file.set_times(FileTimes::new().set_accessed(accessed))
+            .unwrap();
+
+        path
+    }
+
+    #[test]
+    fn test_size() {
+        let dir = tempfile::tempdir().unwrap();
+        write(dir.path(), "1_200x200.png", 10, 0);
+        write(dir.path(), "1_400x400.png", 20, 0);
+        write(dir.path(), "1_800x800.tmp", 40, 0);
+        write(dir.path(), "1.tmp.mkv", 80, 0);
+
+        assert_eq!(size(&[dir.path().to_path_buf()]), 30);
+    }
+
+    #[test]
+    fn test_trim_within_budget() {
+        let dir = tempfile::tempdir().unwrap();
+        let large = write(dir.path(), "1_800x800.png", 100, 0);
+
+        assert!(trim(&[dir.path().to_path_buf()], 100).is_empty());
+        assert!(large.exists());
+    }
+
+    #[test]
+    fn test_trim_largest_least_recently_viewed_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let small_1 = write(dir.path(), "1_200x200.png", 10, 9);
+        let medium_1 = write(dir.path(), "1_400x400.png", 40, 9);
+        let large_1 = write(dir.path(), "1_800x800.png", 160, 9);
+        let small_2 = write(dir.path(), "2_200x200.png", 10, 1);
+        let medium_2 = write(dir.path(), "2_400x400.png", 40, 1);
+        let large_2 = write(dir.path(), "2_800x800.png", 160, 1);
+
+        // Removing the largest thumbnail of the item viewed longest ago is enough.
+        let trimmed = trim(&[dir.path().to_path_buf()], 300);
+        assert_eq!(
+            trimmed,
+            vec![Trimmed {
+                path: small_1.clone(),
+                max_edge: 400
+            }]
+        );
+        assert!(!large_1.exists());
+        assert!(large_2.exists());
+
+        // Every large thumbnail goes before any medium thumbnail.
+        let trimmed = trim(&[dir.path().to_path_buf()], 60);
+        assert_eq!(
+            trimmed,
+            vec![
+                Trimmed {
+                    path: small_1.clone(),
+                    max_edge: 200
+                },
+                Trimmed {
+                    path: small_2.clone(),
+                    max_edge: 400
+                },
+            ]
+        );
+        assert!(!medium_1.exists());
+        assert!(!large_2.exists());
+        assert!(medium_2.exists());
+
+        // Smallest thumbnails are never removed.
+        trim(&[dir.path().to_path_buf()], 0);
+        assert!(small_1.exists());
+        assert!(small_2.exists());
+    }
+}
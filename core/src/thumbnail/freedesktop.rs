@@ -0,0 +1,321 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Shared thumbnail cache of the freedesktop.org Thumbnail Managing Standard, usually
+//! at `~/.cache/thumbnails`, which file managers such as Nautilus fill as they browse.
+//!
+//! A thumbnail is named after the MD5 hash of the URI of the original file, and records
+//! the URI and modification time of the original. A thumbnail is only valid if both match.
+//! See <https://specifications.freedesktop.org/thumbnail-spec/latest/>.
+
+use anyhow::*;
+use gio::glib;
+use gio::prelude::FileExt;
+use image::{imageops, DynamicImage, ImageFormat, RgbImage};
+use std::io::{BufWriter, Cursor};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tracing::{event, Level};
+
+/// Directories of the shared cache and the longest edge of the thumbnails in each, smallest first.
+const SIZES: [(&str, u32); 4] = [
+    ("normal", 128),
+    ("large", 256),
+    ("x-large", 512),
+    ("xx-large", 1024),
+];
+
+/// Sizes that generated thumbnails are written back to. Larger sizes would need more
+/// of the original than is kept when generating thumbnails.
+const WRITE_SIZES: [(&str, u32); 2] = [("large", 256), ("x-large", 512)];
+
+/// A thumbnail read from the shared cache.
+#[derive(Debug)]
+pub struct SharedThumbnail {
+    pub image: DynamicImage,
+
+    /// Width and height of the original image, if recorded with the thumbnail.
+    pub size: Option<(u32, u32)>,
+}
+
+/// Shared thumbnail cache.
+#[derive(Debug, Clone)]
+pub struct SharedCache {
+    base_path: PathBuf,
+    is_writable: bool,
+}
+
+impl SharedCache {
+    /// Shared cache of the user, if it exists. Inside Flatpak, the cache of the host
+    /// is used, which the sandbox must have access to.
+    pub fn open() -> Option<SharedCache> {
+        let env_dir = |name: &str| {
+            std::env::var_os(name)
+                .map(PathBuf::from)
+                .filter(|dir| dir.is_absolute())
+        };
+
+        let cache_dir = env_dir("HOST_XDG_CACHE_HOME")
+            .or_else(|| env_dir("XDG_CACHE_HOME").filter(|_| !is_flatpak()))
+            .or_else(|| env_dir("HOME").map(|home| home.join(".cache")))?;
+
+        let base_path = cache_dir.join("thumbnails");
+        if base_path.is_dir() {
+            Some(SharedCache::build(&base_path))
+        } else {
+            None
+        }
+    }
+
+    pub fn build(base_path: &Path) -> SharedCache {
+        SharedCache {
+            base_path: PathBuf::from(base_path),
+            is_writable: false,
+        }
+    }
+
+    /// Should thumbnails we generate be written to the shared cache for other apps?
+    pub fn with_writes(mut self, is_writable: bool) -> Self {
+        self.is_writable = is_writable;
+        self
+    }
+
+    pub fn is_writable(&self) -> bool {
+        self.is_writable
+    }
+
+    /// Smallest valid thumbnail of a file with a longest edge of at least `min_edge` pixels.
+    pub fn find(&self, path: &Path, min_edge: u32) -> Option<SharedThumbnail> {
+        let (uri, mtime) = identify(path)?;
+        let file_name = file_name(&uri);
+
+        SIZES
+            .iter()
+            .filter(|(_, edge)| *edge >= min_edge.min(SIZES[SIZES.len() - 1].1))
+            .map(|(dir, _)| self.base_path.join(dir).join(&file_name))
+            .filter(|thumbnail_path| thumbnail_path.exists())
+            .filter_map(|thumbnail_path| read_valid(&thumbnail_path, &uri, mtime))
+            .find(|thumbnail| {
+                // Thumbnails of images smaller than the directory's size aren't scaled up,
+                // so check the thumbnail itself.
+                let (width, height) = (thumbnail.image.width(), thumbnail.image.height());
+                width.max(height) >= min_edge
+            })
+    }
+
+    /// Write thumbnails of a file to the shared cache, if writes are enabled.
+    /// The image must have the aspect ratio and orientation of the original.
+    pub fn save(&self, path: &Path, image: &RgbImage, size: (u32, u32)) -> Result<()> {
+        if !self.is_writable {
+            return Ok(());
+        }
+
+        let (uri, mtime) = identify(path).ok_or_else(|| anyhow!("Can't identify {:?}", path))?;
+        let file_name = file_name(&uri);
+
+        for (dir, edge) in WRITE_SIZES {
+            let dir = self.base_path.join(dir);
+            let thumbnail_path = dir.join(&file_name);
+
+            if read_valid(&thumbnail_path, &uri, mtime).is_some() {
+                continue;
+            }
+
+            // Thumbnails are never larger than the original.
+            let (width, height) = image.dimensions();
+            let scale = (edge as f64 / width.max(height) as f64).min(1.0);
+            let thumbnail = if scale < 1.0 {
+                let width = ((width as f64 * scale).round() as u32).max(1);
+                let height = ((height as f64 * scale).round() as u32).max(1);
+                imageops::thumbnail(image, width, height)
+            } else {
+                image.clone()
+            };
+
+            create_private_dir(&dir)?;
+            write_png(&thumbnail, &thumbnail_path, &uri, mtime, size)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn is_flatpak() -> bool {
+    Path::new("/.flatpak-info").exists()
+}
+
+/// URI and modification time of a file, as recorded in thumbnails.
+fn identify(path: &Path) -> Option<(String, u64)> {
+    let uri = gio::File::for_path(path).uri().to_string();
+    let mtime = std::fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    Some((uri, mtime))
+}
+
+/// File name of the thumbnails of a URI.
+fn file_name(uri: &str) -> String {
+    let hash = glib::compute_checksum_for_data(glib::ChecksumType::Md5, uri.as_bytes())
+        .expect("MD5 is always supported");
+    format!("{}.png", hash)
+}
+
+/// Decode a thumbnail if it is for the given URI and modification time.
+fn read_valid(thumbnail_path: &Path, uri: &str, mtime: u64) -> Option<SharedThumbnail> {
+    let data = std::fs::read(thumbnail_path).ok()?;
+
+    let reader = png::Decoder::new(Cursor::new(&data)).read_info().ok()?;
+    let info = reader.info();
+
+    let text = |keyword: &str| -> Option<String> {
+        info.uncompressed_latin1_text
+            .iter()
+            .find(|chunk| chunk.keyword == keyword)
+            .map(|chunk| chunk.text.clone())
+            .or_else(|| {
+                info.compressed_latin1_text
+                    .iter()
+                    .find(|chunk| chunk.keyword == keyword)
+                    .and_then(|chunk| chunk.get_text().ok())
+            })
+            .or_else(|| {
+                info.utf8_text
+                    .iter()
+                    .find(|chunk| chunk.keyword == keyword)
+                    .and_then(|chunk| chunk.get_text().ok())
+            })
+    };
+
+    let is_valid = text("Thumb::URI").is_some_and(|x| x == uri)
+        && text("Thumb::MTime").and_then(|x| x.parse::<u64>().ok()) == Some(mtime);
+
+    if !is_valid {
+        event!(Level::DEBUG, "Stale shared thumbnail: {:?}", thumbnail_path);
+        return None;
+    }
+
+    let width = text("Thumb::Image::Width").and_then(|x| x.parse::<u32>().ok());
+    let height = text("Thumb::Image::Height").and_then(|x| x.parse::<u32>().ok());
+    let size = width.zip(height);
+
+    let image = image::load_from_memory_with_format(&data, ImageFormat::Png)
+        .inspect_err(|e| {
+            event!(
+                Level::DEBUG,
+                "Failed decoding shared thumbnail {:?}: {}",
+                thumbnail_path,
+                e
+            )
+        })
+        .ok()?;
+
+    Some(SharedThumbnail { image, size })
+}
+
+/// The standard asks for thumbnail directories to only be readable by the user.
+fn create_private_dir(dir: &Path) -> Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    std::fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)?;
+    Ok(())
+}
+
+/// Write a thumbnail with the metadata the standard requires. The thumbnail is written
+/// to a temporary file and renamed, so other apps never see a partial thumbnail.
+fn write_png(
+    image: &RgbImage,
+    thumbnail_path: &Path,
+    uri: &str,
+    mtime: u64,
+    size: (u32, u32),
+) -> Result<()> {
+    let dir = thumbnail_path
+        .parent()
+        .ok_or_else(|| anyhow!("No parent directory: {:?}", thumbnail_path))?;
+
+    // Temporary files are created readable only by the user, as the standard asks.
+    let file = tempfile::NamedTempFile::new_in(dir)?;
+
+    {
+        let writer = BufWriter::new(file.as_file());
+        let mut encoder = png::Encoder::new(writer, image.width(), image.height());
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk("Thumb::URI".into(), uri.into())?;
+        encoder.add_text_chunk("Thumb::MTime".into(), mtime.to_string())?;
+        encoder.add_text_chunk("Thumb::Image::Width".into(), size.0.to_string())?;
+        encoder.add_text_chunk("Thumb::Image::Height".into(), size.1.to_string())?;
+        encoder.add_text_chunk("Software".into(), "Fotema".into())?;
+
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(image.as_raw())?;
+        writer.finish()?;
+    }
+
+    file.persist(thumbnail_path)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_file_name() {
+        // Example from the Thumbnail Managing Standard.
+        assert_eq!(
+            file_name("file:///home/jens/photos/me.png"),
+            "c6ee772d9e49320e97ec29a7eb5b1697.png"
+        );
+    }
+
+    #[test]
+    fn test_save_and_find() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = SharedCache::build(cache_dir.path()).with_writes(true);
+
+        let photo = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        let image = RgbImage::from_pixel(600, 300, Rgb([10, 20, 30]));
+        image
+            .save_with_format(photo.path(), ImageFormat::Png)
+            .unwrap();
+
+        cache.save(photo.path(), &image, (1200, 600)).unwrap();
+
+        let found = cache.find(photo.path(), 400).expect("x-large thumbnail");
+        assert_eq!((found.image.width(), found.image.height()), (512, 256));
+        assert_eq!(found.size, Some((1200, 600)));
+
+        let found = cache.find(photo.path(), 200).expect("large thumbnail");
+        assert_eq!((found.image.width(), found.image.height()), (256, 128));
+
+        // Nothing is large enough.
+        assert!(cache.find(photo.path(), 800).is_none());
+    }
+
+    #[test]
+    fn test_find_ignores_stale() {
+        let cache_dir = tempfile::tempdir().unwrap();
+        let cache = SharedCache::build(cache_dir.path()).with_writes(true);
+
+        let photo = tempfile::Builder::new().suffix(".png").tempfile().unwrap();
+        let image = RgbImage::from_pixel(300, 300, Rgb([10, 20, 30]));
+        image
+            .save_with_format(photo.path(), ImageFormat::Png)
+            .unwrap();
+
+        let (uri, mtime) = identify(photo.path()).unwrap();
+        let thumbnail_path = cache_dir.path().join("large").join(file_name(&uri));
+        create_private_dir(thumbnail_path.parent().unwrap()).unwrap();
+        write_png(&image, &thumbnail_path, &uri, mtime + 1, (300, 300)).unwrap();
+
+        assert!(cache.find(photo.path(), 200).is_none());
+    }
+}
@@ -0,0 +1,392 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Sizes and shapes of the thumbnails generated for photos and videos.
+//!
+//! Every size of thumbnail for an item sits next to the smallest one, which is the
+//! path recorded in the database, with the edge length in the file name.
+//! For example, `42_200x200.png` has the larger sizes `42_400x400.png` and `42_800x800.png`.
+//!
+//! Thumbnails are either cropped to squares, or keep the aspect ratio of the original
+//! and fit within a square. Aspect-preserving thumbnails have `_aspect` in the file name,
+//! such as `42_aspect_200x200.png`.
+//!
+//! Thumbnails are PNG files by default, but can be encoded in smaller formats.
+//! The file extension gives the format.
+//!
+//! Thumbnails can also be made from, and written to, the cache shared with other apps.
+
+pub mod cache;
+pub mod freedesktop;
+
+use crate::photo::focus::Focus;
+use std::path::{Path, PathBuf};
+
+/// Edge lengths of thumbnails, smallest first.
+pub const EDGES: [u32; 3] = [200, 400, 800];
+
+pub const SMALLEST_EDGE: u32 = EDGES[0];
+
+pub const LARGEST_EDGE: u32 = EDGES[EDGES.len() - 1];
+
+/// Suffix in the file name of aspect-preserving thumbnails.
+const ASPECT_SUFFIX: &str = "_aspect";
+
+/// File extensions of every format, as given by `Format::extension`.
+const EXTENSIONS: [&str; 3] = ["png", "webp", "jpg"];
+
+/// Shape of generated thumbnails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shape {
+    /// Cropped to a square about a focus point.
+    #[default]
+    Square,
+
+    /// Aspect ratio of the original, fitted within a square.
+    Aspect,
+}
+
+impl Shape {
+    /// Shape of thumbnail at a path.
+    pub fn of(path: &Path) -> Shape {
+        let is_aspect = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .is_some_and(|stem| stem.contains(ASPECT_SUFFIX));
+
+        if is_aspect {
+            Shape::Aspect
+        } else {
+            Shape::Square
+        }
+    }
+
+    /// Size of thumbnail with an edge length for an image of a given size.
+    pub fn thumbnail_size(&self, edge: u32, (width, height): (u32, u32)) -> (u32, u32) {
+        match self {
+            Shape::Square => (edge, edge),
+            Shape::Aspect if width >= height => {
+                let height = (height as f64 * edge as f64 / width.max(1) as f64).round();
+                (edge, (height as u32).max(1))
+            }
+            Shape::Aspect => {
+                let width = (width as f64 * edge as f64 / height.max(1) as f64).round();
+                ((width as u32).max(1), edge)
+            }
+        }
+    }
+}
+
+/// File format of generated thumbnails.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Format {
+    /// Lossless, but the largest.
+    #[default]
+    Png,
+
+    /// Lossy, with a quality from 1 to 100. Smaller than JPEG at the same quality,
+    /// and alpha is kept.
+    WebP { quality: u8 },
+
+    /// Lossy, with a quality from 1 to 100. Alpha is discarded.
+    Jpeg { quality: u8 },
+}
+
+impl Format {
+    /// Format with a name used in preferences. Unknown names are PNG.
+    pub fn from_name(name: &str, quality: u8) -> Format {
+        match name {
+            "webp" => Format::WebP {
+                quality: quality.clamp(1, 100),
+            },
+            "jpeg" => Format::Jpeg {
+                quality: quality.clamp(1, 100),
+            },
+            _ => Format::Png,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::WebP { .. } => "webp",
+            Format::Jpeg { .. } => "jpg",
+        }
+    }
+
+    /// Is a thumbnail at a path in this format? Quality isn't recorded, so isn't compared.
+    pub fn matches(&self, path: &Path) -> bool {
+        path.extension().and_then(|ext| ext.to_str()) == Some(self.extension())
+    }
+
+    /// Can thumbnails in this format keep an alpha channel?
+    pub fn has_alpha(&self) -> bool {
+        !matches!(self, Format::Jpeg { .. })
+    }
+}
+
+/// Thumbnails written for an item.
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    /// Path to smallest thumbnail.
+    pub path: PathBuf,
+
+    /// Width and height of the original image, if known.
+    pub size: Option<(u32, u32)>,
+
+    /// Focus found in the image, if thumbnails were generated without a chosen focus.
+    pub focus: Option<Focus>,
+}
+
+/// File name of the smallest thumbnail for an item.
+pub fn file_name(id: impl std::fmt::Display, suffix: &str, shape: Shape, format: Format) -> String {
+    let shape_suffix = match shape {
+        Shape::Square => "",
+        Shape::Aspect => ASPECT_SUFFIX,
+    };
+
+    format!(
+        "{}{}{}_{}x{}.{}",
+        id,
+        suffix,
+        shape_suffix,
+        SMALLEST_EDGE,
+        SMALLEST_EDGE,
+        format.extension()
+    )
+}
+
+/// Path of the thumbnail with an edge length, given the path of the smallest thumbnail.
+pub fn sized_path(path: &Path, edge: u32) -> PathBuf {
+    let smallest = format!("_{}x{}", SMALLEST_EDGE, SMALLEST_EDGE);
+
+    let prefix = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_suffix(&smallest));
+
+    let Some(prefix) = prefix else {
+        return PathBuf::from(path);
+    };
+
+    let file_name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}_{}x{}.{}", prefix, edge, edge, ext),
+        None => format!("{}_{}x{}", prefix, edge, edge),
+    };
+
+    path.with_file_name(file_name)
+}
+
+/// Edge length of a thumbnail and the path of the smallest thumbnail of the same item,
+/// given the path of a thumbnail of any size. The inverse of `sized_path`.
+pub fn smallest_path(path: &Path) -> Option<(PathBuf, u32)> {
+    let stem = path.file_stem()?.to_str()?;
+    let (prefix, size) = stem.rsplit_once('_')?;
+    let (width, height) = size.split_once('x')?;
+    let edge: u32 = width.parse().ok()?;
+    if height.parse::<u32>().ok()? != edge || !EDGES.contains(&edge) {
+        return None;
+    }
+
+    let file_name = match path.extension().and_then(|ext| ext.to_str()) {
+        Some(ext) => format!("{}_{}x{}.{}", prefix, SMALLEST_EDGE, SMALLEST_EDGE, ext),
+        None => format!("{}_{}x{}", prefix, SMALLEST_EDGE, SMALLEST_EDGE),
+    };
+
+    Some((path.with_file_name(file_name), edge))
+}
+
+/// Remove the thumbnails of an item in other shapes and formats than the thumbnails at a path,
+/// given the path of the smallest thumbnail. Thumbnails made before the preferred shape or
+/// format changed would otherwise be left taking up space in the cache.
+pub fn remove_other_variants(path: &Path) -> std::io::Result<()> {
+    let smallest = format!("_{}x{}", SMALLEST_EDGE, SMALLEST_EDGE);
+
+    let stem = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .and_then(|stem| stem.strip_suffix(&smallest));
+
+    let Some(stem) = stem else {
+        return Ok(());
+    };
+    let prefix = stem.strip_suffix(ASPECT_SUFFIX).unwrap_or(stem);
+
+    for shape_suffix in ["", ASPECT_SUFFIX] {
+        for extension in EXTENSIONS {
+            let file_name = format!("{}{}{}.{}", prefix, shape_suffix, smallest, extension);
+            let other = path.with_file_name(file_name);
+            if other == path {
+                continue;
+            }
+
+            for edge in EDGES {
+                match std::fs::remove_file(sized_path(&other, edge)) {
+                    Err(e) if e.kind() != std::io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Do all sizes of thumbnail exist, given the path of the smallest thumbnail?
+pub fn all_exist(path: &Path) -> bool {
+    all_exist_up_to(path, LARGEST_EDGE)
+}
+
+/// Do all sizes of thumbnail up to an edge length exist, given the path of the smallest thumbnail?
+/// Larger sizes might have been removed to keep the cache within its budget.
+pub fn all_exist_up_to(path: &Path, max_edge: u32) -> bool {
+    EDGES
+        .iter()
+        .filter(|edge| **edge <= max_edge)
+        .all(|edge| sized_path(path, *edge).exists())
+}
+
+/// Smallest edge length that is at least `pixels`, out of the edges up to `max_edge`.
+/// If none is large enough, then the largest available edge.
+pub fn edge_for(pixels: u32, max_edge: u32) -> u32 {
+    let mut available = EDGES.iter().copied().filter(|edge| *edge <= max_edge);
+
+    available
+        .clone()
+        .find(|edge| *edge >= pixels)
+        .or_else(|| available.next_back())
+        .unwrap_or(SMALLEST_EDGE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sized_path() {
+        let path = Path::new("/cache/photo_thumbnails/0000/42_200x200.png");
+        assert_eq!(
+            sized_path(path, 800),
+            PathBuf::from("/cache/photo_thumbnails/0000/42_800x800.png")
+        );
+
+        let path = Path::new("/cache/photo_thumbnails/0000/42_edited_200x200.png");
+        assert_eq!(
+            sized_path(path, 400),
+            PathBuf::from("/cache/photo_thumbnails/0000/42_edited_400x400.png")
+        );
+    }
+
+    #[test]
+    fn test_sized_path_of_unsized_file() {
+        let path = Path::new("/cache/photo_thumbnails/0000/42.png");
+        assert_eq!(sized_path(path, 800), PathBuf::from(path));
+    }
+
+    #[test]
+    fn test_file_name() {
+        assert_eq!(
+            file_name(42, "", Shape::Square, Format::Png),
+            "42_200x200.png"
+        );
+        assert_eq!(
+            file_name(42, "_edited", Shape::Square, Format::Png),
+            "42_edited_200x200.png"
+        );
+        assert_eq!(
+            file_name(42, "", Shape::Aspect, Format::Png),
+            "42_aspect_200x200.png"
+        );
+        assert_eq!(
+            file_name(42, "", Shape::Square, Format::Jpeg { quality: 80 }),
+            "42_200x200.jpg"
+        );
+    }
+
+    #[test]
+    fn test_smallest_path() {
+        let path = Path::new("/cache/photo_thumbnails/0000/42_edited_aspect_800x800.webp");
+        assert_eq!(
+            smallest_path(path),
+            Some((
+                PathBuf::from("/cache/photo_thumbnails/0000/42_edited_aspect_200x200.webp"),
+                800
+            ))
+        );
+
+        assert_eq!(smallest_path(Path::new("/cache/0000/42.png")), None);
+        assert_eq!(smallest_path(Path::new("/cache/0000/42_300x300.png")), None);
+    }
+
+    #[test]
+    fn test_remove_other_variants() {
+        let dir = tempfile::tempdir().unwrap();
+        let touch = |name: &str| {
+            let path = dir.path().join(name);
+            std::fs::write(&path, b"").unwrap();
+            path
+        };
+
+        let current = touch("42_aspect_200x200.webp");
+        let current_large = touch("42_aspect_800x800.webp");
+        let old_format = touch("42_aspect_400x400.png");
+        let old_shape = touch("42_800x800.webp");
+        let edited = touch("42_edited_200x200.png");
+        let other_item = touch("421_200x200.png");
+
+        remove_other_variants(&current).unwrap();
+
+        assert!(current.exists());
+        assert!(current_large.exists());
+        assert!(!old_format.exists());
+        assert!(!old_shape.exists());
+        assert!(edited.exists());
+        assert!(other_item.exists());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(Format::from_name("jpeg", 0), Format::Jpeg { quality: 1 });
+        assert_eq!(Format::from_name("webp", 80), Format::WebP { quality: 80 });
+        assert_eq!(Format::from_name("avif", 80), Format::Png);
+        assert!(Format::WebP { quality: 80 }.matches(Path::new("0000/42_200x200.webp")));
+        assert!(!Format::Png.matches(Path::new("0000/42_200x200.webp")));
+    }
+
+    #[test]
+    fn test_shape_of() {
+        assert_eq!(Shape::of(Path::new("0000/42_200x200.png")), Shape::Square);
+        assert_eq!(
+            Shape::of(Path::new("0000/42_edited_aspect_200x200.png")),
+            Shape::Aspect
+        );
+    }
+
+    #[test]
+    fn test_thumbnail_size() {
+        assert_eq!(Shape::Square.thumbnail_size(400, (4000, 3000)), (400, 400));
+        assert_eq!(Shape::Aspect.thumbnail_size(400, (4000, 3000)), (400, 300));
+        assert_eq!(Shape::Aspect.thumbnail_size(400, (3000, 4000)), (300, 400));
+
+        // Very wide panoramas still have a visible height.
+        assert_eq!(Shape::Aspect.thumbnail_size(200, (100_000, 100)), (200, 1));
+    }
+
+    #[test]
+    fn test_edge_for() {
+        assert_eq!(edge_for(112, LARGEST_EDGE), 200);
+        assert_eq!(edge_for(200, LARGEST_EDGE), 200);
+        assert_eq!(edge_for(340, LARGEST_EDGE), 400);
+        assert_eq!(edge_for(400, LARGEST_EDGE), 400);
+        assert_eq!(edge_for(600, LARGEST_EDGE), 800);
+        assert_eq!(edge_for(1600, LARGEST_EDGE), 800);
+    }
+
+    #[test]
+    fn test_edge_for_older_thumbnails() {
+        // Items thumbnailed before larger sizes were generated only have the smallest size.
+        assert_eq!(edge_for(400, SMALLEST_EDGE), 200);
+        assert_eq!(edge_for(600, 400), 400);
+    }
+}
@@ -0,0 +1,375 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Trimmed copies of videos and stills of video frames. Both are written next to the
+//! original video so that they are scanned into the library like any other file.
+//! They are written outside of the library first, so a scan never finds a partial file.
+
+use crate::photo::model::Orientation;
+use crate::video::{metadata, poster, transcode};
+use anyhow::*;
+use chrono::{DateTime, TimeDelta, Utc};
+use ffmpeg_next as ffmpeg;
+use image::codecs::jpeg::JpegEncoder;
+use image::DynamicImage;
+use std::io::{Cursor, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+use tracing::{event, Level};
+
+/// JPEG quality for stills of video frames.
+const FRAME_QUALITY: u8 = 92;
+
+/// A trim can start at a keyframe this many seconds before the chosen start and still
+/// be made by copying streams. Otherwise the video is re-encoded to start on time.
+const MAX_KEYFRAME_LEAD_SECONDS: f64 = 1.0;
+
+/// Write the part of a video between two positions to a new file next to the original.
+/// Streams are copied without re-encoding where possible, which is quick and lossless,
+/// but can only start at a keyframe. Returns the path of the new file.
+pub fn trim(video_path: &Path, start: TimeDelta, end: TimeDelta) -> Result<PathBuf> {
+    if end <= start {
+        bail!("Trim must end after it starts: {} to {}", start, end);
+    }
+
+    let extension = video_path
+        .extension()
+        .and_then(|x| x.to_str())
+        .unwrap_or("mp4");
+    let target_path = unused_path(video_path, "trimmed", extension)?;
+
+    // FFmpeg picks the container from the extension. The file is removed if it isn't
+    // moved into place.
+    let temporary_path = tempfile::Builder::new()
+        .suffix(&format!(".{}", extension))
+        .tempfile()?
+        .into_temp_path();
+
+    event!(
+        Level::DEBUG,
+        "Trimming {:?} to {:?}",
+        video_path,
+        target_path
+    );
+
+    let to_seconds = |position: TimeDelta| position.num_milliseconds() as f64 / 1000.0;
+
+    if let Err(e) = copy_range(
+        video_path,
+        &temporary_path,
+        to_seconds(start),
+        to_seconds(end),
+    ) {
+        event!(Level::INFO, "Re-encoding trim of {:?}: {:?}", video_path, e);
+        transcode::transcode_range(video_path, &temporary_path, start, end)?;
+    }
+
+    move_into_place(temporary_path, &target_path)?;
+
+    Ok(target_path)
+}
+
+/// Write the frame at a position in a video as a JPEG next to the video. The JPEG is
+/// upright and has the time the frame was recorded, so it sorts beside the video.
+/// Returns the path of the new file.
+pub fn save_frame(video_path: &Path, position: TimeDelta) -> Result<PathBuf> {
+    let target_path = unused_path(video_path, "frame", "jpg")?;
+
+    // Full size frame.
+    let frame = poster::at(video_path, position, u32::MAX)?;
+
+    // Decoded frames don't have the display matrix rotation applied.
+    let metadata = metadata::from_path(video_path).ok();
+    let orientation = metadata
+        .as_ref()
+        .and_then(|m| m.rotation)
+        .map(Orientation::from_degrees)
+        .unwrap_or(Orientation::North);
+    let image = orientation.apply_to(DynamicImage::ImageRgb8(frame));
+
+    let mut jpeg = Vec::new();
+    JpegEncoder::new_with_quality(&mut jpeg, FRAME_QUALITY).encode_image(&image)?;
+
+    let created_at = metadata
+        .and_then(|m| m.created_at)
+        .map(|created_at| created_at + position);
+
+    let jpeg = match created_at {
+        Some(created_at) => with_exif(&jpeg, created_at)?,
+        None => jpeg,
+    };
+
+    let mut file = tempfile::NamedTempFile::new()?;
+    file.write_all(&jpeg)?;
+    file.flush()?;
+
+    move_into_place(file.into_temp_path(), &target_path)?;
+
+    Ok(target_path)
+}
+
+/// Copy streams between two numbers of seconds from the start of the video stream,
+/// starting at the keyframe before the start. Fails if that keyframe is too early.
+fn copy_range(video_path: &Path, target_path: &Path, start: f64, end: f64) -> Result<()> {
+    let mut ictx = ffmpeg::format::input(&video_path)?;
+    let mut octx = ffmpeg::format::output(&target_path)?;
+
+    let video_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .map(|stream| stream.index())
+        .ok_or_else(|| anyhow!("No video stream"))?;
+
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut input_time_bases = vec![ffmpeg::Rational(0, 1); ictx.nb_streams() as usize];
+    let mut input_start_times = vec![0; ictx.nb_streams() as usize];
+
+    let mut output_index = 0;
+    for (input_index, input_stream) in ictx.streams().enumerate() {
+        let medium = input_stream.parameters().medium();
+        if medium != ffmpeg::media::Type::Audio && medium != ffmpeg::media::Type::Video {
+            // Skip data streams, such as the timed metadata tracks in iPhone videos.
+            continue;
+        }
+
+        stream_mapping[input_index] = Some(output_index);
+        input_time_bases[input_index] = input_stream.time_base();
+        input_start_times[input_index] = match input_stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+        output_index += 1;
+
+        let mut output_stream = octx.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+        output_stream.set_parameters(input_stream.parameters());
+        metadata::copy_display_matrix(&input_stream, &mut output_stream);
+
+        // Let the muxer pick a codec tag suitable for the container.
+        unsafe {
+            (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+        }
+    }
+
+    // Keep details such as the creation time and location of the original.
+    octx.set_metadata(ictx.metadata().to_owned());
+
+    let timestamp = (start * 1_000_000.0) as i64;
+    ictx.seek(timestamp, ..timestamp)?;
+
+    octx.write_header()?;
+
+    // Seconds from the start of the video stream that the copy starts at.
+    let mut copy_start: Option<f64> = None;
+
+    for (stream, mut packet) in ictx.packets() {
+        let input_index = stream.index();
+        let Some(output_index) = stream_mapping[input_index] else {
+            continue;
+        };
+
+        let time_base = f64::from(input_time_bases[input_index]);
+        let seconds =
+            |timestamp: i64| (timestamp - input_start_times[input_index]) as f64 * time_base;
+
+        let offset = match copy_start {
+            Some(offset) => offset,
+            None if input_index == video_index => {
+                let seconds = packet.pts().or(packet.dts()).map(seconds).unwrap_or(start);
+                if !packet.is_key() || start - seconds > MAX_KEYFRAME_LEAD_SECONDS {
+                    bail!("No keyframe shortly before {} seconds", start);
+                }
+                *copy_start.insert(seconds.min(start))
+            }
+            None => continue, // Nothing before the first video keyframe.
+        };
+
+        // Timestamps only increase in decoding order, so later packets are after the end too.
+        let decoded_at = packet.dts().or(packet.pts()).map(seconds);
+        if decoded_at.is_some_and(|seconds| seconds > end) {
+            if input_index == video_index {
+                break;
+            }
+            continue;
+        }
+
+        if input_index != video_index
+            && packet
+                .pts()
+                .map(seconds)
+                .is_some_and(|seconds| seconds < offset)
+        {
+            continue;
+        }
+
+        let output_time_base = octx
+            .stream(output_index)
+            .map(|x| x.time_base())
+            .ok_or_else(|| anyhow!("Missing output stream {}", output_index))?;
+
+        let shift = input_start_times[input_index] + (offset / time_base).round() as i64;
+        packet.set_pts(packet.pts().map(|pts| pts - shift));
+        packet.set_dts(packet.dts().map(|dts| dts - shift));
+        packet.rescale_ts(input_time_bases[input_index], output_time_base);
+        packet.set_position(-1);
+        packet.set_stream(output_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    if copy_start.is_none() {
+        bail!("No video after {} seconds", start);
+    }
+
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+/// Insert an EXIF segment with the time a photo was taken near the start of a JPEG.
+fn with_exif(jpeg: &[u8], created_at: DateTime<Utc>) -> Result<Vec<u8>> {
+    let date_time = exif::Field {
+        tag: exif::Tag::DateTimeOriginal,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Ascii(vec![created_at
+            .format("%Y:%m:%d %H:%M:%S")
+            .to_string()
+            .into_bytes()]),
+    };
+    let offset = exif::Field {
+        tag: exif::Tag::OffsetTimeOriginal,
+        ifd_num: exif::In::PRIMARY,
+        value: exif::Value::Ascii(vec![b"+00:00".to_vec()]),
+    };
+
+    let mut writer = exif::experimental::Writer::new();
+    writer.push_field(&date_time);
+    writer.push_field(&offset);
+
+    let mut tiff = Cursor::new(Vec::new());
+    writer.write(&mut tiff, false)?;
+    let tiff = tiff.into_inner();
+
+    // JPEGs start with a two byte start of image marker, which is followed by a JFIF
+    // segment if there is one. EXIF goes after both.
+    if jpeg.len() < 2 || jpeg[0..2] != [0xFF, 0xD8] {
+        bail!("Not a JPEG");
+    }
+    let insert_at = match jpeg.get(2..6) {
+        Some([0xFF, 0xE0, high, low]) => 4 + u16::from_be_bytes([*high, *low]) as usize,
+        _ => 2,
+    }
+    .min(jpeg.len());
+
+    let mut out = Vec::with_capacity(jpeg.len() + tiff.len() + 10);
+    out.extend_from_slice(&jpeg[..insert_at]);
+    out.extend_from_slice(&[0xFF, 0xE1]);
+    out.extend_from_slice(&((2 + 6 + tiff.len()) as u16).to_be_bytes());
+    out.extend_from_slice(b"Exif\0\0");
+    out.extend_from_slice(&tiff);
+    out.extend_from_slice(&jpeg[insert_at..]);
+    Ok(out)
+}
+
+/// Move a finished temporary file to its place in the library. Renaming fails between
+/// file systems, in which case the file is copied next to the target under a name that
+/// isn't scanned and then renamed.
+fn move_into_place(temporary_path: tempfile::TempPath, target_path: &Path) -> Result<()> {
+    let temporary_path = match temporary_path.persist(target_path) {
+        Ok(()) => return Ok(()),
+        Err(e) => e.path,
+    };
+
+    let file_name = target_path
+        .file_name()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| anyhow!("Missing file name: {:?}", target_path))?;
+    let partial_path = target_path.with_file_name(format!("{}.part", file_name));
+
+    let result = std::fs::copy(&temporary_path, &partial_path)
+        .and_then(|_| std::fs::rename(&partial_path, target_path));
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&partial_path);
+    }
+
+    result?;
+    Ok(())
+}
+
+/// Path next to a file, with a suffix on the file stem, that doesn't overwrite an
+/// existing file.
+fn unused_path(path: &Path, suffix: &str, extension: &str) -> Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .and_then(|x| x.to_str())
+        .ok_or_else(|| anyhow!("Missing file name: {:?}", path))?;
+
+    let mut target = path.with_file_name(format!("{}_{}.{}", stem, suffix, extension));
+    let mut n = 1;
+    while target.exists() {
+        target = path.with_file_name(format!("{}_{}_{}.{}", stem, suffix, n, extension));
+        n += 1;
+    }
+
+    Ok(target)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_with_exif() {
+        let created_at = DateTime::parse_from_rfc3339("2024-06-01T12:34:56Z")
+            .unwrap()
+            .to_utc();
+
+        let mut jpeg = Vec::new();
+        let image = DynamicImage::new_rgb8(8, 8);
+        JpegEncoder::new(&mut jpeg).encode_image(&image).unwrap();
+
+        let jpeg = with_exif(&jpeg, created_at).unwrap();
+        assert!(image::load_from_memory(&jpeg).is_ok());
+
+        let exif_data = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(&jpeg))
+            .unwrap();
+        let field = exif_data
+            .get_field(exif::Tag::DateTimeOriginal, exif::In::PRIMARY)
+            .unwrap();
+        let exif::Value::Ascii(ref values) = field.value else {
+            panic!("Date time must be ASCII");
+        };
+        assert_eq!(values[0], b"2024:06:01 12:34:56");
+    }
+
+    #[test]
+    fn test_move_into_place() {
+        let dir = tempfile::tempdir().unwrap();
+        let target_path = dir.path().join("VID_1234_frame.jpg");
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"frame").unwrap();
+        let temporary_path = file.into_temp_path();
+        let original_path = temporary_path.to_path_buf();
+
+        move_into_place(temporary_path, &target_path).unwrap();
+
+        assert_eq!(b"frame".to_vec(), std::fs::read(&target_path).unwrap());
+        assert!(!original_path.exists());
+        assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn test_unused_path() {
+        let path = Path::new("/does/not/exist/VID_1234.mp4");
+        assert_eq!(
+            unused_path(path, "trimmed", "mp4").unwrap(),
+            PathBuf::from("/does/not/exist/VID_1234_trimmed.mp4")
+        );
+        assert_eq!(
+            unused_path(path, "frame", "jpg").unwrap(),
+            PathBuf::from("/does/not/exist/VID_1234_frame.jpg")
+        );
+    }
+}
@@ -11,14 +11,12 @@ use ffmpeg_next as ffmpeg;
 use std::path::Path;
 use std::result::Result::Ok;
 
-use std::process::Command;
-
 /// This version number should be incremented each time metadata scanning has
 /// a bug fix or feature addition that changes the metadata produced.
 /// Each photo will be saved with a metadata scan version which will allow for
 /// easy selection of videos when there metadata can be updated.
 
-pub const VERSION: u32 = 2;
+pub const VERSION: u32 = 3;
 
 pub fn from_path(path: &Path) -> Result<Metadata> {
     let mut metadata = Metadata::default();
@@ -59,33 +57,7 @@ pub fn from_path(path: &Path) -> Result<Metadata> {
             metadata.height = Some(video.height() as u64);
         }
 
-        // We need the video frame side data to get the video rotation information.
-        //
-        // FIXME I can't figure out how to extract the rotation from the side data
-        // using the ffmpeg_next API, so I've had to resort to running ffprobe.
-        // Running ffprobe is slow, so if someone can figure out how to extract
-        // the rotation data with the ffmpeg_next API then I'll remove this.
-        // PRs welcome :-)
-
-        // See https://stackoverflow.com/questions/70920024/extract-rotation-data-with-ffprobe-5-and-newer
-        let output = Command::new("ffprobe")
-            .arg("-loglevel")
-            .arg("error")
-            .arg("-select_streams")
-            .arg("v:0")
-            .arg("-show_entries")
-            .arg("stream_side_data=rotation")
-            .arg("-of")
-            .arg("default=nw=1:nk=1")
-            .arg(path.as_os_str())
-            .output();
-
-        let rotation = output
-            .ok()
-            .and_then(|out| String::from_utf8(out.stdout).ok())
-            .and_then(|x| x.trim().parse::<i32>().ok());
-
-        metadata.rotation = rotation;
+        metadata.rotation = rotation(&stream);
     }
 
     if let Some(stream) = context.streams().best(ffmpeg::media::Type::Audio) {
@@ -96,6 +68,73 @@ pub fn from_path(path: &Path) -> Result<Metadata> {
     Ok(metadata)
 }
 
+/// Rotation in degrees from the display matrix of a video stream, with the same sign
+/// that ffprobe reports. Videos recorded on phones held upright are usually -90.
+pub fn rotation(stream: &ffmpeg::Stream) -> Option<i32> {
+    stream
+        .side_data()
+        .find(|data| data.kind() == ffmpeg::packet::side_data::Type::DisplayMatrix)
+        .and_then(|data| display_matrix_rotation(data.data()))
+}
+
+/// Copy the display matrix of a stream to a stream of a remuxed copy, so that videos
+/// recorded on phones held upright still play the right way up.
+pub fn copy_display_matrix(input: &ffmpeg::Stream, output: &mut ffmpeg::StreamMut) {
+    let kind = ffmpeg::packet::side_data::Type::DisplayMatrix;
+    let Some(matrix) = input.side_data().find(|data| data.kind() == kind) else {
+        return;
+    };
+    let matrix = matrix.data();
+
+    unsafe {
+        let data =
+            ffmpeg::ffi::av_stream_new_side_data(output.as_mut_ptr(), kind.into(), matrix.len());
+        if !data.is_null() {
+            std::ptr::copy_nonoverlapping(matrix.as_ptr(), data, matrix.len());
+        }
+    }
+}
+
+/// Average frames per second of the video stream of a video, if known.
+pub fn frame_rate(path: &Path) -> Result<Option<f64>> {
+    let context = ffmpeg::format::input(path)?;
+    let rate = context
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .map(|stream| stream.avg_frame_rate())
+        .filter(|rate| rate.numerator() > 0 && rate.denominator() > 0)
+        .map(f64::from);
+    Ok(rate)
+}
+
+/// Rotation of a display matrix, as calculated by av_display_rotation_get.
+/// The matrix is 3x3 native-endian 32-bit integers, and the first two columns
+/// are 16.16 fixed point.
+fn display_matrix_rotation(matrix: &[u8]) -> Option<i32> {
+    if matrix.len() < 9 * 4 {
+        return None;
+    }
+
+    let value = |i: usize| {
+        let bytes = [
+            matrix[i * 4],
+            matrix[i * 4 + 1],
+            matrix[i * 4 + 2],
+            matrix[i * 4 + 3],
+        ];
+        i32::from_ne_bytes(bytes) as f64 / 65536.0
+    };
+
+    let scale_x = value(0).hypot(value(3));
+    let scale_y = value(1).hypot(value(4));
+    if scale_x == 0.0 || scale_y == 0.0 {
+        return None;
+    }
+
+    let degrees = (value(1) / scale_y).atan2(value(0) / scale_x).to_degrees();
+    Some(-degrees.round() as i32)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -112,4 +151,43 @@ mod tests {
         //let file = fs::File::open(file).unwrap();
         //let file = &mut BufReader::new(file);
     }
+
+    /// Display matrix for a clockwise rotation, as made by av_display_rotation_set.
+    fn display_matrix(clockwise_degrees: f64) -> Vec<u8> {
+        let radians = -clockwise_degrees.to_radians();
+        let (sin, cos) = radians.sin_cos();
+        let fixed = |v: f64| (v * 65536.0).round() as i32;
+
+        let matrix = [
+            fixed(cos),
+            fixed(-sin),
+            0,
+            fixed(sin),
+            fixed(cos),
+            0,
+            0,
+            0,
+            1 << 30,
+        ];
+
+        matrix.iter().flat_map(|v| v.to_ne_bytes()).collect()
+    }
+
+    #[test]
+    fn test_display_matrix_rotation() {
+        assert_eq!(display_matrix_rotation(&display_matrix(0.0)), Some(0));
+        // Phones held upright record the video on its side, to be turned clockwise.
+        assert_eq!(display_matrix_rotation(&display_matrix(90.0)), Some(-90));
+        assert_eq!(display_matrix_rotation(&display_matrix(-90.0)), Some(90));
+        assert_eq!(
+            display_matrix_rotation(&display_matrix(180.0)).map(i32::abs),
+            Some(180)
+        );
+    }
+
+    #[test]
+    fn test_display_matrix_rotation_invalid() {
+        assert_eq!(display_matrix_rotation(&[0; 36]), None);
+        assert_eq!(display_matrix_rotation(&[0; 8]), None);
+    }
 }
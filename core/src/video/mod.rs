@@ -2,8 +2,12 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod clip;
 pub mod metadata;
 pub mod model;
+pub mod poster;
+pub mod preview;
+pub mod proxy;
 pub mod repo;
 pub mod scanner;
 pub mod thumbnail;
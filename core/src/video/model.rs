@@ -39,6 +39,9 @@ pub struct Video {
     /// Full path to square preview image
     pub thumbnail_path: Option<PathBuf>,
 
+    /// Largest edge length of the thumbnails that exist.
+    pub thumbnail_max_edge: Option<u32>,
+
     /// Time ordering
     pub ordering_ts: DateTime<Utc>,
 
@@ -0,0 +1,347 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Poster frame of a video, which thumbnails are made from.
+//!
+//! The first frame of a video is often black, or blurred while the camera focuses, so
+//! frames are sampled from the first few seconds and the one with the most detail and
+//! contrast is chosen. Users can also choose a poster frame at a position of their own.
+
+use anyhow::*;
+use chrono::TimeDelta;
+use image::{imageops, GrayImage, RgbImage};
+
+use ffmpeg::format::Pixel;
+use ffmpeg::software::scaling::{self, Flags};
+use ffmpeg::util::frame;
+use ffmpeg_next as ffmpeg;
+
+use std::path::Path;
+use std::result::Result::Ok;
+
+/// Seconds from the start of a video that frames are sampled from.
+const SAMPLE_SECONDS: f64 = 5.0;
+
+/// Number of frames sampled. More frames find a better poster, but take longer.
+const SAMPLE_COUNT: usize = 10;
+
+/// Longest edge of the frame that is scored. Small enough to be quick, but large enough
+/// that blur is still visible.
+const ANALYSIS_EDGE: u32 = 256;
+
+/// Frame with the most detail and contrast from the first seconds of a video,
+/// scaled to fit within `max_edge` pixels. Frames aren't rotated by any display matrix.
+pub fn representative(path: &Path, max_edge: u32) -> Result<RgbImage> {
+    let mut decoder = FrameDecoder::open(path, max_edge)?;
+
+    let step = SAMPLE_SECONDS / SAMPLE_COUNT as f64;
+    let mut next_sample = 0.0;
+    let mut samples = 0;
+    let mut best: Option<(f64, RgbImage)> = None;
+
+    while let Some(seconds) = decoder.next_frame()? {
+        if seconds > SAMPLE_SECONDS || samples >= SAMPLE_COUNT {
+            break;
+        } else if seconds < next_sample {
+            continue;
+        }
+
+        samples += 1;
+        next_sample = seconds + step;
+
+        let image = decoder.image()?;
+        let score = score(&image);
+        if best.as_ref().is_none_or(|(best_score, _)| score > *best_score) {
+            best = Some((score, image));
+        }
+    }
+
+    best.map(|(_, image)| image)
+        .ok_or_else(|| anyhow!("No frames decoded: {:?}", path))
+}
+
+/// Frame at a position in a video, scaled to fit within `max_edge` pixels.
+/// Frames aren't rotated by any display matrix.
+pub fn at(path: &Path, position: TimeDelta, max_edge: u32) -> Result<RgbImage> {
+    let mut decoder = FrameDecoder::open(path, max_edge)?;
+
+    let position = position.num_milliseconds() as f64 / 1000.0;
+    decoder.seek(position)?;
+
+    // Decoding starts from the keyframe before the position, so skip to the position.
+    // If the position is past the end, then the last frame is the nearest.
+    let mut is_decoded = false;
+    while let Some(seconds) = decoder.next_frame()? {
+        is_decoded = true;
+        if seconds >= position {
+            return decoder.image();
+        }
+    }
+
+    if is_decoded {
+        decoder.image()
+    } else {
+        Err(anyhow!("No frames decoded: {:?}", path))
+    }
+}
+
+/// Keyframe at or before a position in a video, scaled to fit within `max_edge` pixels.
+/// Much quicker than [`at`] because only one frame is decoded, so suits previews while
+/// seeking. Frames aren't rotated by any display matrix.
+pub fn keyframe(path: &Path, position: TimeDelta, max_edge: u32) -> Result<RgbImage> {
+    let mut decoder = FrameDecoder::open(path, max_edge)?;
+
+    decoder.seek(position.num_milliseconds() as f64 / 1000.0)?;
+
+    if decoder.next_frame()?.is_some() {
+        decoder.image()
+    } else {
+        Err(anyhow!("No frames decoded: {:?}", path))
+    }
+}
+
+/// How good a frame is as a poster. Sharp frames have a large variance of the Laplacian
+/// of brightness, and frames with contrast have a large variance of brightness. Black,
+/// washed-out, and blurred frames score low.
+pub fn score(image: &RgbImage) -> f64 {
+    let (width, height) = image.dimensions();
+    if width == 0 || height == 0 {
+        return 0.0;
+    }
+
+    let gray = if width.max(height) > ANALYSIS_EDGE {
+        let scale = ANALYSIS_EDGE as f64 / width.max(height) as f64;
+        let small_width = ((width as f64 * scale).round() as u32).max(1);
+        let small_height = ((height as f64 * scale).round() as u32).max(1);
+        imageops::grayscale(&imageops::thumbnail(image, small_width, small_height))
+    } else {
+        imageops::grayscale(image)
+    };
+
+    let brightness = variance(gray.pixels().map(|p| p.0[0] as f64));
+    let sharpness = variance(laplacian(&gray));
+
+    // Standard deviations keep both measures in units of brightness, so neither dominates.
+    brightness.sqrt() + sharpness.sqrt()
+}
+
+/// Laplacian of each pixel not on the border of an image.
+fn laplacian(gray: &GrayImage) -> impl Iterator<Item = f64> + '_ {
+    let (width, height) = gray.dimensions();
+    let value = move |x: u32, y: u32| gray.get_pixel(x, y).0[0] as f64;
+
+    (1..height.saturating_sub(1)).flat_map(move |y| {
+        (1..width.saturating_sub(1)).map(move |x| {
+            value(x - 1, y) + value(x + 1, y) + value(x, y - 1) + value(x, y + 1)
+                - 4.0 * value(x, y)
+        })
+    })
+}
+
+fn variance(values: impl Iterator<Item = f64>) -> f64 {
+    let (count, sum, sum_of_squares) = values.fold((0.0, 0.0, 0.0), |(n, s, ss), v| {
+        (n + 1.0, s + v, ss + v * v)
+    });
+
+    if count == 0.0 {
+        return 0.0;
+    }
+
+    let mean = sum / count;
+    (sum_of_squares / count - mean * mean).max(0.0)
+}
+
+/// Decodes frames of the best video stream of a file to RGB images.
+struct FrameDecoder {
+    input: ffmpeg::format::context::Input,
+    stream_index: usize,
+
+    /// Seconds per unit of stream timestamps.
+    time_base: f64,
+
+    /// Timestamp of the first frame of the stream.
+    start_time: i64,
+
+    decoder: ffmpeg::decoder::Video,
+
+    /// Longest edge of scaled images.
+    max_edge: u32,
+
+    /// Scaler for the format and size of the decoded frames.
+    scaler: Option<scaling::Context>,
+
+    /// Most recently decoded frame.
+    decoded: frame::Video,
+
+    /// Have all packets been sent to the decoder?
+    is_eof: bool,
+}
+
+impl FrameDecoder {
+    fn open(path: &Path, max_edge: u32) -> Result<FrameDecoder> {
+        let input = ffmpeg::format::input(&path)?;
+
+        let stream = input
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| anyhow!("No video stream: {:?}", path))?;
+
+        let stream_index = stream.index();
+        let time_base = f64::from(stream.time_base());
+
+        // Streams without a start time start at zero.
+        let start_time = match stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+
+        let context = ffmpeg::codec::context::Context::from_parameters(stream.parameters())?;
+        let decoder = context.decoder().video()?;
+
+        Ok(FrameDecoder {
+            input,
+            stream_index,
+            time_base,
+            start_time,
+            decoder,
+            max_edge,
+            scaler: None,
+            decoded: frame::Video::empty(),
+            is_eof: false,
+        })
+    }
+
+    /// Seek to the keyframe at or before a number of seconds from the start.
+    fn seek(&mut self, seconds: f64) -> Result<()> {
+        // Seeking without a stream is in microseconds.
+        let timestamp = (seconds * 1_000_000.0) as i64;
+        self.input.seek(timestamp, ..timestamp)?;
+        self.decoder.flush();
+        Ok(())
+    }
+
+    /// Decode the next frame, returning its time in seconds from the start of the video,
+    /// or None after the last frame.
+    fn next_frame(&mut self) -> Result<Option<f64>> {
+        loop {
+            // Failing to receive a frame unreferences it, so receive into a new frame
+            // to keep the last frame once the decoder has no more.
+            let mut decoded = frame::Video::empty();
+            if self.decoder.receive_frame(&mut decoded).is_ok() {
+                self.decoded = decoded;
+                let timestamp = self
+                    .decoded
+                    .timestamp()
+                    .or(self.decoded.pts())
+                    .unwrap_or(self.start_time);
+                let seconds = (timestamp - self.start_time) as f64 * self.time_base;
+                return Ok(Some(seconds));
+            } else if self.is_eof {
+                return Ok(None);
+            }
+
+            match self.input.packets().next() {
+                Some((stream, packet)) => {
+                    if stream.index() == self.stream_index {
+                        // Corrupt packets are skipped, as players do.
+                        let _ = self.decoder.send_packet(&packet);
+                    }
+                }
+                None => {
+                    self.decoder.send_eof()?;
+                    self.is_eof = true;
+                }
+            }
+        }
+    }
+
+    /// Most recently decoded frame, scaled to an RGB image.
+    fn image(&mut self) -> Result<RgbImage> {
+        let (width, height) = (self.decoded.width(), self.decoded.height());
+        if width == 0 || height == 0 {
+            bail!("Frame has no size");
+        }
+
+        // The format and size of frames is only certain once they are decoded,
+        // and can change part way through a stream.
+        let source = scaling::context::Definition {
+            format: self.decoded.format(),
+            width,
+            height,
+        };
+
+        let mut scaler = match self.scaler.take() {
+            Some(scaler) if *scaler.input() == source => scaler,
+            _ => {
+                let scale = (self.max_edge as f64 / width.max(height) as f64).min(1.0);
+                let scaled_width = ((width as f64 * scale).round() as u32).max(1);
+                let scaled_height = ((height as f64 * scale).round() as u32).max(1);
+
+                scaling::Context::get(
+                    source.format,
+                    width,
+                    height,
+                    Pixel::RGB24,
+                    scaled_width,
+                    scaled_height,
+                    Flags::AREA,
+                )?
+            }
+        };
+
+        let mut rgb = frame::Video::empty();
+        let result = scaler.run(&self.decoded, &mut rgb);
+        self.scaler = Some(scaler);
+        result?;
+
+        to_image(&rgb)
+    }
+}
+
+/// Copy an RGB24 frame to an image. Rows of frames can be padded, so can't be copied whole.
+fn to_image(rgb: &frame::Video) -> Result<RgbImage> {
+    let (width, height) = (rgb.width(), rgb.height());
+    let stride = rgb.stride(0);
+    let row_bytes = width as usize * 3;
+
+    let data = rgb.data(0);
+    let mut pixels = Vec::with_capacity(row_bytes * height as usize);
+    for row in 0..height as usize {
+        pixels.extend_from_slice(&data[row * stride..row * stride + row_bytes]);
+    }
+
+    RgbImage::from_raw(width, height, pixels).ok_or_else(|| anyhow!("Frame has wrong size"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    #[test]
+    fn test_score_black_frame() {
+        let black = RgbImage::from_pixel(320, 240, Rgb([0, 0, 0]));
+        assert_eq!(score(&black), 0.0);
+    }
+
+    #[test]
+    fn test_score_prefers_sharp_frames() {
+        let sharp = RgbImage::from_fn(320, 240, |x, y| {
+            if (x / 8 + y / 8) % 2 == 0 {
+                Rgb([255, 255, 255])
+            } else {
+                Rgb([0, 0, 0])
+            }
+        });
+
+        // Same brightness range, but a smooth gradient without edges.
+        let blurred = RgbImage::from_fn(320, 240, |x, _| {
+            let value = (x * 255 / 319) as u8;
+            Rgb([value, value, value])
+        });
+
+        assert!(score(&sharp) > score(&blurred));
+        assert!(score(&blurred) > 0.0);
+    }
+}
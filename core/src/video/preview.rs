@@ -0,0 +1,140 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Short, small, silent clips from the start of videos and motion photos, which play
+//! when hovering over items in an album.
+
+use crate::photo::PictureId;
+use crate::video::transcode::{Profile, VideoEncoder};
+use crate::video::VideoId;
+use anyhow::*;
+use ffmpeg_next as ffmpeg;
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+use tracing::{event, Level};
+
+/// Seconds from the start of a video that previews show.
+const PREVIEW_SECONDS: f64 = 3.0;
+
+/// Longest edge of previews. Large enough for album cells, but small enough to be
+/// quick to generate and play.
+const PREVIEW_EDGE: u32 = 320;
+
+/// Path of the preview of a video, or of the video of a motion photo if there is no
+/// video. The path can be computed before the preview exists.
+pub fn path(
+    cache_dir: &Path,
+    video_id: Option<VideoId>,
+    picture_id: Option<PictureId>,
+) -> Option<PathBuf> {
+    let base_path = cache_dir.join("video_previews");
+
+    // Create a directory per 1000 previews
+    if let Some(video_id) = video_id {
+        let partition = format!("{:0>4}", video_id.id() / 1000);
+        Some(base_path.join(partition).join(format!("{}.mp4", video_id)))
+    } else {
+        picture_id.map(|picture_id| {
+            let partition = format!("{:0>4}", picture_id.id() / 1000);
+            base_path
+                .join(partition)
+                .join(format!("{}_motion.mp4", picture_id))
+        })
+    }
+}
+
+/// Generates the preview of a video, if it doesn't exist already.
+pub fn generate(video_path: &Path, preview_path: &Path) -> Result<()> {
+    if preview_path.exists() {
+        return Ok(());
+    } else if let Some(p) = preview_path.parent() {
+        let _ = std::fs::create_dir_all(p);
+    }
+
+    event!(Level::DEBUG, "Video preview: {:?}", video_path);
+
+    let temporary_preview_path = preview_path.with_extension("tmp.mp4");
+
+    // Don't leave a partial preview behind to be mistaken for a complete one.
+    if let Err(e) = generate_to(video_path, &temporary_preview_path) {
+        let _ = std::fs::remove_file(&temporary_preview_path);
+        return Err(e.context(format!("Failed generating preview of {:?}", video_path)));
+    }
+
+    std::fs::rename(&temporary_preview_path, preview_path)?;
+
+    Ok(())
+}
+
+fn generate_to(video_path: &Path, preview_path: &Path) -> Result<()> {
+    let mut ictx = ffmpeg::format::input(&video_path)?;
+    let mut octx = ffmpeg::format::output(&preview_path)?;
+
+    let (video_index, mut video_encoder, start_time, time_base) = {
+        let stream = ictx
+            .streams()
+            .best(ffmpeg::media::Type::Video)
+            .ok_or_else(|| anyhow!("No video stream"))?;
+
+        let profile = Profile {
+            max_edge: Some(PREVIEW_EDGE),
+            ..Profile::default()
+        };
+        let video_encoder = VideoEncoder::new(&stream, &mut octx, 0, &profile, None)?;
+
+        // Streams without a start time start at zero.
+        let start_time = match stream.start_time() {
+            ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+            start_time => start_time,
+        };
+
+        (
+            stream.index(),
+            video_encoder,
+            start_time,
+            f64::from(stream.time_base()),
+        )
+    };
+
+    octx.write_header()?;
+
+    for (stream, packet) in ictx.packets() {
+        if stream.index() != video_index {
+            continue;
+        }
+
+        let timestamp = packet.pts().or(packet.dts()).unwrap_or(start_time);
+        if (timestamp - start_time) as f64 * time_base > PREVIEW_SECONDS {
+            break;
+        }
+
+        video_encoder.send_packet(&packet, &mut octx)?;
+    }
+
+    video_encoder.finish(&mut octx)?;
+
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path() {
+        let cache_dir = Path::new("/cache");
+
+        assert_eq!(
+            path(cache_dir, Some(VideoId::new(1234)), Some(PictureId::new(5))),
+            Some(PathBuf::from("/cache/video_previews/0001/1234.mp4"))
+        );
+        assert_eq!(
+            path(cache_dir, None, Some(PictureId::new(5))),
+            Some(PathBuf::from("/cache/video_previews/0000/5_motion.mp4"))
+        );
+        assert_eq!(path(cache_dir, None, None), None);
+    }
+}
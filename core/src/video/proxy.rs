@@ -0,0 +1,256 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Playing videos that GTK can't play, such as HEVC videos, while they are transcoded.
+//!
+//! A quick, low latency transcode is written as fragmented MP4 into a named pipe, which
+//! a player opens like any other file and plays as fragments arrive. Playback starts as
+//! soon as the first fragment has been encoded, instead of once the whole video has been
+//! transcoded. A pipe can't be seeked, and nothing is kept once the proxy is dropped.
+
+use crate::video::transcode::{Container, Profile, VideoEncoder};
+use anyhow::*;
+use ffmpeg_next as ffmpeg;
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::result::Result::Ok;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tempfile::TempDir;
+use tracing::{event, Level};
+
+/// Longest edge of proxies. Smaller than most videos, so that encoding keeps ahead of playback.
+const PROXY_EDGE: u32 = 1920;
+
+/// Microseconds of video in each fragment, so that playback can start after a second or so.
+const FRAGMENT_MICROS: &str = "1000000";
+
+/// A video being transcoded into a named pipe for playing.
+pub struct Proxy {
+    /// Directory holding the pipe, removed once the transcode has stopped.
+    dir: Option<TempDir>,
+
+    path: PathBuf,
+
+    is_cancelled: Arc<AtomicBool>,
+
+    transcode: Option<JoinHandle<()>>,
+}
+
+impl Proxy {
+    /// Starts transcoding a video into a new named pipe. The transcode waits for a
+    /// player to open the pipe, and stops if the player closes it.
+    pub fn start(video_path: &Path) -> Result<Proxy> {
+        let dir = tempfile::Builder::new().prefix("fotema-proxy").tempdir()?;
+        let path = dir.path().join("proxy.mp4");
+
+        let c_path = CString::new(path.as_os_str().as_bytes())?;
+        if unsafe { libc::mkfifo(c_path.as_ptr(), 0o600) } != 0 {
+            bail!(
+                "Failed making pipe {:?}: {}",
+                path,
+                std::io::Error::last_os_error()
+            );
+        }
+
+        event!(Level::DEBUG, "Streaming {:?} to {:?}", video_path, path);
+
+        let is_cancelled = Arc::new(AtomicBool::new(false));
+
+        let transcode = {
+            let video_path = PathBuf::from(video_path);
+            let path = path.clone();
+            let is_cancelled = is_cancelled.clone();
+            std::thread::spawn(move || {
+                if let Err(e) = stream(&video_path, &path, &is_cancelled) {
+                    if !is_cancelled.load(Ordering::Relaxed) {
+                        event!(Level::ERROR, "Failed streaming {:?}: {:?}", video_path, e);
+                    }
+                }
+            })
+        };
+
+        Ok(Proxy {
+            dir: Some(dir),
+            path,
+            is_cancelled,
+            transcode: Some(transcode),
+        })
+    }
+
+    /// Path of the pipe to play.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for Proxy {
+    fn drop(&mut self) {
+        self.is_cancelled.store(true, Ordering::Relaxed);
+
+        let (Some(dir), Some(transcode)) = (self.dir.take(), self.transcode.take()) else {
+            return;
+        };
+
+        // The transcode might be waiting for a player to open the pipe, so briefly open
+        // it until the transcode notices the cancellation. Writing to a pipe without a
+        // reader then fails. Waiting happens off the calling thread, which is usually
+        // the UI thread.
+        let path = self.path.clone();
+        std::thread::spawn(move || {
+            while !transcode.is_finished() {
+                let _ = std::fs::OpenOptions::new()
+                    .read(true)
+                    .custom_flags(libc::O_NONBLOCK)
+                    .open(&path);
+                std::thread::sleep(Duration::from_millis(50));
+            }
+            drop(dir);
+        });
+    }
+}
+
+/// Transcodes a video as fragmented MP4 into a pipe until the end of the video,
+/// cancellation, or the pipe is closed.
+fn stream(video_path: &Path, pipe_path: &Path, is_cancelled: &AtomicBool) -> Result<()> {
+    let mut ictx = ffmpeg::format::input(&video_path)?;
+
+    // Opening the pipe waits for a reader.
+    let mut octx = ffmpeg::format::output_as(&pipe_path, "mp4")?;
+    if is_cancelled.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+
+    let video_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .map(|stream| stream.index())
+        .ok_or_else(|| anyhow!("No video stream"))?;
+
+    let audio_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Audio)
+        .map(|stream| stream.index());
+
+    let profile = Profile {
+        max_edge: Some(PROXY_EDGE),
+        container: Container::Mp4,
+        is_realtime: true,
+        ..Profile::default()
+    };
+
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut input_time_bases = vec![ffmpeg::Rational(0, 1); ictx.nb_streams() as usize];
+    let mut video_encoder = None;
+
+    let mut output_index = 0;
+    for (input_index, input_stream) in ictx.streams().enumerate() {
+        if input_index == video_index {
+            video_encoder = Some(VideoEncoder::new(
+                &input_stream,
+                &mut octx,
+                output_index,
+                &profile,
+                None,
+            )?);
+        } else if Some(input_index) == audio_index && is_mp4_codec(&octx, &input_stream) {
+            let mut output_stream =
+                octx.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+            output_stream.set_parameters(input_stream.parameters());
+
+            // Let the muxer pick a codec tag suitable for the container.
+            unsafe {
+                (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+            }
+        } else {
+            continue;
+        }
+
+        stream_mapping[input_index] = Some(output_index);
+        input_time_bases[input_index] = input_stream.time_base();
+        output_index += 1;
+    }
+
+    let mut video_encoder = video_encoder.ok_or_else(|| anyhow!("No video encoder"))?;
+
+    // A pipe can't be seeked to write an index at the end, so write the index
+    // in each fragment instead.
+    let mut options = ffmpeg::Dictionary::new();
+    options.set("movflags", "frag_keyframe+empty_moov+default_base_moof");
+    options.set("frag_duration", FRAGMENT_MICROS);
+    octx.write_header_with(options)?;
+
+    for (stream, mut packet) in ictx.packets() {
+        if is_cancelled.load(Ordering::Relaxed) {
+            return Ok(());
+        }
+
+        let input_index = stream.index();
+        let Some(output_index) = stream_mapping[input_index] else {
+            continue;
+        };
+
+        if input_index == video_index {
+            video_encoder.send_packet(&packet, &mut octx)?;
+            continue;
+        }
+
+        let output_time_base = octx
+            .stream(output_index)
+            .map(|x| x.time_base())
+            .ok_or_else(|| anyhow!("Missing output stream {}", output_index))?;
+
+        packet.rescale_ts(input_time_bases[input_index], output_time_base);
+        packet.set_position(-1);
+        packet.set_stream(output_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    video_encoder.finish(&mut octx)?;
+
+    octx.write_trailer()?;
+
+    Ok(())
+}
+
+/// Can a stream be copied into an MP4 container without re-encoding?
+/// Not every audio codec fits, such as the PCM audio of some cameras.
+fn is_mp4_codec(octx: &ffmpeg::format::context::Output, stream: &ffmpeg::Stream) -> bool {
+    let codec_id = stream.parameters().id().into();
+    let is_supported = unsafe {
+        ffmpeg::ffi::avformat_query_codec(
+            octx.format().as_ptr(),
+            codec_id,
+            ffmpeg::ffi::FF_COMPLIANCE_NORMAL as i32,
+        )
+    };
+    is_supported == 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_drop_removes_pipe() {
+        let proxy = Proxy::start(Path::new("/does/not/exist.mov")).unwrap();
+        let path = proxy.path().to_path_buf();
+        assert!(path.exists());
+
+        drop(proxy);
+
+        // The pipe is removed in the background once the transcode stops.
+        for _ in 0..100 {
+            if !path.exists() {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("Pipe not removed: {:?}", path);
+    }
+}
@@ -5,6 +5,7 @@
 use super::metadata;
 use super::Metadata;
 use crate::path_encoding;
+use crate::thumbnail::{self, Thumbnail};
 use crate::video::model::{ScannedFile, Video, VideoId};
 use anyhow::*;
 use chrono::*;
@@ -47,7 +48,7 @@ impl Repository {
         Ok(repo)
     }
 
-    pub fn add_thumbnail(&mut self, video_id: &VideoId, thumbnail_path: &Path) -> Result<()> {
+    pub fn add_thumbnail(&mut self, video_id: &VideoId, thumbnail: &Thumbnail) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
 
@@ -56,16 +57,26 @@ impl Repository {
                 "UPDATE videos
                 SET
                     thumbnail_path = ?2,
+                    thumbnail_max_edge = ?3,
+                    width = COALESCE(?4, width),
+                    height = COALESCE(?5, height),
+                    focus_x = COALESCE(?6, focus_x),
+                    focus_y = COALESCE(?7, focus_y),
                     is_broken = FALSE
                 WHERE video_id = ?1",
             )?;
 
             // convert to relative path before saving to database
-            let thumbnail_path = thumbnail_path.strip_prefix(&self.thumbnail_base_path).ok();
+            let thumbnail_path = thumbnail.path.strip_prefix(&self.thumbnail_base_path).ok();
 
             stmt.execute(params![
                 video_id.id(),
                 thumbnail_path.as_ref().map(|p| p.to_str()),
+                thumbnail::LARGEST_EDGE,
+                thumbnail.size.map(|(width, _)| width),
+                thumbnail.size.map(|(_, height)| height),
+                thumbnail.focus.map(|focus| focus.x),
+                thumbnail.focus.map(|focus| focus.y),
             ])?;
         }
 
@@ -73,6 +84,51 @@ impl Repository {
         Ok(())
     }
 
+    /// Position of the frame chosen by the user for thumbnails.
+    pub fn poster(&self, video_id: &VideoId) -> Result<Option<TimeDelta>> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare("SELECT poster_millis FROM videos WHERE video_id = ?1")?;
+
+        let millis: Option<i64> = stmt.query_row(params![video_id.id()], |row| row.get(0))?;
+
+        Ok(millis.and_then(TimeDelta::try_milliseconds))
+    }
+
+    /// Set the position of the frame that thumbnails are made from.
+    /// None goes back to choosing a representative frame.
+    pub fn set_poster(&mut self, video_id: &VideoId, position: Option<TimeDelta>) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE videos
+            SET poster_millis = ?2
+            WHERE video_id = ?1",
+        )?;
+
+        stmt.execute(params![
+            video_id.id(),
+            position.map(|p| p.num_milliseconds())
+        ])?;
+        Ok(())
+    }
+
+    /// Records the largest thumbnail that remains after larger thumbnails were removed
+    /// to keep the cache within its budget.
+    pub fn set_thumbnail_max_edge(&mut self, thumbnail_path: &Path, max_edge: u32) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE videos
+            SET thumbnail_max_edge = ?2
+            WHERE thumbnail_path = ?1",
+        )?;
+
+        // thumbnail paths are relative in the database
+        let thumbnail_path = thumbnail_path.strip_prefix(&self.thumbnail_base_path)?;
+
+        stmt.execute(params![thumbnail_path.to_str(), max_edge])?;
+
+        Ok(())
+    }
+
     pub fn mark_broken(&mut self, video_id: &VideoId) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
@@ -117,6 +173,23 @@ impl Repository {
         Ok(())
     }
 
+    /// Forgets a transcoded video that was removed to keep the cache within its budget.
+    pub fn remove_transcode(&mut self, transcoded_path: &Path) -> Result<()> {
+        let con = self.con.lock().unwrap();
+        let mut stmt = con.prepare_cached(
+            "UPDATE videos
+            SET transcoded_path = NULL
+            WHERE transcoded_path = ?1",
+        )?;
+
+        // transcoded paths are relative in the database
+        let transcoded_path = transcoded_path.strip_prefix(&self.thumbnail_base_path)?;
+
+        stmt.execute(params![transcoded_path.to_str()])?;
+
+        Ok(())
+    }
+
     pub fn add_metadata(&mut self, vids: Vec<(VideoId, Metadata)>) -> Result<()> {
         let mut con = self.con.lock().unwrap();
         let tx = con.transaction()?;
@@ -210,6 +283,7 @@ impl Repository {
                     video_id,
                     video_path_b64,
                     thumbnail_path,
+                    thumbnail_max_edge,
                     COALESCE(
                         videos.stream_created_ts,
                         videos.fs_created_ts,
@@ -237,6 +311,7 @@ impl Repository {
                     video_id,
                     video_path_b64,
                     thumbnail_path,
+                    thumbnail_max_edge,
                     COALESCE(
                         videos.stream_created_ts,
                         videos.fs_created_ts,
@@ -270,6 +345,8 @@ impl Repository {
             .map(|p: String| self.thumbnail_base_path.join(p))
             .ok();
 
+        let thumbnail_max_edge = row.get("thumbnail_max_edge").ok();
+
         let ordering_ts = row.get("ordering_ts").expect("must have ordering_ts");
 
         let stream_duration = row
@@ -288,6 +365,7 @@ impl Repository {
             video_id,
             path: video_path,
             thumbnail_path,
+            thumbnail_max_edge,
             ordering_ts,
             stream_duration,
             video_codec,
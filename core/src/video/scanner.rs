@@ -27,13 +27,21 @@ impl Scanner {
         Ok(Scanner { scan_base })
     }
 
+    /// Does the path have the file extension of a supported video type?
+    pub fn is_supported(path: &Path) -> bool {
+        let suffixes = ["mov", "mp4"];
+
+        path.extension()
+            .and_then(|s| s.to_str())
+            .map(|s| s.to_lowercase())
+            .is_some_and(|ext| suffixes.contains(&ext.as_str()))
+    }
+
     /// Scans all videos in the base directory for function `func` to visit.
     pub fn scan_all_visit<F>(&self, func: F)
     where
         F: FnMut(ScannedFile),
     {
-        let suffixes = [String::from("mov"), String::from("mp4")];
-
         WalkDir::new(&self.scan_base)
             .into_iter()
             .inspect(|x| {
@@ -43,15 +51,7 @@ impl Scanner {
             })
             .flatten() // skip files we failed to read
             .filter(|x| x.path().is_file()) // only process files
-            .filter(|x| {
-                // only process supported image types
-                let ext = x
-                    .path()
-                    .extension()
-                    .and_then(|s| s.to_str())
-                    .map(|s| s.to_lowercase());
-                suffixes.contains(&ext.unwrap_or(String::from("not_a_video")))
-            })
+            .filter(|x| Self::is_supported(x.path())) // only process supported video types
             .map(|x| self.scan_one(x.path())) // Get video info for path
             .inspect(|x| {
                 let _ = x
@@ -72,6 +72,23 @@ impl Scanner {
         Ok(vids)
     }
 
+    /// Scans just the given paths, such as newly imported files.
+    /// Paths that aren't videos are ignored.
+    pub fn scan_paths(&self, paths: &[PathBuf]) -> Result<Vec<ScannedFile>> {
+        let vids = paths
+            .iter()
+            .filter(|x| x.is_file() && Self::is_supported(x))
+            .map(|x| self.scan_one(x))
+            .inspect(|x| {
+                let _ = x
+                    .as_ref()
+                    .inspect_err(|e| error!("Failed scanning: {:?}", e));
+            })
+            .flatten()
+            .collect();
+        Ok(vids)
+    }
+
     pub fn scan_one(&self, path: &Path) -> Result<ScannedFile> {
         let file = fs::File::open(path)?;
 
@@ -2,21 +2,27 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+use crate::photo::model::Orientation;
 use crate::photo::thumbnail::Thumbnailer as PhotoThumbnailer;
+use crate::thumbnail::freedesktop::SharedCache;
+use crate::thumbnail::{self, Format, Shape, Thumbnail};
+use crate::video::metadata;
 use crate::video::model::VideoId;
+use crate::video::poster;
 use anyhow::*;
+use chrono::TimeDelta;
+use image::DynamicImage;
 use std::path::{Path, PathBuf};
-use std::process::Command;
 use std::result::Result::Ok;
-use tempfile;
 use tracing::{event, Level};
 
-const EDGE: u32 = 200;
-
 /// Thumbnail operations for videos.
 #[derive(Debug, Clone)]
 pub struct Thumbnailer {
     base_path: PathBuf,
+    shape: Shape,
+    format: Format,
+    shared_cache: Option<SharedCache>,
 }
 
 impl Thumbnailer {
@@ -24,50 +30,176 @@ impl Thumbnailer {
         let base_path = PathBuf::from(base_path).join("video_thumbnails");
         std::fs::create_dir_all(&base_path)?;
 
-        Ok(Thumbnailer { base_path })
+        Ok(Thumbnailer {
+            base_path,
+            shape: Shape::default(),
+            format: Format::default(),
+            shared_cache: None,
+        })
     }
 
-    /// Computes a preview square for an image that has been inserted
-    /// into the Repository. Preview image will be written to file system.
-    pub fn thumbnail(&self, video_id: &VideoId, video_path: &Path) -> Result<PathBuf> {
-        let thumbnail_path = {
-            // Create a directory per 1000 thumbnails
-            let partition = (video_id.id() / 1000) as i32;
-            let partition = format!("{:0>4}", partition);
-            let file_name = format!("{}_{}x{}.png", video_id, EDGE, EDGE);
-            self.base_path.join(partition).join(file_name)
-        };
+    /// Shape of thumbnails to generate.
+    pub fn with_shape(mut self, shape: Shape) -> Self {
+        self.shape = shape;
+        self
+    }
+
+    /// File format of thumbnails to generate.
+    pub fn with_format(mut self, format: Format) -> Self {
+        self.format = format;
+        self
+    }
+
+    /// Shared thumbnail cache to make thumbnails from, and to write thumbnails to.
+    pub fn with_shared_cache(mut self, shared_cache: Option<SharedCache>) -> Self {
+        self.shared_cache = shared_cache;
+        self
+    }
 
-        if thumbnail_path.exists() {
-            return Ok(thumbnail_path);
+    /// Computes previews of every thumbnail size for a video that has been inserted
+    /// into the Repository. Preview images will be written to file system and the path
+    /// of the smallest returned, along with the size of the video if it was decoded.
+    /// Thumbnails are made from the frame at the poster position if given, or else from
+    /// a representative frame.
+    pub fn thumbnail(
+        &self,
+        video_id: &VideoId,
+        video_path: &Path,
+        poster: Option<TimeDelta>,
+    ) -> Result<Thumbnail> {
+        let thumbnail_path = self.thumbnail_path(video_id);
+
+        if thumbnail::all_exist(&thumbnail_path) {
+            // The aspect ratio of the largest thumbnail is near enough to the video's.
+            let size = if self.shape == Shape::Aspect {
+                let largest = thumbnail::sized_path(&thumbnail_path, thumbnail::LARGEST_EDGE);
+                image::image_dimensions(largest).ok()
+            } else {
+                None
+            };
+            return Ok(Thumbnail {
+                path: thumbnail_path,
+                size,
+                focus: None,
+            });
         } else if let Some(p) = thumbnail_path.parent() {
             let _ = std::fs::create_dir_all(p);
         }
 
+        // Other apps don't know of the poster chosen by the user.
+        if poster.is_none() {
+            if let Some(thumbnail) = self.shared_thumbnail(video_path, &thumbnail_path) {
+                return Ok(thumbnail);
+            }
+        }
+
         event!(Level::DEBUG, "Standard thumbnail: {:?}", video_path);
 
-        self.compute_thumbnail(video_path, &thumbnail_path)
-            .map(|_| thumbnail_path)
+        self.compute_thumbnail(video_path, &thumbnail_path, poster)
             .inspect_err(|e| event!(Level::ERROR, "Video thumbnail error: {:?}", e))
     }
 
-    fn compute_thumbnail(&self, video_path: &Path, thumbnail_path: &Path) -> Result<()> {
-        let temporary_png_file = tempfile::Builder::new().suffix(".png").tempfile()?;
-
-        // ffmpeg command will extract the first frame and save it as a PNG file.
-        Command::new("ffmpeg")
-            .arg("-loglevel")
-            .arg("error")
-            .arg("-y") // temp file will already exist, so allow overwriting
-            .arg("-i")
-            .arg(video_path.as_os_str())
-            .arg("-update")
-            .arg("true")
-            .arg("-vf")
-            .arg(r"select=eq(n\,0)") // select frame zero
-            .arg(temporary_png_file.path())
-            .status()?;
-
-        PhotoThumbnailer::fast_thumbnail(temporary_png_file.path(), thumbnail_path)
+    /// Remove the thumbnails of a video so that the next call to `thumbnail`
+    /// regenerates them, such as after the poster has changed.
+    pub fn remove(&self, video_id: &VideoId) -> Result<()> {
+        let thumbnail_path = self.thumbnail_path(video_id);
+
+        for edge in thumbnail::EDGES {
+            let path = thumbnail::sized_path(&thumbnail_path, edge);
+            if path.exists() {
+                std::fs::remove_file(path)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Path of the smallest thumbnail of a video.
+    fn thumbnail_path(&self, video_id: &VideoId) -> PathBuf {
+        // Create a directory per 1000 thumbnails
+        let partition = (video_id.id() / 1000) as i32;
+        let partition = format!("{:0>4}", partition);
+        let file_name = thumbnail::file_name(video_id, "", self.shape, self.format);
+        self.base_path.join(partition).join(file_name)
+    }
+
+    /// Make thumbnails from a thumbnail in the shared cache that is large enough, which
+    /// is much quicker than decoding the video. Unlike photos, the frames of both
+    /// are already oriented.
+    fn shared_thumbnail(&self, video_path: &Path, thumbnail_path: &Path) -> Option<Thumbnail> {
+        let cache = self.shared_cache.as_ref()?;
+        let shared = cache.find(video_path, thumbnail::LARGEST_EDGE)?;
+        event!(Level::DEBUG, "Shared thumbnail: {:?}", video_path);
+
+        PhotoThumbnailer::write_thumbnail(
+            shared.image,
+            thumbnail_path,
+            self.shape,
+            self.format,
+            None,
+            None,
+        )
+        .inspect_err(|e| {
+            event!(
+                Level::DEBUG,
+                "Failed thumbnail from shared thumbnail: {:?}",
+                e
+            )
+        })
+        .ok()
+    }
+
+    fn compute_thumbnail(
+        &self,
+        video_path: &Path,
+        thumbnail_path: &Path,
+        poster: Option<TimeDelta>,
+    ) -> Result<Thumbnail> {
+        let frame = match poster {
+            Some(position) => poster::at(video_path, position, thumbnail::LARGEST_EDGE)?,
+            None => poster::representative(video_path, thumbnail::LARGEST_EDGE)?,
+        };
+
+        // Decoded frames don't have the display matrix rotation applied.
+        let metadata = metadata::from_path(video_path).ok();
+        let orientation = metadata
+            .as_ref()
+            .and_then(|m| m.rotation)
+            .map(Orientation::from_degrees)
+            .unwrap_or(Orientation::North);
+        let image = orientation.apply_to(DynamicImage::ImageRgb8(frame));
+
+        let shared = self
+            .shared_cache
+            .as_ref()
+            .filter(|_| poster.is_none())
+            .map(|cache| (cache, video_path));
+
+        let thumbnail = PhotoThumbnailer::write_thumbnail(
+            image,
+            thumbnail_path,
+            self.shape,
+            self.format,
+            None,
+            shared,
+        )?;
+
+        // Frames are scaled down before thumbnails are made, so give the size of the video,
+        // as displayed.
+        let size = metadata
+            .and_then(|m| m.width.zip(m.height))
+            .map(|(width, height)| (width as u32, height as u32))
+            .map(|(width, height)| {
+                if orientation.is_sideways() {
+                    (height, width)
+                } else {
+                    (width, height)
+                }
+            });
+
+        Ok(Thumbnail {
+            size: size.or(thumbnail.size),
+            ..thumbnail
+        })
     }
 }
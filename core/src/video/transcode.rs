@@ -3,43 +3,200 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use anyhow::*;
+use chrono::TimeDelta;
+use ffmpeg_next as ffmpeg;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::result::Result::Ok;
 
+use crate::photo::model::Orientation;
+use crate::thumbnail::cache;
+use crate::video::metadata;
 use crate::video::VideoId;
 
 use tracing::{event, Level};
 
+/// Threading for video decoders. Frame threading decodes one frame per thread,
+/// which keeps decoding from being the bottleneck for large videos.
+pub(crate) fn decoder_threading() -> ffmpeg::threading::Config {
+    let mut config = ffmpeg::threading::Config::kind(ffmpeg::threading::Type::Frame);
+    config.count = std::thread::available_parallelism().map_or(0, |count| count.get());
+    config
+}
+
+/// Video codec of transcoded videos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    /// Plays almost everywhere.
+    #[default]
+    H264,
+
+    /// About half the size of H.264 at the same quality, but slower to encode.
+    H265,
+}
+
+impl Codec {
+    fn id(&self) -> ffmpeg::codec::Id {
+        match self {
+            Codec::H264 => ffmpeg::codec::Id::H264,
+            Codec::H265 => ffmpeg::codec::Id::HEVC,
+        }
+    }
+}
+
+/// Container format of transcoded videos.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Container {
+    /// Matroska, which can hold any audio codec.
+    #[default]
+    Matroska,
+
+    /// MPEG-4, which more apps can open.
+    Mp4,
+}
+
+impl Container {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Matroska => "mkv",
+            Container::Mp4 => "mp4",
+        }
+    }
+}
+
+/// How videos are transcoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Profile {
+    pub codec: Codec,
+
+    /// Constant rate factor from 0 to 51. Lower is better quality and larger.
+    pub crf: u8,
+
+    /// Frames are scaled down to fit within this many pixels, if given.
+    pub max_edge: Option<u32>,
+
+    pub container: Container,
+
+    /// Encode as quickly as possible, for playing while transcoding, at the cost of size.
+    pub is_realtime: bool,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Profile {
+            codec: Codec::default(),
+            crf: 23,
+            max_edge: None,
+            container: Container::default(),
+            is_realtime: false,
+        }
+    }
+}
+
+impl Profile {
+    /// Profile with codec and container names used in preferences. Unknown names are
+    /// the defaults, and a `max_edge` of zero keeps the original size.
+    pub fn from_names(codec: &str, crf: u32, max_edge: u32, container: &str) -> Profile {
+        let codec = match codec {
+            "h265" => Codec::H265,
+            _ => Codec::H264,
+        };
+        let container = match container {
+            "mp4" => Container::Mp4,
+            _ => Container::Matroska,
+        };
+        Profile {
+            codec,
+            crf: crf.min(51) as u8,
+            max_edge: Some(max_edge).filter(|edge| *edge > 0),
+            container,
+            is_realtime: false,
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct Transcoder {
     /// Base path for storing transcoded videos
     base_path: PathBuf,
+
+    profile: Profile,
 }
 
 impl Transcoder {
     pub fn new(base_path: &Path) -> Self {
         let base_path = PathBuf::from(base_path).join("video_transcodes");
         let _ = std::fs::create_dir_all(&base_path);
-        Self { base_path }
+        Self {
+            base_path,
+            profile: Profile::default(),
+        }
+    }
+
+    /// Profile of videos transcoded from now on. Existing transcodes are kept.
+    pub fn with_profile(mut self, profile: Profile) -> Self {
+        self.profile = profile;
+        self
     }
 
     /// Transcodes the video at 'path' and returns a path to the transcoded video.
-    pub fn transcode(&self, video_id: VideoId, video_path: &Path) -> Result<PathBuf> {
+    /// Progress is reported as the fraction of the video transcoded so far.
+    pub fn transcode(
+        &self,
+        video_id: VideoId,
+        video_path: &Path,
+        progress: impl FnMut(f64),
+    ) -> Result<PathBuf> {
         let transcoded_path = {
             // Create a directory per 1000 videos
             let partition = (video_id.id() / 1000) as i32;
             let partition = format!("{:0>4}", partition);
-            let file_name = format!("{}.mkv", video_id);
+            let file_name = format!("{}.{}", video_id, self.profile.container.extension());
             self.base_path.join(partition).join(file_name)
         };
 
-        transcode(video_path, &transcoded_path)?;
+        transcode_with(video_path, &transcoded_path, &self.profile, progress)?;
 
         Ok(transcoded_path)
     }
+
+    /// Total bytes of transcoded videos, except ones still being written.
+    pub fn size(&self) -> u64 {
+        cache::size(std::slice::from_ref(&self.base_path))
+    }
+
+    /// Remove the transcoded videos played longest ago until the rest fit within a budget
+    /// of bytes. When a video was last played is taken from its access time, as for
+    /// thumbnails. Videos to keep, such as ones just transcoded, are never removed.
+    /// Returns the paths of removed videos.
+    pub fn trim(&self, budget: u64, keep: &[PathBuf]) -> Vec<PathBuf> {
+        let mut files = cache::files(std::slice::from_ref(&self.base_path));
+        let total: u64 = files.iter().map(|file| file.bytes).sum();
+
+        files.retain(|file| !keep.contains(&file.path));
+        files.sort_by_key(|file| file.accessed);
+
+        let files = files.into_iter().map(|file| (file, ())).collect();
+        cache::remove_in_order(files, total, budget)
+            .into_iter()
+            .map(|(file, _)| file.path)
+            .collect()
+    }
 }
 
+/// Transcodes a video to H.264 in a Matroska container, rotating frames by the display
+/// matrix so players don't have to. The best audio stream is copied without re-encoding.
 pub fn transcode(video_path: &Path, transcoded_path: &Path) -> Result<()> {
+    transcode_with(video_path, transcoded_path, &Profile::default(), |_| {})
+}
+
+/// Transcodes a video as [`transcode`] does, but with a profile, reporting progress as
+/// the fraction of the video transcoded so far.
+pub fn transcode_with(
+    video_path: &Path,
+    transcoded_path: &Path,
+    profile: &Profile,
+    mut progress: impl FnMut(f64),
+) -> Result<()> {
     if transcoded_path.exists() {
         return Ok(());
     } else if let Some(p) = transcoded_path.parent() {
@@ -48,24 +205,500 @@ pub fn transcode(video_path: &Path, transcoded_path: &Path) -> Result<()> {
 
     event!(Level::DEBUG, "Transcoding video: {:?}", video_path);
 
-    let temporary_transcoded_path = transcoded_path.with_extension("tmp.mkv");
-
-    Command::new("ffmpeg")
-        .arg("-y")
-        .arg("-loglevel")
-        .arg("error")
-        .arg("-hwaccel")
-        .arg("auto")
-        .arg("-i")
-        .arg(video_path.as_os_str())
-        .arg("-c:a")
-        .arg("copy")
-        .arg("-c:v")
-        .arg("h264")
-        .arg(temporary_transcoded_path.as_os_str())
-        .status()?;
+    let temporary_transcoded_path =
+        transcoded_path.with_extension(format!("tmp.{}", profile.container.extension()));
+
+    // Don't leave a partial transcode behind to be mistaken for a complete one.
+    if let Err(e) = transcode_to(
+        video_path,
+        &temporary_transcoded_path,
+        None,
+        profile,
+        &mut progress,
+    ) {
+        let _ = std::fs::remove_file(&temporary_transcoded_path);
+        return Err(e.context(format!("Failed transcoding {:?}", video_path)));
+    }
 
     std::fs::rename(&temporary_transcoded_path, transcoded_path)?;
 
     Ok(())
 }
+
+/// Transcodes the part of a video between two positions from its start, as [`transcode`]
+/// does for a whole video. The container is chosen by the extension of `transcoded_path`.
+pub fn transcode_range(
+    video_path: &Path,
+    transcoded_path: &Path,
+    start: TimeDelta,
+    end: TimeDelta,
+) -> Result<()> {
+    let to_seconds = |position: TimeDelta| position.num_milliseconds() as f64 / 1000.0;
+    transcode_to(
+        video_path,
+        transcoded_path,
+        Some((to_seconds(start), to_seconds(end))),
+        &Profile::default(),
+        &mut |_| {},
+    )
+    .with_context(|| format!("Failed transcoding {:?}", video_path))
+}
+
+/// Transcodes a video, or the part between two numbers of seconds from the start of its
+/// video stream.
+fn transcode_to(
+    video_path: &Path,
+    transcoded_path: &Path,
+    range: Option<(f64, f64)>,
+    profile: &Profile,
+    progress: &mut dyn FnMut(f64),
+) -> Result<()> {
+    let mut ictx = ffmpeg::format::input(&video_path)?;
+    let mut octx = ffmpeg::format::output(&transcoded_path)?;
+
+    let video_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Video)
+        .map(|stream| stream.index())
+        .ok_or_else(|| anyhow!("No video stream"))?;
+
+    // Decoding must start from the keyframe before the start of the range, and frames
+    // before the start are dropped by the filter graph.
+    if let Some((start, _)) = range {
+        let timestamp = (start * 1_000_000.0) as i64;
+        ictx.seek(timestamp, ..timestamp)?;
+    }
+
+    let audio_index = ictx
+        .streams()
+        .best(ffmpeg::media::Type::Audio)
+        .map(|stream| stream.index());
+
+    let mut stream_mapping: Vec<Option<usize>> = vec![None; ictx.nb_streams() as usize];
+    let mut input_time_bases = vec![ffmpeg::Rational(0, 1); ictx.nb_streams() as usize];
+    let mut input_start_times = vec![0; ictx.nb_streams() as usize];
+    let mut video_encoder = None;
+
+    let mut output_index = 0;
+    for (input_index, input_stream) in ictx.streams().enumerate() {
+        if input_index == video_index {
+            video_encoder = Some(VideoEncoder::new(
+                &input_stream,
+                &mut octx,
+                output_index,
+                profile,
+                range,
+            )?);
+        } else if Some(input_index) == audio_index {
+            let mut output_stream =
+                octx.add_stream(ffmpeg::encoder::find(ffmpeg::codec::Id::None))?;
+            output_stream.set_parameters(input_stream.parameters());
+
+            // Let the muxer pick a codec tag suitable for the container.
+            unsafe {
+                (*output_stream.parameters().as_mut_ptr()).codec_tag = 0;
+            }
+        } else {
+            continue;
+        }
+
+        stream_mapping[input_index] = Some(output_index);
+        input_time_bases[input_index] = input_stream.time_base();
+        input_start_times[input_index] = start_time(&input_stream);
+        output_index += 1;
+    }
+
+    let mut video_encoder = video_encoder.ok_or_else(|| anyhow!("No video encoder"))?;
+
+    // Seconds of video to transcode, for reporting progress. Unknown if not positive.
+    let duration = match range {
+        Some((start, end)) => end - start,
+        None => ictx.duration() as f64 / f64::from(ffmpeg::ffi::AV_TIME_BASE),
+    };
+
+    octx.set_metadata(ictx.metadata().to_owned());
+    octx.write_header()?;
+
+    for (stream, mut packet) in ictx.packets() {
+        let input_index = stream.index();
+        let Some(output_index) = stream_mapping[input_index] else {
+            continue;
+        };
+
+        if let Some((start, end)) = range {
+            // Timestamps only increase in decoding order, so later packets are after the end too.
+            let time_base = f64::from(input_time_bases[input_index]);
+            let seconds =
+                |timestamp: i64| (timestamp - input_start_times[input_index]) as f64 * time_base;
+
+            let decoded_at = packet.dts().or(packet.pts()).map(seconds);
+            if decoded_at.is_some_and(|seconds| seconds > end) {
+                if input_index == video_index {
+                    break;
+                }
+                continue;
+            }
+
+            if input_index != video_index {
+                // Audio starts at the start of the range, as video does after filtering.
+                if packet
+                    .pts()
+                    .map(seconds)
+                    .is_some_and(|seconds| seconds < start)
+                {
+                    continue;
+                }
+                let shift = input_start_times[input_index] + (start / time_base).round() as i64;
+                packet.set_pts(packet.pts().map(|pts| pts - shift));
+                packet.set_dts(packet.dts().map(|dts| dts - shift));
+            }
+        }
+
+        if input_index == video_index {
+            if let Some(pts) = packet.pts().filter(|_| duration > 0.0) {
+                let time_base = f64::from(input_time_bases[input_index]);
+                let start = range.map(|(start, _)| start).unwrap_or(0.0);
+                let seconds = (pts - input_start_times[input_index]) as f64 * time_base - start;
+                progress((seconds / duration).clamp(0.0, 1.0));
+            }
+
+            video_encoder.send_packet(&packet, &mut octx)?;
+            continue;
+        }
+
+        let output_time_base = octx
+            .stream(output_index)
+            .map(|x| x.time_base())
+            .ok_or_else(|| anyhow!("Missing output stream {}", output_index))?;
+
+        packet.rescale_ts(input_time_bases[input_index], output_time_base);
+        packet.set_position(-1);
+        packet.set_stream(output_index);
+        packet.write_interleaved(&mut octx)?;
+    }
+
+    video_encoder.finish(&mut octx)?;
+
+    octx.write_trailer()?;
+
+    progress(1.0);
+
+    Ok(())
+}
+
+/// Decodes a video stream, rotates the frames by the display matrix, and encodes
+/// them with the codec of a profile.
+pub(super) struct VideoEncoder {
+    output_index: usize,
+
+    decoder: ffmpeg::decoder::Video,
+
+    /// Rotates and scales frames, and converts them to a pixel format the encoder accepts.
+    filter: ffmpeg::filter::Graph,
+
+    encoder: ffmpeg::encoder::Video,
+
+    /// Time base of decoded frames, which are encoded with the same time base.
+    time_base: ffmpeg::Rational,
+}
+
+impl VideoEncoder {
+    /// Adds a stream to the output for the input stream, encoded as the profile gives.
+    /// The container of the profile is ignored, because the output is already open.
+    /// If a range of seconds from the start of the stream is given, then only frames in
+    /// the range are encoded, starting from zero.
+    pub(super) fn new(
+        input_stream: &ffmpeg::Stream,
+        octx: &mut ffmpeg::format::context::Output,
+        output_index: usize,
+        profile: &Profile,
+        range: Option<(f64, f64)>,
+    ) -> Result<VideoEncoder> {
+        let time_base = input_stream.time_base();
+
+        let mut context = ffmpeg::codec::context::Context::from_parameters(input_stream.parameters())?;
+        context.set_threading(decoder_threading());
+        let decoder = context.decoder().video()?;
+
+        let orientation = metadata::rotation(input_stream)
+            .map(Orientation::from_degrees)
+            .unwrap_or_default();
+
+        let (width, height) = if orientation.is_sideways() {
+            (decoder.height(), decoder.width())
+        } else {
+            (decoder.width(), decoder.height())
+        };
+
+        let scaled = profile
+            .max_edge
+            .map(|max_edge| scaled_size(width, height, max_edge));
+        let (width, height) = scaled.unwrap_or((width, height));
+
+        // The trim filter works with timestamps, which don't necessarily start at zero.
+        let start_seconds = start_time(input_stream) as f64 * f64::from(time_base);
+        let range = range.map(|(start, end)| (start + start_seconds, end + start_seconds));
+
+        let filter = Self::filter(&decoder, time_base, orientation, scaled, range)?;
+
+        let codec = ffmpeg::encoder::find(profile.codec.id())
+            .ok_or_else(|| anyhow!("No {:?} encoder", profile.codec))?;
+
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(ffmpeg::format::Flags::GLOBAL_HEADER);
+
+        let mut output_stream = octx.add_stream(codec)?;
+
+        let mut encoder = ffmpeg::codec::context::Context::new_with_codec(codec)
+            .encoder()
+            .video()?;
+        encoder.set_width(width);
+        encoder.set_height(height);
+        encoder.set_aspect_ratio(decoder.aspect_ratio());
+        encoder.set_format(ffmpeg::format::Pixel::YUV420P);
+        encoder.set_frame_rate(decoder.frame_rate().or(Some(input_stream.avg_frame_rate())));
+        encoder.set_time_base(time_base);
+        if global_header {
+            encoder.set_flags(ffmpeg::codec::Flags::GLOBAL_HEADER);
+        }
+
+        // Encoders without these options, such as OpenH264, ignore them.
+        let mut options = ffmpeg::Dictionary::new();
+        options.set("crf", &profile.crf.to_string());
+        if profile.is_realtime {
+            options.set("preset", "ultrafast");
+            options.set("tune", "zerolatency");
+        }
+
+        let encoder = encoder.open_as_with(codec, options)?;
+        output_stream.set_parameters(&encoder);
+        output_stream.set_time_base(time_base);
+
+        Ok(VideoEncoder {
+            output_index,
+            decoder,
+            filter,
+            encoder,
+            time_base,
+        })
+    }
+
+    /// Filter graph from decoded frames to frames for the encoder.
+    fn filter(
+        decoder: &ffmpeg::decoder::Video,
+        time_base: ffmpeg::Rational,
+        orientation: Orientation,
+        scaled: Option<(u32, u32)>,
+        range: Option<(f64, f64)>,
+    ) -> Result<ffmpeg::filter::Graph> {
+        let pixel_format = decoder
+            .format()
+            .descriptor()
+            .map(|descriptor| descriptor.name())
+            .ok_or_else(|| anyhow!("Unknown pixel format"))?;
+
+        let aspect_ratio = match decoder.aspect_ratio() {
+            ffmpeg::Rational(0, _) => ffmpeg::Rational(1, 1),
+            aspect_ratio => aspect_ratio,
+        };
+
+        let args = format!(
+            "video_size={}x{}:pix_fmt={}:time_base={}:pixel_aspect={}",
+            decoder.width(),
+            decoder.height(),
+            pixel_format,
+            time_base,
+            aspect_ratio,
+        );
+
+        let mut spec = String::new();
+
+        if let Some((start, end)) = range {
+            spec.push_str(&format!(
+                "trim=start={}:end={},setpts=PTS-STARTPTS,",
+                start, end
+            ));
+        }
+
+        // Same filters as the ffmpeg command uses to automatically rotate videos.
+        spec.push_str(match orientation {
+            Orientation::West => "transpose=clock",
+            Orientation::South => "hflip,vflip",
+            Orientation::East => "transpose=cclock",
+            _ => "null",
+        });
+
+        if let Some((width, height)) = scaled {
+            spec.push_str(&format!(",scale={}:{}", width, height));
+        }
+
+        let mut filter = ffmpeg::filter::Graph::new();
+        filter.add(&ffmpeg::filter::find("buffer").unwrap(), "in", &args)?;
+        filter.add(&ffmpeg::filter::find("buffersink").unwrap(), "out", "")?;
+
+        filter
+            .get("out")
+            .unwrap()
+            .set_pixel_format(ffmpeg::format::Pixel::YUV420P);
+
+        filter.output("in", 0)?.input("out", 0)?.parse(&spec)?;
+        filter.validate()?;
+
+        Ok(filter)
+    }
+
+    pub(super) fn send_packet(
+        &mut self,
+        packet: &ffmpeg::Packet,
+        octx: &mut ffmpeg::format::context::Output,
+    ) -> Result<()> {
+        // Corrupt packets are skipped, as the ffmpeg command does.
+        if let Err(e) = self.decoder.send_packet(packet) {
+            event!(Level::WARN, "Skipping corrupt video packet: {:?}", e);
+            return Ok(());
+        }
+        self.receive_frames(octx)
+    }
+
+    /// Flush frames and packets still held by the decoder, filter, and encoder.
+    pub(super) fn finish(&mut self, octx: &mut ffmpeg::format::context::Output) -> Result<()> {
+        self.decoder.send_eof()?;
+        self.receive_frames(octx)?;
+
+        self.filter.get("in").unwrap().source().flush()?;
+        self.receive_filtered(octx)?;
+
+        self.encoder.send_eof()?;
+        self.receive_packets(octx)
+    }
+
+    fn receive_frames(&mut self, octx: &mut ffmpeg::format::context::Output) -> Result<()> {
+        let mut decoded = ffmpeg::frame::Video::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            let timestamp = decoded.timestamp();
+            decoded.set_pts(timestamp);
+            decoded.set_kind(ffmpeg::picture::Type::None);
+            self.filter.get("in").unwrap().source().add(&decoded)?;
+            self.receive_filtered(octx)?;
+        }
+        Ok(())
+    }
+
+    fn receive_filtered(&mut self, octx: &mut ffmpeg::format::context::Output) -> Result<()> {
+        let mut filtered = ffmpeg::frame::Video::empty();
+        while self
+            .filter
+            .get("out")
+            .unwrap()
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            self.encoder.send_frame(&filtered)?;
+            self.receive_packets(octx)?;
+        }
+        Ok(())
+    }
+
+    fn receive_packets(&mut self, octx: &mut ffmpeg::format::context::Output) -> Result<()> {
+        let output_time_base = octx
+            .stream(self.output_index)
+            .map(|x| x.time_base())
+            .ok_or_else(|| anyhow!("Missing output stream {}", self.output_index))?;
+
+        let mut encoded = ffmpeg::Packet::empty();
+        while self.encoder.receive_packet(&mut encoded).is_ok() {
+            encoded.set_stream(self.output_index);
+            encoded.rescale_ts(self.time_base, output_time_base);
+            encoded.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Timestamp of the first frame of a stream. Streams without a start time start at zero.
+fn start_time(stream: &ffmpeg::Stream) -> i64 {
+    match stream.start_time() {
+        ffmpeg::ffi::AV_NOPTS_VALUE => 0,
+        start_time => start_time,
+    }
+}
+
+/// Size scaled down to fit within `max_edge` pixels. H.264 and H.265 need even sizes
+/// for their subsampled colour.
+fn scaled_size(width: u32, height: u32, max_edge: u32) -> (u32, u32) {
+    let scale = (max_edge as f64 / width.max(height) as f64).min(1.0);
+    let even = |length: u32| {
+        let length = (length as f64 * scale).round() as u32;
+        (length - length % 2).max(2)
+    };
+    (even(width), even(height))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thumbnail::cache::tests::write;
+
+    #[test]
+    fn test_profile_from_names() {
+        assert_eq!(
+            Profile::from_names("h264", 23, 0, "mkv"),
+            Profile::default()
+        );
+
+        let profile = Profile::from_names("h265", 99, 1080, "mp4");
+        assert_eq!(profile.codec, Codec::H265);
+        assert_eq!(profile.crf, 51);
+        assert_eq!(profile.max_edge, Some(1080));
+        assert_eq!(profile.container, Container::Mp4);
+
+        let profile = Profile::from_names("av1", 23, 0, "webm");
+        assert_eq!(profile.codec, Codec::H264);
+        assert_eq!(profile.container, Container::Matroska);
+    }
+
+    #[test]
+    fn test_trim_least_recently_played_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcoder = Transcoder::new(dir.path());
+        let partition = dir.path().join("video_transcodes").join("0000");
+        let oldest = write(&partition, "1.mkv", 100, 9);
+        let older = write(&partition, "2.mkv", 100, 5);
+        let newest = write(&partition, "3.mkv", 100, 1);
+
+        assert_eq!(transcoder.size(), 300);
+        assert!(transcoder.trim(300, &[]).is_empty());
+
+        assert_eq!(transcoder.trim(150, &[]), vec![oldest.clone(), older.clone()]);
+        assert!(!oldest.exists());
+        assert!(!older.exists());
+        assert!(newest.exists());
+        assert_eq!(transcoder.size(), 100);
+    }
+
+    #[test]
+    fn test_trim_keeps_videos() {
+        let dir = tempfile::tempdir().unwrap();
+        let transcoder = Transcoder::new(dir.path());
+        let partition = dir.path().join("video_transcodes").join("0000");
+        let played = write(&partition, "1.mkv", 100, 1);
+        let transcoded = write(&partition, "2.mkv", 100, 9);
+        let partial = write(&partition, "3.tmp.mkv", 100, 9);
+
+        assert_eq!(transcoder.size(), 200);
+        assert_eq!(transcoder.trim(0, std::slice::from_ref(&transcoded)), vec![played.clone()]);
+        assert!(transcoded.exists());
+        assert!(partial.exists());
+    }
+
+    #[test]
+    fn test_scaled_size() {
+        assert_eq!(scaled_size(1920, 1080, 320), (320, 180));
+        assert_eq!(scaled_size(1080, 1920, 320), (180, 320));
+        assert_eq!(scaled_size(201, 101, 320), (200, 100));
+        assert_eq!(scaled_size(4000, 1, 320), (320, 2));
+    }
+}
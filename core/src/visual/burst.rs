@@ -0,0 +1,216 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Bursts of photos taken in quick succession, which are stacked as one item in albums
+//! so that they don't flood the album.
+//!
+//! A burst is identified by a burst ID from the photo metadata or the file name.
+//! Photos from cameras that don't identify bursts are treated as a burst if they were
+//! taken by the same camera in the same second and have consecutive file names.
+
+use crate::visual::model::Visual;
+use std::ops::Range;
+use std::path::Path;
+
+/// Largest gap between photos of a burst without a burst ID. EXIF timestamps are in
+/// whole seconds, so shots either side of the turn of a second are a second apart.
+const MAX_GAP_SECS: i64 = 1;
+
+/// Burst ID from a file name, such as IMG_20240101_120000_BURST001.jpg. Pixel phones
+/// number each shot before the burst marker and put the time of the burst after it,
+/// such as 00001IMG_00001_BURST20240101120000123.jpg.
+pub fn filename_burst_id(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?.to_uppercase();
+    let index = stem.find("_BURST")?;
+
+    let digits: String = stem[index + "_BURST".len()..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    if digits.is_empty() {
+        None
+    } else if digits.len() >= 14 {
+        Some(format!("BURST{}", digits))
+    } else {
+        Some(stem[..index].to_string())
+    }
+}
+
+/// Are two photos part of the same burst?
+pub fn is_same_burst(a: &Visual, b: &Visual) -> bool {
+    if !a.is_photo_only() || !b.is_photo_only() {
+        return false;
+    }
+
+    match (&a.burst_id, &b.burst_id) {
+        (Some(a_id), Some(b_id)) => a_id == b_id,
+        (None, None) => {
+            a.parent_path == b.parent_path
+                && a.camera_model.is_some()
+                && a.camera_model == b.camera_model
+                && (a.ordering_ts - b.ordering_ts).num_seconds().abs() <= MAX_GAP_SECS
+                && a.picture_path
+                    .as_deref()
+                    .zip(b.picture_path.as_deref())
+                    .is_some_and(|(a, b)| is_consecutive(a, b))
+        }
+        _ => false,
+    }
+}
+
+/// Ranges of items that are bursts of two or more photos. Items must be in the order
+/// they were taken in.
+pub fn stacks<T: AsRef<Visual>>(visuals: &[T]) -> Vec<Range<usize>> {
+    let mut stacks = Vec::new();
+    let mut start = 0;
+
+    for index in 1..=visuals.len() {
+        let is_burst_continued = index < visuals.len()
+            && is_same_burst(visuals[index - 1].as_ref(), visuals[index].as_ref());
+
+        if !is_burst_continued {
+            if index - start >= 2 {
+                stacks.push(start..index);
+            }
+            start = index;
+        }
+    }
+
+    stacks
+}
+
+/// Index of the item that represents a burst, which is the photo the user has chosen
+/// to keep, or otherwise the first photo.
+pub fn cover<T: AsRef<Visual>>(visuals: &[T], stack: &Range<usize>) -> usize {
+    stack
+        .clone()
+        .find(|&index| visuals[index].as_ref().is_burst_pick.is_some_and(|x| x))
+        .unwrap_or(stack.start)
+}
+
+/// Are file names numbered one after the other, such as DSC01234.JPG and DSC01235.JPG?
+fn is_consecutive(a: &Path, b: &Path) -> bool {
+    let (Some((a_prefix, a_number)), Some((b_prefix, b_number))) = (numbering(a), numbering(b))
+    else {
+        return false;
+    };
+
+    a_prefix == b_prefix && a_number.abs_diff(b_number) == 1
+}
+
+/// File stem split into a prefix and the number at the end.
+fn numbering(path: &Path) -> Option<(String, u64)> {
+    let stem = path.file_stem()?.to_str()?;
+    let prefix = stem.trim_end_matches(|c: char| c.is_ascii_digit());
+    let number = stem[prefix.len()..].parse().ok()?;
+    Some((prefix.to_string(), number))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::thumbnail;
+    use crate::visual::model::VisualId;
+    use crate::PictureId;
+    use chrono::*;
+    use std::path::PathBuf;
+
+    fn photo(id: i64, path: &str, seconds: i64, burst_id: Option<&str>) -> Visual {
+        let path = PathBuf::from(path);
+        Visual {
+            visual_id: VisualId::new(format!("{}_x", id)),
+            parent_path: path.parent().map(PathBuf::from).unwrap(),
+            thumbnail_path: None,
+            thumbnail_max_edge: thumbnail::SMALLEST_EDGE,
+            width: None,
+            height: None,
+            thumbnail_focus: None,
+            video_id: None,
+            video_path: None,
+            video_transcoded_path: None,
+            video_duration: None,
+            video_orientation: None,
+            picture_id: Some(PictureId::new(id)),
+            picture_path: Some(path),
+            picture_orientation: None,
+            picture_orientation_override: None,
+            picture_edit_orientation: None,
+            motion_photo_video_path: None,
+            motion_photo_still_ts: None,
+            voice_note_path: None,
+            preview_path: None,
+            ordering_ts: DateTime::from_timestamp(1_700_000_000 + seconds, 0).unwrap(),
+            is_selfie: None,
+            is_portrait: None,
+            is_hdr: None,
+            is_panorama: None,
+            is_screenshot: None,
+            camera_model: Some("OM Digital Solutions OM-1".into()),
+            burst_id: burst_id.map(String::from),
+            is_burst_pick: None,
+            is_live_photo: false,
+            is_transcode_required: None,
+            location: None,
+            altitude: None,
+            direction: None,
+        }
+    }
+
+    #[test]
+    fn test_filename_burst_id() {
+        assert_eq!(
+            Some("IMG_20240101_120000".to_string()),
+            filename_burst_id(Path::new("/a/IMG_20240101_120000_BURST001.jpg"))
+        );
+        assert_eq!(
+            Some("BURST20240101120000123".to_string()),
+            filename_burst_id(Path::new(
+                "/a/00001IMG_00001_BURST20240101120000123_COVER.jpg"
+            ))
+        );
+        assert_eq!(None, filename_burst_id(Path::new("/a/IMG_0001.jpg")));
+    }
+
+    #[test]
+    fn test_stacks_by_burst_id() {
+        let visuals = vec![
+            photo(1, "/a/IMG_0001.JPG", 0, None),
+            photo(2, "/a/IMG_0002.JPG", 60, Some("X")),
+            photo(3, "/a/IMG_0003.JPG", 61, Some("X")),
+            photo(4, "/a/IMG_0004.JPG", 62, Some("X")),
+            photo(5, "/a/IMG_0005.JPG", 62, Some("Y")),
+        ];
+
+        assert_eq!(vec![1..4], stacks(&visuals));
+    }
+
+    #[test]
+    fn test_stacks_by_same_camera_second_and_consecutive_names() {
+        let visuals = vec![
+            photo(1, "/a/P1010001.JPG", 0, None),
+            photo(2, "/a/P1010002.JPG", 0, None),
+            photo(3, "/a/P1010003.JPG", 1, None),
+            photo(4, "/a/P1010004.JPG", 5, None), // too late
+            photo(5, "/a/P1010006.JPG", 5, None), // not consecutive
+            photo(6, "/b/P1010007.JPG", 5, None), // other folder
+        ];
+
+        assert_eq!(vec![0..3], stacks(&visuals));
+    }
+
+    #[test]
+    fn test_cover_is_pick() {
+        let mut visuals = vec![
+            photo(1, "/a/IMG_0001.JPG", 0, Some("X")),
+            photo(2, "/a/IMG_0002.JPG", 0, Some("X")),
+        ];
+
+        assert_eq!(0, cover(&visuals, &(0..2)));
+
+        visuals[0].is_burst_pick = Some(false);
+        visuals[1].is_burst_pick = Some(true);
+        assert_eq!(1, cover(&visuals, &(0..2)));
+    }
+}
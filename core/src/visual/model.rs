@@ -5,7 +5,9 @@
 use std::fmt::Display;
 use std::path::PathBuf;
 
+use crate::photo::focus::Focus;
 use crate::photo::model::Orientation;
+use crate::thumbnail;
 use crate::{PictureId, VideoId, YearMonth};
 
 use chrono::*;
@@ -43,10 +45,22 @@ pub struct Visual {
     // Path to parent directory
     pub parent_path: PathBuf,
 
-    /// Path to thumbnail. If both a picture and a video are present, then this will
+    /// Path to smallest thumbnail. If both a picture and a video are present, then this will
     /// be the picture thumbnail path.
     pub thumbnail_path: Option<PathBuf>,
 
+    /// Largest edge length of thumbnails generated for this item.
+    pub thumbnail_max_edge: u32,
+
+    /// Width of the image thumbnails are made from, before the thumbnail orientation is applied.
+    pub width: Option<u32>,
+
+    /// Height of the image thumbnails are made from, before the thumbnail orientation is applied.
+    pub height: Option<u32>,
+
+    /// Point of interest that thumbnails are cropped about.
+    pub thumbnail_focus: Option<Focus>,
+
     pub video_id: Option<VideoId>,
 
     pub video_path: Option<PathBuf>,
@@ -66,14 +80,52 @@ pub struct Visual {
 
     pub picture_orientation: Option<Orientation>,
 
+    // User correction of a wrong EXIF orientation. Applied after picture_orientation.
+    pub picture_orientation_override: Option<Orientation>,
+
+    // Combined rotation and flip of any photo edits. Applied after picture_orientation
+    // and picture_orientation_override.
+    pub picture_edit_orientation: Option<Orientation>,
+
     pub motion_photo_video_path: Option<PathBuf>,
 
+    // Position in the motion photo video of the frame the still photo was taken from.
+    pub motion_photo_still_ts: Option<TimeDelta>,
+
+    // Audio recorded to accompany the picture, such as a camera's voice annotation.
+    pub voice_note_path: Option<PathBuf>,
+
+    // Short clip of a video or motion photo that plays when hovering over the item
+    // in an album. The preview might not have been generated yet.
+    pub preview_path: Option<PathBuf>,
+
     /// Best candidate for ordering visual items. With a final fallback of the current timestamp.
     pub ordering_ts: DateTime<Utc>,
 
     // Is this a selfie?
     pub is_selfie: Option<bool>,
 
+    // Is this a portrait with a depth map?
+    pub is_portrait: Option<bool>,
+
+    // Is this an HDR photo?
+    pub is_hdr: Option<bool>,
+
+    // Is this a panorama or photo sphere?
+    pub is_panorama: Option<bool>,
+
+    // Is this a screenshot?
+    pub is_screenshot: Option<bool>,
+
+    // Make and model of camera that took the picture.
+    pub camera_model: Option<String>,
+
+    // Identifies the burst the picture was taken in, from picture metadata or the file name.
+    pub burst_id: Option<String>,
+
+    // Has the user chosen to keep this picture of a burst (true), or another picture (false)?
+    pub is_burst_pick: Option<bool>,
+
     // Is this an iOS live photo?
     pub is_live_photo: bool,
 
@@ -82,6 +134,18 @@ pub struct Visual {
 
     // Where photo was taken
     pub location: Option<LatLng>,
+
+    // Altitude in metres where photo was taken. Negative if below sea level.
+    pub altitude: Option<f64>,
+
+    // Direction camera was pointing in degrees clockwise from north.
+    pub direction: Option<f64>,
+}
+
+impl AsRef<Visual> for Visual {
+    fn as_ref(&self) -> &Visual {
+        self
+    }
 }
 
 impl Visual {
@@ -93,6 +157,22 @@ impl Visual {
         self.is_selfie.is_some_and(|x| x)
     }
 
+    pub fn is_portrait(&self) -> bool {
+        self.is_portrait.is_some_and(|x| x)
+    }
+
+    pub fn is_hdr(&self) -> bool {
+        self.is_hdr.is_some_and(|x| x)
+    }
+
+    pub fn is_panorama(&self) -> bool {
+        self.is_panorama.is_some_and(|x| x)
+    }
+
+    pub fn is_screenshot(&self) -> bool {
+        self.is_screenshot.is_some_and(|x| x)
+    }
+
     pub fn is_motion_photo(&self) -> bool {
         self.is_live_photo
     }
@@ -101,15 +181,56 @@ impl Visual {
         self.picture_id.is_some() && self.video_id.is_none() && !self.is_live_photo
     }
 
+    pub fn has_voice_note(&self) -> bool {
+        self.voice_note_path.is_some()
+    }
+
     pub fn is_video_only(&self) -> bool {
         self.picture_id.is_none() && self.video_id.is_some()
     }
 
+    /// Path to thumbnail best suited to be displayed with an edge of `pixels` device pixels.
+    pub fn thumbnail_path_for(&self, pixels: u32) -> Option<PathBuf> {
+        let path = self.thumbnail_path.as_ref()?;
+        let edge = thumbnail::edge_for(pixels, self.thumbnail_max_edge);
+        Some(thumbnail::sized_path(path, edge))
+    }
+
+    /// Width divided by height of the thumbnail as displayed, if the size is known.
+    pub fn aspect_ratio(&self) -> Option<f64> {
+        let (width, height) = (self.width?, self.height?);
+        if width == 0 || height == 0 {
+            return None;
+        }
+
+        let ratio = width as f64 / height as f64;
+        if self.thumbnail_orientation().is_sideways() {
+            Some(1.0 / ratio)
+        } else {
+            Some(ratio)
+        }
+    }
+
     pub fn thumbnail_orientation(&self) -> PictureOrientation {
         // Video thumbnails are generated by ffmpeg which will have applied
         // the rotation transformation if the metadata was available in the video file.
-        self.picture_orientation
-            .unwrap_or(PictureOrientation::North)
+        let orientation = self.corrected_picture_orientation();
+
+        match self.picture_edit_orientation {
+            Some(edit_orientation) => orientation.then(edit_orientation),
+            None => orientation,
+        }
+    }
+
+    /// Orientation of the picture after any user correction of the EXIF orientation.
+    pub fn corrected_picture_orientation(&self) -> PictureOrientation {
+        let orientation = self.picture_orientation
+            .unwrap_or(PictureOrientation::North);
+
+        match self.picture_orientation_override {
+            Some(correction) => orientation.then(correction),
+            None => orientation,
+        }
     }
 
     pub fn year(&self) -> u32 {
@@ -2,11 +2,14 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use crate::photo::PictureId;
+use crate::photo::{Focus, PictureId};
+use crate::video::preview;
 use crate::video::VideoId;
+use crate::visual::burst;
 use crate::visual::model::{PictureOrientation, Visual, VisualId};
 
 use crate::path_encoding;
+use crate::thumbnail;
 use anyhow::*;
 use chrono::*;
 use h3o::LatLng;
@@ -57,14 +60,33 @@ impl Repository {
                     picture_id,
                     picture_path_b64,
                     picture_thumbnail,
+                    picture_thumbnail_max_edge,
                     picture_orientation,
+                    picture_orientation_override,
+                    picture_edit_orientation,
                     is_selfie,
+                    is_portrait,
+                    is_hdr,
+                    is_panorama,
+                    is_screenshot,
+                    camera_model,
+                    burst_id,
+                    is_burst_pick,
 
                     video_id,
                     video_path_b64,
                     video_thumbnail,
+                    video_thumbnail_max_edge,
+
+                    width,
+                    height,
+                    focus_x,
+                    focus_y,
 
                     motion_photo_video_path,
+                    motion_photo_still_micros,
+
+                    voice_note_path_b64,
 
                     ordering_ts,
                     is_live_photo,
@@ -75,9 +97,12 @@ impl Repository {
                     video_rotation,
 
                     latitude,
-                    longitude
+                    longitude,
+                    altitude,
+                    direction
                 FROM visual
-                ORDER BY ordering_ts ASC",
+                -- Photos taken in the same second, such as bursts, are ordered by file name.
+                ORDER BY ordering_ts ASC, picture_path_lossy ASC",
         )?;
 
         let result = stmt.query_map([], |row| self.to_visual(row))?;
@@ -115,7 +140,30 @@ impl Repository {
             .map(|x: u32| PictureOrientation::from(x))
             .ok();
 
+        let picture_orientation_override: Option<PictureOrientation> = row
+            .get("picture_orientation_override")
+            .map(|x: u32| PictureOrientation::from(x))
+            .ok();
+
+        let picture_edit_orientation: Option<PictureOrientation> = row
+            .get("picture_edit_orientation")
+            .map(|x: u32| PictureOrientation::from(x))
+            .ok();
+
         let is_selfie: Option<bool> = row.get("is_selfie").ok();
+        let is_portrait: Option<bool> = row.get("is_portrait").ok();
+        let is_hdr: Option<bool> = row.get("is_hdr").ok();
+        let is_panorama: Option<bool> = row.get("is_panorama").ok();
+        let is_screenshot: Option<bool> = row.get("is_screenshot").ok();
+
+        let camera_model: Option<String> = row.get("camera_model").ok();
+
+        let burst_id: Option<String> = row
+            .get("burst_id")
+            .ok()
+            .or_else(|| picture_path.as_deref().and_then(burst::filename_burst_id));
+
+        let is_burst_pick: Option<bool> = row.get("is_burst_pick").ok();
 
         let video_id: Option<VideoId> = row.get("video_id").map(VideoId::new).ok();
 
@@ -137,14 +185,46 @@ impl Repository {
             .map(|x: i32| PictureOrientation::from_degrees(x))
             .ok();
 
+        // Items thumbnailed before multiple sizes were generated only have the smallest size.
+        let thumbnail_max_edge: u32 = if picture_thumbnail.is_some() {
+            row.get("picture_thumbnail_max_edge").ok()
+        } else {
+            row.get("video_thumbnail_max_edge").ok()
+        }
+        .unwrap_or(thumbnail::SMALLEST_EDGE);
+
         let thumbnail_path: Option<PathBuf> = picture_thumbnail.or(video_thumbnail);
 
+        let width: Option<u32> = row.get("width").ok();
+        let height: Option<u32> = row.get("height").ok();
+
+        let focus_x: Option<f64> = row.get("focus_x").ok();
+        let focus_y: Option<f64> = row.get("focus_y").ok();
+        let thumbnail_focus = focus_x.zip(focus_y).map(|(x, y)| Focus::new(x, y));
+
         let motion_photo_video_path: Option<PathBuf> = row
             .get("motion_photo_video_path")
             .map(|x: String| PathBuf::from(x))
             .map(|x| self.cache_dir_base_path.join(x))
             .ok();
 
+        let motion_photo_still_ts: Option<TimeDelta> = row
+            .get("motion_photo_still_micros")
+            .ok()
+            .map(TimeDelta::microseconds);
+
+        let voice_note_path: Option<PathBuf> = row
+            .get("voice_note_path_b64")
+            .ok()
+            .and_then(|x: String| path_encoding::from_base64(&x).ok())
+            .map(|x| self.library_base_path.join(x));
+
+        let preview_path = if video_path.is_some() || motion_photo_video_path.is_some() {
+            preview::path(&self.cache_dir_base_path, video_id, picture_id)
+        } else {
+            None
+        };
+
         let ordering_ts: DateTime<Utc> = row.get("ordering_ts").expect("Must have ordering_ts");
 
         let is_live_photo: Option<bool> = row.get("is_live_photo").ok();
@@ -173,24 +253,45 @@ impl Repository {
             None
         };
 
+        let altitude: Option<f64> = row.get("altitude").ok();
+        let direction: Option<f64> = row.get("direction").ok();
+
         let v = Visual {
             visual_id,
             parent_path: link_path.parent().map(PathBuf::from).expect("Parent path"),
             thumbnail_path,
+            thumbnail_max_edge,
+            width,
+            height,
+            thumbnail_focus,
             picture_id,
             picture_path,
             picture_orientation,
+            picture_orientation_override,
+            picture_edit_orientation,
             video_id,
             video_path,
             ordering_ts,
             is_selfie,
+            is_portrait,
+            is_hdr,
+            is_panorama,
+            is_screenshot,
+            camera_model,
+            burst_id,
+            is_burst_pick,
             is_live_photo,
             video_transcoded_path,
             video_orientation,
             is_transcode_required,
             video_duration,
             motion_photo_video_path,
+            motion_photo_still_ts,
+            voice_note_path,
+            preview_path,
             location,
+            altitude,
+            direction,
         };
         Ok(v)
     }
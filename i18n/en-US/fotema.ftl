@@ -28,6 +28,18 @@ videos-album = Videos
 # Title for album of selfies.
 selfies-album = Selfies
 
+# Title for album of portrait photos, which have a depth map for blurring the background.
+portraits-album = Portraits
+
+# Title for album of HDR (high dynamic range) photos.
+hdr-album = HDR
+
+# Title for album of panoramas and photo spheres.
+panoramas-album = Panoramas
+
+# Title for album of screenshots.
+screenshots-album = Screenshots
+
 # Title for album of iOS live photos and Android motion photos.
 animated-album = Animated
 
@@ -78,6 +90,15 @@ about-translator-credits =
 # Tooltip for (i) button to show photo/video information sidebar
 viewer-info-tooltip = Show properties
 
+# Tooltip for button showing export menu
+viewer-export-tooltip = Export
+
+# Menu item to export photo or video being viewed
+viewer-export-item = Export This Item…
+
+# Menu item to export all photos and videos in the album being viewed
+viewer-export-album = Export All Items in Album…
+
 # Go to next button when viewing photo or video.
 # Attributes:
 #  .tooltip - Tooltip on mouse hover.
@@ -114,12 +135,58 @@ viewer-skip-backwards-10-seconds =
 viewer-mute =
   .tooltip = Mute/Unmute
 
+# Choose the frame that thumbnails of a video are made from.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-poster =
+  .tooltip = Thumbnail Frame
+
+# Menu items for choosing the thumbnail frame of a video.
+viewer-poster-current-frame = Use Current Frame
+viewer-poster-automatic = Choose Automatically
+
+# Toggle button to show controls for trimming a video.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-trim =
+  .tooltip = Trim
+
+# Buttons to mark where a trimmed copy of a video starts and ends.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-trim-start = Start
+  .tooltip = Start Trim at Current Position
+viewer-trim-end = End
+  .tooltip = End Trim at Current Position
+
+# Button to write the trimmed part of a video to a new file.
+viewer-trim-save = Save Trimmed Copy
+
+# Write the frame being shown to a JPEG next to the video.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-save-frame =
+  .tooltip = Save Frame as JPEG
+
+# Headings of dialogs shown when saving a trimmed copy or a frame of a video fails.
+viewer-trim-save-failed = Couldn't Save Trimmed Copy
+viewer-save-frame-failed = Couldn't Save Frame
+
+# Play or pause the voice note recorded with a photo.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-voice-note = Voice Note
+  .tooltip = Play/Pause Voice Note
+
 # Convert all incompatible videos description.
 viewer-convert-all-description = This video must be converted before it can be played. This only needs to happen once, but it takes a while to convert a video.
 
 # Button to convert all incompatible videos.
 viewer-convert-all-button = Convert all incompatible videos
 
+# Button to convert just the video being viewed, showing progress.
+viewer-convert-now-button = Convert this video
+
 # Viewer failed to load an image or video.
 viewer-error-failed-to-load = Failed to load
 
@@ -134,6 +201,129 @@ viewer-error-missing-file = Cannot display file because it is missing:
 # be raised.
 viewer-error-missing-path = File path not present in database
 
+# Tooltip for button showing menu to fix the orientation of a photo
+viewer-orientation-tooltip = Orientation
+
+# Menu items to correct a photo that is displayed the wrong way around.
+viewer-orientation-rotate-left = Rotate Left
+viewer-orientation-rotate-right = Rotate Right
+viewer-orientation-flip = Flip Horizontally
+viewer-orientation-reset = Reset Orientation
+
+# Menu item to write the corrected orientation into the photo file.
+viewer-orientation-save = Save Orientation to File
+
+# Heading of dialog shown when writing the corrected orientation into the photo file fails.
+viewer-orientation-save-failed = Couldn't Save Orientation
+
+# Tooltip for button to enter or leave fullscreen.
+viewer-fullscreen-tooltip = Fullscreen
+
+# Button to start a slideshow of the album being viewed.
+viewer-slideshow-tooltip = Slideshow
+
+# Resume a paused slideshow.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-slideshow-resume =
+  .tooltip = Resume Slideshow
+
+# Stop a slideshow.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-slideshow-stop =
+  .tooltip = Stop Slideshow
+
+# Reason given to the desktop for keeping the screen on during a slideshow.
+viewer-slideshow-inhibit-reason = Slideshow is running
+
+# Toggle button to show the photos of a burst, which are photos taken in quick succession.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-burst =
+  .tooltip = Burst Photos
+
+# Button to keep the photo being viewed as the photo shown for its burst.
+# Attributes:
+#  .tooltip - Tooltip on the thumbnail of the photo already kept.
+viewer-burst-keep = Keep This Photo
+  .tooltip = Kept Photo
+
+# Button to keep the photo being viewed and move the other photos of its burst to the trash.
+viewer-burst-trash-others = Keep and Trash Others
+
+# Dialog asking to confirm moving the other photos of a burst to the trash.
+# Attributes:
+#  .body - Explanation. $count is the number of photos to move to the trash.
+#  .cancel - Button to close the dialog without changes.
+#  .trash - Button to move the photos to the trash.
+viewer-burst-trash-dialog = Trash Other Photos?
+  .body = { $count ->
+    [one] One other photo of this burst will be moved to the trash.
+   *[other] { $count } other photos of this burst will be moved to the trash.
+  }
+  .cancel = Cancel
+  .trash = Move to Trash
+
+# Heading of dialog listing the file names of burst photos that couldn't be moved to the trash.
+viewer-burst-trash-failed = Couldn't Move Photos to Trash
+
+# Toggle button to show photo editing controls.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-edit =
+  .tooltip = Edit
+
+# Photo editing buttons.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-edit-rotate-left =
+  .tooltip = Rotate Left
+viewer-edit-rotate-right =
+  .tooltip = Rotate Right
+viewer-edit-flip-horizontal =
+  .tooltip = Flip Horizontally
+viewer-edit-flip-vertical =
+  .tooltip = Flip Vertically
+viewer-edit-crop =
+  .tooltip = Crop
+viewer-edit-adjust =
+  .tooltip = Adjust Colors
+viewer-edit-undo =
+  .tooltip = Undo
+viewer-edit-reset =
+  .tooltip = Revert to Original
+viewer-edit-save-copy =
+  .tooltip = Save a Copy
+
+# Crop aspect ratio presets.
+viewer-edit-crop-original = Original
+viewer-edit-crop-square = Square
+viewer-edit-crop-4-3 = 4:3
+viewer-edit-crop-3-2 = 3:2
+viewer-edit-crop-16-9 = 16:9
+
+# Button to choose the point that thumbnails are cropped about.
+# Attributes:
+#  .tooltip - Tooltip on mouse hover.
+viewer-edit-focus =
+  .tooltip = Thumbnail Focus
+
+# Menu items for choosing the thumbnail focus.
+viewer-edit-focus-choose = Choose Focus Point
+viewer-edit-focus-automatic = Automatic Focus
+
+# Labels for color adjustment sliders.
+viewer-edit-exposure = Exposure
+viewer-edit-contrast = Contrast
+viewer-edit-saturation = Saturation
+
+# Heading of dialog shown when saving an edited copy of a photo fails.
+viewer-edit-save-copy-failed = Couldn't Save Copy
+
+# Button to close a dialog explaining why an action in the viewer failed.
+viewer-error-dialog-close = Close
+
 ## Photo/Video Information Sidebar
 
 # Name of containing folder of photo or video being viewed.
@@ -178,6 +368,13 @@ infobar-audio-codec = Audio Codec
 # Width and height of photo or video.
 infobar-dimensions = Dimensions
 
+# Altitude where photo was taken, in metres above sea level.
+infobar-altitude = Altitude
+  .value = { $metres } m
+
+# Compass direction camera was pointing when photo was taken.
+infobar-direction = Direction
+
 ## Preferences
 
 # Title of preferences dialog
@@ -185,13 +382,156 @@ prefs-title = Preferences
 
 # Title of section of preferences for views
 prefs-views-section = Views
-  .description = Show or hide sidebar views
+  .description = Show or hide sidebar views and choose how items are laid out
 
 # Selfies page enabled or disabled.
 # Attributes:
 #   .subtitle - Description of toggle button action action.
 prefs-views-selfies = Selfies
-  .subtitle = Shows a separate view for selfies taken on iOS devices. Restart {-app-name} to apply.
+  .subtitle = Shows a separate view for selfies taken on iPhones and Pixel phones. Restart {-app-name} to apply.
+
+# Smart album pages enabled or disabled.
+# Attributes:
+#   .subtitle - Description of toggle button action action.
+prefs-views-smart-albums = Smart Albums
+  .subtitle = Shows separate views for portraits, HDR photos, panoramas, and screenshots. Restart {-app-name} to apply.
+
+# Justified layout of albums enabled or disabled.
+# Attributes:
+#   .subtitle - Description of toggle button action action.
+prefs-views-justified-layout = Justified Layout
+  .subtitle = Shows photos and videos in rows at their own aspect ratio, instead of as squares.
+
+# Hover previews of videos and motion photos enabled or disabled.
+# Attributes:
+#   .subtitle - Description of toggle button action action.
+prefs-views-hover-previews = Hover Previews
+  .subtitle = Plays a short clip of videos and motion photos when the pointer is over them. Disable on slow computers.
+
+# Aspect-preserving thumbnails enabled or disabled.
+# Attributes:
+#   .subtitle - Description of toggle button action action.
+prefs-views-thumbnail-aspect = Uncropped Thumbnails
+  .subtitle = Keeps the whole photo or video in thumbnails, which looks best with the justified layout. Changing this regenerates all thumbnails.
+
+# Sharing generated thumbnails with other apps enabled or disabled.
+# Attributes:
+#   .subtitle - Description of toggle button action action.
+prefs-views-thumbnail-share = Share Thumbnails
+  .subtitle = Saves thumbnails to the cache used by file managers and other apps.
+
+# File format of generated thumbnails.
+# Attributes:
+#   .subtitle - Description of the combo row.
+#   .png - Lossless and largest format.
+#   .webp - Lossy format with a quality setting, which keeps transparency.
+#   .jpeg - Lossy format with a quality setting.
+prefs-views-thumbnail-format = Thumbnail Format
+  .subtitle = Changing this regenerates all thumbnails.
+  .png = PNG
+  .webp = WebP
+  .jpeg = JPEG
+
+# Quality of JPEG and WebP thumbnails, from 1 to 100.
+# Attributes:
+#   .subtitle - Description of the spin button.
+prefs-views-thumbnail-quality = Thumbnail Quality
+  .subtitle = Quality of JPEG and WebP thumbnails. Applies to thumbnails generated from now on.
+
+# Size budget of the thumbnail cache, in megabytes.
+# Attributes:
+#   .subtitle - Description of the spin button.
+prefs-views-thumbnail-budget = Thumbnail Cache Budget
+  .subtitle = Megabytes to keep thumbnails within, by removing larger thumbnails of items viewed longest ago. Zero is unlimited.
+
+# Total size of generated thumbnails on disk.
+# Attributes:
+#   .calculating - Shown while the size is being calculated.
+prefs-views-thumbnail-cache-size = Thumbnail Cache Size
+  .calculating = Calculating…
+
+# Title of section of preferences for converting videos that can't be played
+prefs-videos-section = Video Conversion
+  .description = How videos that can't be played are converted. Applies to videos converted from now on.
+
+# Convert incompatible videos without being asked.
+# Attributes:
+#   .subtitle - Description of setting.
+prefs-videos-automatic = Convert Automatically
+  .subtitle = Convert videos that can't be played in the background, after new photos and videos are processed.
+
+# Play videos that can't be played while they are quickly converted.
+# Attributes:
+#   .subtitle - Description of setting.
+prefs-videos-streaming = Play While Converting
+  .subtitle = Start playing videos that can't be played straight away, without seeking. The quick conversion isn't kept.
+
+# Video codec of converted videos.
+# Attributes:
+#   .subtitle - Description of the combo row.
+#   .h264 - Codec that plays almost everywhere.
+#   .h265 - Codec with smaller files, but slower to convert.
+prefs-videos-codec = Codec
+  .subtitle = H.265 files are about half the size, but take longer to convert.
+  .h264 = H.264
+  .h265 = H.265 (HEVC)
+
+# Constant rate factor of converted videos, from 0 to 51.
+# Attributes:
+#   .subtitle - Description of the spin button.
+prefs-videos-crf = Quality
+  .subtitle = Constant rate factor. Lower is better quality and larger files.
+
+# Largest resolution of converted videos.
+# Attributes:
+#   .subtitle - Description of the combo row.
+#   .original - Keep the resolution of the original video.
+#   .uhd, .fhd, .hd - Scale down to fit within 3840, 1920, or 1280 pixels.
+prefs-videos-resolution = Maximum Resolution
+  .subtitle = Larger videos are scaled down.
+  .original = Original
+  .uhd = 4K
+  .fhd = 1080p
+  .hd = 720p
+
+# Container format of converted videos.
+# Attributes:
+#   .subtitle - Description of the combo row.
+#   .mkv - Format that can hold any audio codec.
+#   .mp4 - Format that more apps can open.
+prefs-videos-container = Container
+  .subtitle = MP4 files open in more apps, but not every audio format fits in them.
+  .mkv = Matroska (MKV)
+  .mp4 = MP4
+
+# Size budget of converted videos, in megabytes.
+# Attributes:
+#   .subtitle - Description of the spin button.
+prefs-videos-budget = Converted Video Cache Budget
+  .subtitle = Megabytes to keep converted videos within, by removing those played longest ago. Zero is unlimited.
+
+# Title of section of preferences for importing photos and videos
+prefs-import-section = Import
+  .description = Where to put photos and videos imported from a camera, phone, or SD card.
+
+# Pattern for path of imported files relative to the Pictures folder.
+prefs-import-pattern = Folder pattern
+  .invalid = Pattern must be a path inside the Pictures folder, such as {"{"}year{"}"}/{"{"}month{"}"}/{"{"}filename{"}"}
+
+# Title of section of preferences for slideshows
+prefs-slideshow-section = Slideshow
+
+# How long each photo is shown in a slideshow.
+# Attributes:
+#   .subtitle - Description of setting.
+prefs-slideshow-interval = Interval
+  .subtitle = Seconds to show each photo. Videos and live photos play to the end.
+
+# Show slideshow items in a random order.
+# Attributes:
+#   .subtitle - Description of setting.
+prefs-slideshow-shuffle = Shuffle
+  .subtitle = Show items in a random order instead of by date.
 
 ## Progress bar for background tasks
 
@@ -207,12 +547,21 @@ progress-thumbnails-photos = Generating photo thumbnails.
 # Generating thumbnails from videos
 progress-thumbnails-videos = Generating video thumbnails.
 
+# Generating short clips of videos that play when hovering over them
+progress-previews = Generating video previews.
+
 # Transcoding videos to a compatible format
 progress-convert-videos = Converting videos.
 
 # Extracting motion photo videos
 progress-motion-photo = Processing motion photos.
 
+# Exporting photos and videos to a folder
+progress-export = Exporting.
+
+# Importing photos and videos into the library
+progress-import = Importing.
+
 # Not doing any background work
 progress-idle = Idle.
 
@@ -238,6 +587,12 @@ banner-thumbnails-photos = Generating photo thumbnails. This will take a while.
 # Generating thumbnails for all videos.
 banner-thumbnails-videos = Generating video thumbnails. This will take a while.
 
+# Removing larger thumbnails to keep the thumbnail cache within its size budget.
+banner-thumbnail-budget = Trimming thumbnail cache.
+
+# Generating short clips of videos and motion photos that play when hovering over them.
+banner-previews = Generating video previews.
+
 # Updating the database to remove details of absent photos.
 banner-clean-photos = Photo database maintenance.
 
@@ -247,6 +602,12 @@ banner-clean-videos = Video database maintenance.
 # Extracting video component from Android motion photos
 banner-extract-motion-photos = Processing motion photos.
 
+# Exporting photos and videos to a folder chosen by the user.
+banner-export = Exporting photos and videos.
+
+# Copying photos and videos from a camera, phone, or SD card into the library.
+banner-import = Importing photos and videos.
+
 ## Primary menu
 
 # The "hamburger" menu on the main app navigation sidebar.
@@ -256,3 +617,65 @@ primary-menu-preferences = Preferences
 
 # Menu item to show "about" dialog
 primary-menu-about = About {-app-name}
+
+# Menu item to import photos and videos from a camera, phone, or SD card
+primary-menu-import = Import…
+
+# Menu item to show dialog for geotagging photos from a GPS track
+primary-menu-geotag = Geotag from GPS Track…
+
+## Geotag dialog
+
+# Title of dialog for geotagging photos from a GPX file recorded by a GPS logger.
+geotag-title = Geotag from GPS Track
+
+# Name of file filter for GPX files in file chooser.
+geotag-gpx-filter = GPX tracks
+
+# Button to write locations to the photos in the preview list.
+geotag-apply = Apply
+
+# Row showing the loaded GPX file.
+geotag-track = GPS track
+
+# Name of loaded GPX file and number of points in track.
+geotag-track-points = { $file_name } ({ $count } points)
+
+# Camera clock offset in minutes.
+geotag-camera-offset = Camera clock offset
+  .subtitle = Minutes the camera clock was ahead of the GPS clock, including any time zone difference.
+
+# List of photos that will be geotagged.
+geotag-preview = Photos to geotag
+  .description = { $count } photos without a location were taken while the track was recorded.
+
+## Export dialog
+
+# Title of dialog for exporting photos and videos.
+export-title = Export
+  .description = { $count } items will be exported.
+
+# Button to choose a folder and start exporting.
+export-button = Export…
+
+# How to export photos and videos.
+export-mode = Format
+  .originals = Originals
+  .originals-description = Copy original files, including the video of live photos.
+  .jpeg = Resized JPEG
+  .jpeg-description = Convert photos to JPEG files without metadata.
+  .strip-metadata = Remove metadata
+  .strip-metadata-description = Copy files without location and camera details.
+
+# Maximum width or height of exported JPEG files.
+export-max-edge = Maximum size
+  .subtitle = Longest edge in pixels.
+
+# JPEG quality
+export-quality = Quality
+  .subtitle = Higher quality produces larger files.
+
+## Import
+
+# Title of folder chooser for choosing where to import photos and videos from.
+import-title = Import from Folder
@@ -11,7 +11,7 @@ use relm4::{
     gtk::{
         gio, glib,
         prelude::{
-            ApplicationExt, ButtonExt, GtkWindowExt, OrientableExt,
+            ApplicationExt, ButtonExt, FileExt, GtkWindowExt, OrientableExt,
             SettingsExt, WidgetExt,
         },
     },
@@ -27,6 +27,8 @@ use crate::adaptive;
 use crate::fl;
 
 use fotema_core::database;
+use fotema_core::export::ExportMode;
+use fotema_core::photo;
 use fotema_core::video;
 use fotema_core::VisualId;
 
@@ -45,6 +47,8 @@ mod components;
 
 use self::components::{
     about::AboutDialog,
+    export_dialog::{ExportDialog, ExportDialogInput, ExportDialogOutput},
+    geotag_dialog::{GeotagDialog, GeotagDialogInput, GeotagDialogOutput},
     albums:: {
         album::{Album, AlbumInput, AlbumOutput},
         album_filter::AlbumFilter,
@@ -60,7 +64,9 @@ mod background;
 
 use self::background::{
     bootstrap::{Bootstrap, BootstrapInput, BootstrapOutput, TaskName, MediaType},
-    video_transcode::{VideoTranscode, VideoTranscodeInput},
+    export::{Export, ExportInput, ExportOutput},
+    import::{Import, ImportInput, ImportOutput},
+    video_transcode::{VideoTranscode, VideoTranscodeInput, VideoTranscodeOutput},
 };
 
 use self::components::progress_monitor::ProgressMonitor;
@@ -85,6 +91,10 @@ pub enum ViewName {
     Folder,
     Places,
     Selfies,
+    Portraits,
+    Hdr,
+    Panoramas,
+    Screenshots,
 }
 
 /// Currently visible view
@@ -99,9 +109,13 @@ pub(super) struct App {
 
     about_dialog: Controller<AboutDialog>,
     preferences_dialog: Controller<PreferencesDialog>,
+    geotag_dialog: Controller<GeotagDialog>,
+    export_dialog: Controller<ExportDialog>,
 
     bootstrap: WorkerController<Bootstrap>,
     video_transcode: WorkerController<VideoTranscode>,
+    export: WorkerController<Export>,
+    import: WorkerController<Import>,
 
     library: Controller<Library>,
 
@@ -109,6 +123,14 @@ pub(super) struct App {
 
     show_selfies: bool,
     selfies_page: Controller<Album>,
+
+    /// Albums of photos by how they were taken.
+    show_smart_albums: bool,
+    portraits_page: Controller<Album>,
+    hdr_page: Controller<Album>,
+    panoramas_page: Controller<Album>,
+    screenshots_page: Controller<Album>,
+
     videos_page: Controller<Album>,
     motion_page: Controller<Album>,
 
@@ -138,9 +160,14 @@ pub(super) struct App {
 
     bootstrap_progress: Controller<ProgressPanel>,
     transcode_progress: Controller<ProgressPanel>,
+    export_progress: Controller<ProgressPanel>,
+    import_progress: Controller<ProgressPanel>,
 
     // Message banner
     banner: adw::Banner,
+
+    // Is the banner showing export progress, rather than progress of a background task?
+    export_owns_banner: bool,
 }
 
 #[derive(Debug)]
@@ -171,11 +198,53 @@ pub(super) enum AppMsg {
     // Preferences
     PreferencesUpdated,
 
+    // Preferred shape or format of thumbnails has changed
+    ThumbnailsChanged,
+
     // All background bootstrap tasks have completed
     BootstrapCompleted,
 
     TranscodeAll,
 
+    // Transcode one video, showing progress through the video.
+    Transcode(Arc<fotema_core::Visual>),
+
+    // A video has been transcoded to a path.
+    Transcoded(VisualId, PathBuf),
+
+    // Transcoding has completed. Count of videos transcoded.
+    TranscodeCompleted(usize),
+
+    // Photos have been geotagged from a GPS track
+    Geotagged(usize),
+
+    // Edits to a photo have changed its thumbnail
+    PhotoEdited,
+
+    // An edited copy of a photo, or a trimmed copy or frame of a video, has been written into the library
+    EditedCopySaved(PathBuf),
+
+    // Show export options for items
+    ShowExport(Vec<Arc<fotema_core::Visual>>),
+
+    // Export items into a folder
+    Export(Vec<Arc<fotema_core::Visual>>, PathBuf, ExportMode),
+
+    // Import photos and videos from a folder, such as a camera's DCIM folder
+    Import(PathBuf),
+
+    // Import has started.
+    ImportStarted,
+
+    // Import has completed with paths of files added to library.
+    ImportCompleted(Vec<PathBuf>),
+
+    // Export has started.
+    ExportStarted,
+
+    // Export has completed. usize is count of exported files.
+    ExportCompleted(usize),
+
     // Adapt to layout change
     Adapt(adaptive::Layout),
 }
@@ -183,6 +252,8 @@ pub(super) enum AppMsg {
 relm4::new_action_group!(pub(super) WindowActionGroup, "win");
 relm4::new_stateless_action!(PreferencesAction, WindowActionGroup, "preferences");
 relm4::new_stateless_action!(AboutAction, WindowActionGroup, "about");
+relm4::new_stateless_action!(GeotagAction, WindowActionGroup, "geotag");
+relm4::new_stateless_action!(ImportAction, WindowActionGroup, "import");
 
 #[relm4::component(pub)]
 impl SimpleComponent for App {
@@ -193,6 +264,10 @@ impl SimpleComponent for App {
 
     menu! {
         primary_menu: {
+            section! {
+                &fl!("primary-menu-import") => ImportAction,
+                &fl!("primary-menu-geotag") => GeotagAction,
+            },
             section! {
                 &fl!("primary-menu-preferences") => PreferencesAction,
                 &fl!("primary-menu-about") => AboutAction,
@@ -270,6 +345,8 @@ impl SimpleComponent for App {
 
                                     model.bootstrap_progress.widget(),
                                     model.transcode_progress.widget(),
+                                    model.export_progress.widget(),
+                                    model.import_progress.widget(),
                                 }
                             }
                         },
@@ -362,6 +439,62 @@ impl SimpleComponent for App {
                                             set_icon_name: "sentiment-very-satisfied-symbolic",
                                         },
 
+                                        add_child = &gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+                                            container_add: model.portraits_page.widget(),
+
+                                            // A stack page is visible when its child is.
+                                            #[watch]
+                                            set_visible: model.show_smart_albums,
+                                        } -> {
+                                            set_title: &fl!("portraits-album"),
+                                            set_name: ViewName::Portraits.into(),
+                                            // NOTE gtk::StackSidebar doesn't show icon :-/
+                                            set_icon_name: "avatar-default-symbolic",
+                                        },
+
+                                        add_child = &gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+                                            container_add: model.hdr_page.widget(),
+
+                                            // A stack page is visible when its child is.
+                                            #[watch]
+                                            set_visible: model.show_smart_albums,
+                                        } -> {
+                                            set_title: &fl!("hdr-album"),
+                                            set_name: ViewName::Hdr.into(),
+                                            // NOTE gtk::StackSidebar doesn't show icon :-/
+                                            set_icon_name: "display-brightness-symbolic",
+                                        },
+
+                                        add_child = &gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+                                            container_add: model.panoramas_page.widget(),
+
+                                            // A stack page is visible when its child is.
+                                            #[watch]
+                                            set_visible: model.show_smart_albums,
+                                        } -> {
+                                            set_title: &fl!("panoramas-album"),
+                                            set_name: ViewName::Panoramas.into(),
+                                            // NOTE gtk::StackSidebar doesn't show icon :-/
+                                            set_icon_name: "view-continuous-symbolic",
+                                        },
+
+                                        add_child = &gtk::Box {
+                                            set_orientation: gtk::Orientation::Vertical,
+                                            container_add: model.screenshots_page.widget(),
+
+                                            // A stack page is visible when its child is.
+                                            #[watch]
+                                            set_visible: model.show_smart_albums,
+                                        } -> {
+                                            set_title: &fl!("screenshots-album"),
+                                            set_name: ViewName::Screenshots.into(),
+                                            // NOTE gtk::StackSidebar doesn't show icon :-/
+                                            set_icon_name: "applets-screenshooter-symbolic",
+                                        },
+
                                         add_child = &adw::NavigationView {
                                             set_pop_on_escape: true,
 
@@ -429,6 +562,8 @@ impl SimpleComponent for App {
         let con = database::setup(&db_path).expect("Must be able to open database");
         let con = Arc::new(Mutex::new(con));
 
+        let photo_repo = photo::Repository::open(&pic_base_dir, &cache_dir, con.clone()).unwrap();
+
         let video_repo = {
             video::Repository::open(&pic_base_dir, &cache_dir, con.clone()).unwrap()
         };
@@ -451,6 +586,34 @@ impl SimpleComponent for App {
             .launch(transcode_progress_monitor.clone())
             .detach();
 
+        let export_progress_monitor: Reducer<ProgressMonitor> = Reducer::new();
+        let export_progress_monitor = Arc::new(export_progress_monitor);
+
+        let export_progress = self::components::progress_panel::ProgressPanel::builder()
+            .launch(export_progress_monitor.clone())
+            .detach();
+
+        let export = Export::builder()
+            .detach_worker(export_progress_monitor)
+            .forward(sender.input_sender(), |msg| match msg {
+                ExportOutput::Started => AppMsg::ExportStarted,
+                ExportOutput::Completed(count) => AppMsg::ExportCompleted(count),
+            });
+
+        let import_progress_monitor: Reducer<ProgressMonitor> = Reducer::new();
+        let import_progress_monitor = Arc::new(import_progress_monitor);
+
+        let import_progress = self::components::progress_panel::ProgressPanel::builder()
+            .launch(import_progress_monitor.clone())
+            .detach();
+
+        let import = Import::builder()
+            .detach_worker((pic_base_dir.clone(), con.clone(), import_progress_monitor))
+            .forward(sender.input_sender(), |msg| match msg {
+                ImportOutput::Started => AppMsg::ImportStarted,
+                ImportOutput::Completed(paths) => AppMsg::ImportCompleted(paths),
+            });
+
         let bootstrap = Bootstrap::builder()
             .detach_worker((con.clone(), state.clone(), bootstrap_progress_monitor))
             .forward(sender.input_sender(), |msg| match msg {
@@ -467,13 +630,31 @@ impl SimpleComponent for App {
         let transcoder = video::Transcoder::new(&cache_dir);
 
         let video_transcode = VideoTranscode::builder()
-            .detach_worker((state.clone(), video_repo, transcoder.clone(), transcode_progress_monitor.clone()))
-            .detach();
+            .detach_worker((state.clone(), video_repo.clone(), transcoder.clone(), transcode_progress_monitor.clone()))
+            .forward(sender.input_sender(), |msg| match msg {
+                VideoTranscodeOutput::Transcoded(visual_id, path) => AppMsg::Transcoded(visual_id, path),
+                VideoTranscodeOutput::Completed(count) => AppMsg::TranscodeCompleted(count),
+            });
+
+        let photo_thumbnailer = photo::Thumbnailer::build(&cache_dir).unwrap();
+        let video_thumbnailer = video::Thumbnailer::build(&cache_dir).unwrap();
 
         let view_nav = ViewNav::builder()
-            .launch((state.clone(), transcode_progress_monitor.clone(), adaptive_layout.clone()))
+            .launch((
+                state.clone(),
+                transcode_progress_monitor.clone(),
+                adaptive_layout.clone(),
+                photo_repo.clone(),
+                photo_thumbnailer,
+                video_repo,
+                video_thumbnailer,
+            ))
             .forward(sender.input_sender(), |msg| match msg {
                 ViewNavOutput::TranscodeAll => AppMsg::TranscodeAll,
+                ViewNavOutput::Transcode(visual) => AppMsg::Transcode(visual),
+                ViewNavOutput::Export(items) => AppMsg::ShowExport(items),
+                ViewNavOutput::Edited => AppMsg::PhotoEdited,
+                ViewNavOutput::CopySaved(path) => AppMsg::EditedCopySaved(path),
             });
 
         let selfies_page = Album::builder()
@@ -487,6 +668,26 @@ impl SimpleComponent for App {
 
         let show_selfies = AppWidgets::show_selfies();
 
+        // Albums of photos by how they were taken.
+        let [portraits_page, hdr_page, panoramas_page, screenshots_page] = [
+            (ViewName::Portraits, AlbumFilter::Portraits),
+            (ViewName::Hdr, AlbumFilter::Hdr),
+            (ViewName::Panoramas, AlbumFilter::Panoramas),
+            (ViewName::Screenshots, AlbumFilter::Screenshots),
+        ].map(|(view_name, filter)| {
+            let page = Album::builder()
+                .launch((state.clone(), active_view.clone(), view_name, filter))
+                .forward(sender.input_sender(), |msg| match msg {
+                    AlbumOutput::Selected(id, filter) => AppMsg::View(id, filter),
+                });
+
+            state.subscribe(page.sender(), |_| AlbumInput::Refresh);
+            adaptive_layout.subscribe(page.sender(), |layout| AlbumInput::Adapt(*layout));
+            page
+        });
+
+        let show_smart_albums = AppWidgets::show_smart_albums();
+
         let motion_page = Album::builder()
             .launch((state.clone(), active_view.clone(), ViewName::Animated, AlbumFilter::Motion))
             .forward(sender.input_sender(), |msg| match msg {
@@ -542,9 +743,22 @@ impl SimpleComponent for App {
             sender.input_sender(),
             |msg| match msg {
                 PreferencesOutput::Updated => AppMsg::PreferencesUpdated,
+                PreferencesOutput::ThumbnailsChanged => AppMsg::ThumbnailsChanged,
             },
         );
 
+        let geotag_dialog = GeotagDialog::builder()
+            .launch((root.clone(), photo_repo))
+            .forward(sender.input_sender(), |msg| match msg {
+                GeotagDialogOutput::Tagged(count) => AppMsg::Geotagged(count),
+            });
+
+        let export_dialog = ExportDialog::builder()
+            .launch(root.clone())
+            .forward(sender.input_sender(), |msg| match msg {
+                ExportDialogOutput::Export(items, target_dir, mode) => AppMsg::Export(items, target_dir, mode),
+            });
+
         let picture_navigation_view = adw::NavigationView::builder().build();
 
         let main_navigation = adw::OverlaySplitView::builder().build();
@@ -561,9 +775,13 @@ impl SimpleComponent for App {
             adaptive_layout,
             bootstrap,
             video_transcode,
+            export,
+            import,
 
             about_dialog,
             preferences_dialog,
+            geotag_dialog,
+            export_dialog,
 
             library,
 
@@ -573,6 +791,11 @@ impl SimpleComponent for App {
             places_page,
             selfies_page,
             show_selfies,
+            portraits_page,
+            hdr_page,
+            panoramas_page,
+            screenshots_page,
+            show_smart_albums,
             folders_album,
             folder_album,
 
@@ -585,8 +808,11 @@ impl SimpleComponent for App {
 
             bootstrap_progress,
             transcode_progress,
+            export_progress,
+            import_progress,
 
             banner: banner.clone(),
+            export_owns_banner: false,
         };
 
         let widgets = view_output!();
@@ -607,8 +833,35 @@ impl SimpleComponent for App {
             })
         };
 
+        let geotag_action = {
+            let sender = model.geotag_dialog.sender().clone();
+            RelmAction::<GeotagAction>::new_stateless(move |_| {
+                sender.send(GeotagDialogInput::Present).unwrap();
+            })
+        };
+
         actions.add_action(about_action);
         actions.add_action(preferences_action);
+        let import_action = {
+            let sender = sender.clone();
+            let root = root.clone();
+            RelmAction::<ImportAction>::new_stateless(move |_| {
+                let file_dialog = gtk::FileDialog::builder()
+                    .title(fl!("import-title"))
+                    .modal(true)
+                    .build();
+
+                let sender = sender.clone();
+                file_dialog.select_folder(Some(&root), None::<&gio::Cancellable>, move |result| {
+                    if let Some(path) = result.ok().and_then(|file| file.path()) {
+                        sender.input(AppMsg::Import(path));
+                    }
+                });
+            })
+        };
+
+        actions.add_action(geotag_action);
+        actions.add_action(import_action);
 
         actions.register_for_widget(&widgets.main_window);
 
@@ -675,6 +928,10 @@ impl SimpleComponent for App {
                     },
                     ViewName::Videos => self.videos_page.emit(AlbumInput::Activate),
                     ViewName::Selfies => self.selfies_page.emit(AlbumInput::Activate),
+                    ViewName::Portraits => self.portraits_page.emit(AlbumInput::Activate),
+                    ViewName::Hdr => self.hdr_page.emit(AlbumInput::Activate),
+                    ViewName::Panoramas => self.panoramas_page.emit(AlbumInput::Activate),
+                    ViewName::Screenshots => self.screenshots_page.emit(AlbumInput::Activate),
                     ViewName::Animated => self.motion_page.emit(AlbumInput::Activate),
                     ViewName::Folders => self.folders_album.emit(FoldersAlbumInput::Activate),
                     ViewName::Folder => self.folder_album.emit(AlbumInput::Activate),
@@ -708,6 +965,7 @@ impl SimpleComponent for App {
                 self.spinner.start();
                 self.spinner.set_visible(!self.main_navigation.shows_sidebar());
                 self.banner.set_revealed(true);
+                self.export_owns_banner = false;
 
                 match task_name {
                     TaskName::Scan(MediaType::Photo) => {
@@ -731,27 +989,116 @@ impl SimpleComponent for App {
                     TaskName::Thumbnail(MediaType::Video) => {
                         self.banner.set_title(&fl!("banner-thumbnails-videos"));
                     },
+                    TaskName::ThumbnailBudget => {
+                        self.banner.set_title(&fl!("banner-thumbnail-budget"));
+                    },
+                    TaskName::Preview => {
+                        self.banner.set_title(&fl!("banner-previews"));
+                    },
                     TaskName::Clean(MediaType::Photo) => {
                         self.banner.set_title(&fl!("banner-clean-photos"));
                     },
                     TaskName::Clean(MediaType::Video) => {
                         self.banner.set_title(&fl!("banner-clean-videos"));
                     },
+
                 };
             }
             AppMsg::BootstrapCompleted => {
                 event!(Level::INFO, "Bootstrap completed.");
                 self.spinner.stop();
                 self.banner.set_revealed(false);
+
+                // Videos found by the background tasks are transcoded after them,
+                // so that playable items appear in the library first.
+                let settings = gio::Settings::new(APP_ID);
+                if settings.boolean("transcode-automatic") {
+                    self.video_transcode.emit(VideoTranscodeInput::All);
+                }
             }
             AppMsg::TranscodeAll => {
                 event!(Level::INFO, "Transcode all");
                 self.video_transcode.emit(VideoTranscodeInput::All);
             },
+            AppMsg::Transcode(visual) => {
+                event!(Level::INFO, "Transcode {}", visual.visual_id);
+                self.video_transcode.emit(VideoTranscodeInput::One(visual));
+            },
+            AppMsg::Transcoded(visual_id, path) => {
+                self.view_nav.emit(ViewNavInput::Transcoded(visual_id, path));
+            },
+            AppMsg::TranscodeCompleted(count) => {
+                event!(Level::INFO, "Transcoded {} videos", count);
+                if count > 0 {
+                    self.bootstrap.emit(BootstrapInput::RefreshLibrary);
+                }
+            },
+            AppMsg::Geotagged(count) => {
+                event!(Level::INFO, "Geotagged {} photos", count);
+                if count > 0 {
+                    self.bootstrap.emit(BootstrapInput::RefreshLibrary);
+                }
+            },
+            AppMsg::PhotoEdited => {
+                self.bootstrap.emit(BootstrapInput::RefreshLibrary);
+            },
+            AppMsg::EditedCopySaved(path) => {
+                event!(Level::INFO, "Saved edited copy {:?}", path);
+                self.bootstrap.emit(BootstrapInput::Import(vec![path]));
+            },
+            AppMsg::ShowExport(items) => {
+                self.export_dialog.emit(ExportDialogInput::Present(items));
+            },
+            AppMsg::Export(items, target_dir, mode) => {
+                self.export.emit(ExportInput::Start(items, target_dir, mode));
+            },
+            AppMsg::Import(source) => {
+                let settings = gio::Settings::new(APP_ID);
+                let pattern = settings.string("import-pattern").to_string();
+                self.import.emit(ImportInput::Start(source, pattern));
+            },
+            AppMsg::ImportStarted => {
+                self.spinner.start();
+                self.spinner.set_visible(!self.main_navigation.shows_sidebar());
+                self.banner.set_title(&fl!("banner-import"));
+                self.banner.set_revealed(true);
+                self.export_owns_banner = false;
+            },
+            AppMsg::ImportCompleted(paths) => {
+                event!(Level::INFO, "Imported {} files", paths.len());
+                self.banner.set_revealed(false);
+                if paths.is_empty() {
+                    self.spinner.stop();
+                } else {
+                    // Spinner will stop when bootstrap completes.
+                    self.bootstrap.emit(BootstrapInput::Import(paths));
+                }
+            },
+            AppMsg::ExportStarted => {
+                self.spinner.start();
+                self.spinner.set_visible(!self.main_navigation.shows_sidebar());
+                self.banner.set_title(&fl!("banner-export"));
+                self.banner.set_revealed(true);
+                self.export_owns_banner = true;
+            },
+            AppMsg::ExportCompleted(count) => {
+                event!(Level::INFO, "Exported {} files", count);
+                // Leave the banner and spinner alone if a background task has since taken them over.
+                if self.export_owns_banner {
+                    self.export_owns_banner = false;
+                    self.spinner.stop();
+                    self.banner.set_revealed(false);
+                }
+            },
             AppMsg::PreferencesUpdated => {
                 event!(Level::INFO, "Preferences updated.");
                 // TODO create a Preferences struct to hold preferences and send with update message.
                 self.show_selfies = AppWidgets::show_selfies();
+                self.show_smart_albums = AppWidgets::show_smart_albums();
+            },
+            AppMsg::ThumbnailsChanged => {
+                event!(Level::INFO, "Thumbnail preferences changed.");
+                self.bootstrap.emit(BootstrapInput::RegenerateThumbnails);
             },
             AppMsg::Adapt(adaptive::Layout::Narrow) => {
                 self.main_navigation.set_collapsed(true);
@@ -782,6 +1129,11 @@ impl AppWidgets {
         settings.boolean("show-selfies")
     }
 
+    fn show_smart_albums() -> bool {
+        let settings = gio::Settings::new(APP_ID);
+        settings.boolean("show-smart-albums")
+    }
+
     fn save_window_size(&self) -> Result<(), glib::BoolError> {
         let settings = gio::Settings::new(APP_ID);
         let (width, height) = self.main_window.default_size();
@@ -15,6 +15,7 @@ use fotema_core::photo;
 use fotema_core::video;
 use fotema_core::visual;
 
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -29,8 +30,11 @@ use super::{
     photo_thumbnail::{PhotoThumbnail, PhotoThumbnailInput, PhotoThumbnailOutput},
     photo_extract_motion::{PhotoExtractMotion, PhotoExtractMotionInput, PhotoExtractMotionOutput},
 
+    thumbnail_budget::{ThumbnailBudget, ThumbnailBudgetInput, ThumbnailBudgetOutput},
+
     video_clean::{VideoClean, VideoCleanInput, VideoCleanOutput},
     video_enrich::{VideoEnrich, VideoEnrichInput, VideoEnrichOutput},
+    video_preview::{VideoPreview, VideoPreviewInput, VideoPreviewOutput},
     video_scan::{VideoScan, VideoScanInput, VideoScanOutput},
     video_thumbnail::{VideoThumbnail, VideoThumbnailInput, VideoThumbnailOutput},
 };
@@ -54,6 +58,8 @@ pub enum TaskName {
     Enrich(MediaType),
     MotionPhoto,
     Thumbnail(MediaType),
+    ThumbnailBudget,
+    Preview,
     Clean(MediaType),
 }
 
@@ -67,6 +73,16 @@ pub enum BootstrapInput {
     // A background task has completed.
     // usize is count of processed items.
     TaskCompleted(TaskName, Option<usize>),
+
+    // Process just the given files, which have been imported into the library.
+    Import(Vec<PathBuf>),
+
+    // Library state has been changed outside of the background tasks, such as by
+    // a user action, so reload the library.
+    RefreshLibrary,
+
+    // Thumbnails must be regenerated, such as when the preferred shape of thumbnails changes.
+    RegenerateThumbnails,
 }
 
 #[derive(Debug)]
@@ -85,6 +101,18 @@ pub struct Bootstrap {
     /// Whether a background task has updated some library state and the library should be reloaded.
     library_stale: bool,
 
+    /// Whether the background tasks are currently running.
+    is_running: bool,
+
+    /// Imported files being processed. None if processing the whole library.
+    import_paths: Option<Vec<PathBuf>>,
+
+    /// Imported files waiting for the background tasks to finish before being processed.
+    pending_import_paths: Vec<PathBuf>,
+
+    /// Whether thumbnails must be regenerated once the background tasks finish.
+    is_thumbnail_regeneration_pending: bool,
+
     load_library: WorkerController<LoadLibrary>,
 
     photo_scan: WorkerController<PhotoScan>,
@@ -99,7 +127,11 @@ pub struct Bootstrap {
     photo_thumbnail: WorkerController<PhotoThumbnail>,
     video_thumbnail: WorkerController<VideoThumbnail>,
 
+    thumbnail_budget: WorkerController<ThumbnailBudget>,
+
     photo_extract_motion: WorkerController<PhotoExtractMotion>,
+
+    video_preview: WorkerController<VideoPreview>,
 }
 
 impl Worker for Bootstrap {
@@ -149,6 +181,13 @@ impl Worker for Bootstrap {
             .detach_worker((visual_repo.clone(), state))
             .detach();
 
+        let video_preview = VideoPreview::builder()
+            .detach_worker((visual_repo.clone(), progress_monitor.clone()))
+            .forward(sender.input_sender(), |msg| match msg {
+                VideoPreviewOutput::Started => BootstrapInput::TaskStarted(TaskName::Preview),
+                VideoPreviewOutput::Completed(count) => BootstrapInput::TaskCompleted(TaskName::Preview, Some(count)),
+            });
+
         let photo_scan = PhotoScan::builder()
             .detach_worker((photo_scanner.clone(), photo_repo.clone()))
             .forward(sender.input_sender(), |msg| match msg {
@@ -198,6 +237,13 @@ impl Worker for Bootstrap {
                 VideoThumbnailOutput::Completed(count) => BootstrapInput::TaskCompleted(TaskName::Thumbnail(MediaType::Video), Some(count)),
             });
 
+        let thumbnail_budget = ThumbnailBudget::builder()
+            .detach_worker((cache_dir.clone(), photo_repo.clone(), video_repo.clone()))
+            .forward(sender.input_sender(), |msg| match msg {
+                ThumbnailBudgetOutput::Started => BootstrapInput::TaskStarted(TaskName::ThumbnailBudget),
+                ThumbnailBudgetOutput::Completed(count) => BootstrapInput::TaskCompleted(TaskName::ThumbnailBudget, Some(count)),
+            });
+
         let photo_clean = PhotoClean::builder()
             .detach_worker(photo_repo.clone())
             .forward(sender.input_sender(), |msg| match msg {
@@ -215,6 +261,10 @@ impl Worker for Bootstrap {
         Bootstrap {
             started_at: None,
             library_stale: false,
+            is_running: false,
+            import_paths: None,
+            pending_import_paths: Vec::new(),
+            is_thumbnail_regeneration_pending: false,
             load_library,
             photo_scan,
             video_scan,
@@ -225,6 +275,8 @@ impl Worker for Bootstrap {
             video_clean,
             photo_thumbnail,
             video_thumbnail,
+            thumbnail_budget,
+            video_preview,
         }
     }
 
@@ -235,11 +287,43 @@ impl Worker for Bootstrap {
             BootstrapInput::Start => {
                 info!("Start");
                 self.started_at = Some(Instant::now());
+                self.is_running = true;
 
                 // Initial library load to reduce time from starting app and seeing a photo grid
                 self.load_library.emit(LoadLibraryInput::Refresh);
                 self.photo_scan.emit(PhotoScanInput::Start);
             }
+            BootstrapInput::Import(paths) => {
+                if self.is_running {
+                    info!("Queueing {} imported files until background tasks complete", paths.len());
+                    self.pending_import_paths.extend(paths);
+                    return;
+                }
+
+                info!("Processing {} imported files", paths.len());
+                self.started_at = Some(Instant::now());
+                self.is_running = true;
+                self.photo_scan.emit(PhotoScanInput::ScanPaths(paths.clone()));
+                self.import_paths = Some(paths);
+            }
+            BootstrapInput::RefreshLibrary => {
+                info!("Refreshing library");
+                self.load_library.emit(LoadLibraryInput::Refresh);
+            }
+            BootstrapInput::RegenerateThumbnails => {
+                if self.is_running {
+                    info!("Queueing thumbnail regeneration until background tasks complete");
+                    self.is_thumbnail_regeneration_pending = true;
+                    return;
+                }
+
+                // Thumbnail tasks pick up the current thumbnail preferences when started,
+                // so run the tasks from photo thumbnails onwards.
+                info!("Regenerating thumbnails");
+                self.started_at = Some(Instant::now());
+                self.is_running = true;
+                self.photo_thumbnail.emit(PhotoThumbnailInput::Start);
+            }
             BootstrapInput::TaskStarted(task_name @ TaskName::Scan(MediaType::Photo)) => {
                 info!("Scan photos started");
                 let _  = sender.output(BootstrapOutput::TaskStarted(task_name));
@@ -247,7 +331,11 @@ impl Worker for Bootstrap {
             BootstrapInput::TaskCompleted(TaskName::Scan(MediaType::Photo), updated) => {
                 info!("Scan photos completed");
                 self.library_stale = self.library_stale || updated.is_some_and(|x| x > 0);
-                self.video_scan.emit(VideoScanInput::Start);
+                if let Some(ref paths) = self.import_paths {
+                    self.video_scan.emit(VideoScanInput::ScanPaths(paths.clone()));
+                } else {
+                    self.video_scan.emit(VideoScanInput::Start);
+                }
             }
             BootstrapInput::TaskStarted(task_name @ TaskName::Scan(MediaType::Video)) => {
                 info!("Scan videos started");
@@ -289,6 +377,15 @@ impl Worker for Bootstrap {
             BootstrapInput::TaskCompleted(TaskName::MotionPhoto, updated) => {
                 info!("photo thumbnails completed");
                 self.library_stale = self.library_stale || updated.is_some_and(|x| x > 0);
+                self.video_preview.emit(VideoPreviewInput::Start);
+            }
+            BootstrapInput::TaskStarted(task_name @ TaskName::Preview) => {
+                info!("Video previews started");
+                let _  = sender.output(BootstrapOutput::TaskStarted(task_name));
+            }
+            BootstrapInput::TaskCompleted(TaskName::Preview, _) => {
+                // Previews are found by path when hovering, so the library needn't be reloaded.
+                info!("Video previews completed");
                 self.photo_clean.emit(PhotoCleanInput::Start);
             }
             BootstrapInput::TaskStarted(task_name @ TaskName::Thumbnail(MediaType::Photo)) => {
@@ -308,6 +405,15 @@ impl Worker for Bootstrap {
                 let duration = self.started_at.map(|x| x.elapsed());
                 info!("Video thumbnails completed in {:?}", duration);
                 self.library_stale = self.library_stale || updated.is_some_and(|x| x > 0);
+                self.thumbnail_budget.emit(ThumbnailBudgetInput::Start);
+            }
+            BootstrapInput::TaskStarted(task_name @ TaskName::ThumbnailBudget) => {
+                info!("Thumbnail cache trimming started");
+                let _  = sender.output(BootstrapOutput::TaskStarted(task_name));
+            }
+            BootstrapInput::TaskCompleted(TaskName::ThumbnailBudget, updated) => {
+                info!("Thumbnail cache trimming completed");
+                self.library_stale = self.library_stale || updated.is_some_and(|x| x > 0);
                 self.photo_extract_motion.emit(PhotoExtractMotionInput::Start);
             }
             BootstrapInput::TaskStarted(task_name @ TaskName::Clean(MediaType::Photo)) => {
@@ -334,8 +440,20 @@ impl Worker for Bootstrap {
                     self.load_library.emit(LoadLibraryInput::Refresh);
                 }
                 self.library_stale = false;
+                self.is_running = false;
+                self.import_paths = None;
 
                 let _ = sender.output(BootstrapOutput::Completed);
+
+                if !self.pending_import_paths.is_empty() {
+                    let paths = std::mem::take(&mut self.pending_import_paths);
+                    sender.input(BootstrapInput::Import(paths));
+                }
+
+                if self.is_thumbnail_regeneration_pending {
+                    self.is_thumbnail_regeneration_pending = false;
+                    sender.input(BootstrapInput::RegenerateThumbnails);
+                }
             }
         };
     }
@@ -0,0 +1,125 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use relm4::prelude::*;
+use relm4::Worker;
+use relm4::Reducer;
+use rayon::prelude::*;
+use futures::executor::block_on;
+use anyhow::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::result::Result::Ok;
+use tracing::{error, info};
+
+use fotema_core::export::{Exporter, ExportMode};
+use fotema_core::Visual;
+
+use crate::app::components::progress_monitor::{
+    ProgressMonitor,
+    ProgressMonitorInput,
+    TaskName,
+};
+
+#[derive(Debug)]
+pub enum ExportInput {
+    /// Export visual items into a directory
+    Start(Vec<Arc<Visual>>, PathBuf, ExportMode),
+}
+
+#[derive(Debug)]
+pub enum ExportOutput {
+    // Export has started.
+    Started,
+
+    // Export has completed.
+    // usize is count of exported files.
+    Completed(usize),
+}
+
+pub struct Export {
+    progress_monitor: Arc<Reducer<ProgressMonitor>>,
+}
+
+impl Export {
+
+    fn export(
+        items: Vec<Arc<Visual>>,
+        exporter: Exporter,
+        progress_monitor: Arc<Reducer<ProgressMonitor>>,
+        sender: ComponentSender<Self>) -> Result<()>
+     {
+        let start = std::time::Instant::now();
+
+        let count = items.len();
+        info!("Exporting {} items with mode {:?}", count, exporter.mode());
+
+        let _ = sender.output(ExportOutput::Started);
+
+        progress_monitor.emit(ProgressMonitorInput::Start(TaskName::Export, count));
+
+        let exported_count = AtomicUsize::new(0);
+
+        items
+            .par_iter()
+            .for_each(|visual| {
+                match block_on(async { exporter.export(visual).await }) {
+                    Ok(paths) => {
+                        exported_count.fetch_add(paths.len(), Ordering::Relaxed);
+                    },
+                    Err(e) => {
+                        error!("Failed exporting {:?}: {:?}", visual.path(), e);
+                    },
+                }
+
+                progress_monitor.emit(ProgressMonitorInput::Advance);
+            });
+
+        let exported_count = exported_count.into_inner();
+
+        info!("Exported {} files in {} seconds.", exported_count, start.elapsed().as_secs());
+
+        progress_monitor.emit(ProgressMonitorInput::Complete);
+
+        let _ = sender.output(ExportOutput::Completed(exported_count));
+
+        Ok(())
+    }
+}
+
+impl Worker for Export {
+    type Init = Arc<Reducer<ProgressMonitor>>;
+    type Input = ExportInput;
+    type Output = ExportOutput;
+
+    fn init(progress_monitor: Self::Init, _sender: ComponentSender<Self>) -> Self  {
+        Export {
+            progress_monitor,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            ExportInput::Start(items, target_dir, mode) => {
+                let exporter = match Exporter::build(&target_dir, mode) {
+                    Ok(exporter) => exporter,
+                    Err(e) => {
+                        error!("Cannot export to {:?}: {}", target_dir, e);
+                        return;
+                    },
+                };
+
+                let progress_monitor = self.progress_monitor.clone();
+
+                // Avoid runtime panic from calling block_on
+                rayon::spawn(move || {
+                    if let Err(e) = Export::export(items, exporter, progress_monitor, sender) {
+                        error!("Failed to export: {}", e);
+                    }
+                });
+            }
+        };
+    }
+}
@@ -0,0 +1,125 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use relm4::prelude::*;
+use relm4::Worker;
+use relm4::Reducer;
+use anyhow::*;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::result::Result::Ok;
+use tracing::{error, info};
+
+use fotema_core::database;
+use fotema_core::import::{Importer, ImportPattern};
+
+use crate::app::components::progress_monitor::{
+    ProgressMonitor,
+    ProgressMonitorInput,
+    TaskName,
+};
+
+#[derive(Debug)]
+pub enum ImportInput {
+    /// Import photos and videos from a source directory using a path pattern.
+    Start(PathBuf, String),
+}
+
+#[derive(Debug)]
+pub enum ImportOutput {
+    // Import has started.
+    Started,
+
+    // Import has completed with paths of files added to library.
+    Completed(Vec<PathBuf>),
+}
+
+pub struct Import {
+    library_base_path: PathBuf,
+
+    con: Arc<Mutex<database::Connection>>,
+
+    progress_monitor: Arc<Reducer<ProgressMonitor>>,
+}
+
+impl Import {
+
+    fn import(
+        importer: Importer,
+        source: PathBuf,
+        progress_monitor: Arc<Reducer<ProgressMonitor>>,
+        sender: ComponentSender<Self>) -> Result<()>
+     {
+        let start = std::time::Instant::now();
+
+        let items = importer.scan(&source)?;
+
+        let count = items.len();
+        info!("Found {} items to import from {:?}", count, source);
+
+        let _ = sender.output(ImportOutput::Started);
+
+        progress_monitor.emit(ProgressMonitorInput::Start(TaskName::Import, count));
+
+        // Import one item at a time. Copying is limited by the speed of the source device,
+        // and importing sequentially stops duplicate files being copied twice.
+        let imported: Vec<PathBuf> = items
+            .iter()
+            .flat_map(|item| {
+                let result = importer.import(item);
+                progress_monitor.emit(ProgressMonitorInput::Advance);
+                result
+                    .inspect_err(|e| error!("Failed importing {:?}: {:?}", item.paths, e))
+                    .unwrap_or_default()
+            })
+            .collect();
+
+        info!("Imported {} files in {} seconds.", imported.len(), start.elapsed().as_secs());
+
+        progress_monitor.emit(ProgressMonitorInput::Complete);
+
+        let _ = sender.output(ImportOutput::Completed(imported));
+
+        Ok(())
+    }
+}
+
+impl Worker for Import {
+    type Init = (PathBuf, Arc<Mutex<database::Connection>>, Arc<Reducer<ProgressMonitor>>);
+    type Input = ImportInput;
+    type Output = ImportOutput;
+
+    fn init((library_base_path, con, progress_monitor): Self::Init, _sender: ComponentSender<Self>) -> Self  {
+        Import {
+            library_base_path,
+            con,
+            progress_monitor,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            ImportInput::Start(source, pattern) => {
+                let importer = ImportPattern::new(&pattern)
+                    .and_then(|pattern| Importer::build(&self.library_base_path, pattern, self.con.clone()));
+
+                let importer = match importer {
+                    Ok(importer) => importer,
+                    Err(e) => {
+                        error!("Cannot import: {}", e);
+                        return;
+                    },
+                };
+
+                let progress_monitor = self.progress_monitor.clone();
+
+                rayon::spawn(move || {
+                    if let Err(e) = Import::import(importer, source, progress_monitor, sender) {
+                        error!("Failed to import: {}", e);
+                    }
+                });
+            }
+        };
+    }
+}
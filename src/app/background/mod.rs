@@ -3,6 +3,8 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 pub mod bootstrap;
+pub mod export;
+pub mod import;
 pub mod load_library;
 
 pub mod photo_clean;
@@ -11,8 +13,11 @@ pub mod photo_extract_motion;
 pub mod photo_scan;
 pub mod photo_thumbnail;
 
+pub mod thumbnail_budget;
+
 pub mod video_clean;
 pub mod video_enrich;
+pub mod video_preview;
 pub mod video_scan;
 pub mod video_thumbnail;
 pub mod video_transcode;
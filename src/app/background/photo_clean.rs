@@ -64,6 +64,17 @@ impl PhotoClean {
                 }
             });
 
+        // Scrub voice notes too, so that a deleted voice note isn't offered for playing.
+        for path in self.repo.all_voice_notes()? {
+            if !path.exists() {
+                if let Err(e) = self.repo.remove_voice_note(&path) {
+                    error!("Failed removing voice note {:?}: {:?}", path, e);
+                } else {
+                    info!("Removed voice note {:?}", path);
+                }
+            }
+        }
+
         info!("Cleaned {} photos in {} seconds.", count, start.elapsed().as_secs());
 
         if let Err(e) = sender.output(PhotoCleanOutput::Completed(count)) {
@@ -90,6 +101,7 @@ impl Worker for PhotoClean {
 
                 if let Err(e) = self.cleanup(&sender) {
                     error!("Failed to clean photos: {}", e);
+                    let _ = sender.output(PhotoCleanOutput::Completed(0));
                 }
             }
         };
@@ -93,6 +93,7 @@ impl Worker for PhotoEnrich {
                 rayon::spawn(move || {
                     if let Err(e) = PhotoEnrich::enrich(repo, &sender) {
                         error!("Failed to update previews: {}", e);
+                        let _ = sender.output(PhotoEnrichOutput::Completed(0));
                     }
                 });
             }
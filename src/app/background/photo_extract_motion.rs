@@ -129,8 +129,9 @@ impl Worker for PhotoExtractMotion {
                 let progress_monitor = self.progress_monitor.clone();
 
                 rayon::spawn(move || {
-                    if let Err(e) = PhotoExtractMotion::extract(repo, extractor, progress_monitor, sender) {
+                    if let Err(e) = PhotoExtractMotion::extract(repo, extractor, progress_monitor, sender.clone()) {
                         error!("Failed to update previews: {}", e);
+                        let _ = sender.output(PhotoExtractMotionOutput::Completed(0));
                     }
                 });
             }
@@ -4,11 +4,15 @@
 
 use relm4::prelude::*;
 use relm4::Worker;
+use std::path::PathBuf;
 use tracing::{error, info};
 
 #[derive(Debug)]
 pub enum PhotoScanInput {
     Start,
+
+    // Scan just the given paths, such as newly imported files.
+    ScanPaths(Vec<PathBuf>),
 }
 
 #[derive(Debug)]
@@ -34,9 +38,17 @@ impl Worker for PhotoScan {
     fn update(&mut self, msg: PhotoScanInput, sender: ComponentSender<Self>) {
         match msg {
             PhotoScanInput::Start => {
-                let result = self.scan_and_add(sender);
+                let result = self.scan_and_add(sender.clone());
+                if let Err(e) = result {
+                    error!("Failed scan with: {}", e);
+                    let _ = sender.output(PhotoScanOutput::Completed);
+                }
+            }
+            PhotoScanInput::ScanPaths(paths) => {
+                let result = self.scan_paths_and_add(paths, sender.clone());
                 if let Err(e) = result {
                     error!("Failed scan with: {}", e);
+                    let _ = sender.output(PhotoScanOutput::Completed);
                 }
             }
         };
@@ -51,13 +63,38 @@ impl PhotoScan {
 
         info!("Scanning file system for pictures...");
 
-        let result = self.scan.scan_all().map_err(|e| e.to_string())?;
+        let (result, notes) = self.scan.scan_all_with_voice_notes().map_err(|e| e.to_string())?;
         info!("Found {} photos to add to database", result.len());
 
         self.repo.add_all(&result).map_err(|e| e.to_string())?;
 
+        info!("Found {} voice notes to add to database", notes.len());
+
+        self.repo.add_voice_notes(&notes).map_err(|e| e.to_string())?;
+
         sender.output(PhotoScanOutput::Completed)
             .map_err(|e| format!("{:?}", e))
 
     }
+
+    fn scan_paths_and_add(&mut self, paths: Vec<PathBuf>, sender: ComponentSender<Self>) -> std::result::Result<(), String> {
+
+        sender.output(PhotoScanOutput::Started)
+            .map_err(|e| format!("{:?}", e))?;
+
+        info!("Scanning {} imported files for pictures...", paths.len());
+
+        let result = self.scan.scan_paths(&paths).map_err(|e| e.to_string())?;
+        info!("Found {} photos to add to database", result.len());
+
+        self.repo.add_all(&result).map_err(|e| e.to_string())?;
+
+        let notes = self.scan.scan_voice_note_paths(&paths).map_err(|e| e.to_string())?;
+        info!("Found {} voice notes to add to database", notes.len());
+
+        self.repo.add_voice_notes(&notes).map_err(|e| e.to_string())?;
+
+        sender.output(PhotoScanOutput::Completed)
+            .map_err(|e| format!("{:?}", e))
+    }
 }
@@ -14,6 +14,16 @@ use tracing::{error, info};
 
 use std::panic;
 
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
+use fotema_core::photo::model::Picture;
+use fotema_core::thumbnail::{self, Format, Shape, Thumbnail};
+use fotema_core::thumbnail::freedesktop::SharedCache;
+
+use crate::config::APP_ID;
+use crate::app::components::thumbnail_settings;
+
 use crate::app::components::progress_monitor::{
     ProgressMonitor,
     ProgressMonitorInput,
@@ -51,16 +61,24 @@ impl PhotoThumbnail {
     fn enrich(
         repo: fotema_core::photo::Repository,
         thumbnailer: fotema_core::photo::Thumbnailer,
+        shape: Shape,
+        format: Format,
         progress_monitor: Arc<Reducer<ProgressMonitor>>,
         sender: ComponentSender<Self>) -> Result<()>
      {
         let start = std::time::Instant::now();
 
-        let mut unprocessed: Vec<fotema_core::photo::model::Picture> = repo
+        // Thumbnails are regenerated if the preferred shape or format has changed.
+        // Larger thumbnails removed to keep the cache within its budget aren't regenerated.
+        let mut unprocessed: Vec<Picture> = repo
             .all()?
             .into_iter()
             .filter(|pic| pic.path.exists())
-            .filter(|pic| !pic.thumbnail_path.as_ref().is_some_and(|p| p.exists()))
+            .filter(|pic| !pic.thumbnail_path.as_ref()
+                .is_some_and(|p| {
+                    let max_edge = pic.thumbnail_max_edge.unwrap_or(thumbnail::LARGEST_EDGE);
+                    thumbnail::all_exist_up_to(p, max_edge) && Shape::of(p) == shape && format.matches(p)
+                }))
             .collect();
 
         // should be ascending time order from database, so reverse to process newest items first
@@ -90,8 +108,8 @@ impl PhotoThumbnail {
                 // Careful! panic::catch_unwind returns Ok(Err) if the evaluated expression returns
                 // an error but doesn't panic.
                 let result = panic::catch_unwind(|| {
-                    block_on(async {thumbnailer.thumbnail(&pic.picture_id, &pic.path).await})
-                        .and_then(|thumbnail_path| repo.clone().add_thumbnail(&pic.picture_id, &thumbnail_path))
+                    block_on(Self::thumbnail(&repo, &thumbnailer, pic))
+                        .and_then(|thumbnail| repo.clone().add_thumbnail(&pic.picture_id, &thumbnail))
                 });
 
                 // If we got an err, then there was a panic.
@@ -115,6 +133,27 @@ impl PhotoThumbnail {
 
         Ok(())
     }
+
+    /// Generate thumbnails with any edits applied, so that regenerating the thumbnails
+    /// of an edited photo doesn't lose the edits. Thumbnails are cropped about any
+    /// focus chosen by the user.
+    async fn thumbnail(
+        repo: &fotema_core::photo::Repository,
+        thumbnailer: &fotema_core::photo::Thumbnailer,
+        pic: &Picture,
+    ) -> Result<Thumbnail> {
+        let focus = repo.focus_override(&pic.picture_id)?;
+
+        let edits = repo.edits(&pic.picture_id)?;
+        if edits.is_empty() {
+            return thumbnailer.thumbnail(&pic.picture_id, &pic.path, focus).await;
+        }
+
+        let orientation = repo.corrected_orientation(&pic.picture_id)?;
+        thumbnailer
+            .edited_thumbnail(&pic.picture_id, &pic.path, orientation, &edits, focus)
+            .await
+    }
 }
 
 impl Worker for PhotoThumbnail {
@@ -136,13 +175,23 @@ impl Worker for PhotoThumbnail {
             PhotoThumbnailInput::Start => {
                 info!("Generating photo thumbnails...");
                 let repo = self.repo.clone();
-                let thumbnailer = self.thumbnailer.clone();
                 let progress_monitor = self.progress_monitor.clone();
 
+                let settings = gio::Settings::new(APP_ID);
+                let (shape, format) = thumbnail_settings::from_settings(&settings);
+                let shared_cache = SharedCache::open()
+                    .map(|cache| cache.with_writes(settings.boolean("thumbnail-share")));
+                let thumbnailer = self.thumbnailer
+                    .clone()
+                    .with_shape(shape)
+                    .with_format(format)
+                    .with_shared_cache(shared_cache);
+
                 // Avoid runtime panic from calling block_on
                 rayon::spawn(move || {
-                    if let Err(e) = PhotoThumbnail::enrich(repo, thumbnailer, progress_monitor, sender) {
+                    if let Err(e) = PhotoThumbnail::enrich(repo, thumbnailer, shape, format, progress_monitor, sender.clone()) {
                         error!("Failed to update previews: {}", e);
+                        let _ = sender.output(PhotoThumbnailOutput::Completed(0));
                     }
                 });
             }
@@ -0,0 +1,118 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use relm4::prelude::*;
+use relm4::Worker;
+use anyhow::*;
+use std::path::PathBuf;
+
+use tracing::{error, info};
+
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
+use fotema_core::thumbnail::cache;
+
+use crate::config::APP_ID;
+
+#[derive(Debug)]
+pub enum ThumbnailBudgetInput {
+    Start,
+}
+
+#[derive(Debug)]
+pub enum ThumbnailBudgetOutput {
+    // Removing thumbnails to fit the budget has started.
+    Started,
+
+    // Removing thumbnails has completed. Count of items that had thumbnails removed.
+    Completed(usize),
+}
+
+/// Keeps the thumbnail cache within the size budget in preferences by removing
+/// larger thumbnails of the items viewed longest ago.
+pub struct ThumbnailBudget {
+    photo_thumbnails_dir: PathBuf,
+    video_thumbnails_dir: PathBuf,
+
+    photo_repo: fotema_core::photo::Repository,
+    video_repo: fotema_core::video::Repository,
+}
+
+impl ThumbnailBudget {
+
+    fn trim(&mut self, sender: &ComponentSender<Self>) -> Result<()> {
+        let settings = gio::Settings::new(APP_ID);
+
+        // Budget is in megabytes, with zero meaning no budget.
+        let budget = u64::from(settings.uint("thumbnail-cache-budget")) * 1024 * 1024;
+        if budget == 0 {
+            let _ = sender.output(ThumbnailBudgetOutput::Completed(0));
+            return Ok(());
+        }
+
+        let start = std::time::Instant::now();
+
+        let dirs = [self.photo_thumbnails_dir.clone(), self.video_thumbnails_dir.clone()];
+
+        // Short-circuit before sending progress messages to stop
+        // banner from appearing and disappearing.
+        if cache::size(&dirs) <= budget {
+            let _ = sender.output(ThumbnailBudgetOutput::Completed(0));
+            return Ok(());
+        }
+
+        let _ = sender.output(ThumbnailBudgetOutput::Started);
+
+        let trimmed = cache::trim(&dirs, budget);
+
+        // Record which thumbnails remain so they aren't regenerated and the viewer
+        // doesn't ask for thumbnails that no longer exist.
+        for item in &trimmed {
+            let result = if item.path.starts_with(&self.photo_thumbnails_dir) {
+                self.photo_repo.set_thumbnail_max_edge(&item.path, item.max_edge)
+            } else {
+                self.video_repo.set_thumbnail_max_edge(&item.path, item.max_edge)
+            };
+
+            if let Err(e) = result {
+                error!("Failed recording trimmed thumbnail {:?}: {:?}", item.path, e);
+            }
+        }
+
+        info!("Trimmed thumbnails of {} items in {} seconds.", trimmed.len(), start.elapsed().as_secs());
+
+        let _ = sender.output(ThumbnailBudgetOutput::Completed(trimmed.len()));
+
+        Ok(())
+    }
+}
+
+impl Worker for ThumbnailBudget {
+    type Init = (PathBuf, fotema_core::photo::Repository, fotema_core::video::Repository);
+    type Input = ThumbnailBudgetInput;
+    type Output = ThumbnailBudgetOutput;
+
+    fn init((cache_dir, photo_repo, video_repo): Self::Init, _sender: ComponentSender<Self>) -> Self {
+        Self {
+            photo_thumbnails_dir: cache_dir.join("photo_thumbnails"),
+            video_thumbnails_dir: cache_dir.join("video_thumbnails"),
+            photo_repo,
+            video_repo,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            ThumbnailBudgetInput::Start => {
+                info!("Trimming thumbnail cache...");
+
+                if let Err(e) = self.trim(&sender) {
+                    error!("Failed to trim thumbnail cache: {}", e);
+                    let _ = sender.output(ThumbnailBudgetOutput::Completed(0));
+                }
+            }
+        };
+    }
+}
@@ -90,6 +90,7 @@ impl Worker for VideoClean {
 
                 if let Err(e) = self.cleanup(&sender) {
                     error!("Failed to clean videos: {}", e);
+                    let _ = sender.output(VideoCleanOutput::Completed(0));
                 }
             }
         };
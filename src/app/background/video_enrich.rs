@@ -110,6 +110,7 @@ impl Worker for VideoEnrich {
                 rayon::spawn(move || {
                     if let Err(e) = VideoEnrich::enrich(repo, progress_monitor, &sender) {
                         error!("Failed to enrich videos: {}", e);
+                        let _ = sender.output(VideoEnrichOutput::Completed(0));
                     }
                 });
             }
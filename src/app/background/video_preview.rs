@@ -0,0 +1,159 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use relm4::prelude::*;
+use relm4::Worker;
+use relm4::Reducer;
+use anyhow::*;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::panic;
+use std::result::Result::Ok;
+use tracing::{error, info};
+use rayon::prelude::*;
+
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
+use fotema_core::video::preview;
+use fotema_core::visual::Repository;
+use fotema_core::Visual;
+
+use crate::config::APP_ID;
+
+use crate::app::components::progress_monitor::{
+    ProgressMonitor,
+    ProgressMonitorInput,
+    TaskName,
+};
+
+#[derive(Debug)]
+pub enum VideoPreviewInput {
+    Start,
+}
+
+#[derive(Debug)]
+pub enum VideoPreviewOutput {
+    // Preview generation has started
+    Started,
+
+    // Preview generation has completed
+    Completed(usize),
+}
+
+/// Generates the short clips of videos and motion photos that play when hovering
+/// over them in an album.
+pub struct VideoPreview {
+    repo: Repository,
+
+    progress_monitor: Arc<Reducer<ProgressMonitor>>,
+}
+
+impl VideoPreview {
+
+    fn enrich(
+        repo: Repository,
+        progress_monitor: Arc<Reducer<ProgressMonitor>>,
+        sender: ComponentSender<VideoPreview>) -> Result<()>
+     {
+        let start = std::time::Instant::now();
+
+        let mut unprocessed: Vec<Visual> = repo
+            .all()?
+            .into_iter()
+            .filter(|visual| visual.preview_path.as_ref().is_some_and(|p| !p.exists()))
+            .filter(|visual| Self::source_path(visual).is_some_and(|p| p.exists()))
+            .collect();
+
+        // should be ascending time order from database, so reverse to process newest items first
+        unprocessed.reverse();
+
+        let count = unprocessed.len();
+        info!("Found {} videos to generate previews for", count);
+
+        // Short-circuit before sending progress messages to stop
+        // banner from appearing and disappearing.
+        if count == 0 {
+            let _ = sender.output(VideoPreviewOutput::Completed(count));
+            return Ok(());
+        }
+
+        let _ = sender.output(VideoPreviewOutput::Started);
+
+        progress_monitor.emit(ProgressMonitorInput::Start(TaskName::Preview, count));
+
+        unprocessed
+            .par_iter()
+            .for_each(|visual| {
+                let (Some(source_path), Some(preview_path)) = (Self::source_path(visual), visual.preview_path.as_ref()) else {
+                    return;
+                };
+
+                // Careful! panic::catch_unwind returns Ok(Err) if the evaluated expression returns
+                // an error but doesn't panic.
+                let result = panic::catch_unwind(|| preview::generate(source_path, preview_path));
+
+                // A missing preview just means nothing plays on hover, so the item isn't
+                // marked as broken.
+                if let Ok(Err(e)) = result {
+                    error!("Failed generating preview: {:?}: Video path: {:?}", e, source_path);
+                } else if result.is_err() {
+                    error!("Panicked generating preview: Video path: {:?}", source_path);
+                }
+
+                progress_monitor.emit(ProgressMonitorInput::Advance);
+            });
+
+        info!("Generated {} video previews in {} seconds.", count, start.elapsed().as_secs());
+
+        progress_monitor.emit(ProgressMonitorInput::Complete);
+
+        let _ = sender.output(VideoPreviewOutput::Completed(count));
+
+        Ok(())
+    }
+
+    /// Video that a preview is made from.
+    fn source_path(visual: &Visual) -> Option<&PathBuf> {
+        visual.video_path.as_ref().or(visual.motion_photo_video_path.as_ref())
+    }
+}
+
+impl Worker for VideoPreview {
+    type Init = (Repository, Arc<Reducer<ProgressMonitor>>);
+    type Input = VideoPreviewInput;
+    type Output = VideoPreviewOutput;
+
+    fn init((repo, progress_monitor): Self::Init, _sender: ComponentSender<Self>) -> Self  {
+        Self {
+            repo,
+            progress_monitor,
+        }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            VideoPreviewInput::Start => {
+                // Don't spend time generating previews that won't be played.
+                let settings = gio::Settings::new(APP_ID);
+                if !settings.boolean("hover-previews") {
+                    let _ = sender.output(VideoPreviewOutput::Completed(0));
+                    return;
+                }
+
+                info!("Generating video previews...");
+                let repo = self.repo.clone();
+                let progress_monitor = self.progress_monitor.clone();
+
+                // Avoid runtime panic from calling block_on
+                rayon::spawn(move || {
+                    if let Err(e) = VideoPreview::enrich(repo, progress_monitor, sender.clone()) {
+                        error!("Failed to update video previews: {}", e);
+                        let _ = sender.output(VideoPreviewOutput::Completed(0));
+                    }
+                });
+            }
+        };
+    }
+}
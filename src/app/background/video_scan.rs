@@ -4,6 +4,7 @@
 
 use relm4::prelude::*;
 use relm4::Worker;
+use std::path::PathBuf;
 use fotema_core::video;
 
 use tracing::{error, info};
@@ -11,6 +12,9 @@ use tracing::{error, info};
 #[derive(Debug)]
 pub enum VideoScanInput {
     Start,
+
+    // Scan just the given paths, such as newly imported files.
+    ScanPaths(Vec<PathBuf>),
 }
 
 #[derive(Debug)]
@@ -36,9 +40,17 @@ impl Worker for VideoScan {
     fn update(&mut self, msg: VideoScanInput, sender: ComponentSender<Self>) {
         match msg {
             VideoScanInput::Start => {
-                let result = self.scan_and_add(sender);
+                let result = self.scan_and_add(sender.clone());
+                if let Err(e) = result {
+                    error!("Failed scan with: {}", e);
+                    let _ = sender.output(VideoScanOutput::Completed);
+                }
+            }
+            VideoScanInput::ScanPaths(paths) => {
+                let result = self.scan_paths_and_add(paths, sender.clone());
                 if let Err(e) = result {
                     error!("Failed scan with: {}", e);
+                    let _ = sender.output(VideoScanOutput::Completed);
                 }
             }
         };
@@ -62,4 +74,20 @@ impl VideoScan {
             .map_err(|e| format!("{:?}", e))
 
     }
+
+    fn scan_paths_and_add(&mut self, paths: Vec<PathBuf>, sender: ComponentSender<Self>) -> std::result::Result<(), String> {
+
+        sender.output(VideoScanOutput::Started)
+            .map_err(|e| format!("{:?}", e))?;
+
+        info!("Scanning {} imported files for videos...", paths.len());
+
+        let result = self.scan.scan_paths(&paths).map_err(|e| e.to_string())?;
+        info!("Found {} videos to add to database", result.len());
+
+        self.repo.add_all(&result).map_err(|e| e.to_string())?;
+
+        sender.output(VideoScanOutput::Completed)
+            .map_err(|e| format!("{:?}", e))
+    }
 }
@@ -12,7 +12,15 @@ use std::result::Result::Ok;
 use tracing::{error, info};
 use rayon::prelude::*;
 
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
 use fotema_core::video::{Video, Thumbnailer, Repository};
+use fotema_core::thumbnail::{self, Format, Shape};
+use fotema_core::thumbnail::freedesktop::SharedCache;
+
+use crate::config::APP_ID;
+use crate::app::components::thumbnail_settings;
 
 use crate::app::components::progress_monitor::{
     ProgressMonitor,
@@ -51,16 +59,24 @@ impl VideoThumbnail {
     fn enrich(
         repo: Repository,
         thumbnailer: Thumbnailer,
+        shape: Shape,
+        format: Format,
         progress_monitor: Arc<Reducer<ProgressMonitor>>,
         sender: ComponentSender<VideoThumbnail>) -> Result<()>
      {
         let start = std::time::Instant::now();
 
+        // Thumbnails are regenerated if the preferred shape or format has changed.
+        // Larger thumbnails removed to keep the cache within its budget aren't regenerated.
         let mut unprocessed: Vec<Video> = repo
             .all()?
             .into_iter()
             .filter(|vid| vid.path.exists())
-            .filter(|vid| !vid.thumbnail_path.as_ref().is_some_and(|p| p.exists()))
+            .filter(|vid| !vid.thumbnail_path.as_ref()
+                .is_some_and(|p| {
+                    let max_edge = vid.thumbnail_max_edge.unwrap_or(thumbnail::LARGEST_EDGE);
+                    thumbnail::all_exist_up_to(p, max_edge) && Shape::of(p) == shape && format.matches(p)
+                }))
             .collect();
 
         // should be ascending time order from database, so reverse to process newest items first
@@ -86,8 +102,9 @@ impl VideoThumbnail {
                 // Careful! panic::catch_unwind returns Ok(Err) if the evaluated expression returns
                 // an error but doesn't panic.
                 let result = panic::catch_unwind(|| {
-                    thumbnailer.thumbnail(&vid.video_id, &vid.path)
-                        .and_then(|thumbnail_path| repo.clone().add_thumbnail(&vid.video_id, &thumbnail_path))
+                    repo.poster(&vid.video_id)
+                        .and_then(|poster| thumbnailer.thumbnail(&vid.video_id, &vid.path, poster))
+                        .and_then(|thumbnail| repo.clone().add_thumbnail(&vid.video_id, &thumbnail))
                 });
 
                 // If we got an err, then there was a panic.
@@ -132,13 +149,23 @@ impl Worker for VideoThumbnail {
             VideoThumbnailInput::Start => {
                 info!("Generating video thumbnails...");
                 let repo = self.repo.clone();
-                let thumbnailer = self.thumbnailer.clone();
                 let progress_monitor = self.progress_monitor.clone();
 
+                let settings = gio::Settings::new(APP_ID);
+                let (shape, format) = thumbnail_settings::from_settings(&settings);
+                let shared_cache = SharedCache::open()
+                    .map(|cache| cache.with_writes(settings.boolean("thumbnail-share")));
+                let thumbnailer = self.thumbnailer
+                    .clone()
+                    .with_shape(shape)
+                    .with_format(format)
+                    .with_shared_cache(shared_cache);
+
                 // Avoid runtime panic from calling block_on
                 rayon::spawn(move || {
-                    if let Err(e) = VideoThumbnail::enrich(repo, thumbnailer, progress_monitor, sender) {
+                    if let Err(e) = VideoThumbnail::enrich(repo, thumbnailer, shape, format, progress_monitor, sender.clone()) {
                         error!("Failed to update video thumbnails: {}", e);
+                        let _ = sender.output(VideoThumbnailOutput::Completed(0));
                     }
                 });
             }
@@ -8,9 +8,14 @@ use relm4::Reducer;
 
 use anyhow::*;
 
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
 use fotema_core::video::Repository;
 use fotema_core::video::Transcoder;
+use fotema_core::video::transcode::Profile;
 use fotema_core::Visual;
+use fotema_core::VisualId;
 use tracing::{error, info};
 
 use crate::app::components::progress_monitor::{
@@ -19,24 +24,38 @@ use crate::app::components::progress_monitor::{
     TaskName,
 };
 
+use crate::config::APP_ID;
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 use crate::app::SharedState;
 
+/// Steps of progress when transcoding a single video.
+const PROGRESS_STEPS: usize = 100;
+
 #[derive(Debug)]
 pub enum VideoTranscodeInput {
     /// Transcode all videos
     All,
+
+    /// Transcode one video, showing progress through the video.
+    /// Videos still to be transcoded by All wait until it is done.
+    One(Arc<Visual>),
+
+    /// Transcode the next of the videos found by All. Each video is transcoded by
+    /// its own message, so that videos to transcode with One can go first.
+    Next,
 }
 
 #[derive(Debug)]
 pub enum VideoTranscodeOutput {
-    // Video transcoding has started
-    Started,
-
-    // Video transcoding has completed
-    Completed,
+    // A video has been transcoded to a path.
+    Transcoded(VisualId, PathBuf),
 
+    // Video transcoding has completed. Count of videos transcoded.
+    Completed(usize),
 }
 
 pub struct VideoTranscode {
@@ -47,11 +66,24 @@ pub struct VideoTranscode {
     state: SharedState,
 
     progress_monitor: Arc<Reducer<ProgressMonitor>>,
+
+    /// Is All transcoding videos?
+    is_transcoding_all: bool,
+
+    /// Videos found by All that are still to be transcoded.
+    pending: VecDeque<Arc<Visual>>,
+
+    /// Paths of videos transcoded since All started, which are kept when trimming.
+    transcoded: Vec<PathBuf>,
 }
 
 impl VideoTranscode {
 
     fn transcode_all(&mut self, sender: &ComponentSender<Self>) -> Result<()> {
+        if self.is_transcoding_all {
+            info!("Already transcoding all videos");
+            return Ok(());
+        }
 
         let unprocessed: Vec<Arc<Visual>> = {
             let data = self.state.read();
@@ -67,38 +99,153 @@ impl VideoTranscode {
 
          info!("Found {} videos to transcode", unprocessed.len());
 
+        if unprocessed.is_empty() {
+            let _ = sender.output(VideoTranscodeOutput::Completed(0));
+            return Ok(());
+        }
+
         self.progress_monitor
             .emit(ProgressMonitorInput::Start(TaskName::Transcode, unprocessed.len()));
 
-        let _ = sender.output(VideoTranscodeOutput::Started);
+        self.is_transcoding_all = true;
+        self.pending = unprocessed.into();
+        self.transcoded.clear();
+        sender.input(VideoTranscodeInput::Next);
 
-        unprocessed
-            .iter()
-            .for_each(|visual| {
-                let video_id = visual.video_id.expect("Must have video_id");
-                let video_path = visual.video_path.as_ref().expect("Must have video_path");
+        Ok(())
+    }
 
-                let result = self.transcoder.transcode(video_id, video_path)
-                    .with_context(|| format!("Video path: {:?}", video_path));
+    fn transcode_next(&mut self, sender: &ComponentSender<Self>) -> Result<()> {
+        // Stop when the budget is full, rather than removing videos that were just
+        // transcoded, which would be transcoded again next time.
+        if Self::budget().is_some_and(|budget| self.transcoder.size() >= budget) {
+            info!("Transcode cache budget is full");
+            self.pending.clear();
+        }
 
-                if let std::result::Result::Ok(ref transcode_path) = result {
-                    if let Err(e) = self.repo.add_transcode(video_id, transcode_path) {
-                        error!("Failed adding transcode path: {:?}", e);
-                    }
-                } else if let Err(ref e) = result {
-                    error!("Failed transcoding: {:?}", e);
-                }
+        let Some(visual) = self.pending.pop_front() else {
+            self.is_transcoding_all = false;
+            self.progress_monitor.emit(ProgressMonitorInput::Complete);
+
+            let transcoded = std::mem::take(&mut self.transcoded);
+            self.trim(&transcoded);
+
+            let _ = sender.output(VideoTranscodeOutput::Completed(transcoded.len()));
+            return Ok(());
+        };
+
+        let transcoder = self.transcoder.clone().with_profile(Self::profile());
+        if let Some(path) = self.transcode(&transcoder, &visual, |_| {}, sender) {
+            self.transcoded.push(path);
+        }
+        self.progress_monitor.emit(ProgressMonitorInput::Advance);
+
+        sender.input(VideoTranscodeInput::Next);
 
-                self.progress_monitor.emit(ProgressMonitorInput::Advance);
+        Ok(())
+    }
+
+    fn transcode_one(&mut self, visual: &Visual, sender: &ComponentSender<Self>) -> Result<()> {
+        self.progress_monitor
+            .emit(ProgressMonitorInput::Start(TaskName::Transcode, PROGRESS_STEPS));
+
+        let transcoder = self.transcoder.clone().with_profile(Self::profile());
+
+        // Progress is reported for every packet, so only advance for each whole step.
+        let progress_monitor = self.progress_monitor.clone();
+        let mut steps = 0;
+        let progress = |fraction: f64| {
+            let step = (fraction * PROGRESS_STEPS as f64) as usize;
+            while steps < step.min(PROGRESS_STEPS) {
+                progress_monitor.emit(ProgressMonitorInput::Advance);
+                steps += 1;
+            }
+        };
 
-            });
+        let transcoded = self.transcode(&transcoder, visual, progress, sender);
 
         self.progress_monitor.emit(ProgressMonitorInput::Complete);
 
-        let _ = sender.output(VideoTranscodeOutput::Completed);
+        let mut keep = self.transcoded.clone();
+        keep.extend(transcoded.iter().cloned());
+        self.trim(&keep);
+
+        let _ = sender.output(VideoTranscodeOutput::Completed(usize::from(transcoded.is_some())));
+
+        // Carry on with the videos found by All.
+        if !self.pending.is_empty() {
+            self.progress_monitor
+                .emit(ProgressMonitorInput::Start(TaskName::Transcode, self.pending.len()));
+        }
 
         Ok(())
     }
+
+    /// Transcode a video and record the transcoded path. Returns the transcoded path.
+    fn transcode(
+        &mut self,
+        transcoder: &Transcoder,
+        visual: &Visual,
+        progress: impl FnMut(f64),
+        sender: &ComponentSender<Self>,
+    ) -> Option<PathBuf> {
+        let video_id = visual.video_id?;
+        let video_path = visual.video_path.as_ref()?;
+
+        let result = transcoder.transcode(video_id, video_path, progress)
+            .with_context(|| format!("Video path: {:?}", video_path));
+
+        match result {
+            std::result::Result::Ok(transcode_path) => {
+                if let Err(e) = self.repo.add_transcode(video_id, &transcode_path) {
+                    error!("Failed adding transcode path: {:?}", e);
+                }
+                let _ = sender.output(VideoTranscodeOutput::Transcoded(visual.visual_id.clone(), transcode_path.clone()));
+                Some(transcode_path)
+            },
+            Err(e) => {
+                error!("Failed transcoding: {:?}", e);
+                None
+            },
+        }
+    }
+
+    /// Remove the transcoded videos played longest ago to keep within the budget in preferences.
+    /// Videos to keep are never removed.
+    fn trim(&mut self, keep: &[PathBuf]) {
+        let Some(budget) = Self::budget() else {
+            return;
+        };
+
+        let removed = self.transcoder.trim(budget, keep);
+        for path in &removed {
+            if let Err(e) = self.repo.remove_transcode(path) {
+                error!("Failed forgetting removed transcode {:?}: {:?}", path, e);
+            }
+        }
+
+        info!("Removed {} transcoded videos to fit the cache budget", removed.len());
+    }
+
+    /// Bytes that transcoded videos must fit within, if there is a budget.
+    fn budget() -> Option<u64> {
+        let settings = gio::Settings::new(APP_ID);
+
+        // Budget is in megabytes, with zero meaning no budget.
+        let budget = u64::from(settings.uint("transcode-cache-budget")) * 1024 * 1024;
+        Some(budget).filter(|budget| *budget > 0)
+    }
+
+    /// Transcoding profile from preferences.
+    fn profile() -> Profile {
+        let settings = gio::Settings::new(APP_ID);
+        Profile::from_names(
+            &settings.string("transcode-codec"),
+            settings.uint("transcode-crf"),
+            settings.uint("transcode-max-edge"),
+            &settings.string("transcode-container"),
+        )
+    }
 }
 
 impl Worker for VideoTranscode {
@@ -107,7 +254,15 @@ impl Worker for VideoTranscode {
     type Output = VideoTranscodeOutput;
 
     fn init((state, repo, transcoder, progress_monitor): Self::Init, _sender: ComponentSender<Self>) -> Self {
-        Self { state, repo, transcoder, progress_monitor }
+        Self {
+            state,
+            repo,
+            transcoder,
+            progress_monitor,
+            is_transcoding_all: false,
+            pending: VecDeque::new(),
+            transcoded: Vec::new(),
+        }
     }
 
     fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
@@ -119,6 +274,18 @@ impl Worker for VideoTranscode {
                     error!("Failed to transcode photo: {}", e);
                 }
             },
+            VideoTranscodeInput::One(visual) => {
+                info!("Transcoding video {}", visual.visual_id);
+
+                if let Err(e) = self.transcode_one(&visual, &sender) {
+                    error!("Failed to transcode video: {}", e);
+                }
+            },
+            VideoTranscodeInput::Next => {
+                if let Err(e) = self.transcode_next(&sender) {
+                    error!("Failed to transcode video: {}", e);
+                }
+            },
         };
     }
 }
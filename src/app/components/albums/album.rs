@@ -5,23 +5,29 @@
 use gtk::prelude::OrientableExt;
 use fotema_core::VisualId;
 use fotema_core::YearMonth;
+use fotema_core::visual::burst;
 use fotema_core::visual::model::PictureOrientation;
 use strum::IntoEnumIterator;
 use relm4::gtk;
 use relm4::gtk::gdk;
+use relm4::gtk::gio;
 use relm4::gtk::prelude::*;
 use relm4::gtk::gdk_pixbuf;
 use relm4::typed_view::grid::{RelmGridItem, TypedGridView};
+use relm4::typed_view::list::TypedListView;
 use relm4::*;
 use relm4::binding::*;
 use std::path::Path;
 use std::sync::Arc;
 
+use crate::config::APP_ID;
 use crate::app::adaptive;
 use crate::app::SharedState;
 use crate::app::ActiveView;
 use crate::app::ViewName;
 use super::album_filter::AlbumFilter;
+use super::hover_preview::HoverPreview;
+use super::justified::{self, JustifiedRow};
 
 use tracing::{event, Level, info};
 
@@ -40,6 +46,9 @@ pub enum AlbumInput {
     /// User has selected photo in grid view
     Selected(u32), // Index into a Vec
 
+    /// User has selected photo in justified layout
+    SelectedVisual(VisualId),
+
     // Scroll to first photo of year/month.
     GoToMonth(YearMonth),
 
@@ -51,6 +60,12 @@ pub enum AlbumInput {
 
     // Adapt to layout
     Adapt(adaptive::Layout),
+
+    // Preference for grid or justified layout has changed
+    LayoutChanged,
+
+    // Width available to justified rows has changed
+    Resized(i32),
 }
 
 #[derive(Debug)]
@@ -65,6 +80,12 @@ struct PhotoGridItem {
 
     // Length of thumbnail edge to allow for resizing when layout changes.
     edge_length: I32Binding,
+
+    // Number of photos in the burst this item stands for, or zero if it isn't a burst.
+    burst_size: usize,
+
+    // Is this a photo of a burst that is stacked under another photo?
+    is_stacked: bool,
 }
 
 struct PhotoGridItemWidgets {
@@ -73,6 +94,9 @@ struct PhotoGridItemWidgets {
     motion_type_icon: gtk::Image,
     duration_overlay: gtk::Frame,
     duration_label: gtk::Label,
+    burst_overlay: gtk::Frame,
+    burst_label: gtk::Label,
+    hover_preview: HoverPreview,
 
     // If the gtk::Picture has been bound to edge_length.
     is_bound: bool,
@@ -86,6 +110,7 @@ impl RelmGridItem for PhotoGridItem {
         relm4::view! {
             root = gtk::AspectFrame {
                 gtk::Frame {
+                    #[name(overlay)]
                     gtk::Overlay {
                         #[name(status_overlay)]
                         add_overlay =  &gtk::Frame {
@@ -117,10 +142,35 @@ impl RelmGridItem for PhotoGridItem {
                             },
                         },
 
+                        #[name(burst_overlay)]
+                        add_overlay =  &gtk::Frame {
+                            set_halign: gtk::Align::End,
+                            set_valign: gtk::Align::Start,
+                            set_margin_all: 8,
+                            add_css_class: "photo-grid-photo-status-frame",
+
+                            #[wrap(Some)]
+                            set_child = &gtk::Box {
+                                set_orientation: gtk::Orientation::Horizontal,
+                                set_spacing: 4,
+
+                                gtk::Image {
+                                    set_icon_name: Some("edit-copy-symbolic"),
+                                    add_css_class: "photo-grid-photo-status-label",
+                                },
+
+                                #[name(burst_label)]
+                                gtk::Label {
+                                    add_css_class: "photo-grid-photo-status-label",
+                                },
+                            },
+                        },
+
                         #[wrap(Some)]
                         #[name(picture)]
                         set_child = &gtk::Picture {
                             set_can_shrink: true,
+                            set_content_fit: gtk::ContentFit::Cover,
                             set_width_request: NARROW_EDGE_LENGTH,
                             set_height_request: NARROW_EDGE_LENGTH,
                         }
@@ -129,12 +179,24 @@ impl RelmGridItem for PhotoGridItem {
             }
         }
 
+        // Preview plays over the thumbnail, so re-add the status overlays to keep them on top.
+        let hover_preview = HoverPreview::new(&overlay);
+        overlay.remove_overlay(&status_overlay);
+        overlay.add_overlay(&status_overlay);
+        overlay.remove_overlay(&duration_overlay);
+        overlay.add_overlay(&duration_overlay);
+        overlay.remove_overlay(&burst_overlay);
+        overlay.add_overlay(&burst_overlay);
+
         let widgets = PhotoGridItemWidgets {
             picture,
             status_overlay,
             motion_type_icon,
             duration_overlay,
             duration_label,
+            burst_overlay,
+            burst_label,
+            hover_preview,
             is_bound: false,
         };
 
@@ -156,8 +218,12 @@ impl RelmGridItem for PhotoGridItem {
             widgets.is_bound = true;
         }
 
-        if self.visual.thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
-            widgets.picture.set_filename(self.visual.thumbnail_path.clone());
+        // Use a thumbnail large enough for the cell on high resolution displays.
+        let pixels = self.edge_length.value() * widgets.picture.scale_factor();
+        let thumbnail_path = self.visual.thumbnail_path_for(pixels as u32);
+
+        if thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
+            widgets.picture.set_filename(thumbnail_path);
 
             // Add CSS class for orientation
             let orientation = self.visual.thumbnail_orientation();
@@ -190,12 +256,21 @@ impl RelmGridItem for PhotoGridItem {
             widgets.status_overlay.set_visible(true);
             widgets.duration_overlay.set_visible(false);
             widgets.motion_type_icon.set_icon_name(Some("play-symbolic"));
+        } else if self.visual.has_voice_note() {
+            widgets.status_overlay.set_visible(true);
+            widgets.duration_overlay.set_visible(false);
+            widgets.motion_type_icon.set_icon_name(Some("audio-input-microphone-symbolic"));
         } else { // is_photo_only()
             widgets.status_overlay.set_visible(false);
             widgets.motion_type_icon.set_icon_name(None);
             widgets.duration_overlay.set_visible(false);
             widgets.duration_label.set_label("");
         }
+
+        widgets.burst_overlay.set_visible(self.burst_size > 1);
+        widgets.burst_label.set_label(&self.burst_size.to_string());
+
+        widgets.hover_preview.set_visual(Some(&self.visual));
     }
 
     fn unbind(&mut self, widgets: &mut Self::Widgets, _root: &mut Self::Root) {
@@ -204,6 +279,8 @@ impl RelmGridItem for PhotoGridItem {
         widgets.status_overlay.set_visible(false);
         widgets.duration_overlay.set_visible(false);
         widgets.duration_label.set_label("");
+        widgets.burst_overlay.set_visible(false);
+        widgets.hover_preview.set_visual(None);
 
         // clear orientation transformation css classes
         for orient in PictureOrientation::iter() {
@@ -219,6 +296,20 @@ pub struct Album {
     photo_grid: TypedGridView<PhotoGridItem, gtk::SingleSelection>,
     filter: AlbumFilter,
     edge_length: I32Binding,
+
+    // Rows of items at their own aspect ratio, for the justified layout.
+    justified_rows: TypedListView<JustifiedRow, gtk::NoSelection>,
+
+    // Whether items are laid out in justified rows rather than as a grid of squares.
+    is_justified: bool,
+
+    // Width available to justified rows. Zero until the rows have been shown.
+    row_width: i32,
+
+    // Kept to be notified of changes to the layout preference.
+    settings: gio::Settings,
+
+    input_sender: relm4::Sender<AlbumInput>,
 }
 
 #[relm4::component(pub)]
@@ -228,18 +319,36 @@ impl SimpleComponent for Album {
     type Output = AlbumOutput;
 
     view! {
-        gtk::ScrolledWindow {
+        gtk::Stack {
             set_vexpand: true,
 
-            #[local_ref]
-            grid_view -> gtk::GridView {
-                set_orientation: gtk::Orientation::Vertical,
-                set_single_click_activate: true,
+            #[watch]
+            set_visible_child_name: if model.is_justified { "justified" } else { "grid" },
 
-                connect_activate[sender] => move |_, idx| {
-                    sender.input(AlbumInput::Selected(idx))
-                },
-            }
+            add_named[Some("grid")] = &gtk::ScrolledWindow {
+                set_vexpand: true,
+
+                #[local_ref]
+                grid_view -> gtk::GridView {
+                    set_orientation: gtk::Orientation::Vertical,
+                    set_single_click_activate: true,
+
+                    connect_activate[sender] => move |_, idx| {
+                        sender.input(AlbumInput::Selected(idx))
+                    },
+                }
+            },
+
+            #[name(justified_window)]
+            add_named[Some("justified")] = &gtk::ScrolledWindow {
+                set_vexpand: true,
+                set_hscrollbar_policy: gtk::PolicyType::Never,
+
+                #[local_ref]
+                justified_view -> gtk::ListView {
+                    add_css_class: "justified-album",
+                }
+            },
         }
     }
 
@@ -251,6 +360,19 @@ impl SimpleComponent for Album {
         let photo_grid = TypedGridView::new();
         let grid_view = &photo_grid.view.clone();
 
+        let justified_rows = TypedListView::new();
+        let justified_view = &justified_rows.view.clone();
+
+        let settings = gio::Settings::new(APP_ID);
+        let is_justified = settings.boolean("justified-layout");
+
+        {
+            let sender = sender.clone();
+            settings.connect_changed(Some("justified-layout"), move |_, _| {
+                sender.input(AlbumInput::LayoutChanged);
+            });
+        }
+
         let mut model = Album {
             state,
             active_view,
@@ -258,11 +380,22 @@ impl SimpleComponent for Album {
             photo_grid,
             filter,
             edge_length: I32Binding::new(NARROW_EDGE_LENGTH),
+            justified_rows,
+            is_justified,
+            row_width: 0,
+            settings,
+            input_sender: sender.input_sender().clone(),
         };
 
         model.update_filter();
 
         let widgets = view_output!();
+
+        // The horizontal page size is the width of the list of rows.
+        widgets.justified_window.hadjustment().connect_page_size_notify(move |adjustment| {
+            sender.input(AlbumInput::Resized(adjustment.page_size() as i32));
+        });
+
         ComponentParts { model, widgets }
     }
 
@@ -281,11 +414,19 @@ impl SimpleComponent for Album {
                 } else {
                     info!("{:?} view is inactive so clearing", self.view_name);
                     self.photo_grid.clear();
+                    self.justified_rows.clear();
                 }
             }
             AlbumInput::Filter(filter) => {
                 self.filter = filter;
-                self.update_filter();
+
+                // Bursts depend on which items match, so the items are rebuilt.
+                if *self.active_view.read() == self.view_name {
+                    self.refresh();
+                } else {
+                    self.photo_grid.clear();
+                    self.justified_rows.clear();
+                }
             }
             AlbumInput::Selected(index) => {
                 // Photos are filters so must use get_visible(...) over get(...), otherwise
@@ -296,6 +437,17 @@ impl SimpleComponent for Album {
                     let _ = sender.output(AlbumOutput::Selected(visual_id, self.filter.clone()));
                 }
             }
+            AlbumInput::SelectedVisual(visual_id) => {
+                let _ = sender.output(AlbumOutput::Selected(visual_id, self.filter.clone()));
+            }
+            AlbumInput::GoToMonth(ym) if self.is_justified => {
+                event!(Level::INFO, "Showing for month: {}", ym);
+                let index_opt = self.justified_rows
+                    .find(|row| row.cells.iter().any(|(visual, _, _)| visual.year_month() == ym));
+                if let Some(index) = index_opt {
+                    self.justified_rows.view.scroll_to(index, gtk::ListScrollFlags::NONE, None);
+                }
+            },
             AlbumInput::GoToMonth(ym) => {
                 event!(Level::INFO, "Showing for month: {}", ym);
                 let index_opt = self.photo_grid.find(|p| p.visual.year_month() == ym);
@@ -308,9 +460,22 @@ impl SimpleComponent for Album {
             },
             AlbumInput::Adapt(adaptive::Layout::Narrow) => {
                 self.edge_length.set_value(NARROW_EDGE_LENGTH);
+                self.update_rows();
             },
             AlbumInput::Adapt(adaptive::Layout::Wide) => {
                 self.edge_length.set_value(WIDE_EDGE_LENGTH);
+                self.update_rows();
+            },
+            AlbumInput::LayoutChanged => {
+                self.is_justified = self.settings.boolean("justified-layout");
+                info!("{:?} view layout is justified: {}", self.view_name, self.is_justified);
+                self.update_rows();
+            },
+            AlbumInput::Resized(width) => {
+                if width != self.row_width {
+                    self.row_width = width;
+                    self.update_rows();
+                }
             },
         }
     }
@@ -319,16 +484,15 @@ impl SimpleComponent for Album {
 impl Album {
 
     fn refresh(&mut self) {
-        let all = {
-            let data = self.state.read();
-            data
-                .iter()
-                .map(|visual| PhotoGridItem {
-                    visual: visual.clone(),
-                    edge_length: self.edge_length.clone(),
-                })
-                .collect::<Vec<PhotoGridItem>>()
-        };
+        let all = self.filtered()
+            .into_iter()
+            .map(|(visual, burst_size)| PhotoGridItem {
+                visual,
+                edge_length: self.edge_length.clone(),
+                burst_size: burst_size.unwrap_or(0),
+                is_stacked: burst_size.is_none(),
+            })
+            .collect::<Vec<PhotoGridItem>>();
 
         self.photo_grid.clear();
 
@@ -350,6 +514,68 @@ impl Album {
 
             self.enable_filters();
         }
+
+        self.update_rows();
+
+        if !self.justified_rows.is_empty() {
+            self.justified_rows.view.scroll_to(
+                self.justified_rows.len() - 1,
+                gtk::ListScrollFlags::NONE,
+                None,
+            );
+        }
+    }
+
+    /// Lay out filtered items in rows to fit the available width.
+    /// The grid handles its own layout so only needs filtering.
+    fn update_rows(&mut self) {
+        self.justified_rows.clear();
+
+        // Don't lay out rows until they are shown, so that the width is known.
+        if !self.is_justified || self.row_width <= 0 {
+            return;
+        }
+
+        let (visuals, burst_sizes): (Vec<Arc<fotema_core::visual::Visual>>, Vec<usize>) = self.filtered()
+            .into_iter()
+            .filter_map(|(visual, burst_size)| Some((visual, burst_size?)))
+            .unzip();
+
+        // Items without a recorded size are shown as squares until their thumbnails
+        // are next generated.
+        let aspect_ratios: Vec<f64> = visuals
+            .iter()
+            .map(|visual| visual.aspect_ratio().unwrap_or(1.0))
+            .collect();
+
+        let rows = justified::layout(&aspect_ratios, self.row_width, self.edge_length.value())
+            .into_iter()
+            .map(|row| JustifiedRow {
+                cells: visuals[row.start..]
+                    .iter()
+                    .cloned()
+                    .zip(row.widths)
+                    .zip(&burst_sizes[row.start..])
+                    .map(|((visual, width), burst_size)| (visual, width, *burst_size))
+                    .collect(),
+                height: row.height,
+                sender: self.input_sender.clone(),
+            });
+
+        self.justified_rows.extend_from_iter(rows);
+    }
+
+    /// Items matching the filter, with the burst sizes of [`burst_sizes`]. Bursts are
+    /// found among the matching items, so that a burst isn't hidden because the photo
+    /// it is stacked under doesn't match.
+    fn filtered(&self) -> Vec<(Arc<fotema_core::visual::Visual>, Option<usize>)> {
+        let visuals: Vec<Arc<fotema_core::visual::Visual>> = self.state.read()
+            .iter()
+            .filter(|visual| self.filter.clone().filter(visual))
+            .cloned()
+            .collect();
+        let burst_sizes = burst_sizes(&visuals);
+        visuals.into_iter().zip(burst_sizes).collect()
     }
 
     fn disable_filters(&mut self) {
@@ -364,9 +590,23 @@ impl Album {
         }
     }
 
+    /// Only matching items are in the grid, so just hide photos stacked under others of their burst.
     fn update_filter(&mut self) {
         self.photo_grid.clear_filters();
-        let filter = self.filter.clone();
-        self.photo_grid.add_filter(move |item| filter.clone().filter(&item.visual));
+        self.photo_grid.add_filter(|item| !item.is_stacked);
+    }
+}
+
+/// For each item, the number of photos in the burst it stands for, which is zero if it
+/// isn't a burst, or None if it is stacked under another photo of its burst.
+fn burst_sizes(visuals: &[Arc<fotema_core::visual::Visual>]) -> Vec<Option<usize>> {
+    let mut sizes = vec![Some(0); visuals.len()];
+    for stack in burst::stacks(visuals) {
+        let cover = burst::cover(visuals, &stack);
+        for index in stack.clone() {
+            sizes[index] = None;
+        }
+        sizes[cover] = Some(stack.len());
     }
+    sizes
 }
@@ -23,6 +23,18 @@ pub enum AlbumFilter {
     // Show only selfies
     Selfies,
 
+    // Show only portraits with a depth map
+    Portraits,
+
+    // Show only HDR photos
+    Hdr,
+
+    // Show only panoramas and photo spheres
+    Panoramas,
+
+    // Show only screenshots
+    Screenshots,
+
     // Show only videos
     Videos,
 
@@ -45,6 +57,10 @@ impl AlbumFilter {
             AlbumFilter::Folder(path) => v.parent_path == path,
             AlbumFilter::Motion => v.is_motion_photo(),
             AlbumFilter::Selfies => v.is_selfie(),
+            AlbumFilter::Portraits => v.is_portrait(),
+            AlbumFilter::Hdr => v.is_hdr(),
+            AlbumFilter::Panoramas => v.is_panorama(),
+            AlbumFilter::Screenshots => v.is_screenshot(),
             AlbumFilter::Videos => v.is_video_only() && !v.is_motion_photo(),
             AlbumFilter::GeographicArea(cell_index) => {
                 if let Some(location) = v.location {
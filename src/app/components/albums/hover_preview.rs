@@ -0,0 +1,93 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use fotema_core::Visual;
+use relm4::gtk;
+use relm4::gtk::gio;
+use relm4::gtk::prelude::*;
+use std::cell::RefCell;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+use crate::config::APP_ID;
+
+/// Plays the preview clip of a video or motion photo over its thumbnail
+/// while the pointer is over the thumbnail.
+#[derive(Clone)]
+pub struct HoverPreview {
+    picture: gtk::Picture,
+
+    // Preview of the item currently shown by the overlay.
+    path: Rc<RefCell<Option<PathBuf>>>,
+}
+
+impl HoverPreview {
+    /// Adds a hidden preview to the overlay. Overlays added afterwards are drawn
+    /// over the preview.
+    pub fn new(overlay: &gtk::Overlay) -> Self {
+        let picture = gtk::Picture::new();
+        picture.set_can_shrink(true);
+        picture.set_content_fit(gtk::ContentFit::Cover);
+        picture.set_can_target(false);
+        picture.set_visible(false);
+        overlay.add_overlay(&picture);
+
+        let preview = Self {
+            picture,
+            path: Rc::new(RefCell::new(None)),
+        };
+
+        let motion = gtk::EventControllerMotion::new();
+        {
+            let preview = preview.clone();
+            motion.connect_enter(move |_, _, _| preview.play());
+        }
+        {
+            let preview = preview.clone();
+            motion.connect_leave(move |_| preview.stop());
+        }
+        overlay.add_controller(motion);
+
+        preview
+    }
+
+    /// Sets the item to preview, or none for an item without one.
+    pub fn set_visual(&self, visual: Option<&Visual>) {
+        self.stop();
+        *self.path.borrow_mut() = visual.and_then(|v| v.preview_path.clone());
+    }
+
+    fn play(&self) {
+        let settings = gio::Settings::new(APP_ID);
+        if !settings.boolean("hover-previews") {
+            return;
+        }
+
+        // Previews are generated in the background, so might not exist yet.
+        let path = self.path.borrow();
+        let Some(path) = path.as_ref().filter(|p| p.exists()) else {
+            return;
+        };
+
+        let media_file = gtk::MediaFile::for_filename(path);
+        media_file.set_muted(true);
+        media_file.set_loop(true);
+        media_file.play();
+
+        self.picture.set_paintable(Some(&media_file));
+        self.picture.set_visible(true);
+    }
+
+    fn stop(&self) {
+        if let Some(media_stream) = self
+            .picture
+            .paintable()
+            .and_then(|p| p.downcast::<gtk::MediaStream>().ok())
+        {
+            media_stream.pause();
+        }
+        self.picture.set_paintable(None::<&gtk::gdk::Paintable>);
+        self.picture.set_visible(false);
+    }
+}
@@ -0,0 +1,271 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+//! Justified layout of an album, where items are shown at their own aspect ratio
+//! in rows that fill the width of the album.
+
+use fotema_core::Visual;
+use relm4::gtk;
+use relm4::gtk::gdk;
+use relm4::gtk::gdk_pixbuf;
+use relm4::gtk::prelude::*;
+use relm4::typed_view::list::RelmListItem;
+use std::sync::Arc;
+
+use crate::app::components::orientation;
+use super::album::AlbumInput;
+use super::hover_preview::HoverPreview;
+
+/// Space in pixels between items in a row, and between rows.
+pub const SPACING: i32 = 4;
+
+/// Narrowest and widest aspect ratios to lay out, so that very tall or very wide
+/// items don't take up a whole row.
+const MIN_ASPECT_RATIO: f64 = 0.5;
+const MAX_ASPECT_RATIO: f64 = 3.0;
+
+/// Layout of one row of items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowLayout {
+    /// Index of first item in row.
+    pub start: usize,
+
+    /// Width of each item in row.
+    pub widths: Vec<i32>,
+
+    /// Height of every item in row.
+    pub height: i32,
+}
+
+/// Lay out items with the given aspect ratios into rows that exactly fill a width.
+/// Rows are near the target height. The last row is left at the target height
+/// rather than being stretched to fill the width.
+pub fn layout(aspect_ratios: &[f64], width: i32, target_height: i32) -> Vec<RowLayout> {
+    let aspect_ratios: Vec<f64> = aspect_ratios
+        .iter()
+        .map(|ratio| ratio.clamp(MIN_ASPECT_RATIO, MAX_ASPECT_RATIO))
+        .collect();
+
+    let mut rows = Vec::new();
+    let mut start = 0;
+    let mut ratio_sum = 0.0;
+
+    for (index, ratio) in aspect_ratios.iter().enumerate() {
+        ratio_sum += ratio;
+
+        let count = (index - start + 1) as i32;
+        let available = (width - SPACING * (count - 1)).max(count);
+
+        // Row is full once the items at the target height are at least as wide as the row.
+        if ratio_sum * target_height as f64 >= available as f64 {
+            let height = ((available as f64 / ratio_sum).round() as i32).max(1);
+            let mut widths = widths(&aspect_ratios[start..=index], height);
+
+            // Rounding leaves the row a few pixels short or over, so take up the
+            // difference in the last item.
+            let others: i32 = widths[..widths.len() - 1].iter().sum();
+            if let Some(last) = widths.last_mut() {
+                *last = (available - others).max(1);
+            }
+
+            rows.push(RowLayout { start, widths, height });
+            start = index + 1;
+            ratio_sum = 0.0;
+        }
+    }
+
+    if start < aspect_ratios.len() {
+        rows.push(RowLayout {
+            start,
+            widths: widths(&aspect_ratios[start..], target_height),
+            height: target_height,
+        });
+    }
+
+    rows
+}
+
+fn widths(aspect_ratios: &[f64], height: i32) -> Vec<i32> {
+    aspect_ratios
+        .iter()
+        .map(|ratio| ((ratio * height as f64).round() as i32).max(1))
+        .collect()
+}
+
+/// A row of items in the justified layout.
+#[derive(Debug)]
+pub struct JustifiedRow {
+    /// Items in the row, their widths, and the number of photos in the burst each
+    /// stands for, which is zero if it isn't a burst.
+    pub cells: Vec<(Arc<Visual>, i32, usize)>,
+
+    /// Height of every item in the row.
+    pub height: i32,
+
+    /// Album to tell when an item is selected.
+    pub sender: relm4::Sender<AlbumInput>,
+}
+
+pub struct JustifiedRowWidgets {
+    row: gtk::Box,
+}
+
+impl RelmListItem for JustifiedRow {
+    type Root = gtk::Box;
+    type Widgets = JustifiedRowWidgets;
+
+    fn setup(_item: &gtk::ListItem) -> (Self::Root, Self::Widgets) {
+        relm4::view! {
+            root = gtk::Box {
+                set_orientation: gtk::Orientation::Horizontal,
+                set_spacing: SPACING,
+                set_margin_bottom: SPACING,
+                set_halign: gtk::Align::Start,
+            }
+        }
+
+        let widgets = JustifiedRowWidgets { row: root.clone() };
+
+        (root, widgets)
+    }
+
+    fn bind(&mut self, widgets: &mut Self::Widgets, _root: &mut Self::Root) {
+        for (visual, width, burst_size) in &self.cells {
+            let cell = cell(visual, *width, self.height, *burst_size, &self.sender);
+            widgets.row.append(&cell);
+        }
+    }
+
+    fn unbind(&mut self, widgets: &mut Self::Widgets, _root: &mut Self::Root) {
+        while let Some(child) = widgets.row.first_child() {
+            widgets.row.remove(&child);
+        }
+    }
+}
+
+/// Widget showing the thumbnail of one item at the given size.
+fn cell(
+    visual: &Arc<Visual>,
+    width: i32,
+    height: i32,
+    burst_size: usize,
+    sender: &relm4::Sender<AlbumInput>,
+) -> gtk::Overlay {
+    let overlay = gtk::Overlay::new();
+    overlay.set_size_request(width, height);
+    overlay.set_overflow(gtk::Overflow::Hidden);
+
+    // Thumbnails are stored unoriented, so size the picture before orientation
+    // and let the transformation turn it to fill the cell.
+    let orientation = visual.thumbnail_orientation();
+    let (picture_width, picture_height) = if orientation.is_sideways() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+
+    let picture = gtk::Picture::new();
+    picture.set_can_shrink(true);
+    picture.set_content_fit(gtk::ContentFit::Cover);
+    picture.set_size_request(picture_width, picture_height);
+
+    // Use a thumbnail large enough for the cell on high resolution displays.
+    let pixels = width.max(height) * overlay.scale_factor();
+    let thumbnail_path = visual.thumbnail_path_for(pixels as u32);
+
+    if thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
+        picture.set_filename(thumbnail_path);
+    } else {
+        let pb = gdk_pixbuf::Pixbuf::from_resource_at_scale(
+            "/app/fotema/Fotema/icons/scalable/actions/image-missing-symbolic.svg",
+            200, 200, true
+        ).unwrap();
+        let img = gdk::Texture::for_pixbuf(&pb);
+        picture.set_paintable(Some(&img));
+    }
+
+    let fixed = gtk::Fixed::new();
+    fixed.put(&picture, 0.0, 0.0);
+    let transform = orientation::transform(orientation, picture_width as f32, picture_height as f32);
+    fixed.set_child_transform(&picture, Some(&transform));
+    overlay.set_child(Some(&fixed));
+
+    // Add before the status so the status is drawn over the preview.
+    HoverPreview::new(&overlay).set_visual(Some(visual.as_ref()));
+
+    if let Some(status) = status(visual) {
+        overlay.add_overlay(&status);
+    }
+
+    if burst_size > 1 {
+        overlay.add_overlay(&burst_badge(burst_size));
+    }
+
+    let click = gtk::GestureClick::new();
+    {
+        let sender = sender.clone();
+        let visual_id = visual.visual_id.clone();
+        click.connect_released(move |_, _, _, _| {
+            sender.emit(AlbumInput::SelectedVisual(visual_id.clone()));
+        });
+    }
+    overlay.add_controller(click);
+
+    overlay
+}
+
+/// Status shown over thumbnails of motion photos, videos, and photos with voice notes,
+/// as in the grid layout.
+fn status(visual: &Visual) -> Option<gtk::Frame> {
+    let child: gtk::Widget = if visual.is_motion_photo() {
+        gtk::Image::from_icon_name("cd-symbolic").upcast()
+    } else if visual.is_video_only() && visual.video_duration.is_some() {
+        let hhmmss = visual
+            .video_duration
+            .map(|ref x| fotema_core::time::format_hhmmss(x))
+            .unwrap_or(String::from("—"));
+        gtk::Label::new(Some(&hhmmss)).upcast()
+    } else if visual.is_video_only() {
+        gtk::Image::from_icon_name("play-symbolic").upcast()
+    } else if visual.has_voice_note() {
+        gtk::Image::from_icon_name("audio-input-microphone-symbolic").upcast()
+    } else {
+        return None;
+    };
+
+    child.add_css_class("photo-grid-photo-status-label");
+
+    let frame = gtk::Frame::new(None);
+    frame.set_halign(gtk::Align::End);
+    frame.set_valign(gtk::Align::End);
+    frame.set_margin_end(8);
+    frame.set_margin_bottom(8);
+    frame.add_css_class("photo-grid-photo-status-frame");
+    frame.set_child(Some(&child));
+
+    Some(frame)
+}
+
+/// Count of photos in a burst, shown over the thumbnail of the photo the burst is stacked under.
+fn burst_badge(burst_size: usize) -> gtk::Frame {
+    let icon = gtk::Image::from_icon_name("edit-copy-symbolic");
+    icon.add_css_class("photo-grid-photo-status-label");
+
+    let label = gtk::Label::new(Some(&burst_size.to_string()));
+    label.add_css_class("photo-grid-photo-status-label");
+
+    let content = gtk::Box::new(gtk::Orientation::Horizontal, 4);
+    content.append(&icon);
+    content.append(&label);
+
+    let frame = gtk::Frame::new(None);
+    frame.set_halign(gtk::Align::End);
+    frame.set_valign(gtk::Align::Start);
+    frame.set_margin_end(8);
+    frame.set_margin_top(8);
+    frame.add_css_class("photo-grid-photo-status-frame");
+    frame.set_child(Some(&content));
+
+    frame
+}
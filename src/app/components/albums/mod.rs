@@ -5,6 +5,8 @@
 pub mod album;
 pub mod album_filter;
 pub mod folders_album;
+pub mod hover_preview;
+pub mod justified;
 pub mod months_album;
 pub mod places_album;
 pub mod years_album;
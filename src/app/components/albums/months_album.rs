@@ -99,6 +99,7 @@ impl RelmGridItem for PhotoGridItem {
                         #[name(picture)]
                         set_child = &gtk::Picture {
                             set_can_shrink: true,
+                            set_content_fit: gtk::ContentFit::Cover,
                             set_width_request: NARROW_EDGE_LENGTH,
                             set_height_request: NARROW_EDGE_LENGTH,
                         }
@@ -136,10 +137,14 @@ impl RelmGridItem for PhotoGridItem {
                 year = ym.year.to_string()) // Should we convert to string?
             );
 
-        if self.picture.thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
+        // Use a thumbnail large enough for the cell on high resolution displays.
+        let pixels = self.edge_length.value() * widgets.picture.scale_factor();
+        let thumbnail_path = self.picture.thumbnail_path_for(pixels as u32);
+
+        if thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
             widgets
                 .picture
-                .set_filename(self.picture.thumbnail_path.clone());
+                .set_filename(thumbnail_path);
 
             // Add CSS class for orientation
             let orientation = self.picture.thumbnail_orientation();
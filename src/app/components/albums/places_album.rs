@@ -21,6 +21,8 @@ use crate::app::SharedState;
 use crate::app::ActiveView;
 use crate::app::ViewName;
 use fotema_core::{Visual, VisualId};
+use fotema_core::photo::Focus;
+use fotema_core::thumbnail::Shape;
 
 use h3o;
 use h3o::CellIndex;
@@ -387,10 +389,35 @@ impl PlacesAlbum {
         self.need_refresh = false;
     }
 
+    /// Crop an aspect-preserving thumbnail to a square about its focus, so that
+    /// pins show the same part of a photo as square thumbnails.
+    fn square_about_focus(path: &std::path::Path, focus: Focus) -> Option<gdk::Texture> {
+        let pb = gdk_pixbuf::Pixbuf::from_file(path)
+            .inspect_err(|e| error!("Failed loading pin thumbnail: {:?}", e))
+            .ok()?;
+
+        let (width, height) = (pb.width(), pb.height());
+        let edge = width.min(height);
+        let (cx, cy) = focus.centering((width as u32, height as u32), (1, 1));
+        let x = ((width - edge) as f64 * cx).round() as i32;
+        let y = ((height - edge) as f64 * cy).round() as i32;
+
+        let square = pb.new_subpixbuf(x, y, edge, edge);
+        Some(gdk::Texture::for_pixbuf(&square))
+    }
+
     /// Make thumbnail to put onto map
     fn to_pin_thumbnail(&self, visual: &Visual, count: Option<usize>, sender: &ComponentSender<PlacesAlbum>) -> gtk::Frame {
         let picture = if visual.thumbnail_path.as_ref().is_some_and(|x| x.exists()) {
-            let picture = gtk::Image::from_file(visual.thumbnail_path.as_ref().expect("Must have path"));
+            let path = visual.thumbnail_path.as_ref().expect("Must have path");
+            let picture = if Shape::of(path) == Shape::Aspect {
+                let focus = visual.thumbnail_focus.unwrap_or_default();
+                Self::square_about_focus(path, focus)
+                    .map(|img| gtk::Image::from_paintable(Some(&img)))
+                    .unwrap_or_else(|| gtk::Image::from_file(path))
+            } else {
+                gtk::Image::from_file(path)
+            };
 
             // Add CSS class for orientation
             let orientation = visual.thumbnail_orientation();
@@ -0,0 +1,209 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Choose how and where to export photos and videos.
+use relm4::{adw, gtk, ComponentParts, ComponentSender, SimpleComponent};
+use relm4::adw::prelude::*;
+use relm4::gtk::gio;
+
+use fotema_core::export::ExportMode;
+use fotema_core::Visual;
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::fl;
+
+use tracing::info;
+
+/// Position of export modes in the mode combo row.
+const MODE_ORIGINALS: u32 = 0;
+const MODE_JPEG: u32 = 1;
+const MODE_STRIP_METADATA: u32 = 2;
+
+#[derive(Debug)]
+pub enum ExportDialogInput {
+    /// Show dialog to export items.
+    Present(Vec<Arc<Visual>>),
+
+    /// Export mode combo row selection has changed.
+    Mode(u32),
+
+    MaxEdge(u32),
+
+    Quality(u8),
+
+    /// Ask user for folder to export into.
+    ChooseFolder,
+
+    /// Export folder has been chosen.
+    Export(PathBuf),
+}
+
+#[derive(Debug)]
+pub enum ExportDialogOutput {
+    /// Export items to folder.
+    Export(Vec<Arc<Visual>>, PathBuf, ExportMode),
+}
+
+pub struct ExportDialog {
+    parent: adw::ApplicationWindow,
+    dialog: adw::Dialog,
+
+    /// Items to export
+    items: Vec<Arc<Visual>>,
+
+    mode: u32,
+    max_edge: u32,
+    quality: u8,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for ExportDialog {
+    type Init = adw::ApplicationWindow;
+    type Input = ExportDialogInput;
+    type Output = ExportDialogOutput;
+
+    view! {
+        adw::Dialog {
+            set_title: &fl!("export-title"),
+            set_content_width: 420,
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_end = &gtk::Button {
+                        set_label: &fl!("export-button"),
+                        add_css_class: "suggested-action",
+                        connect_clicked => ExportDialogInput::ChooseFolder,
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &adw::PreferencesPage {
+                    add = &adw::PreferencesGroup {
+                        #[watch]
+                        set_description: Some(&fl!("export-title", "description", count = model.items.len())),
+
+                        adw::ComboRow {
+                            set_title: &fl!("export-mode"),
+                            set_model: Some(&gtk::StringList::new(&[
+                                fl!("export-mode", "originals").as_str(),
+                                fl!("export-mode", "jpeg").as_str(),
+                                fl!("export-mode", "strip-metadata").as_str(),
+                            ])),
+
+                            #[watch]
+                            set_subtitle: &Self::mode_description(model.mode),
+
+                            connect_selected_notify[sender] => move |row| {
+                                sender.input(ExportDialogInput::Mode(row.selected()));
+                            },
+                        },
+
+                        adw::SpinRow {
+                            set_title: &fl!("export-max-edge"),
+                            set_subtitle: &fl!("export-max-edge", "subtitle"),
+                            set_adjustment: Some(&gtk::Adjustment::new(2048.0, 256.0, 16384.0, 64.0, 512.0, 0.0)),
+                            set_digits: 0,
+
+                            #[watch]
+                            set_sensitive: model.mode == MODE_JPEG,
+
+                            connect_value_notify[sender] => move |row| {
+                                sender.input(ExportDialogInput::MaxEdge(row.value() as u32));
+                            },
+                        },
+
+                        adw::SpinRow {
+                            set_title: &fl!("export-quality"),
+                            set_subtitle: &fl!("export-quality", "subtitle"),
+                            set_adjustment: Some(&gtk::Adjustment::new(85.0, 1.0, 100.0, 1.0, 10.0, 0.0)),
+                            set_digits: 0,
+
+                            #[watch]
+                            set_sensitive: model.mode == MODE_JPEG,
+
+                            connect_value_notify[sender] => move |row| {
+                                sender.input(ExportDialogInput::Quality(row.value() as u8));
+                            },
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        parent: Self::Init,
+        dialog: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let model = Self {
+            parent,
+            dialog: dialog.clone(),
+            items: Vec::new(),
+            mode: MODE_ORIGINALS,
+            max_edge: 2048,
+            quality: 85,
+        };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            ExportDialogInput::Present(items) => {
+                self.items = items;
+                self.dialog.present(&self.parent);
+            },
+            ExportDialogInput::Mode(mode) => {
+                self.mode = mode;
+            },
+            ExportDialogInput::MaxEdge(max_edge) => {
+                self.max_edge = max_edge;
+            },
+            ExportDialogInput::Quality(quality) => {
+                self.quality = quality;
+            },
+            ExportDialogInput::ChooseFolder => {
+                let file_dialog = gtk::FileDialog::builder()
+                    .title(fl!("export-title"))
+                    .modal(true)
+                    .build();
+
+                file_dialog.select_folder(Some(&self.parent), None::<&gio::Cancellable>, move |result| {
+                    if let Some(path) = result.ok().and_then(|file| file.path()) {
+                        sender.input(ExportDialogInput::Export(path));
+                    }
+                });
+            },
+            ExportDialogInput::Export(target_dir) => {
+                let mode = match self.mode {
+                    MODE_JPEG => ExportMode::Jpeg { max_edge: self.max_edge, quality: self.quality },
+                    MODE_STRIP_METADATA => ExportMode::StripMetadata,
+                    _ => ExportMode::Originals,
+                };
+
+                info!("Exporting {} items to {:?}", self.items.len(), target_dir);
+
+                let items = std::mem::take(&mut self.items);
+                let _ = sender.output(ExportDialogOutput::Export(items, target_dir, mode));
+                self.dialog.close();
+            },
+        }
+    }
+}
+
+impl ExportDialog {
+    fn mode_description(mode: u32) -> String {
+        match mode {
+            MODE_JPEG => fl!("export-mode", "jpeg-description"),
+            MODE_STRIP_METADATA => fl!("export-mode", "strip-metadata-description"),
+            _ => fl!("export-mode", "originals-description"),
+        }
+    }
+}
@@ -0,0 +1,248 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+/// Geotag photos without GPS data from a GPX track recorded by a GPS logger.
+use relm4::{adw, gtk, ComponentParts, ComponentSender, SimpleComponent};
+use relm4::adw::prelude::*;
+use relm4::gtk::gio;
+
+use fotema_core::photo::geotag::{GeotagMatch, Geotagger, Track};
+use fotema_core::photo::model::Picture;
+use fotema_core::photo::Repository;
+
+use chrono::TimeDelta;
+use std::path::PathBuf;
+
+use crate::fl;
+
+use tracing::{error, info};
+
+/// Maximum number of rows to show in the preview list.
+const MAX_PREVIEW_ROWS: usize = 200;
+
+#[derive(Debug)]
+pub enum GeotagDialogInput {
+    /// Ask user for a GPX file and then show dialog.
+    Present,
+
+    /// GPX file has been chosen.
+    Load(PathBuf),
+
+    /// Camera clock offset in minutes has changed.
+    Offset(i64),
+
+    /// Write inferred locations to database.
+    Apply,
+}
+
+#[derive(Debug)]
+pub enum GeotagDialogOutput {
+    /// Photos have been tagged with a location.
+    Tagged(usize),
+}
+
+pub struct GeotagDialog {
+    parent: adw::ApplicationWindow,
+    dialog: adw::Dialog,
+
+    repo: Repository,
+
+    geotagger: Option<Geotagger>,
+
+    /// Photos that could be tagged, loaded when a track is chosen.
+    candidates: Vec<Picture>,
+
+    /// Photos that will be tagged when changes are applied.
+    matches: Vec<GeotagMatch>,
+
+    track_row: adw::ActionRow,
+    preview_group: adw::PreferencesGroup,
+    preview_list: gtk::ListBox,
+    apply_button: gtk::Button,
+}
+
+#[relm4::component(pub)]
+impl SimpleComponent for GeotagDialog {
+    type Init = (adw::ApplicationWindow, Repository);
+    type Input = GeotagDialogInput;
+    type Output = GeotagDialogOutput;
+
+    view! {
+        adw::Dialog {
+            set_title: &fl!("geotag-title"),
+            set_content_width: 480,
+            set_content_height: 600,
+
+            #[wrap(Some)]
+            set_child = &adw::ToolbarView {
+                add_top_bar = &adw::HeaderBar {
+                    pack_end = &apply_button.clone() -> gtk::Button {
+                        set_label: &fl!("geotag-apply"),
+                        add_css_class: "suggested-action",
+                        connect_clicked => GeotagDialogInput::Apply,
+                    },
+                },
+
+                #[wrap(Some)]
+                set_content = &adw::PreferencesPage {
+                    add = &adw::PreferencesGroup {
+                        #[local_ref]
+                        track_row -> adw::ActionRow {
+                            set_title: &fl!("geotag-track"),
+                            add_css_class: "property",
+                        },
+
+                        adw::SpinRow {
+                            set_title: &fl!("geotag-camera-offset"),
+                            set_subtitle: &fl!("geotag-camera-offset", "subtitle"),
+                            set_adjustment: Some(&gtk::Adjustment::new(0.0, -1440.0, 1440.0, 1.0, 60.0, 0.0)),
+                            set_digits: 0,
+
+                            connect_value_notify[sender] => move |row| {
+                                sender.input(GeotagDialogInput::Offset(row.value() as i64));
+                            },
+                        },
+                    },
+
+                    #[local_ref]
+                    add = &preview_group -> adw::PreferencesGroup {
+                        set_title: &fl!("geotag-preview"),
+
+                        #[local_ref]
+                        preview_list -> gtk::ListBox {
+                            add_css_class: "boxed-list",
+                            set_selection_mode: gtk::SelectionMode::None,
+                        },
+                    },
+                },
+            },
+        }
+    }
+
+    fn init(
+        (parent, repo): Self::Init,
+        dialog: Self::Root,
+        sender: ComponentSender<Self>,
+    ) -> ComponentParts<Self> {
+        let track_row = adw::ActionRow::new();
+        let preview_group = adw::PreferencesGroup::new();
+        let preview_list = gtk::ListBox::new();
+        let apply_button = gtk::Button::new();
+
+        let model = Self {
+            parent,
+            dialog: dialog.clone(),
+            repo,
+            geotagger: None,
+            candidates: Vec::new(),
+            matches: Vec::new(),
+            track_row: track_row.clone(),
+            preview_group: preview_group.clone(),
+            preview_list: preview_list.clone(),
+            apply_button: apply_button.clone(),
+        };
+
+        let widgets = view_output!();
+
+        ComponentParts { model, widgets }
+    }
+
+    fn update(&mut self, msg: Self::Input, sender: ComponentSender<Self>) {
+        match msg {
+            GeotagDialogInput::Present => {
+                let filter = gtk::FileFilter::new();
+                filter.set_name(Some(&fl!("geotag-gpx-filter")));
+                filter.add_suffix("gpx");
+
+                let filters = gio::ListStore::new::<gtk::FileFilter>();
+                filters.append(&filter);
+
+                let file_dialog = gtk::FileDialog::builder()
+                    .title(fl!("geotag-title"))
+                    .modal(true)
+                    .filters(&filters)
+                    .build();
+
+                file_dialog.open(Some(&self.parent), None::<&gio::Cancellable>, move |result| {
+                    if let Some(path) = result.ok().and_then(|file| file.path()) {
+                        sender.input(GeotagDialogInput::Load(path));
+                    }
+                });
+            },
+            GeotagDialogInput::Load(path) => {
+                let track = match Track::from_gpx_path(&path) {
+                    Ok(track) => track,
+                    Err(e) => {
+                        error!("Failed loading GPX track: {:?}", e);
+                        return;
+                    },
+                };
+
+                info!("Loaded {} track points from {:?}", track.len(), path);
+
+                let file_name = path.file_name()
+                    .map(|x| x.to_string_lossy().to_string())
+                    .unwrap_or_default();
+
+                self.track_row.set_subtitle(&fl!("geotag-track-points", file_name = file_name, count = track.len()));
+
+                self.geotagger = Some(Geotagger::new(track));
+                self.candidates = self.repo.find_need_geotag()
+                    .inspect_err(|e| error!("Failed finding photos to geotag: {:?}", e))
+                    .unwrap_or_default();
+                self.update_preview();
+                self.dialog.present(&self.parent);
+            },
+            GeotagDialogInput::Offset(minutes) => {
+                let offset = TimeDelta::try_minutes(minutes).unwrap_or_default();
+                self.geotagger = self.geotagger.take().map(|g| g.camera_offset(offset));
+                self.update_preview();
+            },
+            GeotagDialogInput::Apply => {
+                info!("Geotagging {} photos", self.matches.len());
+                if let Err(e) = self.repo.add_inferred_locations(&self.matches) {
+                    error!("Failed adding inferred locations: {:?}", e);
+                    return;
+                }
+
+                let _ = sender.output(GeotagDialogOutput::Tagged(self.matches.len()));
+                self.dialog.close();
+            },
+        }
+    }
+}
+
+impl GeotagDialog {
+    /// Recompute photos to tag from the candidates and show them in the preview list.
+    fn update_preview(&mut self) {
+        self.preview_list.remove_all();
+
+        let Some(ref geotagger) = self.geotagger else {
+            return;
+        };
+
+        self.matches = geotagger.preview(&self.candidates);
+
+        self.preview_group.set_description(Some(&fl!("geotag-preview", "description", count = self.matches.len())));
+        self.apply_button.set_sensitive(!self.matches.is_empty());
+
+        for m in self.matches.iter().take(MAX_PREVIEW_ROWS) {
+            let title = m.path.file_name()
+                .map(|x| x.to_string_lossy().to_string())
+                .unwrap_or_default();
+
+            let subtitle = format!("{} · {:.5}, {:.5}",
+                m.capture_ts.format("%Y-%m-%d %H:%M:%S"),
+                m.location.latitude,
+                m.location.longitude);
+
+            let row = adw::ActionRow::builder()
+                .title(title)
+                .subtitle(subtitle)
+                .build();
+
+            self.preview_list.append(&row);
+        }
+    }
+}
@@ -3,9 +3,13 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 pub mod about;
+pub mod export_dialog;
+pub mod geotag_dialog;
 pub mod preferences;
 pub mod albums;
 pub mod library;
+pub mod orientation;
 pub mod progress_monitor;
 pub mod progress_panel;
+pub mod thumbnail_settings;
 pub mod viewer;
@@ -0,0 +1,33 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use fotema_core::visual::model::PictureOrientation;
+use relm4::gtk::{graphene, gsk};
+
+/// Transformation to orient a picture of a given size, keeping the oriented picture
+/// at the origin so it can be scrolled or laid out.
+pub fn transform(orientation: PictureOrientation, width: f32, height: f32) -> gsk::Transform {
+    let (is_flipped, turns) = orientation.to_flip_and_turns();
+
+    // Clockwise quarter turns about the origin move the picture out of view.
+    let (x, y) = match turns {
+        1 => (height, 0.0),
+        2 => (width, height),
+        3 => (0.0, width),
+        _ => (0.0, 0.0),
+    };
+
+    let transform = gsk::Transform::new()
+        .translate(&graphene::Point::new(x, y))
+        .rotate(90.0 * turns as f32);
+
+    // Transformations are applied to the picture from last to first, so this flips first.
+    if is_flipped {
+        transform
+            .translate(&graphene::Point::new(width, 0.0))
+            .scale(-1.0, 1.0)
+    } else {
+        transform
+    }
+}
@@ -2,36 +2,102 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
-use relm4::{adw, ComponentParts, ComponentSender, SimpleComponent};
+use relm4::{adw, gtk, ComponentParts, ComponentSender, SimpleComponent};
 use relm4::adw::prelude::AdwDialogExt;
 use relm4::gtk::prelude::SettingsExt;
 use relm4::gtk::gio;
+use relm4::gtk::glib;
 use relm4::adw::prelude::PreferencesDialogExt;
 use relm4::adw::prelude::PreferencesPageExt;
 use relm4::adw::prelude::PreferencesGroupExt;
 use relm4::adw::prelude::ActionRowExt;
 use relm4::adw::prelude::PreferencesRowExt;
+use relm4::gtk::prelude::EditableExt;
+use relm4::adw::prelude::ComboRowExt;
+use relm4::gtk::prelude::WidgetExt;
+
+use fotema_core::import::ImportPattern;
+use fotema_core::thumbnail::cache;
 
 use crate::config::APP_ID;
 use crate::fl;
 
+/// Names of thumbnail formats in settings, by position in the format combo row.
+const THUMBNAIL_FORMATS: [&str; 3] = ["png", "webp", "jpeg"];
+const THUMBNAIL_FORMAT_PNG: u32 = 0;
+
+/// Names of transcoding codecs in settings, by position in the codec combo row.
+const TRANSCODE_CODECS: [&str; 2] = ["h264", "h265"];
+
+/// Longest edges of transcoded videos, by position in the resolution combo row.
+/// Zero keeps the original size.
+const TRANSCODE_MAX_EDGES: [u32; 4] = [0, 3840, 1920, 1280];
+
+/// Names of transcoding containers in settings, by position in the container combo row.
+const TRANSCODE_CONTAINERS: [&str; 2] = ["mkv", "mp4"];
+
 pub struct PreferencesDialog {
     parent: adw::ApplicationWindow,
     dialog: adw::PreferencesDialog,
 
     // Preference values
     show_selfies: bool,
+    show_smart_albums: bool,
+    justified_layout: bool,
+    hover_previews: bool,
+    thumbnail_preserve_aspect: bool,
+    thumbnail_share: bool,
+    thumbnail_format: u32,
+    thumbnail_quality: u32,
+    thumbnail_cache_budget: u32,
+
+    /// Bytes of thumbnails on disk. None while being calculated.
+    thumbnail_cache_size: Option<u64>,
+
+    transcode_automatic: bool,
+    transcode_streaming: bool,
+    transcode_codec: u32,
+    transcode_crf: u32,
+    transcode_resolution: u32,
+    transcode_container: u32,
+    transcode_cache_budget: u32,
+
+    import_pattern: String,
+    slideshow_interval: u32,
+    slideshow_shuffle: bool,
 }
 
 #[derive(Debug)]
 pub enum PreferencesInput {
     Present,
     ShowSelfies(bool),
+    ShowSmartAlbums(bool),
+    JustifiedLayout(bool),
+    HoverPreviews(bool),
+    ThumbnailPreserveAspect(bool),
+    ThumbnailShare(bool),
+    ThumbnailFormat(u32),
+    ThumbnailQuality(u32),
+    ThumbnailCacheBudget(u32),
+    ThumbnailCacheSize(u64),
+    TranscodeAutomatic(bool),
+    TranscodeStreaming(bool),
+    TranscodeCodec(u32),
+    TranscodeCrf(u32),
+    TranscodeResolution(u32),
+    TranscodeContainer(u32),
+    TranscodeCacheBudget(u32),
+    ImportPattern(String),
+    SlideshowInterval(u32),
+    SlideshowShuffle(bool),
 }
 
 #[derive(Debug)]
 pub enum PreferencesOutput {
     Updated,
+
+    // Shape or format of thumbnails has changed, so thumbnails must be regenerated.
+    ThumbnailsChanged,
 }
 
 #[relm4::component(pub)]
@@ -58,7 +124,286 @@ impl SimpleComponent for PreferencesDialog {
 		                connect_active_notify[sender] => move |switch| {
 		                    sender.input_sender().send(PreferencesInput::ShowSelfies(switch.is_active())).unwrap();
 		                },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-views-smart-albums"),
+                        set_subtitle: &fl!("prefs-views-smart-albums", "subtitle"),
+
+                        #[watch]
+                        set_active: model.show_smart_albums,
+
+		                connect_active_notify[sender] => move |switch| {
+		                    sender.input_sender().send(PreferencesInput::ShowSmartAlbums(switch.is_active())).unwrap();
+		                },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-views-justified-layout"),
+                        set_subtitle: &fl!("prefs-views-justified-layout", "subtitle"),
+
+                        #[watch]
+                        set_active: model.justified_layout,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::JustifiedLayout(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-views-hover-previews"),
+                        set_subtitle: &fl!("prefs-views-hover-previews", "subtitle"),
+
+                        #[watch]
+                        set_active: model.hover_previews,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::HoverPreviews(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-views-thumbnail-aspect"),
+                        set_subtitle: &fl!("prefs-views-thumbnail-aspect", "subtitle"),
+
+                        #[watch]
+                        set_active: model.thumbnail_preserve_aspect,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::ThumbnailPreserveAspect(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-views-thumbnail-share"),
+                        set_subtitle: &fl!("prefs-views-thumbnail-share", "subtitle"),
+
+                        #[watch]
+                        set_active: model.thumbnail_share,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::ThumbnailShare(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::ComboRow {
+                        set_title: &fl!("prefs-views-thumbnail-format"),
+                        set_subtitle: &fl!("prefs-views-thumbnail-format", "subtitle"),
+                        set_model: Some(&gtk::StringList::new(&[
+                            fl!("prefs-views-thumbnail-format", "png").as_str(),
+                            fl!("prefs-views-thumbnail-format", "webp").as_str(),
+                            fl!("prefs-views-thumbnail-format", "jpeg").as_str(),
+                        ])),
+
+                        #[watch]
+                        set_selected: model.thumbnail_format,
+
+                        connect_selected_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::ThumbnailFormat(row.selected())).unwrap();
+                        },
+                    },
+
+                    adw::SpinRow {
+                        set_title: &fl!("prefs-views-thumbnail-quality"),
+                        set_subtitle: &fl!("prefs-views-thumbnail-quality", "subtitle"),
+                        set_adjustment: Some(&gtk::Adjustment::new(80.0, 1.0, 100.0, 1.0, 10.0, 0.0)),
+                        set_digits: 0,
+
+                        #[watch]
+                        set_sensitive: model.thumbnail_format != THUMBNAIL_FORMAT_PNG,
+
+                        #[watch]
+                        set_value: model.thumbnail_quality as f64,
+
+                        connect_value_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::ThumbnailQuality(row.value() as u32)).unwrap();
+                        },
+                    },
+
+                    adw::SpinRow {
+                        set_title: &fl!("prefs-views-thumbnail-budget"),
+                        set_subtitle: &fl!("prefs-views-thumbnail-budget", "subtitle"),
+                        set_adjustment: Some(&gtk::Adjustment::new(0.0, 0.0, 100_000.0, 50.0, 500.0, 0.0)),
+                        set_digits: 0,
+
+                        #[watch]
+                        set_value: model.thumbnail_cache_budget as f64,
+
+                        connect_value_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::ThumbnailCacheBudget(row.value() as u32)).unwrap();
+                        },
+                    },
+
+                    adw::ActionRow {
+                        set_title: &fl!("prefs-views-thumbnail-cache-size"),
+
+                        #[watch]
+                        set_subtitle: &model.thumbnail_cache_size
+                            .map(|bytes| glib::format_size(bytes).to_string())
+                            .unwrap_or_else(|| fl!("prefs-views-thumbnail-cache-size", "calculating")),
+                    },
+                },
+
+                add = &adw::PreferencesGroup {
+                    set_title: &fl!("prefs-videos-section"),
+                    set_description: Some(&fl!("prefs-videos-section", "description")),
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-videos-automatic"),
+                        set_subtitle: &fl!("prefs-videos-automatic", "subtitle"),
+
+                        #[watch]
+                        set_active: model.transcode_automatic,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::TranscodeAutomatic(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-videos-streaming"),
+                        set_subtitle: &fl!("prefs-videos-streaming", "subtitle"),
+
+                        #[watch]
+                        set_active: model.transcode_streaming,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::TranscodeStreaming(switch.is_active())).unwrap();
+                        },
+                    },
+
+                    adw::ComboRow {
+                        set_title: &fl!("prefs-videos-codec"),
+                        set_subtitle: &fl!("prefs-videos-codec", "subtitle"),
+                        set_model: Some(&gtk::StringList::new(&[
+                            fl!("prefs-videos-codec", "h264").as_str(),
+                            fl!("prefs-videos-codec", "h265").as_str(),
+                        ])),
+
+                        #[watch]
+                        set_selected: model.transcode_codec,
+
+                        connect_selected_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::TranscodeCodec(row.selected())).unwrap();
+                        },
+                    },
+
+                    adw::SpinRow {
+                        set_title: &fl!("prefs-videos-crf"),
+                        set_subtitle: &fl!("prefs-videos-crf", "subtitle"),
+                        set_adjustment: Some(&gtk::Adjustment::new(23.0, 0.0, 51.0, 1.0, 5.0, 0.0)),
+                        set_digits: 0,
+
+                        #[watch]
+                        set_value: model.transcode_crf as f64,
+
+                        connect_value_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::TranscodeCrf(row.value() as u32)).unwrap();
+                        },
+                    },
+
+                    adw::ComboRow {
+                        set_title: &fl!("prefs-videos-resolution"),
+                        set_subtitle: &fl!("prefs-videos-resolution", "subtitle"),
+                        set_model: Some(&gtk::StringList::new(&[
+                            fl!("prefs-videos-resolution", "original").as_str(),
+                            fl!("prefs-videos-resolution", "uhd").as_str(),
+                            fl!("prefs-videos-resolution", "fhd").as_str(),
+                            fl!("prefs-videos-resolution", "hd").as_str(),
+                        ])),
+
+                        #[watch]
+                        set_selected: model.transcode_resolution,
+
+                        connect_selected_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::TranscodeResolution(row.selected())).unwrap();
+                        },
+                    },
+
+                    adw::ComboRow {
+                        set_title: &fl!("prefs-videos-container"),
+                        set_subtitle: &fl!("prefs-videos-container", "subtitle"),
+                        set_model: Some(&gtk::StringList::new(&[
+                            fl!("prefs-videos-container", "mkv").as_str(),
+                            fl!("prefs-videos-container", "mp4").as_str(),
+                        ])),
+
+                        #[watch]
+                        set_selected: model.transcode_container,
+
+                        connect_selected_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::TranscodeContainer(row.selected())).unwrap();
+                        },
+                    },
+
+                    adw::SpinRow {
+                        set_title: &fl!("prefs-videos-budget"),
+                        set_subtitle: &fl!("prefs-videos-budget", "subtitle"),
+                        set_adjustment: Some(&gtk::Adjustment::new(0.0, 0.0, 1_000_000.0, 100.0, 1000.0, 0.0)),
+                        set_digits: 0,
+
+                        #[watch]
+                        set_value: model.transcode_cache_budget as f64,
+
+                        connect_value_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::TranscodeCacheBudget(row.value() as u32)).unwrap();
+                        },
+                    },
+                },
+
+                add = &adw::PreferencesGroup {
+                    set_title: &fl!("prefs-import-section"),
+                    set_description: Some(&fl!("prefs-import-section", "description")),
+
+                    // Text is only set once, as setting it while the user types moves the cursor.
+                    adw::EntryRow {
+                        set_title: &fl!("prefs-import-pattern"),
+                        set_text: &model.import_pattern,
+
+                        connect_changed[sender] => move |row| {
+                            let pattern = row.text().to_string();
+
+                            // Only save patterns that are a valid relative path.
+                            if ImportPattern::new(&pattern).is_ok() {
+                                row.remove_css_class("error");
+                                row.set_tooltip_text(None);
+                                sender.input_sender().send(PreferencesInput::ImportPattern(pattern)).unwrap();
+                            } else {
+                                row.add_css_class("error");
+                                row.set_tooltip_text(Some(&fl!("prefs-import-pattern", "invalid")));
+                            }
+                        },
                     }
+                },
+
+                add = &adw::PreferencesGroup {
+                    set_title: &fl!("prefs-slideshow-section"),
+
+                    adw::SpinRow {
+                        set_title: &fl!("prefs-slideshow-interval"),
+                        set_subtitle: &fl!("prefs-slideshow-interval", "subtitle"),
+                        set_adjustment: Some(&relm4::gtk::Adjustment::new(5.0, 1.0, 600.0, 1.0, 10.0, 0.0)),
+                        set_digits: 0,
+
+                        #[watch]
+                        set_value: model.slideshow_interval as f64,
+
+                        connect_value_notify[sender] => move |row| {
+                            sender.input_sender().send(PreferencesInput::SlideshowInterval(row.value() as u32)).unwrap();
+                        },
+                    },
+
+                    adw::SwitchRow {
+                        set_title: &fl!("prefs-slideshow-shuffle"),
+                        set_subtitle: &fl!("prefs-slideshow-shuffle", "subtitle"),
+
+                        #[watch]
+                        set_active: model.slideshow_shuffle,
+
+                        connect_active_notify[sender] => move |switch| {
+                            sender.input_sender().send(PreferencesInput::SlideshowShuffle(switch.is_active())).unwrap();
+                        },
+                    },
                 }
             }
         }
@@ -74,11 +419,48 @@ impl SimpleComponent for PreferencesDialog {
 
         let settings = gio::Settings::new(APP_ID);
         let show_selfies = settings.boolean("show-selfies");
+        let show_smart_albums = settings.boolean("show-smart-albums");
+        let justified_layout = settings.boolean("justified-layout");
+        let hover_previews = settings.boolean("hover-previews");
+        let thumbnail_preserve_aspect = settings.boolean("thumbnail-preserve-aspect");
+        let thumbnail_share = settings.boolean("thumbnail-share");
+        let thumbnail_format = Self::choice_position(&settings, "thumbnail-format", &THUMBNAIL_FORMATS);
+        let thumbnail_quality = settings.uint("thumbnail-quality");
+        let thumbnail_cache_budget = settings.uint("thumbnail-cache-budget");
+        let transcode_automatic = settings.boolean("transcode-automatic");
+        let transcode_streaming = settings.boolean("transcode-streaming");
+        let transcode_codec = Self::choice_position(&settings, "transcode-codec", &TRANSCODE_CODECS);
+        let transcode_crf = settings.uint("transcode-crf");
+        let transcode_resolution = Self::max_edge_position(&settings);
+        let transcode_container = Self::choice_position(&settings, "transcode-container", &TRANSCODE_CONTAINERS);
+        let transcode_cache_budget = settings.uint("transcode-cache-budget");
+        let import_pattern = settings.string("import-pattern").to_string();
+        let slideshow_interval = settings.uint("slideshow-interval");
+        let slideshow_shuffle = settings.boolean("slideshow-shuffle");
 
         let model = Self {
             parent,
             dialog: dialog.clone(),
             show_selfies,
+            show_smart_albums,
+            justified_layout,
+            hover_previews,
+            thumbnail_preserve_aspect,
+            thumbnail_share,
+            thumbnail_format,
+            thumbnail_quality,
+            thumbnail_cache_budget,
+            thumbnail_cache_size: None,
+            transcode_automatic,
+            transcode_streaming,
+            transcode_codec,
+            transcode_crf,
+            transcode_resolution,
+            transcode_container,
+            transcode_cache_budget,
+            import_pattern,
+            slideshow_interval,
+            slideshow_shuffle,
         };
 
         let widgets = view_output!();
@@ -91,7 +473,34 @@ impl SimpleComponent for PreferencesDialog {
             PreferencesInput::Present => {
                 let settings = gio::Settings::new(APP_ID);
                 self.show_selfies = settings.boolean("show-selfies");
+                self.show_smart_albums = settings.boolean("show-smart-albums");
+                self.justified_layout = settings.boolean("justified-layout");
+                self.hover_previews = settings.boolean("hover-previews");
+                self.thumbnail_preserve_aspect = settings.boolean("thumbnail-preserve-aspect");
+                self.thumbnail_share = settings.boolean("thumbnail-share");
+                self.thumbnail_format = Self::choice_position(&settings, "thumbnail-format", &THUMBNAIL_FORMATS);
+                self.thumbnail_quality = settings.uint("thumbnail-quality");
+                self.thumbnail_cache_budget = settings.uint("thumbnail-cache-budget");
+                self.transcode_automatic = settings.boolean("transcode-automatic");
+                self.transcode_streaming = settings.boolean("transcode-streaming");
+                self.transcode_codec = Self::choice_position(&settings, "transcode-codec", &TRANSCODE_CODECS);
+                self.transcode_crf = settings.uint("transcode-crf");
+                self.transcode_resolution = Self::max_edge_position(&settings);
+                self.transcode_container = Self::choice_position(&settings, "transcode-container", &TRANSCODE_CONTAINERS);
+                self.transcode_cache_budget = settings.uint("transcode-cache-budget");
+                self.import_pattern = settings.string("import-pattern").to_string();
+                self.slideshow_interval = settings.uint("slideshow-interval");
+                self.slideshow_shuffle = settings.boolean("slideshow-shuffle");
                 self.dialog.present(&self.parent);
+
+                // Walking the whole cache can take a while, so don't block showing the dialog.
+                self.thumbnail_cache_size = None;
+                let cache_dir = glib::user_cache_dir().join(APP_ID);
+                let dirs = [cache_dir.join("photo_thumbnails"), cache_dir.join("video_thumbnails")];
+                let sender = sender.input_sender().clone();
+                std::thread::spawn(move || {
+                    sender.emit(PreferencesInput::ThumbnailCacheSize(cache::size(&dirs)));
+                });
             },
             PreferencesInput::ShowSelfies(visible) => {
                 let settings = gio::Settings::new(APP_ID);
@@ -101,6 +510,179 @@ impl SimpleComponent for PreferencesDialog {
 
                 sender.output(PreferencesOutput::Updated).expect("Sending update prefs");
             },
+            PreferencesInput::ShowSmartAlbums(visible) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.show_smart_albums = visible;
+
+                settings.set_boolean("show-smart-albums", visible).expect("Update settings");
+
+                sender.output(PreferencesOutput::Updated).expect("Sending update prefs");
+            },
+            PreferencesInput::JustifiedLayout(justified) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.justified_layout = justified;
+
+                // Albums watch this setting, so don't need to be told of the change.
+                settings.set_boolean("justified-layout", justified).expect("Update settings");
+            },
+            PreferencesInput::HoverPreviews(enabled) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.hover_previews = enabled;
+
+                // Albums check this setting when hovering. Missing previews are generated
+                // the next time the background tasks run.
+                settings.set_boolean("hover-previews", enabled).expect("Update settings");
+            },
+            PreferencesInput::ThumbnailPreserveAspect(preserve) => {
+                if self.thumbnail_preserve_aspect == preserve {
+                    return;
+                }
+
+                let settings = gio::Settings::new(APP_ID);
+                self.thumbnail_preserve_aspect = preserve;
+
+                settings.set_boolean("thumbnail-preserve-aspect", preserve).expect("Update settings");
+
+                sender.output(PreferencesOutput::ThumbnailsChanged).expect("Sending update prefs");
+            },
+            PreferencesInput::ThumbnailShare(share) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.thumbnail_share = share;
+
+                // Only thumbnails generated from now on are shared.
+                settings.set_boolean("thumbnail-share", share).expect("Update settings");
+            },
+            PreferencesInput::ThumbnailFormat(position) => {
+                if self.thumbnail_format == position {
+                    return;
+                }
+
+                let Some(name) = THUMBNAIL_FORMATS.get(position as usize) else {
+                    return;
+                };
+
+                let settings = gio::Settings::new(APP_ID);
+                self.thumbnail_format = position;
+
+                settings.set_string("thumbnail-format", name).expect("Update settings");
+
+                sender.output(PreferencesOutput::ThumbnailsChanged).expect("Sending update prefs");
+            },
+            PreferencesInput::ThumbnailQuality(quality) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.thumbnail_quality = quality;
+
+                // Only thumbnails generated from now on have the new quality.
+                settings.set_uint("thumbnail-quality", quality).expect("Update settings");
+            },
+            PreferencesInput::ThumbnailCacheBudget(megabytes) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.thumbnail_cache_budget = megabytes;
+
+                // The cache is trimmed to the budget the next time the background tasks run.
+                settings.set_uint("thumbnail-cache-budget", megabytes).expect("Update settings");
+            },
+            PreferencesInput::ThumbnailCacheSize(bytes) => {
+                self.thumbnail_cache_size = Some(bytes);
+            },
+            PreferencesInput::TranscodeAutomatic(automatic) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_automatic = automatic;
+
+                // Videos are transcoded the next time the background tasks finish.
+                settings.set_boolean("transcode-automatic", automatic).expect("Update settings");
+            },
+            PreferencesInput::TranscodeStreaming(streaming) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_streaming = streaming;
+
+                // The viewer checks this setting when showing a video.
+                settings.set_boolean("transcode-streaming", streaming).expect("Update settings");
+            },
+            PreferencesInput::TranscodeCodec(position) => {
+                let Some(name) = TRANSCODE_CODECS.get(position as usize) else {
+                    return;
+                };
+
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_codec = position;
+
+                // Only videos transcoded from now on use the new profile.
+                settings.set_string("transcode-codec", name).expect("Update settings");
+            },
+            PreferencesInput::TranscodeCrf(crf) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_crf = crf;
+
+                settings.set_uint("transcode-crf", crf).expect("Update settings");
+            },
+            PreferencesInput::TranscodeResolution(position) => {
+                let Some(max_edge) = TRANSCODE_MAX_EDGES.get(position as usize) else {
+                    return;
+                };
+
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_resolution = position;
+
+                settings.set_uint("transcode-max-edge", *max_edge).expect("Update settings");
+            },
+            PreferencesInput::TranscodeContainer(position) => {
+                let Some(name) = TRANSCODE_CONTAINERS.get(position as usize) else {
+                    return;
+                };
+
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_container = position;
+
+                settings.set_string("transcode-container", name).expect("Update settings");
+            },
+            PreferencesInput::TranscodeCacheBudget(megabytes) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.transcode_cache_budget = megabytes;
+
+                // Transcoded videos are removed to fit the budget the next time a video is transcoded.
+                settings.set_uint("transcode-cache-budget", megabytes).expect("Update settings");
+            },
+            PreferencesInput::ImportPattern(pattern) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.import_pattern = pattern;
+
+                settings.set_string("import-pattern", &self.import_pattern).expect("Update settings");
+
+                sender.output(PreferencesOutput::Updated).expect("Sending update prefs");
+            },
+            PreferencesInput::SlideshowInterval(seconds) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.slideshow_interval = seconds;
+
+                settings.set_uint("slideshow-interval", seconds).expect("Update settings");
+            },
+            PreferencesInput::SlideshowShuffle(shuffle) => {
+                let settings = gio::Settings::new(APP_ID);
+                self.slideshow_shuffle = shuffle;
+
+                settings.set_boolean("slideshow-shuffle", shuffle).expect("Update settings");
+            },
         }
     }
 }
+
+impl PreferencesDialog {
+    /// Position in a combo row of the choice for a string key in settings.
+    fn choice_position(settings: &gio::Settings, key: &str, choices: &[&str]) -> u32 {
+        let name = settings.string(key);
+        choices
+            .iter()
+            .position(|choice| *choice == name.as_str())
+            .unwrap_or(0) as u32
+    }
+
+    /// Position in the resolution combo row of the longest edge of transcoded videos in settings.
+    fn max_edge_position(settings: &gio::Settings) -> u32 {
+        let max_edge = settings.uint("transcode-max-edge");
+        TRANSCODE_MAX_EDGES
+            .iter()
+            .position(|edge| *edge == max_edge)
+            .unwrap_or(0) as u32
+    }
+}
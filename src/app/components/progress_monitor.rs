@@ -17,8 +17,11 @@ pub enum MediaType {
 pub enum TaskName {
     Enrich(MediaType),
     Thumbnail(MediaType),
+    Preview,
     Transcode,
     MotionPhoto,
+    Export,
+    Import,
 
     /// FIXME figure out if 'Idle' will be used.
     Idle,
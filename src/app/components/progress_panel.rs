@@ -71,12 +71,21 @@ impl SimpleComponent for ProgressPanel {
                         TaskName::Thumbnail(MediaType::Video) => {
                             self.progress_bar.set_text(Some(&fl!("progress-thumbnails-videos")));
                         },
+                        TaskName::Preview => {
+                            self.progress_bar.set_text(Some(&fl!("progress-previews")));
+                        },
                         TaskName::Transcode => {
                             self.progress_bar.set_text(Some(&fl!("progress-convert-videos")));
                         },
                         TaskName::MotionPhoto => {
                             self.progress_bar.set_text(Some(&fl!("progress-motion-photo")));
                         },
+                        TaskName::Export => {
+                            self.progress_bar.set_text(Some(&fl!("progress-export")));
+                        },
+                        TaskName::Import => {
+                            self.progress_bar.set_text(Some(&fl!("progress-import")));
+                        },
                         TaskName::Idle => {
                             self.progress_bar.set_text(Some(&fl!("progress-idle")));
                         },
@@ -0,0 +1,22 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use relm4::gtk::gio;
+use relm4::gtk::prelude::SettingsExt;
+
+use fotema_core::thumbnail::{Format, Shape};
+
+/// Thumbnail shape and format chosen in preferences.
+pub fn from_settings(settings: &gio::Settings) -> (Shape, Format) {
+    let shape = if settings.boolean("thumbnail-preserve-aspect") {
+        Shape::Aspect
+    } else {
+        Shape::Square
+    };
+    let format = Format::from_name(
+        &settings.string("thumbnail-format"),
+        settings.uint("thumbnail-quality") as u8,
+    );
+    (shape, format)
+}
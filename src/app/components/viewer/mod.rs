@@ -2,6 +2,8 @@
 //
 // SPDX-License-Identifier: GPL-3.0-or-later
 
+pub mod slideshow;
+pub mod texture_cache;
 pub mod view_info;
 pub mod view_nav;
 pub mod view_one;
@@ -0,0 +1,66 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use rand::seq::SliceRandom;
+use std::time::Duration;
+
+/// State of a running slideshow over the filtered items of the viewer.
+#[derive(Debug)]
+pub struct Slideshow {
+    /// Indexes into the filtered items, in the order they will be shown.
+    order: Vec<usize>,
+
+    /// Position in order of item being shown.
+    position: usize,
+
+    /// How long to show photos for.
+    pub interval: Duration,
+
+    /// Slideshow has been paused by user input.
+    pub is_paused: bool,
+
+    /// Video or live photo being shown has played through to the end.
+    pub is_media_ended: bool,
+
+    /// A snapshot of the previous item should fade out once the next item is shown.
+    pub is_fade_pending: bool,
+
+    /// Cookie for screensaver inhibition, to be released when the slideshow stops.
+    pub inhibit_cookie: Option<u32>,
+}
+
+impl Slideshow {
+    /// Slideshow of count items that begins with the item at start_index.
+    /// If not shuffled, then items are shown chronologically and wrap around at the end.
+    pub fn new(count: usize, start_index: usize, shuffle: bool, interval: Duration) -> Self {
+        let mut order: Vec<usize> = (start_index..count).chain(0..start_index).collect();
+
+        if shuffle && order.len() > 1 {
+            // Keep the item the user was looking at as the first slide.
+            order[1..].shuffle(&mut rand::thread_rng());
+        }
+
+        Self {
+            order,
+            position: 0,
+            interval,
+            is_paused: false,
+            is_media_ended: false,
+            is_fade_pending: false,
+            inhibit_cookie: None,
+        }
+    }
+
+    /// Index of item to show.
+    pub fn current(&self) -> usize {
+        self.order[self.position]
+    }
+
+    /// Move to next item and return its index.
+    pub fn advance(&mut self) -> usize {
+        self.position = (self.position + 1) % self.order.len();
+        self.is_media_ended = false;
+        self.current()
+    }
+}
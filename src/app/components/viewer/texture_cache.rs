@@ -0,0 +1,106 @@
+// SPDX-FileCopyrightText: © 2024 David Bliss
+//
+// SPDX-License-Identifier: GPL-3.0-or-later
+
+use anyhow::*;
+use relm4::adw::gdk;
+use relm4::gtk::gio;
+use relm4::gtk::prelude::*;
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+/// A decoded image ready to be shown.
+#[derive(Debug, Clone)]
+pub struct CachedImage {
+    pub texture: gdk::Texture,
+    pub info: glycin::ImageInfo,
+}
+
+/// Decoded images kept in memory so that moving to an adjacent item in the viewer
+/// doesn't have to wait for the image to be decoded.
+#[derive(Debug)]
+pub struct TextureCache {
+    /// Maximum number of bytes of decoded images to keep.
+    budget: usize,
+
+    /// Number of bytes of decoded images currently kept.
+    size: usize,
+
+    /// Least recently used image first.
+    entries: VecDeque<(PathBuf, CachedImage)>,
+}
+
+impl TextureCache {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            size: 0,
+            entries: VecDeque::new(),
+        }
+    }
+
+    pub fn contains(&self, path: &Path) -> bool {
+        self.entries.iter().any(|(p, _)| p == path)
+    }
+
+    /// Get image and mark it as most recently used.
+    pub fn get(&mut self, path: &Path) -> Option<CachedImage> {
+        let index = self.entries.iter().position(|(p, _)| p == path)?;
+        let entry = self.entries.remove(index)?;
+        let image = entry.1.clone();
+        self.entries.push_back(entry);
+        Some(image)
+    }
+
+    /// Add image, evicting least recently used images to stay within budget.
+    /// The image just added is always kept, even if it alone exceeds the budget.
+    pub fn insert(&mut self, path: PathBuf, image: CachedImage) {
+        if let Some(index) = self.entries.iter().position(|(p, _)| *p == path) {
+            if let Some((_, old)) = self.entries.remove(index) {
+                self.size -= image_size(&old);
+            }
+        }
+
+        self.size += image_size(&image);
+        self.entries.push_back((path, image));
+
+        while self.size > self.budget && self.entries.len() > 1 {
+            if let Some((_, old)) = self.entries.pop_front() {
+                self.size -= image_size(&old);
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.size = 0;
+    }
+}
+
+/// Approximate memory used by a decoded image. Most images are decoded
+/// to four bytes per pixel.
+fn image_size(image: &CachedImage) -> usize {
+    image.texture.width() as usize * image.texture.height() as usize * 4
+}
+
+/// Decode first frame of an image. Decoding stops early if cancelled.
+pub async fn load(path: &Path, cancellable: gio::Cancellable) -> Result<CachedImage> {
+    let file = gio::File::for_path(path);
+
+    let mut loader = glycin::Loader::new(file);
+    loader.cancellable(cancellable);
+
+    let image = loader.load()
+        .await
+        .map_err(|e| anyhow!("Failed loading image: {:?}", e))?;
+
+    let frame = image.next_frame()
+        .await
+        .map_err(|e| anyhow!("Failed getting image frame: {:?}", e))?;
+
+    Ok(CachedImage {
+        texture: frame.texture,
+        info: image.info().clone(),
+    })
+}
@@ -52,6 +52,10 @@ pub struct ViewInfo {
     exif_originally_created_at: adw::ActionRow,
     exif_originally_modified_at: adw::ActionRow,
 
+    location_details: adw::PreferencesGroup,
+    altitude: adw::ActionRow,
+    direction: adw::ActionRow,
+
     video_details: adw::PreferencesGroup,
     video_dimensions: adw::ActionRow,
     video_container_format: adw::ActionRow,
@@ -159,6 +163,23 @@ impl SimpleComponent for ViewInfo {
                     },
                 },
 
+                #[local_ref]
+                location_details -> adw::PreferencesGroup {
+                    #[local_ref]
+                    altitude -> adw::ActionRow {
+                        set_title: &fl!("infobar-altitude"),
+                        add_css_class: "property",
+                        set_subtitle_selectable: true,
+                    },
+
+                    #[local_ref]
+                    direction -> adw::ActionRow {
+                        set_title: &fl!("infobar-direction"),
+                        add_css_class: "property",
+                        set_subtitle_selectable: true,
+                    },
+                },
+
 
                 #[local_ref]
                 video_details -> adw::PreferencesGroup {
@@ -237,6 +258,10 @@ impl SimpleComponent for ViewInfo {
         let exif_originally_created_at = adw::ActionRow::new();
         let exif_originally_modified_at = adw::ActionRow::new();
 
+        let location_details = adw::PreferencesGroup::new();
+        let altitude = adw::ActionRow::new();
+        let direction = adw::ActionRow::new();
+
         let video_details = adw::PreferencesGroup::new();
         let video_duration = adw::ActionRow::new();
         let video_dimensions = adw::ActionRow::new();
@@ -266,6 +291,10 @@ impl SimpleComponent for ViewInfo {
             exif_originally_created_at: exif_originally_created_at.clone(),
             exif_originally_modified_at: exif_originally_modified_at.clone(),
 
+            location_details: location_details.clone(),
+            altitude: altitude.clone(),
+            direction: direction.clone(),
+
             video_details: video_details.clone(),
             video_file_size: video_file_size.clone(),
             video_originally_created_at: video_originally_created_at.clone(),
@@ -309,6 +338,8 @@ impl SimpleComponent for ViewInfo {
                 if vis.picture_id.is_some() {
                     let _ = self.update_photo_details(vis.clone(), image_info);
                 }
+
+                self.update_location_details(vis.clone());
             },
             ViewInfoInput::Video(ref visual_id) => {
                 let result = {
@@ -323,6 +354,7 @@ impl SimpleComponent for ViewInfo {
 
                 self.image_details.set_visible(false);
                 self.exif_details.set_visible(false);
+                self.location_details.set_visible(false);
 
                 let _ = self.update_file_details(vis.clone());
 
@@ -477,6 +509,30 @@ impl ViewInfo {
         Ok(())
     }
 
+    fn update_location_details(&mut self, vis: Arc<fotema_core::visual::Visual>) {
+        let altitude = vis.altitude
+            .map(|x| fl!("infobar-altitude", "value", metres = format!("{:.0}", x)));
+
+        let direction = vis.direction
+            .map(|x| format!("{} {:.0}°", Self::heading_arrow(x), x));
+
+        let has_location_details = [
+            Self::update_row(&self.altitude, altitude),
+            Self::update_row(&self.direction, direction),
+        ]
+        .into_iter()
+        .any(|x| x);
+
+        self.location_details.set_visible(has_location_details);
+    }
+
+    /// Arrow pointing to nearest of the eight compass points for a heading in degrees.
+    fn heading_arrow(degrees: f64) -> &'static str {
+        const ARROWS: [&str; 8] = ["↑", "↗", "→", "↘", "↓", "↙", "←", "↖"];
+        let index = ((degrees.rem_euclid(360.0) + 22.5) / 45.0) as usize % 8;
+        ARROWS[index]
+    }
+
     /// Borrowed from Loupe.
     /// Updates a row to be visible if it has a value to display, and returns
     /// visibility status.
@@ -3,22 +3,35 @@
 // SPDX-License-Identifier: GPL-3.0-or-later
 
 use fotema_core::VisualId;
+use relm4::actions::{RelmAction, RelmActionGroup};
+use relm4::adw::prelude::*;
 use relm4::gtk;
-use relm4::gtk::prelude::*;
+use relm4::gtk::{gdk, gio, glib};
 use relm4::*;
 use relm4::prelude::*;
 
 use crate::app::components::albums::album_filter::AlbumFilter;
 use super::view_one::{ViewOne, ViewOneInput, ViewOneOutput};
 use super::view_info::{ViewInfo, ViewInfoInput};
+use super::slideshow::Slideshow;
 use crate::app::components::progress_monitor::ProgressMonitor;
 use crate::app::SharedState;
 use crate::adaptive;
+use crate::config::APP_ID;
 use crate::fl;
 
 use fotema_core::Visual;
+use fotema_core::photo;
+use fotema_core::video;
+use fotema_core::visual::burst;
+use fotema_core::visual::model::PictureOrientation;
 
+use std::cell::Cell;
+use std::ops::Range;
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tracing::{event, Level};
 
@@ -31,6 +44,15 @@ pub enum ViewNavInput {
 
     ToggleInfo,
 
+    // Show or hide photo editing controls.
+    Editing(bool),
+
+    // Edits have changed the thumbnail of a photo.
+    Edited,
+
+    // An edited copy of a photo, or a trimmed copy or frame of a video, has been written to a new file.
+    CopySaved(PathBuf),
+
     // The photo/video page has been hidden so any playing media should stop.
     Hidden,
 
@@ -41,21 +63,94 @@ pub enum ViewNavInput {
     // Transcode all incompatible videos
     TranscodeAll,
 
+    // Transcode one video, showing progress through the video.
+    Transcode(Arc<Visual>),
+
+    // A video has been transcoded to a path.
+    Transcoded(VisualId, PathBuf),
+
     // Go to the previous photo
     GoLeft,
 
     // Go to the next photo
     GoRight,
 
+    // Enter or leave fullscreen.
+    ToggleFullscreen,
+
     // Adapt to layout
     Adapt(adaptive::Layout),
+
+    // Export item currently being viewed
+    ExportItem,
+
+    // Export all items in album of item currently being viewed
+    ExportAlbum,
+
+    // Start a fullscreen slideshow of the filtered items, beginning with the current item.
+    StartSlideshow,
+
+    StopSlideshow,
+
+    // Pause slideshow because of user input.
+    PauseSlideshow,
+
+    ResumeSlideshow,
+
+    ToggleSlideshowPause,
+
+    // Slideshow interval has elapsed. The number identifies the timer so that
+    // timers for earlier slides can be ignored.
+    SlideshowTick(u64),
+
+    // Video or live photo has played through to the end.
+    MediaEnded,
+
+    // Correct orientation of photo being viewed by applying a transformation
+    // after the current orientation.
+    CorrectOrientation(PictureOrientation),
+
+    // Remove orientation correction of photo being viewed.
+    ResetOrientation,
+
+    // Write corrected orientation to EXIF data of photo being viewed.
+    SaveOrientation,
+
+    // Show or hide the photos of the burst the photo being viewed is part of.
+    ToggleBurst(bool),
+
+    // Keep the photo being viewed as the photo shown for its burst.
+    KeepBurstPick,
+
+    // Ask before keeping the photo being viewed and moving the rest of its burst to the trash.
+    ConfirmTrashBurstOthers,
+
+    // Keep the photo being viewed and move the rest of its burst to the trash.
+    TrashBurstOthers,
 }
 
 #[derive(Debug)]
 pub enum ViewNavOutput {
     TranscodeAll,
+
+    Transcode(Arc<Visual>),
+
+    Export(Vec<Arc<Visual>>),
+
+    Edited,
+
+    CopySaved(PathBuf),
 }
 
+relm4::new_action_group!(ViewerActionGroup, "viewer");
+relm4::new_stateless_action!(ExportItemAction, ViewerActionGroup, "export-item");
+relm4::new_stateless_action!(ExportAlbumAction, ViewerActionGroup, "export-album");
+relm4::new_stateless_action!(RotateLeftAction, ViewerActionGroup, "orientation-rotate-left");
+relm4::new_stateless_action!(RotateRightAction, ViewerActionGroup, "orientation-rotate-right");
+relm4::new_stateless_action!(FlipAction, ViewerActionGroup, "orientation-flip");
+relm4::new_stateless_action!(ResetOrientationAction, ViewerActionGroup, "orientation-reset");
+relm4::new_stateless_action!(SaveOrientationAction, ViewerActionGroup, "orientation-save");
+
 pub struct ViewNav {
     state: SharedState,
 
@@ -71,6 +166,9 @@ pub struct ViewNav {
     left_button: gtk::Button,
     right_button: gtk::Button,
 
+    // Only photos without a video can have their orientation corrected.
+    orientation_button: gtk::MenuButton,
+
     current_index: Option<usize>,
 
     // Album currently displayed item is a member of
@@ -79,14 +177,71 @@ pub struct ViewNav {
     // Visual items filtered by album filter.
     // This is to support the next and previous buttons.
     filtered_items: Vec<Arc<Visual>>,
+
+    photo_repo: photo::Repository,
+
+    root: adw::ToolbarView,
+
+    slideshow: Option<Slideshow>,
+
+    // Shared with input event handlers so they can tell if a slideshow is running.
+    is_slideshow_running: Rc<Cell<bool>>,
+
+    // Identifies most recent slideshow timer.
+    slideshow_timer: u64,
+
+    // Resume and stop buttons shown when slideshow is paused.
+    slideshow_controls: gtk::Box,
+
+    // Snapshot of previous slide that fades out to reveal the next slide.
+    fade_picture: gtk::Picture,
+
+    fade_animation: Option<adw::TimedAnimation>,
+
+    // Shows the photos of a burst when the item being viewed is part of one.
+    burst_button: gtk::ToggleButton,
+
+    // Photos of the burst being viewed and buttons to pick one to keep.
+    burst_bar: gtk::Box,
+
+    // Thumbnails of the photos of the burst being viewed.
+    burst_strip: gtk::Box,
 }
 
 #[relm4::component(pub async)]
 impl SimpleAsyncComponent for ViewNav {
-    type Init = (SharedState, Arc<Reducer<ProgressMonitor>>, Arc<adaptive::LayoutState>);
+    type Init = (
+        SharedState,
+        Arc<Reducer<ProgressMonitor>>,
+        Arc<adaptive::LayoutState>,
+        photo::Repository,
+        photo::Thumbnailer,
+        video::Repository,
+        video::Thumbnailer,
+    );
     type Input = ViewNavInput;
     type Output = ViewNavOutput;
 
+    menu! {
+        export_menu: {
+            section! {
+                &fl!("viewer-export-item") => ExportItemAction,
+                &fl!("viewer-export-album") => ExportAlbumAction,
+            }
+        },
+        orientation_menu: {
+            section! {
+                &fl!("viewer-orientation-rotate-left") => RotateLeftAction,
+                &fl!("viewer-orientation-rotate-right") => RotateRightAction,
+                &fl!("viewer-orientation-flip") => FlipAction,
+                &fl!("viewer-orientation-reset") => ResetOrientationAction,
+            },
+            section! {
+                &fl!("viewer-orientation-save") => SaveOrientationAction,
+            }
+        }
+    }
+
     view! {
         adw::ToolbarView {
             add_top_bar = &adw::HeaderBar {
@@ -94,7 +249,50 @@ impl SimpleAsyncComponent for ViewNav {
                     set_icon_name: "info-outline-symbolic",
                     set_tooltip_text: Some(&fl!("viewer-info-tooltip")),
                     connect_clicked => ViewNavInput::ToggleInfo,
-                }
+                },
+
+                pack_end = &gtk::ToggleButton {
+                    set_icon_name: "document-edit-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-edit", "tooltip")),
+                    connect_toggled[sender] => move |button| {
+                        sender.input(ViewNavInput::Editing(button.is_active()));
+                    },
+                },
+
+                pack_end = &gtk::Button {
+                    set_icon_name: "view-fullscreen-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-fullscreen-tooltip")),
+                    connect_clicked => ViewNavInput::ToggleFullscreen,
+                },
+
+                pack_end = &gtk::Button {
+                    set_icon_name: "media-playback-start-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-slideshow-tooltip")),
+                    connect_clicked => ViewNavInput::StartSlideshow,
+                },
+
+                #[local_ref]
+                pack_end = &burst_button -> gtk::ToggleButton {
+                    set_icon_name: "edit-copy-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-burst", "tooltip")),
+                    set_visible: false,
+                    connect_toggled[sender] => move |button| {
+                        sender.input(ViewNavInput::ToggleBurst(button.is_active()));
+                    },
+                },
+
+                #[local_ref]
+                pack_end = &orientation_button -> gtk::MenuButton {
+                    set_icon_name: "object-rotate-right-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-orientation-tooltip")),
+                    set_menu_model: Some(&orientation_menu),
+                },
+
+                pack_end = &gtk::MenuButton {
+                    set_icon_name: "document-save-symbolic",
+                    set_tooltip_text: Some(&fl!("viewer-export-tooltip")),
+                    set_menu_model: Some(&export_menu),
+                },
             },
 
             #[wrap(Some)]
@@ -144,6 +342,83 @@ impl SimpleAsyncComponent for ViewNav {
                         },
                     },
 
+                    #[local_ref]
+                    add_overlay = &fade_picture -> gtk::Picture {
+                        set_can_target: false,
+                        set_content_fit: gtk::ContentFit::Fill,
+                        set_visible: false,
+                    },
+
+                    #[local_ref]
+                    add_overlay = &slideshow_controls -> gtk::Box {
+                        set_halign: gtk::Align::Center,
+                        set_valign: gtk::Align::End,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_margin_all: 18,
+                        set_spacing: 12,
+                        set_visible: false,
+
+                        gtk::Button {
+                            set_icon_name: "media-playback-start-symbolic",
+                            add_css_class: "osd",
+                            add_css_class: "circular",
+                            set_tooltip_text: Some(&fl!("viewer-slideshow-resume", "tooltip")),
+                            connect_clicked => ViewNavInput::ResumeSlideshow,
+                        },
+
+                        gtk::Button {
+                            set_icon_name: "media-playback-stop-symbolic",
+                            add_css_class: "osd",
+                            add_css_class: "circular",
+                            set_tooltip_text: Some(&fl!("viewer-slideshow-stop", "tooltip")),
+                            connect_clicked => ViewNavInput::StopSlideshow,
+                        },
+                    },
+
+                    #[local_ref]
+                    add_overlay = &burst_bar -> gtk::Box {
+                        set_halign: gtk::Align::Center,
+                        set_valign: gtk::Align::End,
+                        set_orientation: gtk::Orientation::Vertical,
+                        set_margin_all: 18,
+                        set_spacing: 12,
+                        set_visible: false,
+                        add_css_class: "osd",
+                        add_css_class: "toolbar",
+
+                        gtk::ScrolledWindow {
+                            set_vscrollbar_policy: gtk::PolicyType::Never,
+                            set_propagate_natural_width: true,
+                            set_max_content_width: 600,
+
+                            #[local_ref]
+                            burst_strip -> gtk::Box {
+                                set_orientation: gtk::Orientation::Horizontal,
+                                set_spacing: 6,
+                            },
+                        },
+
+                        gtk::Box {
+                            set_halign: gtk::Align::Center,
+                            set_orientation: gtk::Orientation::Horizontal,
+                            set_spacing: 12,
+
+                            gtk::Button {
+                                set_label: &fl!("viewer-burst-keep"),
+                                add_css_class: "suggested-action",
+                                add_css_class: "pill",
+                                connect_clicked => ViewNavInput::KeepBurstPick,
+                            },
+
+                            gtk::Button {
+                                set_label: &fl!("viewer-burst-trash-others"),
+                                add_css_class: "destructive-action",
+                                add_css_class: "pill",
+                                connect_clicked => ViewNavInput::ConfirmTrashBurstOthers,
+                            },
+                        },
+                    },
+
                     #[wrap(Some)]
                     set_child = model.view_one.widget(),
                 },
@@ -152,7 +427,7 @@ impl SimpleAsyncComponent for ViewNav {
     }
 
     async fn init(
-        (state, transcode_progress_monitor, layout_state): Self::Init,
+        (state, transcode_progress_monitor, layout_state, photo_repo, photo_thumbnailer, video_repo, video_thumbnailer): Self::Init,
         root: Self::Root,
         sender: AsyncComponentSender<Self>,
     ) -> AsyncComponentParts<Self>  {
@@ -160,11 +435,15 @@ impl SimpleAsyncComponent for ViewNav {
         let split_view = adw::OverlaySplitView::new();
 
         let view_one = ViewOne::builder()
-            .launch(transcode_progress_monitor)
+            .launch((transcode_progress_monitor, photo_repo.clone(), photo_thumbnailer, video_repo, video_thumbnailer))
             .forward(sender.input_sender(), |msg| match msg {
                 ViewOneOutput::PhotoShown(id, info) => ViewNavInput::ShowPhotoInfo(id, info),
                 ViewOneOutput::VideoShown(id) => ViewNavInput::ShowVideoInfo(id),
                 ViewOneOutput::TranscodeAll => ViewNavInput::TranscodeAll,
+                ViewOneOutput::Transcode(visual) => ViewNavInput::Transcode(visual),
+                ViewOneOutput::Edited => ViewNavInput::Edited,
+                ViewOneOutput::CopySaved(path) => ViewNavInput::CopySaved(path),
+                ViewOneOutput::MediaEnded => ViewNavInput::MediaEnded,
             });
 
         let view_info = ViewInfo::builder()
@@ -175,6 +454,106 @@ impl SimpleAsyncComponent for ViewNav {
 
         let left_button = gtk::Button::new();
         let right_button = gtk::Button::new();
+        let orientation_button = gtk::MenuButton::new();
+
+        let slideshow_controls = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let fade_picture = gtk::Picture::new();
+        let burst_button = gtk::ToggleButton::new();
+        let burst_bar = gtk::Box::default();
+        let burst_strip = gtk::Box::default();
+        let is_slideshow_running = Rc::new(Cell::new(false));
+
+        // Any key press or click pauses a running slideshow. Otherwise, keys navigate
+        // between items, zoom, toggle fullscreen, and control video playback.
+        let key_controller = gtk::EventControllerKey::new();
+        {
+            let sender = sender.clone();
+            let view_one_sender = view_one.sender().clone();
+            let is_slideshow_running = is_slideshow_running.clone();
+            let root = root.clone();
+            key_controller.connect_key_pressed(move |_, key, _, _| {
+                if is_slideshow_running.get() {
+                    match key {
+                        gdk::Key::Escape => sender.input(ViewNavInput::StopSlideshow),
+                        gdk::Key::space => sender.input(ViewNavInput::ToggleSlideshowPause),
+                        _ => sender.input(ViewNavInput::PauseSlideshow),
+                    }
+                    return glib::Propagation::Stop;
+                }
+
+                let is_fullscreen = root.root()
+                    .and_downcast::<gtk::Window>()
+                    .is_some_and(|window| window.is_fullscreen());
+
+                match key {
+                    gdk::Key::F11 => sender.input(ViewNavInput::ToggleFullscreen),
+                    gdk::Key::Escape if is_fullscreen => sender.input(ViewNavInput::ToggleFullscreen),
+                    gdk::Key::Left => sender.input(ViewNavInput::GoLeft),
+                    gdk::Key::Right => sender.input(ViewNavInput::GoRight),
+                    gdk::Key::plus | gdk::Key::equal | gdk::Key::KP_Add => {
+                        let _ = view_one_sender.send(ViewOneInput::ZoomIn);
+                    },
+                    gdk::Key::minus | gdk::Key::KP_Subtract => {
+                        let _ = view_one_sender.send(ViewOneInput::ZoomOut);
+                    },
+                    gdk::Key::_0 | gdk::Key::KP_0 => {
+                        let _ = view_one_sender.send(ViewOneInput::ZoomToFit);
+                    },
+                    gdk::Key::_1 | gdk::Key::KP_1 => {
+                        let _ = view_one_sender.send(ViewOneInput::ZoomToActualSize);
+                    },
+                    gdk::Key::j => {
+                        let _ = view_one_sender.send(ViewOneInput::ShuttleBackwards);
+                    },
+                    gdk::Key::k => {
+                        let _ = view_one_sender.send(ViewOneInput::ShuttlePause);
+                    },
+                    gdk::Key::l => {
+                        let _ = view_one_sender.send(ViewOneInput::ShuttleForward);
+                    },
+                    gdk::Key::comma => {
+                        let _ = view_one_sender.send(ViewOneInput::FrameStep(-1));
+                    },
+                    gdk::Key::period => {
+                        let _ = view_one_sender.send(ViewOneInput::FrameStep(1));
+                    },
+                    gdk::Key::less => {
+                        let _ = view_one_sender.send(ViewOneInput::SlowDown);
+                    },
+                    gdk::Key::greater => {
+                        let _ = view_one_sender.send(ViewOneInput::SpeedUp);
+                    },
+                    _ => return glib::Propagation::Proceed,
+                }
+                glib::Propagation::Stop
+            });
+        }
+        root.add_controller(key_controller);
+
+        let click_gesture = gtk::GestureClick::new();
+        {
+            let sender = sender.clone();
+            let is_slideshow_running = is_slideshow_running.clone();
+            click_gesture.connect_pressed(move |_, _, _, _| {
+                if is_slideshow_running.get() {
+                    sender.input(ViewNavInput::PauseSlideshow);
+                }
+            });
+        }
+        root.add_controller(click_gesture);
+
+        // Double-click toggles fullscreen.
+        let double_click_gesture = gtk::GestureClick::new();
+        {
+            let sender = sender.clone();
+            let is_slideshow_running = is_slideshow_running.clone();
+            double_click_gesture.connect_pressed(move |_, n_press, _, _| {
+                if n_press == 2 && !is_slideshow_running.get() {
+                    sender.input(ViewNavInput::ToggleFullscreen);
+                }
+            });
+        }
+        view_one.widget().add_controller(double_click_gesture);
 
         let model = ViewNav {
             state,
@@ -183,19 +562,93 @@ impl SimpleAsyncComponent for ViewNav {
             current_index: None,
             left_button: left_button.clone(),
             right_button: right_button.clone(),
+            orientation_button: orientation_button.clone(),
             split_view: split_view.clone(),
             filter: AlbumFilter::None,
             filtered_items: Vec::new(),
+            photo_repo,
+            root: root.clone(),
+            slideshow: None,
+            is_slideshow_running,
+            slideshow_timer: 0,
+            slideshow_controls: slideshow_controls.clone(),
+            fade_picture: fade_picture.clone(),
+            fade_animation: None,
+            burst_button: burst_button.clone(),
+            burst_bar: burst_bar.clone(),
+            burst_strip: burst_strip.clone(),
         };
 
         let widgets = view_output!();
 
+        let mut actions = RelmActionGroup::<ViewerActionGroup>::new();
+
+        let export_item_action = {
+            let sender = sender.clone();
+            RelmAction::<ExportItemAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::ExportItem);
+            })
+        };
+
+        let export_album_action = {
+            let sender = sender.clone();
+            RelmAction::<ExportAlbumAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::ExportAlbum);
+            })
+        };
+
+        let rotate_left_action = {
+            let sender = sender.clone();
+            RelmAction::<RotateLeftAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::CorrectOrientation(PictureOrientation::East));
+            })
+        };
+
+        let rotate_right_action = {
+            let sender = sender.clone();
+            RelmAction::<RotateRightAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::CorrectOrientation(PictureOrientation::West));
+            })
+        };
+
+        let flip_action = {
+            let sender = sender.clone();
+            RelmAction::<FlipAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::CorrectOrientation(PictureOrientation::NorthMirrored));
+            })
+        };
+
+        let reset_orientation_action = {
+            let sender = sender.clone();
+            RelmAction::<ResetOrientationAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::ResetOrientation);
+            })
+        };
+
+        let save_orientation_action = {
+            let sender = sender.clone();
+            RelmAction::<SaveOrientationAction>::new_stateless(move |_| {
+                sender.input(ViewNavInput::SaveOrientation);
+            })
+        };
+
+        actions.add_action(export_item_action);
+        actions.add_action(export_album_action);
+        actions.add_action(rotate_left_action);
+        actions.add_action(rotate_right_action);
+        actions.add_action(flip_action);
+        actions.add_action(reset_orientation_action);
+        actions.add_action(save_orientation_action);
+        actions.register_for_widget(&root);
+
         AsyncComponentParts { model, widgets }
     }
 
     async fn update(&mut self, msg: Self::Input, sender: AsyncComponentSender<Self>) {
         match msg {
             ViewNavInput::Hidden => {
+                self.stop_slideshow();
+                self.leave_fullscreen();
                 self.view_one.emit(ViewOneInput::Hidden);
             },
             ViewNavInput::View(visual_id, filter) => {
@@ -232,17 +685,93 @@ impl SimpleAsyncComponent for ViewNav {
                 self.current_index = Some(index);
 
                 self.update_nav_buttons();
+                self.update_burst(&sender);
+                self.orientation_button.set_sensitive(visual.is_photo_only());
 
                 self.view_one.emit(ViewOneInput::View(visual.clone()));
+
+                // Decode the previous and next items so navigating to them is instant.
+                let neighbours = [index.checked_sub(1), Some(index + 1)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| self.filtered_items.get(i))
+                    .cloned()
+                    .collect();
+                self.view_one.emit(ViewOneInput::Preload(neighbours));
             },
             ViewNavInput::ToggleInfo => {
                 let show = self.split_view.shows_sidebar();
                 self.split_view.set_show_sidebar(!show);
             },
+            ViewNavInput::Editing(is_editing) => {
+                self.view_one.emit(ViewOneInput::Editing(is_editing));
+            },
+            ViewNavInput::Edited => {
+                let _ = sender.output(ViewNavOutput::Edited);
+            },
+            ViewNavInput::CopySaved(path) => {
+                let _ = sender.output(ViewNavOutput::CopySaved(path));
+            },
+            ViewNavInput::StartSlideshow => {
+                self.start_slideshow(&sender);
+            },
+            ViewNavInput::StopSlideshow => {
+                self.stop_slideshow();
+            },
+            ViewNavInput::PauseSlideshow => {
+                if let Some(ref mut slideshow) = self.slideshow {
+                    slideshow.is_paused = true;
+                    self.slideshow_timer += 1;
+                    self.slideshow_controls.set_visible(true);
+                }
+            },
+            ViewNavInput::ResumeSlideshow => {
+                let plays_to_end = self.current_plays_to_end();
+                let Some(ref mut slideshow) = self.slideshow else {
+                    return;
+                };
+
+                slideshow.is_paused = false;
+                self.slideshow_controls.set_visible(false);
+
+                // Advance now if a video finished while paused.
+                if plays_to_end && slideshow.is_media_ended {
+                    self.next_slide(&sender);
+                } else if !plays_to_end {
+                    self.schedule_slide(&sender);
+                }
+            },
+            ViewNavInput::ToggleSlideshowPause => {
+                if self.slideshow.as_ref().is_some_and(|s| s.is_paused) {
+                    sender.input(ViewNavInput::ResumeSlideshow);
+                } else {
+                    sender.input(ViewNavInput::PauseSlideshow);
+                }
+            },
+            ViewNavInput::SlideshowTick(timer) => {
+                let is_due = self.slideshow.as_ref().is_some_and(|s| !s.is_paused)
+                    && timer == self.slideshow_timer;
+                if is_due {
+                    self.next_slide(&sender);
+                }
+            },
+            ViewNavInput::MediaEnded => {
+                let plays_to_end = self.current_plays_to_end();
+                let Some(ref mut slideshow) = self.slideshow else {
+                    return;
+                };
+
+                slideshow.is_media_ended = true;
+                if plays_to_end && !slideshow.is_paused {
+                    self.next_slide(&sender);
+                }
+            },
             ViewNavInput::ShowPhotoInfo(visual_id, image_info) => {
+                self.fade_in_slide();
                 self.view_info.emit(ViewInfoInput::Photo(visual_id, image_info));
             },
             ViewNavInput::ShowVideoInfo(visual_id) => {
+                self.fade_in_slide();
                 self.view_info.emit(ViewInfoInput::Video(visual_id));
             },
             ViewNavInput::TranscodeAll => {
@@ -251,6 +780,27 @@ impl SimpleAsyncComponent for ViewNav {
                 // ViewOne should send straight to transcoder.
                 let _ = sender.output(ViewNavOutput::TranscodeAll);
             },
+            ViewNavInput::Transcode(visual) => {
+                let _ = sender.output(ViewNavOutput::Transcode(visual));
+            },
+            ViewNavInput::Transcoded(visual_id, path) => {
+                // The library is reloaded after transcoding, but the items being
+                // navigated are only replaced when next viewing an item from an album.
+                let Some(index) = self.filtered_items.iter().position(|x| x.visual_id == visual_id) else {
+                    return;
+                };
+
+                let visual = Arc::new(Visual {
+                    video_transcoded_path: Some(path),
+                    ..(*self.filtered_items[index]).clone()
+                });
+
+                self.filtered_items[index] = visual.clone();
+
+                if self.current_index == Some(index) {
+                    self.view_one.emit(ViewOneInput::Transcoded(visual));
+                }
+            },
             ViewNavInput::GoLeft => {
                 let Some(index) = self.current_index else {
                     return;
@@ -273,6 +823,200 @@ impl SimpleAsyncComponent for ViewNav {
 
                 sender.input(ViewNavInput::ViewByIndex(index + 1));
             },
+            ViewNavInput::ToggleFullscreen => {
+                if self.slideshow.is_some() {
+                    return;
+                }
+
+                let Some(window) = self.root.root().and_downcast::<gtk::Window>() else {
+                    return;
+                };
+
+                // Header bar is hidden in fullscreen so the photo fills the screen.
+                if window.is_fullscreen() {
+                    window.unfullscreen();
+                    self.root.set_reveal_top_bars(true);
+                } else {
+                    window.fullscreen();
+                    self.root.set_reveal_top_bars(false);
+                }
+            },
+            ViewNavInput::ExportItem => {
+                let Some(item) = self.current_index.and_then(|i| self.filtered_items.get(i)) else {
+                    return;
+                };
+                let _ = sender.output(ViewNavOutput::Export(vec![item.clone()]));
+            },
+            ViewNavInput::ExportAlbum => {
+                let _ = sender.output(ViewNavOutput::Export(self.filtered_items.clone()));
+            },
+            ViewNavInput::CorrectOrientation(transform) => {
+                let Some(visual) = self.current_photo() else {
+                    return;
+                };
+
+                let correction = visual.picture_orientation_override
+                    .unwrap_or(PictureOrientation::North)
+                    .then(transform);
+
+                let correction = Some(correction).filter(|x| *x != PictureOrientation::North);
+                self.set_orientation(&sender, visual.picture_orientation, correction);
+            },
+            ViewNavInput::ResetOrientation => {
+                let Some(visual) = self.current_photo() else {
+                    return;
+                };
+                self.set_orientation(&sender, visual.picture_orientation, None);
+            },
+            ViewNavInput::SaveOrientation => {
+                let Some(visual) = self.current_photo() else {
+                    return;
+                };
+
+                let (Some(picture_id), Some(path)) = (visual.picture_id, visual.picture_path.as_ref()) else {
+                    return;
+                };
+
+                let orientation = visual.corrected_picture_orientation();
+
+                // Rewriting a large photo file is slow, so keep it off the main thread.
+                let result = relm4::spawn_blocking({
+                        let path = path.clone();
+                        move || photo::exif_orientation::write(&path, orientation)
+                    })
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("Writing orientation panicked: {:?}", e)))
+                    .and_then(|_| self.photo_repo.set_orientation(&picture_id, orientation));
+
+                match result {
+                    Ok(_) => {
+                        event!(Level::INFO, "Wrote orientation {:?} to {:?}", orientation, path);
+                        self.set_orientation(&sender, Some(orientation), None);
+                    },
+                    Err(e) => {
+                        event!(Level::ERROR, "Failed writing orientation to {:?}: {:?}", path, e);
+                        self.show_error(&fl!("viewer-orientation-save-failed"), &e.to_string());
+                    },
+                }
+            },
+            ViewNavInput::ToggleBurst(is_shown) => {
+                self.burst_bar.set_visible(is_shown);
+                self.update_burst(&sender);
+            },
+            ViewNavInput::KeepBurstPick => {
+                let (Some(index), Some(burst)) = (self.current_index, self.current_burst()) else {
+                    return;
+                };
+
+                let Some(pick) = self.filtered_items[index].picture_id else {
+                    return;
+                };
+
+                let picture_ids: Vec<_> = self.filtered_items[burst.clone()]
+                    .iter()
+                    .filter_map(|v| v.picture_id)
+                    .collect();
+
+                if let Err(e) = self.photo_repo.set_burst_pick(&pick, &picture_ids) {
+                    event!(Level::ERROR, "Failed saving burst pick: {:?}", e);
+                    return;
+                }
+
+                for i in burst {
+                    let visual = Arc::new(Visual {
+                        is_burst_pick: Some(i == index),
+                        ..(*self.filtered_items[i]).clone()
+                    });
+                    self.filtered_items[i] = visual;
+                }
+
+                self.update_burst(&sender);
+
+                // Refresh library so albums show the pick for the burst.
+                let _ = sender.output(ViewNavOutput::Edited);
+            },
+            ViewNavInput::ConfirmTrashBurstOthers => {
+                let Some(burst) = self.current_burst() else {
+                    return;
+                };
+
+                let others = burst.len() - 1;
+                let dialog = adw::AlertDialog::new(
+                    Some(&fl!("viewer-burst-trash-dialog")),
+                    Some(&fl!("viewer-burst-trash-dialog", "body", count = others)),
+                );
+                dialog.add_response("cancel", &fl!("viewer-burst-trash-dialog", "cancel"));
+                dialog.add_response("trash", &fl!("viewer-burst-trash-dialog", "trash"));
+                dialog.set_response_appearance("trash", adw::ResponseAppearance::Destructive);
+                dialog.set_default_response(Some("cancel"));
+                dialog.set_close_response("cancel");
+
+                let sender = sender.clone();
+                dialog.connect_response(Some("trash"), move |_, _| {
+                    sender.input(ViewNavInput::TrashBurstOthers);
+                });
+
+                dialog.present(&self.root);
+            },
+            ViewNavInput::TrashBurstOthers => {
+                let (Some(index), Some(burst)) = (self.current_index, self.current_burst()) else {
+                    return;
+                };
+
+                let others: Vec<_> = burst
+                    .filter(|i| *i != index)
+                    .map(|i| &self.filtered_items[i])
+                    .filter_map(|v| Some((v.visual_id.clone(), v.picture_id?, v.picture_path.clone()?)))
+                    .collect();
+
+                // Trashing can be slow, such as on network file systems, so keep it off the main thread.
+                let results = relm4::spawn_blocking(move || {
+                    others
+                        .into_iter()
+                        .map(|(visual_id, picture_id, path)| {
+                            let result = gio::File::for_path(&path).trash(gio::Cancellable::NONE);
+                            (visual_id, picture_id, path, result)
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .await
+                .inspect_err(|e| event!(Level::ERROR, "Trashing burst photos panicked: {:?}", e))
+                .unwrap_or_default();
+
+                let mut trashed = Vec::new();
+                let mut failed = Vec::new();
+                for (visual_id, picture_id, path, result) in results {
+                    let result = result
+                        .map_err(anyhow::Error::from)
+                        .and_then(|_| self.photo_repo.remove(picture_id));
+
+                    match result {
+                        Ok(_) => {
+                            event!(Level::INFO, "Moved burst photo {:?} to trash", path);
+                            trashed.push(visual_id);
+                        },
+                        Err(e) => {
+                            event!(Level::ERROR, "Failed moving burst photo {:?} to trash: {:?}", path, e);
+                            failed.push(path.file_name().unwrap_or_default().to_string_lossy().to_string());
+                        },
+                    }
+                }
+
+                if !failed.is_empty() {
+                    self.show_error(&fl!("viewer-burst-trash-failed"), &failed.join("\n"));
+                }
+
+                let visual_id = self.filtered_items[index].visual_id.clone();
+                self.filtered_items.retain(|v| !trashed.contains(&v.visual_id));
+                self.current_index = self.filtered_items.iter().position(|v| v.visual_id == visual_id);
+
+                if let Some(index) = self.current_index {
+                    sender.input(ViewNavInput::ViewByIndex(index));
+                }
+
+                // Refresh library so albums no longer show the trashed photos.
+                let _ = sender.output(ViewNavOutput::Edited);
+            },
             ViewNavInput::Adapt(adaptive::Layout::Narrow) => {
                 let show = self.split_view.shows_sidebar();
                 self.split_view.set_collapsed(true);
@@ -288,6 +1032,258 @@ impl SimpleAsyncComponent for ViewNav {
 }
 
 impl ViewNav {
+    fn start_slideshow(&mut self, sender: &AsyncComponentSender<Self>) {
+        if self.slideshow.is_some() || self.filtered_items.is_empty() {
+            return;
+        }
+
+        let settings = gio::Settings::new(APP_ID);
+        let interval = Duration::from_secs(settings.uint("slideshow-interval").max(1) as u64);
+        let shuffle = settings.boolean("slideshow-shuffle");
+
+        let start_index = self.current_index.unwrap_or(0);
+        let mut slideshow = Slideshow::new(self.filtered_items.len(), start_index, shuffle, interval);
+
+        event!(Level::INFO, "Starting slideshow of {} items", self.filtered_items.len());
+
+        let window = self.root.root().and_downcast::<gtk::Window>();
+        if let Some(ref window) = window {
+            window.fullscreen();
+        }
+
+        // Keep the screen on while the slideshow runs.
+        slideshow.inhibit_cookie = Some(relm4::main_application().inhibit(
+            window.as_ref(),
+            gtk::ApplicationInhibitFlags::IDLE,
+            Some(&fl!("viewer-slideshow-inhibit-reason")),
+        ));
+
+        self.root.set_reveal_top_bars(false);
+        self.split_view.set_show_sidebar(false);
+        self.left_button.set_visible(false);
+        self.right_button.set_visible(false);
+        self.view_one.emit(ViewOneInput::Slideshow(true));
+
+        let index = slideshow.current();
+        self.slideshow = Some(slideshow);
+        self.is_slideshow_running.set(true);
+        self.show_slide(sender, index);
+    }
+
+    fn stop_slideshow(&mut self) {
+        let Some(slideshow) = self.slideshow.take() else {
+            return;
+        };
+
+        event!(Level::INFO, "Stopping slideshow");
+
+        self.is_slideshow_running.set(false);
+        self.slideshow_timer += 1;
+
+        if let Some(cookie) = slideshow.inhibit_cookie {
+            relm4::main_application().uninhibit(cookie);
+        }
+
+        self.leave_fullscreen();
+        self.left_button.set_visible(true);
+        self.right_button.set_visible(true);
+        self.slideshow_controls.set_visible(false);
+        self.fade_picture.set_visible(false);
+        self.view_one.emit(ViewOneInput::Slideshow(false));
+    }
+
+    fn leave_fullscreen(&self) {
+        if let Some(window) = self.root.root().and_downcast::<gtk::Window>() {
+            window.unfullscreen();
+        }
+        self.root.set_reveal_top_bars(true);
+    }
+
+    /// Show slide at index and schedule next slide if it doesn't play through to the end.
+    fn show_slide(&mut self, sender: &AsyncComponentSender<Self>, index: usize) {
+        sender.input(ViewNavInput::ViewByIndex(index));
+        self.current_index = Some(index);
+
+        if !self.current_plays_to_end() {
+            self.schedule_slide(sender);
+        }
+    }
+
+    fn next_slide(&mut self, sender: &AsyncComponentSender<Self>) {
+        let Some(ref mut slideshow) = self.slideshow else {
+            return;
+        };
+
+        let index = slideshow.advance();
+        slideshow.is_fade_pending = true;
+
+        // Snapshot the current slide so it can fade out once the next one is shown.
+        let snapshot = gtk::WidgetPaintable::new(Some(self.view_one.widget())).current_image();
+        self.fade_picture.set_paintable(Some(&snapshot));
+        self.fade_picture.set_opacity(1.0);
+        self.fade_picture.set_visible(true);
+
+        self.show_slide(sender, index);
+    }
+
+    /// Start timer for next slide.
+    fn schedule_slide(&mut self, sender: &AsyncComponentSender<Self>) {
+        let Some(ref slideshow) = self.slideshow else {
+            return;
+        };
+
+        self.slideshow_timer += 1;
+        let timer = self.slideshow_timer;
+        let sender = sender.clone();
+        glib::timeout_add_local_once(slideshow.interval, move || {
+            sender.input(ViewNavInput::SlideshowTick(timer));
+        });
+    }
+
+    /// Cross-fade from the previous slide to the slide now being shown.
+    fn fade_in_slide(&mut self) {
+        let Some(ref mut slideshow) = self.slideshow else {
+            return;
+        };
+
+        if !slideshow.is_fade_pending {
+            return;
+        }
+        slideshow.is_fade_pending = false;
+
+        let fade_picture = self.fade_picture.clone();
+        let target = adw::PropertyAnimationTarget::new(&self.fade_picture, "opacity");
+        let animation = adw::TimedAnimation::new(&self.fade_picture, 1.0, 0.0, 600, target);
+        animation.connect_done(move |_| fade_picture.set_visible(false));
+        animation.play();
+        self.fade_animation = Some(animation);
+    }
+
+    /// Should the slideshow wait for the current item to play through to the end,
+    /// instead of advancing after the interval?
+    fn current_plays_to_end(&self) -> bool {
+        let Some(visual) = self.current_index.and_then(|i| self.filtered_items.get(i)) else {
+            return false;
+        };
+
+        if !visual.is_video_only() && !visual.is_motion_photo() {
+            return false;
+        }
+
+        // Videos that must be transcoded first can't be played, so are skipped after the interval.
+        let is_transcoded = visual.video_transcoded_path.as_ref().is_some_and(|x| x.exists());
+        let is_playable = is_transcoded || !visual.is_transcode_required.is_some_and(|x| x);
+
+        let has_video = visual.video_path.as_ref()
+            .or(visual.motion_photo_video_path.as_ref())
+            .is_some_and(|x| x.exists());
+
+        is_playable && has_video
+    }
+
+    /// Tell the user that an action on the items being viewed failed.
+    fn show_error(&self, heading: &str, body: &str) {
+        let dialog = adw::AlertDialog::new(Some(heading), Some(body));
+        dialog.add_response("close", &fl!("viewer-error-dialog-close"));
+        dialog.present(&self.root);
+    }
+
+    /// Photo currently being viewed, if the item being viewed is a photo.
+    fn current_photo(&self) -> Option<Arc<Visual>> {
+        self.current_index
+            .and_then(|i| self.filtered_items.get(i))
+            .filter(|v| v.is_photo_only())
+            .cloned()
+    }
+
+    /// Save orientation correction of photo being viewed and show it again.
+    fn set_orientation(
+        &mut self,
+        sender: &AsyncComponentSender<Self>,
+        orientation: Option<PictureOrientation>,
+        correction: Option<PictureOrientation>,
+    ) {
+        let Some(index) = self.current_index else {
+            return;
+        };
+
+        let visual = &self.filtered_items[index];
+        let Some(picture_id) = visual.picture_id else {
+            return;
+        };
+
+        if let Err(e) = self.photo_repo.set_orientation_override(&picture_id, correction) {
+            event!(Level::ERROR, "Failed saving orientation correction: {:?}", e);
+            return;
+        }
+
+        let visual = Arc::new(Visual {
+            picture_orientation: orientation,
+            picture_orientation_override: correction,
+            ..(**visual).clone()
+        });
+
+        self.filtered_items[index] = visual.clone();
+        self.view_one.emit(ViewOneInput::View(visual));
+
+        // Refresh library so thumbnails pick up the new orientation.
+        let _ = sender.output(ViewNavOutput::Edited);
+    }
+
+    /// Range of filtered items of the burst the item being viewed is part of.
+    fn current_burst(&self) -> Option<Range<usize>> {
+        let index = self.current_index?;
+        burst::stacks(&self.filtered_items)
+            .into_iter()
+            .find(|stack| stack.contains(&index))
+    }
+
+    /// Show the burst button if the item being viewed is part of a burst, and
+    /// fill the burst bar with thumbnails of the photos of the burst.
+    fn update_burst(&self, sender: &AsyncComponentSender<Self>) {
+        while let Some(child) = self.burst_strip.first_child() {
+            self.burst_strip.remove(&child);
+        }
+
+        let burst = self.current_burst();
+        self.burst_button.set_visible(burst.is_some());
+        self.burst_bar.set_visible(burst.is_some() && self.burst_button.is_active());
+
+        let Some(burst) = burst else {
+            return;
+        };
+
+        if !self.burst_button.is_active() {
+            return;
+        }
+
+        for index in burst {
+            let visual = &self.filtered_items[index];
+
+            let picture = gtk::Picture::new();
+            picture.set_content_fit(gtk::ContentFit::Cover);
+            picture.set_size_request(64, 64);
+            if let Some(path) = visual.thumbnail_path.as_ref().filter(|x| x.exists()) {
+                picture.set_filename(Some(path));
+            }
+
+            let button = gtk::Button::new();
+            button.set_child(Some(&picture));
+            button.add_css_class("flat");
+            if self.current_index == Some(index) {
+                button.add_css_class("suggested-action");
+            }
+            if visual.is_burst_pick.is_some_and(|x| x) {
+                button.set_tooltip_text(Some(&fl!("viewer-burst-keep", "tooltip")));
+            }
+
+            let sender = sender.clone();
+            button.connect_clicked(move |_| sender.input(ViewNavInput::ViewByIndex(index)));
+
+            self.burst_strip.append(&button);
+        }
+    }
+
     fn update_nav_buttons(&self) {
         if self.filtered_items.len() <= 1 {
             self.left_button.set_sensitive(false);
@@ -5,44 +5,113 @@
 use fotema_core::VisualId;
 use fotema_core::Visual;
 use fotema_core::visual::model::PictureOrientation;
+use fotema_core::photo;
+use fotema_core::photo::edit::{CropAspect, CropRect, Edit, EditPreview, EditStack};
+use fotema_core::photo::Focus;
+use fotema_core::video;
+use fotema_core::video::proxy::Proxy;
+use fotema_core::visual::burst;
 use strum::IntoEnumIterator;
 use relm4::gtk;
 use relm4::adw::gdk;
-use relm4::gtk::gio;
-use relm4::gtk::prelude::*;
+use relm4::adw::prelude::*;
+use relm4::gtk::{gio, glib};
 use relm4::*;
 use relm4::prelude::*;
 use glycin;
 use chrono::TimeDelta;
+use futures::executor::block_on;
 
+use crate::config::APP_ID;
+use crate::app::components::orientation;
 use crate::app::components::progress_monitor::ProgressMonitor;
 use crate::app::components::progress_panel::ProgressPanel;
+use crate::app::components::thumbnail_settings;
+use super::texture_cache::{self, CachedImage, TextureCache};
 use crate::fl;
 
+use std::cell::Cell;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::rc::Rc;
 use std::sync::Arc;
+use std::time::Duration;
 
 use tracing::{event, Level};
 
 const TEN_SECS_IN_MICROS: i64 = 10_000_000;
 const FIFTEEN_SECS_IN_MICROS: i64 = 15_000_000;
 
+/// Longest edge of frames previewed while hovering over the seek bar.
+const SCRUB_FRAME_EDGE: u32 = 160;
+
+/// Frames previewed while hovering over the seek bar are decoded at most this often
+/// through a video, so that they can be reused while moving along the seek bar.
+const SCRUB_STEP_MICROS: i64 = 1_000_000;
+
+/// Playback speeds that shortcuts step between.
+const SPEEDS: [f64; 7] = [0.25, 0.5, 1.0, 1.5, 2.0, 4.0, 8.0];
+
+/// Time between seeks when playing at a speed other than normal.
+const SHUTTLE_INTERVAL_MILLIS: u64 = 100;
+
+/// Frame rate assumed for stepping between frames of videos that don't say.
+const DEFAULT_FRAME_RATE: f64 = 30.0;
+
+/// Zoom factor for one step of zooming in or out.
+const ZOOM_STEP: f64 = 1.25;
+
+/// Zoom factor for one step of a mouse wheel.
+const SCROLL_ZOOM_STEP: f64 = 1.1;
+
+/// Largest zoom, relative to the pixels of the photo.
+const MAX_ZOOM: f64 = 8.0;
+
+/// Time an adjustment slider must be still before edits are saved and the photo
+/// is rendered at full size. Until then, a downscaled preview is shown.
+const ADJUSTMENT_SETTLE_MILLIS: u64 = 300;
+
+/// Maximum bytes of decoded images to keep for quickly moving between items.
+/// Enough for the current, previous, and next 40 megapixel photos.
+const TEXTURE_CACHE_BUDGET: usize = 512 * 1024 * 1024;
+
 #[derive(Debug)]
 pub enum ViewOneInput {
     // View an item.
     View(Arc<Visual>),
 
+    // Decode images of items the user is likely to view next. Loads of other
+    // items are cancelled.
+    Preload(Vec<Arc<Visual>>),
+
+    // An image has been decoded. The number identifies the load so that results
+    // of superseded loads can be ignored.
+    Loaded(PathBuf, u64, anyhow::Result<CachedImage>),
+
     // The photo/video page has been hidden so any playing media should stop.
     Hidden,
 
     // Transcode all incompatible videos
     TranscodeAll,
 
+    // Transcode the video being viewed
+    TranscodeNow,
+
+    // A video has been transcoded, so no longer needs a proxy.
+    Transcoded(Arc<Visual>),
+
     MuteToggle,
 
     PlayToggle,
 
     VideoEnded,
 
+    // Play or pause the voice note of the photo being viewed.
+    VoiceNoteToggle,
+
+    // Voice note has played through to the end.
+    VoiceNoteEnded,
+
     SkipBackwards,
 
     SkipForward,
@@ -50,22 +119,200 @@ pub enum ViewOneInput {
     // Constantly sent during video playback so we can update the timestamp.
     Timestamp,
 
+    // Seek bar has been moved to a number of seconds from the start of the video.
+    Seek(f64),
+
+    // Pointer is over the seek bar, at a number of pixels from its start.
+    ScrubHover(f64),
+
+    // Pointer has left the seek bar.
+    ScrubLeave,
+
+    // Frame previewing a position on the seek bar has been decoded. The first number
+    // identifies the video, so that frames of previously viewed videos can be ignored,
+    // and the second is the position in microseconds.
+    ScrubFrame(u64, i64, anyhow::Result<gdk::Texture>),
+
+    // Frame rate of a video has been read. The number identifies the video,
+    // as for ScrubFrame.
+    FrameRateRead(u64, Option<f64>),
+
+    // Step forwards or backwards by a number of frames, pausing the video.
+    FrameStep(i64),
+
+    // Play backwards, or backwards faster if already playing backwards.
+    ShuttleBackwards,
+
+    // Pause, or play at normal speed if paused.
+    ShuttlePause,
+
+    // Play forwards, or forwards faster if already playing forwards.
+    ShuttleForward,
+
+    // Time to seek the video when playing at a speed other than normal. The number
+    // identifies the timer, so that ticks of stopped timers can be ignored.
+    ShuttleTick(u64),
+
+    // Play faster, in the current direction.
+    SpeedUp,
+
+    // Play slower, in the current direction.
+    SlowDown,
+
     // Video has been "prepared", so duration should be available
     Prepared,
+
+    // Slideshow has started or stopped. Videos and live photos play through once
+    // during a slideshow.
+    Slideshow(bool),
+
+    // Show or hide photo editing controls.
+    Editing(bool),
+
+    // Apply an edit to the photo being viewed.
+    Edit(Edit),
+
+    // Crop photo being viewed to an aspect ratio.
+    Crop(CropAspect),
+
+    Exposure(f64),
+
+    Contrast(f64),
+
+    Saturation(f64),
+
+    // A downscaled preview of the edits to a photo has been rendered.
+    PreviewRendered(VisualId, anyhow::Result<(Arc<EditPreview>, gdk::Texture)>),
+
+    // Adjustment sliders have been still for a moment. Number identifies the last adjustment.
+    AdjustmentSettled(u64),
+
+    // Remove most recent edit.
+    UndoEdit,
+
+    // Remove all edits.
+    ResetEdits,
+
+    // Write edited photo to a new file.
+    SaveCopy,
+
+    // Next click on the photo chooses the point that thumbnails are cropped about.
+    ChooseFocus,
+
+    // Photo clicked at a point, as fractions of the width and height of the
+    // edited photo before orientation.
+    FocusAt(f64, f64),
+
+    // Crop thumbnails about a detected focus point instead of a chosen one.
+    AutomaticFocus,
+
+    // Make thumbnails of the video being viewed from the frame being shown.
+    PosterAtCurrentFrame,
+
+    // Make thumbnails of the video being viewed from a representative frame.
+    AutomaticPoster,
+
+    // Show or hide controls for trimming the video being viewed.
+    Trimming(bool),
+
+    // Trimmed copy starts at the current position.
+    TrimStart,
+
+    // Trimmed copy ends at the current position.
+    TrimEnd,
+
+    // Write the trimmed part of the video being viewed to a new file.
+    SaveTrim,
+
+    // Write the frame being shown to a JPEG next to the video.
+    SaveFrame,
+
+    ZoomIn,
+
+    ZoomOut,
+
+    // Zoom by a factor relative to the current zoom, such as for a mouse wheel.
+    ZoomBy(f64),
+
+    // Fit photo to the available space.
+    ZoomToFit,
+
+    // Show one photo pixel per screen pixel.
+    ZoomToActualSize,
+
+    // Pinch gesture has started.
+    PinchBegin,
+
+    // Pinch gesture has scaled by a factor since it started.
+    Pinch(f64),
 }
 
 #[derive(Debug)]
 pub enum ViewOneOutput {
     TranscodeAll,
 
+    // Transcode one video, showing progress through the video.
+    Transcode(Arc<Visual>),
+
     PhotoShown(VisualId, glycin::ImageInfo),
 
     VideoShown(VisualId),
+
+    // Video or live photo has played through to the end.
+    MediaEnded,
+
+    // Edits or a new poster frame have changed the thumbnail of a photo or video.
+    Edited,
+
+    // An edited copy of a photo, or a trimmed copy or frame of a video, has been
+    // written to a new file.
+    CopySaved(PathBuf),
+}
+
+/// Photo shown before the current one, so that zoom can be kept when moving
+/// through a burst of similar shots.
+struct ZoomAnchor {
+    visual: Arc<Visual>,
+    width: i32,
+    height: i32,
+    centre: (f64, f64),
 }
 
 pub struct ViewOne {
+    root: gtk::Box,
+
     picture: gtk::Picture,
 
+    // Scrollable view of the zoomed picture.
+    zoom_window: gtk::ScrolledWindow,
+
+    zoom_fixed: gtk::Fixed,
+
+    zoom_picture: gtk::Picture,
+
+    // Zoom relative to the pixels of the photo, or None if the photo is fitted
+    // to the available space.
+    zoom: Option<f64>,
+
+    // Zoom when a pinch gesture started.
+    pinch_start_zoom: f64,
+
+    // Orientation the photo is displayed with, after edits.
+    display_orientation: PictureOrientation,
+
+    zoom_anchor: Option<ZoomAnchor>,
+
+    texture_cache: TextureCache,
+
+    // Cancellable loads in progress, with numbers identifying them.
+    loads: HashMap<PathBuf, (u64, gio::Cancellable)>,
+
+    // Number of most recent load.
+    load_id: u64,
+
+    // Photo to show once its image has loaded.
+    pending_photo: Option<Arc<Visual>>,
+
     video: Option<gtk::MediaFile>,
 
     video_controls: gtk::Box,
@@ -74,24 +321,161 @@ pub struct ViewOne {
 
     mute_button: gtk::Button,
 
+    // Menu to choose the frame thumbnails of a video are made from.
+    poster_button: gtk::MenuButton,
+
+    save_frame_button: gtk::Button,
+
     skip_backwards: gtk::Button,
 
     skip_forward: gtk::Button,
 
     video_timestamp: gtk::Label,
 
+    seek_scale: gtk::Scale,
+
+    trim_button: gtk::ToggleButton,
+
+    trim_controls: gtk::Box,
+
+    trim_label: gtk::Label,
+
+    trim_save_button: gtk::Button,
+
+    // Positions in microseconds that a trimmed copy starts and ends at.
+    trim_start: Option<i64>,
+
+    trim_end: Option<i64>,
+
+    // Shows frames of the video being viewed while hovering over the seek bar.
+    scrub_popover: gtk::Popover,
+
+    scrub_picture: gtk::Picture,
+
+    scrub_label: gtk::Label,
+
+    // Decoded frames for hovering over the seek bar, by position in microseconds.
+    scrub_frames: HashMap<i64, gdk::Texture>,
+
+    // Number identifying the video that frames are decoded from.
+    scrub_id: u64,
+
+    // Position of the frame to show while hovering over the seek bar.
+    scrub_wanted: Option<i64>,
+
+    // Is a frame for hovering over the seek bar being decoded?
+    is_scrub_pending: bool,
+
+    // Path of the video being played.
+    video_source: Option<PathBuf>,
+
+    // Frame rate of the video being played, read in the background when it is opened.
+    frame_rate: Option<f64>,
+
+    // Playback speed. Negative speeds play backwards.
+    speed: f64,
+
+    // Is the video being played by seeking, for speeds that GTK can't play at?
+    is_shuttling: bool,
+
+    // Position when playing by seeking. Seeks finish asynchronously, so the
+    // timestamp of the video can lag behind.
+    shuttle_position: i64,
+
+    // Number of most recent shuttle timer.
+    shuttle_timer: u64,
+
+    // Audio recorded with the photo being viewed, and its player once played.
+    voice_note_path: Option<PathBuf>,
+
+    voice_note: Option<gtk::MediaFile>,
+
+    voice_note_button: gtk::Button,
+
+    voice_note_content: adw::ButtonContent,
+
     transcode_button: gtk::Button,
 
+    transcode_now_button: gtk::Button,
+
     transcode_status: adw::StatusPage,
 
+    // Video being viewed that must be transcoded before it can be played.
+    transcode_visual: Option<Arc<Visual>>,
+
+    // Quick transcode of a video that must be transcoded, played while viewed.
+    proxy: Option<Proxy>,
+
     transcode_progress: Controller<ProgressPanel>,
 
     broken_status: adw::StatusPage,
+
+    photo_repo: photo::Repository,
+
+    photo_thumbnailer: photo::Thumbnailer,
+
+    video_repo: video::Repository,
+
+    video_thumbnailer: video::Thumbnailer,
+
+    // Video being viewed, but not motion photos.
+    video_visual: Option<Arc<Visual>>,
+
+    edit_controls: gtk::Box,
+
+    exposure_scale: gtk::Scale,
+
+    contrast_scale: gtk::Scale,
+
+    saturation_scale: gtk::Scale,
+
+    is_editing: bool,
+
+    is_slideshow: bool,
+
+    // Photo being viewed and its texture before edits are applied.
+    photo: Option<(Arc<Visual>, gdk::Texture)>,
+
+    // Edits for photo being viewed.
+    edits: EditStack,
+
+    // Have edits changed since the thumbnail was generated?
+    is_edits_dirty: bool,
+
+    // Have edits changed since they were saved?
+    is_edits_unsaved: bool,
+
+    // Downscaled photo being viewed, for previewing adjustments while a slider moves.
+    edit_preview: Option<Arc<EditPreview>>,
+
+    // Is a preview being rendered?
+    is_preview_pending: bool,
+
+    // Have edits changed since the preview being rendered was started?
+    is_preview_stale: bool,
+
+    // Is a preview shown instead of the photo rendered at full size?
+    is_previewing: bool,
+
+    // Number of most recent adjustment.
+    adjustment_id: u64,
+
+    // Has the focus changed since the thumbnail was generated?
+    is_focus_dirty: bool,
+
+    // Will the next click on the photo choose the focus?
+    is_choosing_focus: bool,
 }
 
 #[relm4::component(pub async)]
 impl SimpleAsyncComponent for ViewOne {
-    type Init = Arc<Reducer<ProgressMonitor>>;
+    type Init = (
+        Arc<Reducer<ProgressMonitor>>,
+        photo::Repository,
+        photo::Thumbnailer,
+        video::Repository,
+        video::Thumbnailer,
+    );
     type Input = ViewOneInput;
     type Output = ViewOneOutput;
 
@@ -122,6 +506,61 @@ impl SimpleAsyncComponent for ViewOne {
                             add_css_class: "photo-grid-month-label",
                         },
                     },
+
+                    #[local_ref]
+                    seek_scale -> gtk::Scale {
+                        set_width_request: 360,
+                        set_margin_start: 18,
+                        set_margin_end: 18,
+                        set_margin_top: 6,
+                        add_css_class: "osd",
+
+                        connect_change_value[sender] => move |_, _, value| {
+                            sender.input(ViewOneInput::Seek(value));
+                            glib::Propagation::Proceed
+                        },
+                    },
+
+                    #[local_ref]
+                    trim_controls -> gtk::Box {
+                        set_halign: gtk::Align::Center,
+                        set_orientation: gtk::Orientation::Horizontal,
+                        set_margin_top: 6,
+                        set_spacing: 12,
+                        set_visible: false,
+
+                        gtk::Button {
+                            set_label: &fl!("viewer-trim-start"),
+                            set_tooltip_text: Some(&fl!("viewer-trim-start", "tooltip")),
+                            add_css_class: "osd",
+                            connect_clicked => ViewOneInput::TrimStart,
+                        },
+
+                        gtk::Frame {
+                            add_css_class: "osd",
+
+                            #[wrap(Some)]
+                            #[local_ref]
+                            set_child = &trim_label -> gtk::Label {
+                                add_css_class: "photo-grid-month-label",
+                            },
+                        },
+
+                        gtk::Button {
+                            set_label: &fl!("viewer-trim-end"),
+                            set_tooltip_text: Some(&fl!("viewer-trim-end", "tooltip")),
+                            add_css_class: "osd",
+                            connect_clicked => ViewOneInput::TrimEnd,
+                        },
+
+                        #[local_ref]
+                        trim_save_button -> gtk::Button {
+                            set_label: &fl!("viewer-trim-save"),
+                            add_css_class: "osd",
+                            add_css_class: "suggested-action",
+                            connect_clicked => ViewOneInput::SaveTrim,
+                        },
+                    },
                     gtk::Box {
                         set_halign: gtk::Align::Center,
                         set_valign: gtk::Align::End,
@@ -167,14 +606,274 @@ impl SimpleAsyncComponent for ViewOne {
                             set_tooltip_text: Some(&fl!("viewer-mute", "tooltip")),
                             connect_clicked => ViewOneInput::MuteToggle,
                         },
+
+                        #[local_ref]
+                        poster_button -> gtk::MenuButton {
+                            set_icon_name: "image-x-generic-symbolic",
+                            add_css_class: "circular",
+                            add_css_class: "osd",
+                            set_tooltip_text: Some(&fl!("viewer-poster", "tooltip")),
+
+                            #[wrap(Some)]
+                            set_popover = &gtk::Popover {
+                                gtk::Box {
+                                    set_orientation: gtk::Orientation::Vertical,
+
+                                    gtk::Button {
+                                        set_label: &fl!("viewer-poster-current-frame"),
+                                        add_css_class: "flat",
+                                        connect_clicked => ViewOneInput::PosterAtCurrentFrame,
+                                    },
+                                    gtk::Button {
+                                        set_label: &fl!("viewer-poster-automatic"),
+                                        add_css_class: "flat",
+                                        connect_clicked => ViewOneInput::AutomaticPoster,
+                                    },
+                                },
+                            },
+                        },
+
+                        #[local_ref]
+                        save_frame_button -> gtk::Button {
+                            set_icon_name: "camera-photo-symbolic",
+                            add_css_class: "circular",
+                            add_css_class: "osd",
+                            set_tooltip_text: Some(&fl!("viewer-save-frame", "tooltip")),
+                            connect_clicked => ViewOneInput::SaveFrame,
+                        },
+
+                        #[local_ref]
+                        trim_button -> gtk::ToggleButton {
+                            set_icon_name: "edit-cut-symbolic",
+                            add_css_class: "circular",
+                            add_css_class: "osd",
+                            set_tooltip_text: Some(&fl!("viewer-trim", "tooltip")),
+                            connect_toggled[sender] => move |button| {
+                                sender.input(ViewOneInput::Trimming(button.is_active()));
+                            },
+                        },
+                    },
+                },
+
+                #[local_ref]
+                add_overlay = &voice_note_button -> gtk::Button {
+                    set_halign: gtk::Align::End,
+                    set_valign: gtk::Align::End,
+                    set_margin_all: 18,
+                    set_visible: false,
+                    add_css_class: "osd",
+                    add_css_class: "pill",
+                    set_tooltip_text: Some(&fl!("viewer-voice-note", "tooltip")),
+                    connect_clicked => ViewOneInput::VoiceNoteToggle,
+
+                    #[wrap(Some)]
+                    #[local_ref]
+                    set_child = &voice_note_content -> adw::ButtonContent {
+                        set_icon_name: "play-symbolic",
+                        set_label: &fl!("viewer-voice-note"),
+                    },
+                },
+
+                #[local_ref]
+                add_overlay = &edit_controls -> gtk::Box {
+                    set_halign: gtk::Align::Center,
+                    set_valign: gtk::Align::Start,
+                    set_orientation: gtk::Orientation::Horizontal,
+                    set_margin_all: 18,
+                    set_spacing: 12,
+
+                    gtk::Button {
+                        set_icon_name: "object-rotate-left-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-rotate-left", "tooltip")),
+                        connect_clicked => ViewOneInput::Edit(Edit::RotateAntiClockwise),
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "object-rotate-right-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-rotate-right", "tooltip")),
+                        connect_clicked => ViewOneInput::Edit(Edit::RotateClockwise),
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "object-flip-horizontal-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-flip-horizontal", "tooltip")),
+                        connect_clicked => ViewOneInput::Edit(Edit::FlipHorizontal),
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "object-flip-vertical-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-flip-vertical", "tooltip")),
+                        connect_clicked => ViewOneInput::Edit(Edit::FlipVertical),
+                    },
+
+                    gtk::MenuButton {
+                        set_icon_name: "crop-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-crop", "tooltip")),
+
+                        #[wrap(Some)]
+                        set_popover = &gtk::Popover {
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-crop-original"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::Crop(CropAspect::Original),
+                                },
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-crop-square"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::Crop(CropAspect::Square),
+                                },
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-crop-4-3"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::Crop(CropAspect::FourThree),
+                                },
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-crop-3-2"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::Crop(CropAspect::ThreeTwo),
+                                },
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-crop-16-9"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::Crop(CropAspect::SixteenNine),
+                                },
+                            },
+                        },
+                    },
+
+                    gtk::MenuButton {
+                        set_icon_name: "find-location-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-focus", "tooltip")),
+
+                        #[wrap(Some)]
+                        set_popover = &gtk::Popover {
+                            gtk::Box {
+                                set_orientation: gtk::Orientation::Vertical,
+
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-focus-choose"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::ChooseFocus,
+                                },
+                                gtk::Button {
+                                    set_label: &fl!("viewer-edit-focus-automatic"),
+                                    add_css_class: "flat",
+                                    connect_clicked => ViewOneInput::AutomaticFocus,
+                                },
+                            },
+                        },
+                    },
+
+                    gtk::MenuButton {
+                        set_icon_name: "display-brightness-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-adjust", "tooltip")),
+
+                        #[wrap(Some)]
+                        set_popover = &gtk::Popover {
+                            gtk::Grid {
+                                set_row_spacing: 6,
+                                set_column_spacing: 12,
+
+                                attach[0, 0, 1, 1] = &gtk::Label {
+                                    set_label: &fl!("viewer-edit-exposure"),
+                                    set_halign: gtk::Align::Start,
+                                },
+                                #[local_ref]
+                                attach[1, 0, 1, 1] = &exposure_scale -> gtk::Scale {
+                                    set_width_request: 200,
+                                    connect_value_changed[sender] => move |scale| {
+                                        sender.input(ViewOneInput::Exposure(scale.value()));
+                                    },
+                                },
+
+                                attach[0, 1, 1, 1] = &gtk::Label {
+                                    set_label: &fl!("viewer-edit-contrast"),
+                                    set_halign: gtk::Align::Start,
+                                },
+                                #[local_ref]
+                                attach[1, 1, 1, 1] = &contrast_scale -> gtk::Scale {
+                                    set_width_request: 200,
+                                    connect_value_changed[sender] => move |scale| {
+                                        sender.input(ViewOneInput::Contrast(scale.value()));
+                                    },
+                                },
+
+                                attach[0, 2, 1, 1] = &gtk::Label {
+                                    set_label: &fl!("viewer-edit-saturation"),
+                                    set_halign: gtk::Align::Start,
+                                },
+                                #[local_ref]
+                                attach[1, 2, 1, 1] = &saturation_scale -> gtk::Scale {
+                                    set_width_request: 200,
+                                    connect_value_changed[sender] => move |scale| {
+                                        sender.input(ViewOneInput::Saturation(scale.value()));
+                                    },
+                                },
+                            },
+                        },
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "edit-undo-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-undo", "tooltip")),
+                        connect_clicked => ViewOneInput::UndoEdit,
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "edit-clear-all-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-reset", "tooltip")),
+                        connect_clicked => ViewOneInput::ResetEdits,
+                    },
+
+                    gtk::Button {
+                        set_icon_name: "document-save-as-symbolic",
+                        add_css_class: "circular",
+                        add_css_class: "osd",
+                        set_tooltip_text: Some(&fl!("viewer-edit-save-copy", "tooltip")),
+                        connect_clicked => ViewOneInput::SaveCopy,
                     },
                 },
 
                 #[wrap(Some)]
-                set_child = &gtk::Box {
+                #[local_ref]
+                set_child = &zoom_area -> gtk::Box {
                     #[local_ref]
                     picture -> gtk::Picture {
-                    }
+                    },
+
+                    #[local_ref]
+                    zoom_window -> gtk::ScrolledWindow {
+                        set_hexpand: true,
+                        set_vexpand: true,
+                        set_propagate_natural_width: true,
+                        set_propagate_natural_height: true,
+                        set_visible: false,
+
+                        #[wrap(Some)]
+                        #[local_ref]
+                        set_child = &zoom_fixed -> gtk::Fixed {},
+                    },
                 },
             },
 
@@ -195,11 +894,19 @@ impl SimpleAsyncComponent for ViewOne {
                     #[wrap(Some)]
                     set_child = &gtk::Box {
                         set_orientation: gtk::Orientation::Vertical,
+                        set_spacing: 12,
+
+                        #[local_ref]
+                        transcode_now_button -> gtk::Button {
+                            set_label: &fl!("viewer-convert-now-button"),
+                            add_css_class: "suggested-action",
+                            add_css_class: "pill",
+                            connect_clicked => ViewOneInput::TranscodeNow,
+                        },
 
                         #[local_ref]
                         transcode_button -> gtk::Button {
                             set_label: &fl!("viewer-convert-all-button"),
-                            add_css_class: "suggested-action",
                             add_css_class: "pill",
                             connect_clicked => ViewOneInput::TranscodeAll,
                         },
@@ -221,27 +928,177 @@ impl SimpleAsyncComponent for ViewOne {
     }
 
     async fn init(
-        transcode_progress_monitor: Self::Init,
+        (transcode_progress_monitor, photo_repo, photo_thumbnailer, video_repo, video_thumbnailer): Self::Init,
         root: Self::Root,
-        _sender: AsyncComponentSender<Self>,
+        sender: AsyncComponentSender<Self>,
     ) -> AsyncComponentParts<Self>  {
 
         let picture = gtk::Picture::new();
 
+        let zoom_area = gtk::Box::new(gtk::Orientation::Horizontal, 0);
+        let zoom_window = gtk::ScrolledWindow::new();
+        let zoom_fixed = gtk::Fixed::new();
+
+        // The zoomed picture is sized explicitly and must not be shrunk to fit.
+        let zoom_picture = gtk::Picture::new();
+        zoom_picture.set_can_shrink(false);
+        zoom_picture.set_content_fit(gtk::ContentFit::Fill);
+        zoom_fixed.put(&zoom_picture, 0.0, 0.0);
+
+        // Ctrl+scroll zooms. Scrolling without Ctrl zooms a fitted photo, but
+        // pans a zoomed photo.
+        let scroll_controller = gtk::EventControllerScroll::new(gtk::EventControllerScrollFlags::VERTICAL);
+        scroll_controller.set_propagation_phase(gtk::PropagationPhase::Capture);
+        {
+            let sender = sender.clone();
+            let zoom_window = zoom_window.clone();
+            scroll_controller.connect_scroll(move |controller, _, dy| {
+                let is_ctrl = controller.current_event_state().contains(gdk::ModifierType::CONTROL_MASK);
+                if !is_ctrl && zoom_window.is_visible() {
+                    return glib::Propagation::Proceed;
+                }
+
+                let factor = if dy < 0.0 { SCROLL_ZOOM_STEP } else { 1.0 / SCROLL_ZOOM_STEP };
+                sender.input(ViewOneInput::ZoomBy(factor));
+                glib::Propagation::Stop
+            });
+        }
+        zoom_area.add_controller(scroll_controller);
+
+        let zoom_gesture = gtk::GestureZoom::new();
+        {
+            let sender = sender.clone();
+            zoom_gesture.connect_begin(move |_, _| sender.input(ViewOneInput::PinchBegin));
+        }
+        {
+            let sender = sender.clone();
+            zoom_gesture.connect_scale_changed(move |_, scale| sender.input(ViewOneInput::Pinch(scale)));
+        }
+        zoom_area.add_controller(zoom_gesture);
+
+        // Drag to pan a zoomed photo.
+        let drag_gesture = gtk::GestureDrag::new();
+        {
+            let drag_start = Rc::new(Cell::new((0.0, 0.0)));
+            let zoom_window1 = zoom_window.clone();
+            let zoom_window2 = zoom_window.clone();
+            let drag_start1 = drag_start.clone();
+            drag_gesture.connect_drag_begin(move |_, _, _| {
+                drag_start1.set((zoom_window1.hadjustment().value(), zoom_window1.vadjustment().value()));
+            });
+            drag_gesture.connect_drag_update(move |_, dx, dy| {
+                let (x, y) = drag_start.get();
+                zoom_window2.hadjustment().set_value(x - dx);
+                zoom_window2.vadjustment().set_value(y - dy);
+            });
+        }
+        zoom_window.add_controller(drag_gesture);
+
+        // Click to choose the focus of thumbnails. Click positions are relative to the
+        // photo before the orientation transformation, like the focus.
+        let focus_click = gtk::GestureClick::new();
+        {
+            let sender = sender.clone();
+            let picture = picture.clone();
+            focus_click.connect_released(move |_, _, x, y| {
+                let Some(paintable) = picture.paintable() else {
+                    return;
+                };
+
+                // Fitted picture is scaled to fit and centred.
+                let (width, height) = (picture.width() as f64, picture.height() as f64);
+                let image_width = paintable.intrinsic_width() as f64;
+                let image_height = paintable.intrinsic_height() as f64;
+                if image_width <= 0.0 || image_height <= 0.0 {
+                    return;
+                }
+                let scale = (width / image_width).min(height / image_height);
+                let left = (width - image_width * scale) / 2.0;
+                let top = (height - image_height * scale) / 2.0;
+
+                let fx = (x - left) / (image_width * scale);
+                let fy = (y - top) / (image_height * scale);
+                sender.input(ViewOneInput::FocusAt(fx, fy));
+            });
+        }
+        picture.add_controller(focus_click);
+
+        let zoom_focus_click = gtk::GestureClick::new();
+        {
+            let sender = sender.clone();
+            let zoom_picture = zoom_picture.clone();
+            zoom_focus_click.connect_released(move |_, _, x, y| {
+                // Zoomed picture is sized to fill exactly.
+                let fx = x / zoom_picture.width().max(1) as f64;
+                let fy = y / zoom_picture.height().max(1) as f64;
+                sender.input(ViewOneInput::FocusAt(fx, fy));
+            });
+        }
+        zoom_picture.add_controller(zoom_focus_click);
+
         let video_controls = gtk::Box::new(gtk::Orientation::Horizontal, 12);
 
         let play_button = gtk::Button::new();
 
         let mute_button = gtk::Button::new();
 
+        let poster_button = gtk::MenuButton::new();
+
+        let save_frame_button = gtk::Button::new();
+
+        let trim_button = gtk::ToggleButton::new();
+        let trim_controls = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        let trim_label = gtk::Label::new(None);
+        let trim_save_button = gtk::Button::new();
+
         let skip_backwards = gtk::Button::new();
 
         let skip_forward = gtk::Button::new();
 
         let video_timestamp = gtk::Label::new(None);
 
+        let seek_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, 0.0, 1.0, 1.0);
+
+        // Frames are square so that they fit when turned for the display matrix rotation.
+        let scrub_picture = gtk::Picture::new();
+        scrub_picture.set_can_shrink(true);
+        scrub_picture.set_content_fit(gtk::ContentFit::Contain);
+        scrub_picture.set_size_request(SCRUB_FRAME_EDGE as i32, SCRUB_FRAME_EDGE as i32);
+
+        let scrub_label = gtk::Label::new(None);
+
+        let scrub_box = gtk::Box::new(gtk::Orientation::Vertical, 6);
+        scrub_box.append(&scrub_picture);
+        scrub_box.append(&scrub_label);
+
+        // The popover mustn't take the pointer from the seek bar, or it would close
+        // as soon as it opened.
+        let scrub_popover = gtk::Popover::new();
+        scrub_popover.set_child(Some(&scrub_box));
+        scrub_popover.set_position(gtk::PositionType::Top);
+        scrub_popover.set_autohide(false);
+        scrub_popover.set_can_target(false);
+        scrub_popover.set_parent(&seek_scale);
+
+        let scrub_motion = gtk::EventControllerMotion::new();
+        {
+            let sender = sender.clone();
+            scrub_motion.connect_motion(move |_, x, _| sender.input(ViewOneInput::ScrubHover(x)));
+        }
+        {
+            let sender = sender.clone();
+            scrub_motion.connect_leave(move |_| sender.input(ViewOneInput::ScrubLeave));
+        }
+        seek_scale.add_controller(scrub_motion);
+
+        let voice_note_button = gtk::Button::new();
+
+        let voice_note_content = adw::ButtonContent::new();
+
         let transcode_button = gtk::Button::new();
 
+        let transcode_now_button = gtk::Button::new();
+
         let transcode_progress = ProgressPanel::builder()
             .launch(transcode_progress_monitor.clone())
             .detach();
@@ -250,19 +1107,89 @@ impl SimpleAsyncComponent for ViewOne {
 
         let broken_status = adw::StatusPage::new();
 
+        let edit_controls = gtk::Box::new(gtk::Orientation::Horizontal, 12);
+        edit_controls.set_visible(false);
+
+        let exposure_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, -2.0, 2.0, 0.1);
+        let contrast_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, -1.0, 1.0, 0.05);
+        let saturation_scale = gtk::Scale::with_range(gtk::Orientation::Horizontal, -1.0, 1.0, 0.05);
+
         let model = ViewOne {
+            root: root.clone(),
             picture: picture.clone(),
+            zoom_window: zoom_window.clone(),
+            zoom_fixed: zoom_fixed.clone(),
+            zoom_picture,
+            zoom: None,
+            pinch_start_zoom: 1.0,
+            display_orientation: PictureOrientation::North,
+            zoom_anchor: None,
+            texture_cache: TextureCache::new(TEXTURE_CACHE_BUDGET),
+            loads: HashMap::new(),
+            load_id: 0,
+            pending_photo: None,
             video: None,
             video_controls: video_controls.clone(),
             play_button: play_button.clone(),
             mute_button: mute_button.clone(),
+            poster_button: poster_button.clone(),
+            save_frame_button: save_frame_button.clone(),
             skip_backwards: skip_backwards.clone(),
             skip_forward: skip_forward.clone(),
             video_timestamp: video_timestamp.clone(),
+            seek_scale: seek_scale.clone(),
+            trim_button: trim_button.clone(),
+            trim_controls: trim_controls.clone(),
+            trim_label: trim_label.clone(),
+            trim_save_button: trim_save_button.clone(),
+            trim_start: None,
+            trim_end: None,
+            scrub_popover,
+            scrub_picture,
+            scrub_label,
+            scrub_frames: HashMap::new(),
+            scrub_id: 0,
+            scrub_wanted: None,
+            is_scrub_pending: false,
+            video_source: None,
+            frame_rate: None,
+            speed: 1.0,
+            is_shuttling: false,
+            shuttle_position: 0,
+            shuttle_timer: 0,
+            voice_note_path: None,
+            voice_note: None,
+            voice_note_button: voice_note_button.clone(),
+            voice_note_content: voice_note_content.clone(),
             transcode_button: transcode_button.clone(),
+            transcode_now_button: transcode_now_button.clone(),
             transcode_status: transcode_status.clone(),
+            transcode_visual: None,
+            proxy: None,
             transcode_progress,
             broken_status: broken_status.clone(),
+            photo_repo,
+            photo_thumbnailer,
+            video_repo,
+            video_thumbnailer,
+            video_visual: None,
+            edit_controls: edit_controls.clone(),
+            exposure_scale: exposure_scale.clone(),
+            contrast_scale: contrast_scale.clone(),
+            saturation_scale: saturation_scale.clone(),
+            is_editing: false,
+            is_slideshow: false,
+            photo: None,
+            edits: EditStack::new(),
+            is_edits_dirty: false,
+            is_edits_unsaved: false,
+            edit_preview: None,
+            is_preview_pending: false,
+            is_preview_stale: false,
+            is_previewing: false,
+            adjustment_id: 0,
+            is_focus_dirty: false,
+            is_choosing_focus: false,
         };
 
         let widgets = view_output!();
@@ -273,13 +1200,57 @@ impl SimpleAsyncComponent for ViewOne {
     async fn update(&mut self, msg: Self::Input, sender: AsyncComponentSender<Self>) {
         match msg {
             ViewOneInput::Hidden => {
+                self.commit_edits(&sender).await;
+                self.photo = None;
+                self.edit_preview = None;
+                self.stop_shuttle();
+                self.clear_scrub();
                 self.video = None;
+                self.proxy = None;
+                self.stop_voice_note();
+                self.trim_button.set_active(false);
+                self.video_visual = None;
                 self.picture.set_paintable(None::<&gdk::Paintable>);
+                self.zoom = None;
+                self.zoom_window.set_visible(false);
+                self.zoom_picture.set_paintable(None::<&gdk::Paintable>);
+
+                self.pending_photo = None;
+                for (_, cancellable) in self.loads.values() {
+                    cancellable.cancel();
+                }
+                self.loads.clear();
+                self.texture_cache.clear();
             },
             ViewOneInput::View(visual) => {
                 event!(Level::INFO, "Showing item for {}", visual.visual_id);
 
+                self.commit_edits(&sender).await;
+
+                // Remember zoom in case the next photo is from the same burst of shots.
+                let centre = self.scroll_centre();
+                self.zoom_anchor = self.photo.take()
+                    .filter(|_| self.zoom.is_some())
+                    .map(|(visual, texture)| ZoomAnchor {
+                        visual,
+                        width: texture.width(),
+                        height: texture.height(),
+                        centre,
+                    });
+                self.pending_photo = None;
+
+                self.edits = EditStack::new();
+                self.edit_controls.set_visible(false);
+                self.video_visual = None;
+                self.transcode_visual = None;
+                self.proxy = None;
+
+                self.stop_voice_note();
+                self.voice_note_path = visual.voice_note_path.clone().filter(|x| x.exists());
+                self.voice_note_button.set_visible(self.voice_note_path.is_some() && !self.is_slideshow);
+
                 self.picture.set_visible(false);
+                self.zoom_window.set_visible(false);
                 self.transcode_status.set_visible(false);
                 self.video_controls.set_visible(false);
                 self.broken_status.set_visible(false);
@@ -311,88 +1282,126 @@ impl SimpleAsyncComponent for ViewOne {
                 }
 
                 self.picture.set_paintable(None::<&gdk::Paintable>);
+                self.stop_shuttle();
+                self.clear_scrub();
                 self.video = None;
+                self.speed = 1.0;
+                self.trim_button.set_active(false);
 
                 // clear orientation transformation css classes
                 for orient in PictureOrientation::iter() {
                     self.picture.remove_css_class(orient.as_ref());
+                    self.scrub_picture.remove_css_class(orient.as_ref());
                 }
 
                 if visual.is_photo_only() {
-                    // Apply a CSS transformation to respect the EXIF orientation
-                    let orientation = visual.picture_orientation
-                        .unwrap_or(PictureOrientation::North);
-                    self.picture.add_css_class(orientation.as_ref());
-
-                    let file = gio::File::for_path(visual_path);
-
-                    let image = glycin::Loader::new(file).load().await;
-
-                    let Ok(image) = image else {
-                        event!(Level::ERROR, "Failed loading image: {:?}", image);
-                        self.broken_status.set_icon_name(Some("sad-computer-symbolic"));
-                        self.broken_status.set_description(Some(&fl!("viewer-error-failed-to-load")));
-                        self.broken_status.set_visible(true);
-                        return;
-                    };
-
-                    let frame = image.next_frame().await;
-                    let Ok(frame) = frame else {
-                        event!(Level::ERROR, "Failed getting image frame: {:?}", frame);
-                        self.broken_status.set_icon_name(Some("sad-computer-symbolic"));
-                        self.broken_status.set_description(Some(&fl!("viewer-error-failed-to-load")));
-                        self.broken_status.set_visible(true);
-                        return;
-                    };
+                    if let Some(image) = self.texture_cache.get(visual_path) {
+                        self.show_photo(&sender, visual, image);
+                    } else {
+                        // Photo is shown when its load, or a preload already in progress, finishes.
+                        let path = visual_path.clone();
+                        self.pending_photo = Some(visual);
+                        self.load(&sender, path);
+                    }
+                } else { // video or motion photo
+                    let is_transcoded = visual.video_transcoded_path.as_ref().is_some_and(|x| x.exists());
 
-                    let texture = frame.texture;
+                    self.zoom = None;
 
-                    self.picture.set_paintable(Some(&texture));
-                    self.picture.set_visible(true);
+                    let is_transcode_pending = visual.is_transcode_required.is_some_and(|x| x) && !is_transcoded;
+                    if is_transcode_pending {
+                        self.transcode_visual = Some(visual.clone());
+                        self.proxy = Self::start_proxy(&visual);
 
-                    let _ = sender.output(ViewOneOutput::PhotoShown(visual.visual_id.clone(), image.info().clone()));
-                } else { // video or motion photo
-                    let is_transcoded = visual.video_transcoded_path.as_ref().is_some_and(|x| x.exists());
+                        // The proxy is discarded afterwards, so keep a full transcode
+                        // for next time if videos are transcoded automatically.
+                        let settings = gio::Settings::new(APP_ID);
+                        if self.proxy.is_some() && settings.boolean("transcode-automatic") {
+                            let _ = sender.output(ViewOneOutput::Transcode(visual.clone()));
+                        }
+                    }
 
-                    if visual.is_transcode_required.is_some_and(|x| x) && !is_transcoded {
+                    if is_transcode_pending && self.proxy.is_none() {
                         self.picture.set_visible(false);
                         self.transcode_status.set_visible(true);
+                        self.transcode_now_button.set_visible(true);
                         self.video_controls.set_visible(false);
                     } else {
                         self.picture.set_visible(true);
                         self.transcode_status.set_visible(false);
-                        self.video_controls.set_visible(true);
+                        self.video_controls.set_visible(!self.is_slideshow);
 
-                        // if a video is transcoded then the rotation transformation will
-                        // already have been applied.
-                        if !is_transcoded {
+                        // if a video is transcoded, or played through a proxy, then the
+                        // rotation transformation will already have been applied.
+                        if !is_transcoded && self.proxy.is_none() {
                             // Apply a CSS transformation to respect the display matrix rotation
                             let orientation = visual.video_orientation
                                 .unwrap_or(PictureOrientation::North);
                             self.picture.add_css_class(orientation.as_ref());
+                            self.scrub_picture.add_css_class(orientation.as_ref());
                         }
 
-                        let video_path = visual.video_transcoded_path.as_ref()
-                            .filter(|x| x.exists())
-                            .or_else(|| visual.video_path.as_ref())
-                            .filter(|x| x.exists())
-                            .or_else(|| visual.motion_photo_video_path.as_ref())
-                            .expect("must have video path");
-
-                        let video = gtk::MediaFile::for_filename(video_path);
+                        let video_path = match self.proxy {
+                            Some(ref proxy) => proxy.path().to_path_buf(),
+                            None => visual.video_transcoded_path.as_ref()
+                                .filter(|x| x.exists())
+                                .or_else(|| visual.video_path.as_ref())
+                                .filter(|x| x.exists())
+                                .or_else(|| visual.motion_photo_video_path.as_ref())
+                                .expect("must have video path")
+                                .clone(),
+                        };
+
+                        let video = gtk::MediaFile::for_filename(&video_path);
                         if visual.is_motion_photo() {
                            self.mute_button.set_icon_name("audio-volume-muted-symbolic");
+                           self.poster_button.set_visible(false);
+                           self.save_frame_button.set_visible(false);
+                           self.trim_button.set_visible(false);
                            self.skip_backwards.set_visible(false);
                            self.skip_forward.set_visible(false);
                            self.video_timestamp.set_visible(false);
+                           self.seek_scale.set_visible(false);
                            video.set_muted(true);
-                           video.set_loop(true);
+
+                           if self.is_slideshow {
+                               let sender = sender.clone();
+                               video.set_loop(false);
+                               video.connect_ended_notify(move |_| sender.input(ViewOneInput::VideoEnded));
+                           } else if let Some(still_ts) = visual.motion_photo_still_ts {
+                               // Play once and then rest on the frame the still photo was taken from,
+                               // so the motion leads up to the photo.
+                               let still_micros = still_ts.num_microseconds().unwrap_or(0);
+                               video.set_loop(false);
+                               video.connect_ended_notify(move |video| {
+                                   if video.is_ended() {
+                                       video.seek(still_micros);
+                                       video.pause();
+                                   }
+                               });
+                           } else {
+                               video.set_loop(true);
+                           }
                         } else {
+                            // Proxies are streamed through a pipe, so can't be seeked.
+                            let is_seekable = self.proxy.is_none();
+
                             self.mute_button.set_icon_name("multimedia-volume-control-symbolic");
-                            self.skip_backwards.set_visible(true);
-                            self.skip_forward.set_visible(true);
+                            self.poster_button.set_visible(is_seekable);
+                            self.save_frame_button.set_visible(is_seekable);
+                            self.trim_button.set_visible(is_seekable);
+                            self.skip_backwards.set_visible(is_seekable);
+                            self.skip_forward.set_visible(is_seekable);
                             self.skip_forward.set_sensitive(true);
                             self.video_timestamp.set_visible(true);
+                            self.seek_scale.set_visible(is_seekable);
+                            self.seek_scale.set_value(0.0);
+
+                            if is_seekable {
+                                self.video_visual = Some(visual.clone());
+                                self.video_source = Some(video_path);
+                                self.read_frame_rate(&sender);
+                            }
 
                             // Instead of video.set_muted(false), we must mute and then
                             // send a message to unmute. This seems to work around the problem
@@ -420,11 +1429,67 @@ impl SimpleAsyncComponent for ViewOne {
                     }
                 }
             },
+            ViewOneInput::Preload(visuals) => {
+                let mut wanted: HashSet<PathBuf> = visuals.iter()
+                    .filter(|v| v.is_photo_only())
+                    .filter_map(|v| v.picture_path.clone())
+                    .collect();
+
+                if let Some(path) = self.pending_photo.as_ref().and_then(|v| v.picture_path.clone()) {
+                    wanted.insert(path);
+                }
+
+                // Stop decoding items the user has moved away from.
+                self.loads.retain(|path, (_, cancellable)| {
+                    let is_wanted = wanted.contains(path);
+                    if !is_wanted {
+                        event!(Level::DEBUG, "Cancelling load of {:?}", path);
+                        cancellable.cancel();
+                    }
+                    is_wanted
+                });
+
+                for path in wanted {
+                    self.load(&sender, path);
+                }
+            },
+            ViewOneInput::Loaded(path, load_id, result) => {
+                if self.loads.get(&path).map(|(id, _)| *id) != Some(load_id) {
+                    return;
+                }
+                self.loads.remove(&path);
+
+                let is_pending = self.pending_photo.as_ref()
+                    .is_some_and(|v| v.picture_path.as_ref() == Some(&path));
+
+                match result {
+                    Ok(image) => {
+                        self.texture_cache.insert(path, image.clone());
+                        if is_pending {
+                            if let Some(visual) = self.pending_photo.take() {
+                                self.show_photo(&sender, visual, image);
+                            }
+                        }
+                    },
+                    Err(e) if is_pending => {
+                        event!(Level::ERROR, "{:?}", e);
+                        self.pending_photo = None;
+                        self.broken_status.set_icon_name(Some("sad-computer-symbolic"));
+                        self.broken_status.set_description(Some(&fl!("viewer-error-failed-to-load")));
+                        self.broken_status.set_visible(true);
+                    },
+                    Err(e) => {
+                        event!(Level::WARN, "Failed preloading {:?}: {:?}", path, e);
+                    },
+                }
+            },
             ViewOneInput::Prepared => {
                 // Video details, like duration, aren't available until the video
                 // has been prepared.
                 if let Some(ref video) = self.video {
-                    if video.duration() < FIFTEEN_SECS_IN_MICROS {
+                    self.seek_scale.set_range(0.0, video.duration() as f64 / 1_000_000.0);
+
+                    if video.duration() < FIFTEEN_SECS_IN_MICROS || !video.is_seekable() {
                         self.skip_backwards.set_visible(false);
                         self.skip_forward.set_visible(false);
                     } else {
@@ -445,8 +1510,15 @@ impl SimpleAsyncComponent for ViewOne {
                 }
             },
             ViewOneInput::PlayToggle => {
-                if let Some(ref video) = self.video {
-                    if video.is_ended() {
+                if self.is_playing() {
+                    self.pause();
+                } else if let Some(ref video) = self.video {
+                    if video.is_ended() && self.proxy.is_some() {
+                        // A pipe can't be rewound, so stream the video again.
+                        if let Some(visual) = self.transcode_visual.clone() {
+                            sender.input(ViewOneInput::View(visual));
+                        }
+                    } else if video.is_ended() {
                         video.seek(0);
 
                         // I'd like to just set the play_button icon to pause-symbolic and
@@ -458,16 +1530,40 @@ impl SimpleAsyncComponent for ViewOne {
                         video.play();
                         video.pause();
                         sender.input(ViewOneInput::PlayToggle);
-                    } else if video.is_playing() {
-                        video.pause();
-                        self.play_button.set_icon_name("play-symbolic");
                     } else { // is paused
-                        video.play();
-                        self.play_button.set_icon_name("pause-symbolic");
+                        self.play_at_speed(&sender);
                         self.skip_forward.set_sensitive(true);
                     }
                 }
             },
+            ViewOneInput::VoiceNoteToggle => {
+                if let Some(ref voice_note) = self.voice_note {
+                    if voice_note.is_playing() {
+                        voice_note.pause();
+                        self.voice_note_content.set_icon_name("play-symbolic");
+                    } else {
+                        if voice_note.is_ended() {
+                            voice_note.seek(0);
+                        }
+                        voice_note.play();
+                        self.voice_note_content.set_icon_name("pause-symbolic");
+                    }
+                } else if let Some(ref path) = self.voice_note_path {
+                    let voice_note = gtk::MediaFile::for_filename(path);
+                    let sender = sender.clone();
+                    voice_note.connect_ended_notify(move |media| {
+                        if media.is_ended() {
+                            sender.input(ViewOneInput::VoiceNoteEnded);
+                        }
+                    });
+                    voice_note.play();
+                    self.voice_note_content.set_icon_name("pause-symbolic");
+                    self.voice_note = Some(voice_note);
+                }
+            },
+            ViewOneInput::VoiceNoteEnded => {
+                self.voice_note_content.set_icon_name("play-symbolic");
+            },
             ViewOneInput::SkipBackwards => {
                 if let Some(ref video) = self.video {
                     let ts = video.timestamp();
@@ -499,19 +1595,1121 @@ impl SimpleAsyncComponent for ViewOne {
             ViewOneInput::VideoEnded => {
                 self.play_button.set_icon_name("arrow-circular-top-left-symbolic");
                 self.skip_forward.set_sensitive(false);
+
+                let is_ended = self.video.as_ref().is_some_and(|video| video.is_ended());
+                if is_ended {
+                    let _ = sender.output(ViewOneOutput::MediaEnded);
+                }
+            },
+            ViewOneInput::Slideshow(is_slideshow) => {
+                self.is_slideshow = is_slideshow;
+                self.edit_controls.set_visible(self.is_editing && !is_slideshow && self.photo.is_some());
+                if self.video.is_some() {
+                    self.video_controls.set_visible(!is_slideshow);
+                }
+                self.voice_note_button.set_visible(self.voice_note_path.is_some() && !is_slideshow);
             },
             ViewOneInput::Timestamp => {
+                self.update_timestamp();
+            },
+            ViewOneInput::Seek(seconds) => {
                 if let Some(ref video) = self.video {
-                    let current_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(video.timestamp()));
-                    let total_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(video.duration()));
-                    self.video_timestamp.set_text(&format!("{}/{}", current_ts, total_ts));
+                    let ts = ((seconds * 1_000_000.0) as i64).clamp(0, video.duration());
+                    if video.is_ended() {
+                        // Playing and pausing leaves the ended state, as when skipping backwards.
+                        video.play();
+                        video.pause();
+                        self.play_button.set_icon_name("play-symbolic");
+                        self.skip_forward.set_sensitive(true);
+                    }
+                    video.seek(ts);
+                    self.shuttle_position = ts;
+                }
+            },
+            ViewOneInput::ScrubHover(x) => {
+                let Some(ref video) = self.video else {
+                    return;
+                };
+
+                let width = self.seek_scale.width() as f64;
+                if width <= 0.0 || video.duration() <= 0 {
+                    return;
+                }
+
+                let position = (video.duration() as f64 * (x / width).clamp(0.0, 1.0)) as i64;
+                let step = position - position % SCRUB_STEP_MICROS;
+
+                self.scrub_label.set_text(&fotema_core::time::format_hhmmss(&TimeDelta::microseconds(position)));
+                self.scrub_popover.set_pointing_to(Some(&gdk::Rectangle::new(x as i32, 0, 1, 1)));
+                self.scrub_wanted = Some(step);
+
+                if let Some(texture) = self.scrub_frames.get(&step) {
+                    self.scrub_picture.set_paintable(Some(texture));
+                } else {
+                    self.decode_scrub_frame(&sender);
+                }
+
+                self.scrub_popover.popup();
+            },
+            ViewOneInput::ScrubLeave => {
+                self.scrub_wanted = None;
+                self.scrub_popover.popdown();
+            },
+            ViewOneInput::FrameRateRead(scrub_id, frame_rate) => {
+                if scrub_id == self.scrub_id {
+                    self.frame_rate = frame_rate;
+                }
+            },
+            ViewOneInput::ScrubFrame(scrub_id, position, result) => {
+                if scrub_id != self.scrub_id {
+                    return;
+                }
+                self.is_scrub_pending = false;
+
+                match result {
+                    Ok(texture) => {
+                        if self.scrub_wanted == Some(position) {
+                            self.scrub_picture.set_paintable(Some(&texture));
+                        }
+                        self.scrub_frames.insert(position, texture);
+                    },
+                    Err(e) => {
+                        event!(Level::WARN, "Failed decoding frame for seek bar: {:?}", e);
+                    },
+                }
+
+                // Pointer might have moved on while the frame was decoding.
+                if self.scrub_wanted.is_some_and(|wanted| wanted != position) {
+                    self.decode_scrub_frame(&sender);
+                }
+            },
+            ViewOneInput::FrameStep(frames) => {
+                if self.video_visual.is_none() {
+                    return;
+                }
+                self.pause();
+
+                let frame_rate = self.frame_rate.unwrap_or(DEFAULT_FRAME_RATE);
+
+                if let Some(ref video) = self.video {
+                    let frame_micros = (1_000_000.0 / frame_rate) as i64;
+                    let ts = (video.timestamp() + frames * frame_micros).clamp(0, video.duration());
+                    video.seek(ts);
+                    self.shuttle_position = ts;
+                }
+            },
+            ViewOneInput::ShuttleBackwards => {
+                if self.video_visual.is_none() {
+                    return;
+                }
+                self.speed = if self.is_playing() && self.speed < 0.0 {
+                    next_speed(self.speed, true)
+                } else {
+                    -1.0
+                };
+                self.play_at_speed(&sender);
+            },
+            ViewOneInput::ShuttlePause => {
+                if self.video_visual.is_none() {
+                    return;
+                }
+                if self.is_playing() {
+                    self.pause();
+                } else {
+                    self.speed = 1.0;
+                    sender.input(ViewOneInput::PlayToggle);
+                }
+            },
+            ViewOneInput::ShuttleForward => {
+                if self.video_visual.is_none() {
+                    return;
+                }
+                self.speed = if self.is_playing() && self.speed > 0.0 {
+                    next_speed(self.speed, true)
+                } else {
+                    1.0
+                };
+                self.play_at_speed(&sender);
+            },
+            ViewOneInput::ShuttleTick(timer) => {
+                if !self.is_shuttling || timer != self.shuttle_timer {
+                    return;
+                }
+                let Some(video) = self.video.clone() else {
+                    return;
+                };
+
+                let step = (self.speed * SHUTTLE_INTERVAL_MILLIS as f64 * 1000.0) as i64;
+                let ts = (self.shuttle_position + step).clamp(0, video.duration());
+                video.seek(ts);
+                self.shuttle_position = ts;
+                self.update_timestamp();
+
+                if ts == 0 || ts == video.duration() {
+                    self.pause();
+                } else {
+                    self.schedule_shuttle(&sender);
+                }
+            },
+            ViewOneInput::SpeedUp => {
+                if self.video_visual.is_some() {
+                    self.change_speed(next_speed(self.speed, true), &sender);
+                }
+            },
+            ViewOneInput::SlowDown => {
+                if self.video_visual.is_some() {
+                    self.change_speed(next_speed(self.speed, false), &sender);
+                }
+            },
+            ViewOneInput::Editing(is_editing) => {
+                self.is_editing = is_editing;
+                self.edit_controls.set_visible(is_editing && !self.is_slideshow && self.photo.is_some());
+                if !is_editing {
+                    // Show adjustments at full size if the sliders haven't settled yet.
+                    if self.is_previewing {
+                        self.render_photo();
+                    }
+                    self.commit_edits(&sender).await;
+                }
+            },
+            ViewOneInput::Edit(edit) => {
+                self.edits.push(edit);
+                self.edits_changed();
+            },
+            ViewOneInput::Crop(aspect) => {
+                let Some((ref visual, ref texture)) = self.photo else {
+                    return;
+                };
+
+                // Crop is relative to the photo as currently displayed, without any earlier crop.
+                let orientation = visual.corrected_picture_orientation();
+                let orientation = self.edits.display_orientation(orientation);
+                let (width, height) = if orientation.is_sideways() {
+                    (texture.height(), texture.width())
+                } else {
+                    (texture.width(), texture.height())
+                };
+
+                let rect = CropRect::centred(aspect, width as u32, height as u32);
+                self.edits.push(Edit::Crop(rect));
+                self.edits_changed();
+            },
+            ViewOneInput::Exposure(value) => {
+                if self.edits.adjustments().exposure != value as f32 {
+                    self.edits.push(Edit::Exposure(value as f32));
+                    self.adjustment_changed(&sender);
+                }
+            },
+            ViewOneInput::Contrast(value) => {
+                if self.edits.adjustments().contrast != value as f32 {
+                    self.edits.push(Edit::Contrast(value as f32));
+                    self.adjustment_changed(&sender);
+                }
+            },
+            ViewOneInput::Saturation(value) => {
+                if self.edits.adjustments().saturation != value as f32 {
+                    self.edits.push(Edit::Saturation(value as f32));
+                    self.adjustment_changed(&sender);
+                }
+            },
+            ViewOneInput::PreviewRendered(visual_id, result) => {
+                self.is_preview_pending = false;
+
+                let is_same_photo = self.photo.as_ref().is_some_and(|(visual, _)| visual.visual_id == visual_id);
+                if !is_same_photo || !self.is_previewing {
+                    return;
+                }
+
+                match result {
+                    Ok((preview, texture)) => {
+                        self.edit_preview = Some(preview);
+
+                        // Zoomed size is kept, so the preview is scaled up to fill it.
+                        self.picture.set_paintable(Some(&texture));
+                        if self.zoom.is_some() {
+                            self.zoom_picture.set_paintable(Some(&texture));
+                        }
+                    },
+                    Err(e) => event!(Level::ERROR, "Failed rendering preview of edits: {:?}", e),
+                }
+
+                if self.is_preview_stale {
+                    self.render_preview(&sender);
+                }
+            },
+            ViewOneInput::AdjustmentSettled(adjustment_id) => {
+                if adjustment_id == self.adjustment_id && self.is_previewing {
+                    self.is_previewing = false;
+                    self.save_edits();
+                    self.render_photo();
+                }
+            },
+            ViewOneInput::UndoEdit => {
+                if self.edits.undo().is_some() {
+                    self.edits_changed();
+                }
+            },
+            ViewOneInput::ResetEdits => {
+                if !self.edits.is_empty() {
+                    self.edits.clear();
+                    self.edits_changed();
+                }
+            },
+            ViewOneInput::SaveCopy => {
+                let Some((ref visual, _)) = self.photo else {
+                    return;
+                };
+
+                let Some(path) = visual.picture_path.clone() else {
+                    return;
+                };
+
+                let orientation = visual.corrected_picture_orientation();
+                let edits = self.edits.clone();
+
+                // Decoding, rendering and encoding a full size photo is slow, so keep it
+                // off the main thread.
+                let result = relm4::spawn_blocking(move || block_on(edits.save_copy(&path, orientation)))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("Saving copy panicked: {:?}", e)));
+
+                match result {
+                    Ok(copy_path) => {
+                        event!(Level::INFO, "Saved edited copy to {:?}", copy_path);
+                        let _ = sender.output(ViewOneOutput::CopySaved(copy_path));
+                    },
+                    Err(e) => {
+                        event!(Level::ERROR, "Failed saving edited copy: {:?}", e);
+                        self.show_error(&fl!("viewer-edit-save-copy-failed"), &e);
+                    },
+                }
+            },
+            ViewOneInput::ChooseFocus => {
+                self.is_choosing_focus = self.photo.is_some();
+                self.set_focus_cursor();
+            },
+            ViewOneInput::FocusAt(fx, fy) => {
+                if !self.is_choosing_focus {
+                    return;
+                }
+                self.is_choosing_focus = false;
+                self.set_focus_cursor();
+
+                // Clicks outside of the photo, such as beside a fitted photo, choose nothing.
+                if !(0.0..=1.0).contains(&fx) || !(0.0..=1.0).contains(&fy) {
+                    return;
+                }
+
+                self.set_focus(Some(Focus::new(fx, fy)));
+            },
+            ViewOneInput::AutomaticFocus => {
+                self.is_choosing_focus = false;
+                self.set_focus_cursor();
+                self.set_focus(None);
+            },
+            ViewOneInput::PosterAtCurrentFrame => {
+                let position = self.video.as_ref()
+                    .map(|video| TimeDelta::microseconds(video.timestamp()));
+                if position.is_some() {
+                    self.set_poster(position, &sender).await;
+                }
+            },
+            ViewOneInput::AutomaticPoster => {
+                self.set_poster(None, &sender).await;
+            },
+            ViewOneInput::Trimming(is_trimming) => {
+                self.trim_controls.set_visible(is_trimming);
+                self.trim_start = None;
+                self.trim_end = None;
+                self.update_trim();
+            },
+            ViewOneInput::TrimStart => {
+                if let Some(ref video) = self.video {
+                    let position = video.timestamp();
+                    self.trim_start = Some(position);
+                    self.trim_end = self.trim_end.filter(|end| *end > position);
+                    self.update_trim();
+                }
+            },
+            ViewOneInput::TrimEnd => {
+                if let Some(ref video) = self.video {
+                    let position = video.timestamp();
+                    self.trim_end = Some(position);
+                    self.trim_start = self.trim_start.filter(|start| *start < position);
+                    self.update_trim();
+                }
+            },
+            ViewOneInput::SaveTrim => {
+                let Some(path) = self.video_visual.as_ref().and_then(|v| v.video_path.clone()) else {
+                    return;
+                };
+                let Some(ref video) = self.video else {
+                    return;
+                };
+
+                // Unset markers trim from the start or to the end.
+                let start = TimeDelta::microseconds(self.trim_start.unwrap_or(0));
+                let end = TimeDelta::microseconds(self.trim_end.unwrap_or(video.duration()));
+
+                self.trim_save_button.set_sensitive(false);
+
+                // Re-encoding is slow, so keep it off the main thread.
+                let result = relm4::spawn_blocking(move || video::clip::trim(&path, start, end))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("Trim panicked: {:?}", e)));
+
+                self.trim_save_button.set_sensitive(true);
+
+                match result {
+                    Ok(copy_path) => {
+                        event!(Level::INFO, "Saved trimmed copy to {:?}", copy_path);
+                        self.trim_button.set_active(false);
+                        let _ = sender.output(ViewOneOutput::CopySaved(copy_path));
+                    },
+                    Err(e) => {
+                        event!(Level::ERROR, "Failed saving trimmed copy: {:?}", e);
+                        self.show_error(&fl!("viewer-trim-save-failed"), &e);
+                    },
+                }
+            },
+            ViewOneInput::SaveFrame => {
+                let Some(path) = self.video_visual.as_ref().and_then(|v| v.video_path.clone()) else {
+                    return;
+                };
+                let Some(ref video) = self.video else {
+                    return;
+                };
+
+                let position = TimeDelta::microseconds(video.timestamp());
+
+                let result = relm4::spawn_blocking(move || video::clip::save_frame(&path, position))
+                    .await
+                    .unwrap_or_else(|e| Err(anyhow::anyhow!("Saving frame panicked: {:?}", e)));
+
+                match result {
+                    Ok(frame_path) => {
+                        event!(Level::INFO, "Saved frame to {:?}", frame_path);
+                        let _ = sender.output(ViewOneOutput::CopySaved(frame_path));
+                    },
+                    Err(e) => {
+                        event!(Level::ERROR, "Failed saving frame: {:?}", e);
+                        self.show_error(&fl!("viewer-save-frame-failed"), &e);
+                    },
                 }
             },
+            ViewOneInput::ZoomIn => {
+                self.zoom_to(self.current_zoom() * ZOOM_STEP);
+            },
+            ViewOneInput::ZoomOut => {
+                self.zoom_to(self.current_zoom() / ZOOM_STEP);
+            },
+            ViewOneInput::ZoomBy(factor) => {
+                self.zoom_to(self.current_zoom() * factor);
+            },
+            ViewOneInput::ZoomToFit => {
+                self.zoom_to(0.0);
+            },
+            ViewOneInput::ZoomToActualSize => {
+                self.zoom_to(1.0 / self.root.scale_factor() as f64);
+            },
+            ViewOneInput::PinchBegin => {
+                self.pinch_start_zoom = self.current_zoom();
+            },
+            ViewOneInput::Pinch(scale) => {
+                self.zoom_to(self.pinch_start_zoom * scale);
+            },
             ViewOneInput::TranscodeAll => {
                 event!(Level::INFO, "Transcode all");
                 self.transcode_button.set_visible(false);
                 let _ = sender.output(ViewOneOutput::TranscodeAll);
             },
+            ViewOneInput::Transcoded(visual) => {
+                let is_waiting = self.transcode_visual.as_ref()
+                    .is_some_and(|x| x.visual_id == visual.visual_id);
+                if !is_waiting {
+                    return;
+                }
+
+                if self.proxy.is_some() {
+                    // Keep playing the proxy. Playing again, or viewing the video
+                    // later, plays the transcoded video.
+                    self.transcode_visual = Some(visual);
+                } else {
+                    sender.input(ViewOneInput::View(visual));
+                }
+            },
+            ViewOneInput::TranscodeNow => {
+                let Some(ref visual) = self.transcode_visual else {
+                    return;
+                };
+
+                event!(Level::INFO, "Transcode {}", visual.visual_id);
+
+                // The video plays when it has been transcoded, if still being viewed.
+                self.transcode_now_button.set_visible(false);
+                let _ = sender.output(ViewOneOutput::Transcode(visual.clone()));
+            },
+        }
+    }
+}
+
+impl ViewOne {
+    /// Start streaming a quick transcode of a video that GTK can't play, unless
+    /// streaming is turned off in preferences.
+    fn start_proxy(visual: &Visual) -> Option<Proxy> {
+        let settings = gio::Settings::new(APP_ID);
+        if !settings.boolean("transcode-streaming") {
+            return None;
+        }
+
+        let video_path = visual.video_path.as_ref()
+            .filter(|x| x.exists())
+            .or(visual.motion_photo_video_path.as_ref())
+            .filter(|x| x.exists())?;
+
+        Proxy::start(video_path)
+            .map_err(|e| event!(Level::ERROR, "Failed starting proxy for {:?}: {:?}", video_path, e))
+            .ok()
+    }
+
+    /// Show a photo once its image has been decoded.
+    fn show_photo(&mut self, sender: &AsyncComponentSender<Self>, visual: Arc<Visual>, image: CachedImage) {
+        self.edits = visual.picture_id
+            .and_then(|id| self.photo_repo.edits(&id)
+                .inspect_err(|e| event!(Level::ERROR, "Failed loading edits: {:?}", e))
+                .ok())
+            .unwrap_or_default();
+
+        let burst_centre = self.zoom_anchor.take()
+            .filter(|anchor| {
+                anchor.width == image.texture.width()
+                    && anchor.height == image.texture.height()
+                    && burst::is_same_burst(&anchor.visual, &visual)
+            })
+            .map(|anchor| anchor.centre);
+
+        if burst_centre.is_none() {
+            self.zoom = None;
+        }
+
+        self.photo = Some((visual.clone(), image.texture));
+        self.edit_preview = None;
+        self.render_photo();
+        self.edit_controls.set_visible(self.is_editing && !self.is_slideshow);
+
+        if let Some(centre) = burst_centre {
+            self.scroll_to_centre(centre);
+        }
+
+        let _ = sender.output(ViewOneOutput::PhotoShown(visual.visual_id.clone(), image.info));
+    }
+
+    /// Decode image in the background, unless it is already decoded or being decoded.
+    fn load(&mut self, sender: &AsyncComponentSender<Self>, path: PathBuf) {
+        if self.loads.contains_key(&path) || self.texture_cache.contains(&path) {
+            return;
+        }
+
+        self.load_id += 1;
+        let load_id = self.load_id;
+        let cancellable = gio::Cancellable::new();
+        self.loads.insert(path.clone(), (load_id, cancellable.clone()));
+
+        let sender = sender.clone();
+        relm4::spawn_local(async move {
+            let result = texture_cache::load(&path, cancellable).await;
+            sender.input(ViewOneInput::Loaded(path, load_id, result));
+        });
+    }
+
+    /// Show photo with edits applied. Rotations and flips are combined with the
+    /// EXIF orientation and applied with a CSS transformation.
+    fn render_photo(&mut self) {
+        let Some((ref visual, ref texture)) = self.photo else {
+            return;
+        };
+
+        let orientation = visual.corrected_picture_orientation();
+        self.display_orientation = self.edits.display_orientation(orientation);
+
+        for orient in PictureOrientation::iter() {
+            self.picture.remove_css_class(orient.as_ref());
+        }
+        self.picture.add_css_class(self.display_orientation.as_ref());
+
+        let texture = self.edits.render_texture(texture, orientation);
+        self.picture.set_paintable(Some(&texture));
+        self.apply_zoom();
+
+        // Keep sliders in step with edits, such as after an undo.
+        let adjustments = self.edits.adjustments();
+        self.exposure_scale.set_value(adjustments.exposure as f64);
+        self.contrast_scale.set_value(adjustments.contrast as f64);
+        self.saturation_scale.set_value(adjustments.saturation as f64);
+    }
+
+    /// Show photo fitted to the available space, or at the current zoom in a scrollable view.
+    ///
+    /// A CSS transformation doesn't change the size a widget is laid out with, so a zoomed
+    /// photo is oriented with a child transformation of a gtk::Fixed, which does.
+    fn apply_zoom(&self) {
+        let paintable = self.picture.paintable();
+        let (Some(zoom), Some(paintable)) = (self.zoom, paintable) else {
+            self.zoom_window.set_visible(false);
+            self.zoom_picture.set_paintable(None::<&gdk::Paintable>);
+            self.picture.set_visible(self.photo.is_some());
+            return;
+        };
+
+        let width = (paintable.intrinsic_width() as f64 * zoom).round() as f32;
+        let height = (paintable.intrinsic_height() as f64 * zoom).round() as f32;
+
+        self.zoom_picture.set_paintable(Some(&paintable));
+        self.zoom_picture.set_size_request(width as i32, height as i32);
+
+        let transform = orientation::transform(self.display_orientation, width, height);
+        self.zoom_fixed.set_child_transform(&self.zoom_picture, Some(&transform));
+
+        self.picture.set_visible(false);
+        self.zoom_window.set_visible(true);
+    }
+
+    /// Change zoom, keeping the centre of the view in place. Zooming out to less than
+    /// the fitted size goes back to fitting the photo to the available space.
+    fn zoom_to(&mut self, zoom: f64) {
+        if self.photo.is_none() {
+            return;
+        }
+
+        let centre = self.scroll_centre();
+        self.zoom = Some(zoom.min(MAX_ZOOM)).filter(|z| *z > self.fit_zoom());
+        self.apply_zoom();
+        self.scroll_to_centre(centre);
+    }
+
+    /// Zoom photo is displayed at, including when fitted to the available space.
+    fn current_zoom(&self) -> f64 {
+        self.zoom.unwrap_or_else(|| self.fit_zoom())
+    }
+
+    /// Zoom that fits the photo to the available space.
+    fn fit_zoom(&self) -> f64 {
+        let (width, height) = self.oriented_size();
+        if width <= 0.0 || height <= 0.0 {
+            return 1.0;
+        }
+
+        let area_width = self.root.width() as f64;
+        let area_height = self.root.height() as f64;
+        (area_width / width).min(area_height / height)
+    }
+
+    /// Size of photo with orientation applied, before zooming.
+    fn oriented_size(&self) -> (f64, f64) {
+        let Some(paintable) = self.picture.paintable() else {
+            return (0.0, 0.0);
+        };
+
+        let width = paintable.intrinsic_width() as f64;
+        let height = paintable.intrinsic_height() as f64;
+
+        if self.display_orientation.is_sideways() {
+            (height, width)
+        } else {
+            (width, height)
+        }
+    }
+
+    /// Position of the centre of the view, as a fraction of the zoomed photo's size.
+    fn scroll_centre(&self) -> (f64, f64) {
+        if !self.zoom_window.is_visible() {
+            return (0.5, 0.5);
+        }
+
+        let centre = |adj: gtk::Adjustment| {
+            if adj.upper() > 0.0 {
+                (adj.value() + adj.page_size() / 2.0) / adj.upper()
+            } else {
+                0.5
+            }
+        };
+
+        (centre(self.zoom_window.hadjustment()), centre(self.zoom_window.vadjustment()))
+    }
+
+    /// Scroll zoomed photo so a position, as a fraction of its size, is in the centre.
+    /// The scrolled window hasn't been laid out with the new zoom yet, so the adjustments
+    /// are configured with the size the photo will have.
+    fn scroll_to_centre(&self, (centre_x, centre_y): (f64, f64)) {
+        let Some(zoom) = self.zoom else {
+            return;
+        };
+
+        let (width, height) = self.oriented_size();
+
+        let axes = [
+            (self.zoom_window.hadjustment(), centre_x, width * zoom, self.root.width() as f64),
+            (self.zoom_window.vadjustment(), centre_y, height * zoom, self.root.height() as f64),
+        ];
+
+        for (adj, centre, upper, area) in axes {
+            let page = area.min(upper);
+            let value = (centre * upper - page / 2.0).clamp(0.0, upper - page);
+            adj.configure(value, 0.0, upper, adj.step_increment(), adj.page_increment(), page);
+        }
+    }
+
+    /// Save edits and show the edited photo.
+    fn edits_changed(&mut self) {
+        if self.photo.as_ref().and_then(|(visual, _)| visual.picture_id).is_none() {
+            return;
+        }
+
+        self.is_edits_unsaved = true;
+        self.is_edits_dirty = true;
+        self.is_previewing = false;
+        self.save_edits();
+        self.render_photo();
+    }
+
+    /// Show a preview of edits while an adjustment slider is moving. Rendering at full size
+    /// and saving are left until the slider settles, or the photo or editing is closed.
+    fn adjustment_changed(&mut self, sender: &AsyncComponentSender<Self>) {
+        if self.photo.as_ref().and_then(|(visual, _)| visual.picture_id).is_none() {
+            return;
+        }
+
+        self.is_edits_unsaved = true;
+        self.is_edits_dirty = true;
+        self.is_previewing = true;
+        self.render_preview(sender);
+
+        self.adjustment_id += 1;
+        let adjustment_id = self.adjustment_id;
+        let sender = sender.clone();
+        glib::timeout_add_local_once(Duration::from_millis(ADJUSTMENT_SETTLE_MILLIS), move || {
+            sender.input(ViewOneInput::AdjustmentSettled(adjustment_id));
+        });
+    }
+
+    /// Render a downscaled preview of edits in the background. Only one preview is
+    /// rendered at a time, and edits made meanwhile are rendered once it is done.
+    fn render_preview(&mut self, sender: &AsyncComponentSender<Self>) {
+        if self.is_preview_pending {
+            self.is_preview_stale = true;
+            return;
+        }
+
+        let Some((ref visual, ref texture)) = self.photo else {
+            return;
+        };
+
+        self.is_preview_pending = true;
+        self.is_preview_stale = false;
+
+        let visual_id = visual.visual_id.clone();
+        let orientation = visual.corrected_picture_orientation();
+        let edits = self.edits.clone();
+        let texture = texture.clone();
+        let preview = self.edit_preview.clone();
+        let sender = sender.clone();
+
+        relm4::spawn(async move {
+            let result = relm4::spawn_blocking(move || {
+                let preview = preview.unwrap_or_else(|| Arc::new(EditPreview::new(&texture)));
+                let texture = preview.render_texture(&edits, orientation);
+                (preview, texture)
+            })
+            .await
+            .map_err(|e| anyhow::anyhow!("Preview of edits panicked: {:?}", e));
+
+            sender.input(ViewOneInput::PreviewRendered(visual_id, result));
+        });
+    }
+
+    /// Tell the user that an action on the item being viewed failed.
+    fn show_error(&self, heading: &str, e: &anyhow::Error) {
+        let dialog = adw::AlertDialog::new(Some(heading), Some(&e.to_string()));
+        dialog.add_response("close", &fl!("viewer-error-dialog-close"));
+        dialog.present(&self.root);
+    }
+
+    /// Save edits of the photo being viewed, if they have changed.
+    fn save_edits(&mut self) {
+        if !self.is_edits_unsaved {
+            return;
         }
+        self.is_edits_unsaved = false;
+
+        let Some(picture_id) = self.photo.as_ref().and_then(|(visual, _)| visual.picture_id) else {
+            return;
+        };
+
+        if let Err(e) = self.photo_repo.set_edits(&picture_id, &self.edits) {
+            event!(Level::ERROR, "Failed saving edits: {:?}", e);
+        }
+    }
+
+    /// Save the focus that thumbnails of the photo being viewed are cropped about.
+    /// No focus means that the focus is detected.
+    fn set_focus(&mut self, focus: Option<Focus>) {
+        let Some(picture_id) = self.photo.as_ref().and_then(|(visual, _)| visual.picture_id) else {
+            return;
+        };
+
+        if let Err(e) = self.photo_repo.set_focus_override(&picture_id, focus) {
+            event!(Level::ERROR, "Failed saving focus: {:?}", e);
+            return;
+        }
+
+        self.is_focus_dirty = true;
+    }
+
+    /// Show a crosshair over the photo while choosing the focus.
+    fn set_focus_cursor(&self) {
+        let cursor = if self.is_choosing_focus { Some("crosshair") } else { None };
+        self.picture.set_cursor_from_name(cursor);
+        self.zoom_picture.set_cursor_from_name(cursor);
+    }
+
+    /// Regenerate thumbnail once editing of a photo has finished.
+    async fn commit_edits(&mut self, sender: &AsyncComponentSender<Self>) {
+        self.is_choosing_focus = false;
+        self.set_focus_cursor();
+
+        // Adjustments that haven't settled yet still need saving.
+        self.is_previewing = false;
+        self.save_edits();
+
+        if !self.is_edits_dirty && !self.is_focus_dirty {
+            return;
+        }
+        let is_focus_dirty = self.is_focus_dirty;
+        self.is_edits_dirty = false;
+        self.is_focus_dirty = false;
+
+        let Some((ref visual, _)) = self.photo else {
+            return;
+        };
+
+        let (Some(picture_id), Some(path)) = (visual.picture_id, visual.picture_path.as_ref()) else {
+            return;
+        };
+
+        let orientation = visual.corrected_picture_orientation();
+
+        let settings = gio::Settings::new(APP_ID);
+        let (shape, format) = thumbnail_settings::from_settings(&settings);
+        let thumbnailer = self.photo_thumbnailer
+            .clone()
+            .with_shape(shape)
+            .with_format(format);
+
+        // Standard thumbnails are only regenerated if missing, so remove any
+        // cropped about the old focus.
+        if is_focus_dirty {
+            if let Err(e) = thumbnailer.remove_square(&picture_id) {
+                event!(Level::ERROR, "Failed removing thumbnail: {:?}", e);
+            }
+        }
+
+        let focus = self.photo_repo
+            .focus_override(&picture_id)
+            .inspect_err(|e| event!(Level::ERROR, "Failed loading focus: {:?}", e))
+            .ok()
+            .flatten();
+
+        let thumbnail = thumbnailer
+            .edited_thumbnail(&picture_id, path, orientation, &self.edits, focus)
+            .await;
+
+        let result = thumbnail
+            .and_then(|thumbnail| self.photo_repo.add_thumbnail(&picture_id, &thumbnail));
+
+        if let Err(e) = result {
+            event!(Level::ERROR, "Failed generating edited thumbnail: {:?}", e);
+        }
+
+        // Thumbnail orientation also depends on edits, so refresh even if thumbnail failed.
+        let _ = sender.output(ViewOneOutput::Edited);
+    }
+
+    /// Is the video being viewed playing, at any speed?
+    fn is_playing(&self) -> bool {
+        self.is_shuttling || self.video.as_ref().is_some_and(|video| video.is_playing())
+    }
+
+    /// Play the video being viewed at the current speed. GTK media streams can only play
+    /// forwards at normal speed, so other speeds are played by frequently seeking the
+    /// paused video, which shows fewer frames and no sound.
+    fn play_at_speed(&mut self, sender: &AsyncComponentSender<Self>) {
+        self.stop_shuttle();
+
+        let Some(ref video) = self.video else {
+            return;
+        };
+
+        if self.speed == 1.0 {
+            video.play();
+        } else {
+            video.pause();
+            self.is_shuttling = true;
+            self.shuttle_position = video.timestamp();
+            self.schedule_shuttle(sender);
+        }
+
+        self.play_button.set_icon_name("pause-symbolic");
+        self.update_timestamp();
+    }
+
+    /// Change playback speed, carrying on playing if the video is playing.
+    fn change_speed(&mut self, speed: f64, sender: &AsyncComponentSender<Self>) {
+        self.speed = speed;
+        if self.is_playing() {
+            self.play_at_speed(sender);
+        } else {
+            self.update_timestamp();
+        }
+    }
+
+    /// Pause the video being viewed, whatever speed it is playing at.
+    fn pause(&mut self) {
+        self.stop_shuttle();
+        if let Some(ref video) = self.video {
+            video.pause();
+            self.play_button.set_icon_name("play-symbolic");
+        }
+    }
+
+    /// Start timer for the next seek when playing at a speed other than normal.
+    fn schedule_shuttle(&mut self, sender: &AsyncComponentSender<Self>) {
+        self.shuttle_timer += 1;
+        let timer = self.shuttle_timer;
+        let sender = sender.clone();
+        glib::timeout_add_local_once(Duration::from_millis(SHUTTLE_INTERVAL_MILLIS), move || {
+            sender.input(ViewOneInput::ShuttleTick(timer));
+        });
+    }
+
+    /// Stop any voice note that is playing, so it doesn't play over the next item.
+    fn stop_voice_note(&mut self) {
+        if let Some(voice_note) = self.voice_note.take() {
+            voice_note.pause();
+        }
+        self.voice_note_content.set_icon_name("play-symbolic");
+    }
+
+    fn stop_shuttle(&mut self) {
+        self.is_shuttling = false;
+        self.shuttle_timer += 1;
+    }
+
+    /// Show the position of the video being viewed, and the speed if it isn't normal.
+    fn update_timestamp(&self) {
+        let Some(ref video) = self.video else {
+            return;
+        };
+
+        let position = if self.is_shuttling {
+            self.shuttle_position
+        } else {
+            video.timestamp()
+        };
+
+        let current_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(position));
+        let total_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(video.duration()));
+        if self.speed == 1.0 {
+            self.video_timestamp.set_text(&format!("{}/{}", current_ts, total_ts));
+        } else {
+            self.video_timestamp.set_text(&format!("{}/{} · {}×", current_ts, total_ts, self.speed));
+        }
+
+        // Setting the value doesn't emit change-value, so doesn't seek.
+        self.seek_scale.set_value(position as f64 / 1_000_000.0);
+    }
+
+    /// Show the trim markers on the seek bar and the length of the trimmed copy.
+    fn update_trim(&self) {
+        self.seek_scale.clear_marks();
+
+        let Some(ref video) = self.video else {
+            return;
+        };
+
+        let start = self.trim_start.unwrap_or(0);
+        let end = self.trim_end.unwrap_or(video.duration());
+
+        if let Some(start) = self.trim_start {
+            self.seek_scale.add_mark(start as f64 / 1_000_000.0, gtk::PositionType::Bottom, Some("["));
+        }
+        if let Some(end) = self.trim_end {
+            self.seek_scale.add_mark(end as f64 / 1_000_000.0, gtk::PositionType::Bottom, Some("]"));
+        }
+
+        let start_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(start));
+        let end_ts = fotema_core::time::format_hhmmss(&TimeDelta::microseconds(end));
+        self.trim_label.set_text(&format!("{}–{}", start_ts, end_ts));
+        self.trim_save_button.set_sensitive(end > start);
+    }
+
+    /// Forget frames decoded for the seek bar of the previous video.
+    fn clear_scrub(&mut self) {
+        self.scrub_id += 1;
+        self.scrub_frames.clear();
+        self.scrub_wanted = None;
+        self.is_scrub_pending = false;
+        self.scrub_picture.set_paintable(None::<&gdk::Paintable>);
+        self.scrub_popover.popdown();
+        self.video_source = None;
+        self.frame_rate = None;
+    }
+
+    /// Read the frame rate of the video being played, for stepping between frames.
+    /// Opening the video to read it is too slow for the main thread.
+    fn read_frame_rate(&self, sender: &AsyncComponentSender<Self>) {
+        let Some(path) = self.video_source.clone() else {
+            return;
+        };
+
+        let scrub_id = self.scrub_id;
+        let sender = sender.clone();
+
+        relm4::spawn(async move {
+            let frame_rate = relm4::spawn_blocking(move || {
+                video::metadata::frame_rate(&path)
+                    .inspect_err(|e| event!(Level::ERROR, "Failed reading frame rate of {:?}: {:?}", path, e))
+                    .ok()
+                    .flatten()
+            })
+            .await
+            .ok()
+            .flatten();
+
+            sender.input(ViewOneInput::FrameRateRead(scrub_id, frame_rate));
+        });
+    }
+
+    /// Decode the frame wanted for the seek bar, unless a frame is already being decoded.
+    /// Keyframes are decoded, which are quick to find, but might be a little before
+    /// the position.
+    fn decode_scrub_frame(&mut self, sender: &AsyncComponentSender<Self>) {
+        if self.is_scrub_pending {
+            return;
+        }
+
+        let (Some(position), Some(path)) = (self.scrub_wanted, self.video_source.clone()) else {
+            return;
+        };
+
+        if self.scrub_frames.contains_key(&position) {
+            return;
+        }
+
+        self.is_scrub_pending = true;
+        let scrub_id = self.scrub_id;
+        let sender = sender.clone();
+
+        relm4::spawn(async move {
+            let result = relm4::spawn_blocking(move || -> anyhow::Result<gdk::Texture> {
+                let image = video::poster::keyframe(&path, TimeDelta::microseconds(position), SCRUB_FRAME_EDGE)?;
+                let (width, height) = image.dimensions();
+                let bytes = glib::Bytes::from_owned(image.into_raw());
+                let texture = gdk::MemoryTexture::new(
+                    width as i32,
+                    height as i32,
+                    gdk::MemoryFormat::R8g8b8,
+                    &bytes,
+                    width as usize * 3,
+                );
+                Ok(texture.upcast::<gdk::Texture>())
+            })
+            .await
+            .unwrap_or_else(|e| Err(anyhow::anyhow!("Seek bar frame panicked: {:?}", e)));
+
+            sender.input(ViewOneInput::ScrubFrame(scrub_id, position, result));
+        });
+    }
+
+    /// Save the position of the frame that thumbnails of the video being viewed are
+    /// made from, and regenerate its thumbnails. No position means that a
+    /// representative frame is chosen.
+    async fn set_poster(&mut self, position: Option<TimeDelta>, sender: &AsyncComponentSender<Self>) {
+        let Some(ref visual) = self.video_visual else {
+            return;
+        };
+
+        let (Some(video_id), Some(path)) = (visual.video_id, visual.video_path.clone()) else {
+            return;
+        };
+
+        if let Err(e) = self.video_repo.set_poster(&video_id, position) {
+            event!(Level::ERROR, "Failed saving poster: {:?}", e);
+            return;
+        }
+
+        let settings = gio::Settings::new(APP_ID);
+        let (shape, format) = thumbnail_settings::from_settings(&settings);
+        let thumbnailer = self.video_thumbnailer
+            .clone()
+            .with_shape(shape)
+            .with_format(format);
+
+        // Decoding frames is slow, so keep it off the main thread.
+        let thumbnail = relm4::spawn_blocking(move || {
+            thumbnailer.remove(&video_id)
+                .and_then(|_| thumbnailer.thumbnail(&video_id, &path, position))
+        })
+        .await
+        .unwrap_or_else(|e| Err(anyhow::anyhow!("Poster thumbnail panicked: {:?}", e)));
+
+        let result = thumbnail
+            .and_then(|thumbnail| self.video_repo.add_thumbnail(&video_id, &thumbnail));
+
+        match result {
+            Ok(()) => {
+                let _ = sender.output(ViewOneOutput::Edited);
+            },
+            Err(e) => {
+                event!(Level::ERROR, "Failed generating poster thumbnail: {:?}", e);
+            },
+        }
+    }
+}
+
+/// Next playback speed faster or slower than a speed, in the same direction.
+/// Speeds stop at the fastest and slowest supported speeds.
+fn next_speed(speed: f64, is_faster: bool) -> f64 {
+    let magnitude = speed.abs();
+    let next = if is_faster {
+        SPEEDS.iter().find(|s| **s > magnitude)
+    } else {
+        SPEEDS.iter().rev().find(|s| **s < magnitude)
+    };
+    next.copied().unwrap_or(magnitude).copysign(speed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_speed_faster() {
+        assert_eq!(next_speed(1.0, true), 1.5);
+        assert_eq!(next_speed(0.25, true), 0.5);
+        assert_eq!(next_speed(8.0, true), 8.0);
+    }
+
+    #[test]
+    fn test_next_speed_slower() {
+        assert_eq!(next_speed(1.0, false), 0.5);
+        assert_eq!(next_speed(8.0, false), 4.0);
+        assert_eq!(next_speed(0.25, false), 0.25);
+    }
+
+    #[test]
+    fn test_next_speed_backwards() {
+        assert_eq!(next_speed(-1.0, true), -1.5);
+        assert_eq!(next_speed(-2.0, false), -1.5);
+        assert_eq!(next_speed(-8.0, true), -8.0);
     }
 }